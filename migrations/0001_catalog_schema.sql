@@ -0,0 +1,89 @@
+-- Catalog, drop types and imports
+-- A database created by the former local-dev init.sql already has artist, playlist and drop without
+-- the later columns: the tables are only created when missing and the legacy ones are upgraded below
+
+CREATE TABLE IF NOT EXISTS "artist" (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(255) NOT NULL UNIQUE,
+    version INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS "artwork" (
+    id SERIAL PRIMARY KEY,
+    mime_type VARCHAR(64) NOT NULL,
+    width INTEGER NOT NULL,
+    height INTEGER NOT NULL,
+    version INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS "playlist" (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    version INTEGER NOT NULL DEFAULT 0
+);
+
+-- filled by DropTypeRepo::sync at startup
+CREATE TABLE IF NOT EXISTS "drop_type" (
+    id SMALLINT PRIMARY KEY,
+    name VARCHAR(32) NOT NULL UNIQUE
+);
+
+CREATE TABLE IF NOT EXISTS "drop" (
+    id SERIAL PRIMARY KEY,
+    artist_id INTEGER NOT NULL,
+    artwork_id INTEGER NOT NULL,
+    type_id SMALLINT NOT NULL,
+    playlist_id INTEGER NOT NULL UNIQUE,
+    version INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS "track" (
+    id SERIAL PRIMARY KEY,
+    playlist_id INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    source_file VARCHAR(1024) NOT NULL,
+    title VARCHAR(255),
+    duration_seconds INTEGER,
+    version INTEGER NOT NULL DEFAULT 0,
+    UNIQUE (playlist_id, position)
+);
+
+CREATE TABLE IF NOT EXISTS "api_key" (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    key_hash CHAR(64) NOT NULL UNIQUE,
+    scopes TEXT[] NOT NULL,
+    expires_at TIMESTAMP NULL,
+    last_used_at TIMESTAMP NULL
+);
+
+CREATE TABLE IF NOT EXISTS "import" (
+    id SERIAL PRIMARY KEY,
+    sha256 CHAR(64) NOT NULL UNIQUE,
+    file_name VARCHAR(255) NOT NULL,
+    state VARCHAR(32) NOT NULL,
+    drop_id INTEGER,
+    error TEXT,
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS "import_job" (
+    id SERIAL PRIMARY KEY,
+    state VARCHAR(32) NOT NULL,
+    files JSONB NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL
+);
+
+-- legacy tables: init.sql created artist (id, name), playlist (id, name) and drop (id, artist_id, artwork_id, type_id)
+ALTER TABLE "artist" ADD COLUMN IF NOT EXISTS version INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE "playlist" ADD COLUMN IF NOT EXISTS version INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE "drop" ADD COLUMN IF NOT EXISTS version INTEGER NOT NULL DEFAULT 0;
+
+-- a legacy drop kept the id of its playlist in artwork_id and had no artwork
+ALTER TABLE "drop" ADD COLUMN IF NOT EXISTS playlist_id INTEGER;
+UPDATE "drop" SET playlist_id = artwork_id, artwork_id = 0 WHERE playlist_id IS NULL;
+ALTER TABLE "drop" ALTER COLUMN playlist_id SET NOT NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS drop_playlist_id_key ON "drop" (playlist_id);
+-- legacy artist names are not made unique here, 0006 matches them on their normalized form
@@ -0,0 +1,2 @@
+-- an unpublished drop keeps its rows, its playlist directory is moved out of the web server
+ALTER TABLE "drop" ADD COLUMN published BOOLEAN NOT NULL DEFAULT TRUE;
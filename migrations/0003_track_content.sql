@@ -0,0 +1,8 @@
+-- track bytes are stored once under their SHA-256, tracks imported before have no content_hash
+ALTER TABLE "track" ADD COLUMN content_hash VARCHAR(64);
+
+CREATE TABLE "track_content" (
+    content_hash VARCHAR(64) PRIMARY KEY,
+    size BIGINT NOT NULL,
+    ref_count INTEGER NOT NULL DEFAULT 0
+);
@@ -0,0 +1,11 @@
+-- release fields and credits of the manifest, recording fields of its tracks
+ALTER TABLE "drop" ADD COLUMN release_date DATE;
+ALTER TABLE "drop" ADD COLUMN label TEXT;
+ALTER TABLE "drop" ADD COLUMN genre TEXT;
+ALTER TABLE "drop" ADD COLUMN description TEXT;
+ALTER TABLE "drop" ADD COLUMN credits JSONB NOT NULL DEFAULT '[]';
+
+ALTER TABLE "track" ADD COLUMN isrc VARCHAR(12);
+ALTER TABLE "track" ADD COLUMN explicit BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE "track" ADD COLUMN disc_number INTEGER;
+ALTER TABLE "track" ADD COLUMN track_number INTEGER;
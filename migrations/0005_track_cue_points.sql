@@ -0,0 +1,2 @@
+-- where each tune of a mix starts, as given by the manifest
+ALTER TABLE "track" ADD COLUMN cue_points JSONB NOT NULL DEFAULT '[]';
@@ -0,0 +1,3 @@
+-- names are matched on the form given by normalize_artist_name, the server fills it for the existing artists at startup
+ALTER TABLE "artist" ADD COLUMN normalized_name VARCHAR(255);
+CREATE UNIQUE INDEX artist_normalized_name_key ON "artist" (normalized_name);
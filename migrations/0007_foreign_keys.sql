@@ -0,0 +1,18 @@
+-- a drop without artwork had artwork_id 0, it now has none so that artwork_id can reference the artwork
+ALTER TABLE "drop" ALTER COLUMN artwork_id DROP NOT NULL;
+UPDATE "drop" SET artwork_id = NULL WHERE artwork_id = 0;
+
+-- NOT VALID: rows written before are not checked, orphans left by earlier deletes don't stop the migration.
+-- Once they are cleaned up, ALTER TABLE ... VALIDATE CONSTRAINT checks them too
+ALTER TABLE "drop" ADD CONSTRAINT drop_artist_id_fkey
+    FOREIGN KEY (artist_id) REFERENCES "artist" (id) ON DELETE RESTRICT NOT VALID;
+ALTER TABLE "drop" ADD CONSTRAINT drop_playlist_id_fkey
+    FOREIGN KEY (playlist_id) REFERENCES "playlist" (id) ON DELETE RESTRICT NOT VALID;
+ALTER TABLE "drop" ADD CONSTRAINT drop_artwork_id_fkey
+    FOREIGN KEY (artwork_id) REFERENCES "artwork" (id) ON DELETE RESTRICT NOT VALID;
+
+-- the tracks of a playlist go with it, a stored content can't be forgotten while a track references it
+ALTER TABLE "track" ADD CONSTRAINT track_playlist_id_fkey
+    FOREIGN KEY (playlist_id) REFERENCES "playlist" (id) ON DELETE CASCADE NOT VALID;
+ALTER TABLE "track" ADD CONSTRAINT track_content_hash_fkey
+    FOREIGN KEY (content_hash) REFERENCES "track_content" (content_hash) ON DELETE RESTRICT NOT VALID;
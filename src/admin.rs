@@ -0,0 +1,77 @@
+use crate::repository::api_key::{rotate_api_key, ApiKey, ApiKeyScope};
+use crate::{api_key_guard, AppError, AppState};
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::middleware::from_fn_with_state;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chrono::{Duration, NaiveDateTime};
+use serde::Serialize;
+
+/// Time a rotated key stays valid, so its clients can switch to the new one
+pub const API_KEY_ROTATION_GRACE_MINUTES: i64 = 60;
+
+/// Operator endpoints, nested under /admin, each one needs an api key with its admin scope
+pub fn routes(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route(
+            "/api-keys/{id}",
+            get(api_key).route_layer(from_fn_with_state((state.clone(), ApiKeyScope::AdminRead), api_key_guard)),
+        )
+        .route(
+            "/api-keys/{id}/rotate",
+            post(api_key_rotate).route_layer(from_fn_with_state((state, ApiKeyScope::AdminWrite), api_key_guard)),
+        )
+}
+
+/// An api key without its hash
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ApiKeyView {
+    id: i32,
+    name: String,
+    scopes: Vec<&'static str>,
+    expires_at: Option<NaiveDateTime>,
+    last_used_at: Option<NaiveDateTime>,
+}
+
+impl From<&ApiKey> for ApiKeyView {
+    fn from(api_key: &ApiKey) -> Self {
+        Self {
+            id: api_key.id(),
+            name: api_key.name().to_string(),
+            scopes: api_key.scopes().iter().map(ApiKeyScope::as_str).collect(),
+            expires_at: api_key.expires_at(),
+            last_used_at: api_key.last_used_at(),
+        }
+    }
+}
+
+/// The plain text key is only given in this response
+#[derive(Debug, Serialize)]
+pub struct RotatedApiKeyView {
+    #[serde(flatten)]
+    api_key: ApiKeyView,
+    key: String,
+}
+
+async fn api_key(
+    Path(id): Path<i32>,
+    State(state): State<AppState>,
+) -> Result<Json<ApiKeyView>, AppError> {
+    let api_key = state.api_key_repo.get(id).await?;
+    Ok(Json(ApiKeyView::from(&api_key)))
+}
+
+// The keys declared in app.toml are rotated in memory, app.toml must then be updated with the new hash
+async fn api_key_rotate(
+    Path(id): Path<i32>,
+    State(state): State<AppState>,
+) -> Result<(StatusCode, Json<RotatedApiKeyView>), AppError> {
+    let (api_key, key) = rotate_api_key(
+        state.api_key_repo.as_ref(),
+        id,
+        Duration::minutes(API_KEY_ROTATION_GRACE_MINUTES),
+    ).await?;
+    println!("api key {} rotated, the previous key {id} stays valid {API_KEY_ROTATION_GRACE_MINUTES} minutes", api_key.name());
+    Ok((StatusCode::CREATED, Json(RotatedApiKeyView { api_key: ApiKeyView::from(&api_key), key })))
+}
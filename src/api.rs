@@ -0,0 +1,123 @@
+use crate::api::serializer::{ArtistView, DropView, PageView, PlaylistView};
+use crate::repository::artist::ArtistField;
+use crate::repository::drop::DropField;
+use crate::repository::query::{Field, Filter, Order, DEFAULT_LIMIT};
+use crate::repository::{Repo, RepoByName};
+use crate::{AppError, AppState};
+use axum::extract::{Path, Query, State};
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::Deserialize;
+
+pub mod serializer;
+
+/// Page size above which a requested limit is lowered
+pub const MAX_PAGE_LIMIT: i64 = 200;
+
+/// Read-only catalog, nested under /api
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/artists", get(artists))
+        .route("/artists/{id}", get(artist))
+        .route("/artists/{id}/drops", get(artist_drops))
+        .route("/drops/{id}", get(drop))
+        .route("/playlists/{id}", get(playlist))
+}
+
+/// `cursor` is the `next_cursor` of the previous page
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct PageParams {
+    limit: Option<i64>,
+    cursor: Option<i32>,
+    order: Order,
+}
+
+impl PageParams {
+    fn filter<F: Field>(&self, sort: F) -> Filter<F> {
+        Filter::new()
+            .sort_by(sort, self.order)
+            .after(self.cursor)
+            .limit(self.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_PAGE_LIMIT))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArtistSort {
+    #[default]
+    Id,
+    Name,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ArtistSortParams {
+    sort: ArtistSort,
+}
+
+async fn artists(
+    State(state): State<AppState>,
+    Query(page): Query<PageParams>,
+    Query(sort): Query<ArtistSortParams>,
+) -> Result<Json<PageView<ArtistView>>, AppError> {
+    let sort = match sort.sort {
+        ArtistSort::Id => ArtistField::Id,
+        ArtistSort::Name => ArtistField::Name,
+    };
+    let artists = state.service_conf.drop_service().artist_repository()
+        .list(&page.filter(sort)).await?;
+    Ok(Json(PageView::new(&artists)))
+}
+
+async fn artist(
+    Path(id): Path<i32>,
+    State(state): State<AppState>,
+) -> Result<Json<ArtistView>, AppError> {
+    let artist = state.service_conf.drop_service().artist_repository()
+        .get(id).await?;
+    Ok(Json(ArtistView::from(&artist)))
+}
+
+async fn artist_drops(
+    Path(id): Path<i32>,
+    State(state): State<AppState>,
+    Query(page): Query<PageParams>,
+) -> Result<Json<PageView<DropView>>, AppError> {
+    let drop_service = state.service_conf.drop_service();
+    // an unknown artist is told apart from one without drops
+    if !drop_service.artist_repository().exists(id).await? {
+        return Err(AppError::ResourceNotFound);
+    }
+    let drops = drop_service.drop_repository()
+        .list(&page.filter(DropField::Id).eq(DropField::ArtistId, id).eq(DropField::Published, true)).await?;
+    Ok(Json(PageView::new(&drops)))
+}
+
+async fn drop(
+    Path(id): Path<i32>,
+    State(state): State<AppState>,
+) -> Result<Json<DropView>, AppError> {
+    let drop = state.service_conf.drop_service().drop_repository()
+        .get(id).await?;
+    if !drop.published() {
+        return Err(AppError::ResourceNotFound);
+    }
+    Ok(Json(DropView::from(&drop)))
+}
+
+async fn playlist(
+    Path(id): Path<i32>,
+    State(state): State<AppState>,
+) -> Result<Json<PlaylistView>, AppError> {
+    let drop_service = state.service_conf.drop_service();
+    // a playlist is served with its drop, only once published
+    let drops = drop_service.drop_repository()
+        .list(&Filter::new().eq(DropField::PlaylistId, id).eq(DropField::Published, true)).await?;
+    if drops.items.is_empty() {
+        return Err(AppError::ResourceNotFound);
+    }
+    let playlist = drop_service.playlist_repository().get(id).await?;
+    let tracks = drop_service.track_repository().get_by_playlist(id).await?;
+    Ok(Json(PlaylistView::new(&playlist, &tracks)))
+}
@@ -0,0 +1,117 @@
+use crate::repository::artist::Artist;
+use crate::repository::drop::{Credit, Drop};
+use crate::repository::playlist::Playlist;
+use crate::repository::query::Page;
+use crate::repository::track::{CuePoint, Track};
+use crate::service::artwork::NO_ARTWORK_ID;
+use chrono::NaiveDate;
+use serde::Serialize;
+
+// Public form of the catalog entities, versions, artwork ids and the paths of the files are left out
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ArtistView {
+    id: i32,
+    name: String,
+}
+
+impl From<&Artist> for ArtistView {
+    fn from(artist: &Artist) -> Self {
+        Self { id: artist.id(), name: artist.name().to_string() }
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct DropView {
+    id: i32,
+    artist_id: i32,
+    playlist_id: i32,
+    /// None for a type the server no longer knows
+    drop_type: Option<&'static str>,
+    has_artwork: bool,
+    release_date: Option<NaiveDate>,
+    label: Option<String>,
+    genre: Option<String>,
+    description: Option<String>,
+    credits: Vec<Credit>,
+}
+
+impl From<&Drop> for DropView {
+    fn from(drop: &Drop) -> Self {
+        Self {
+            id: drop.id(),
+            artist_id: drop.artist_id(),
+            playlist_id: drop.playlist_id(),
+            drop_type: drop.drop_type().map(|drop_type| drop_type.name()),
+            has_artwork: drop.artwork_id() != NO_ARTWORK_ID,
+            release_date: drop.release().release_date(),
+            label: drop.release().label().map(str::to_string),
+            genre: drop.release().genre().map(str::to_string),
+            description: drop.release().description().map(str::to_string),
+            credits: drop.release().credits().to_vec(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct TrackView {
+    position: i32,
+    title: Option<String>,
+    duration_seconds: Option<i32>,
+    isrc: Option<String>,
+    explicit: bool,
+    disc_number: Option<i32>,
+    track_number: Option<i32>,
+    cue_points: Vec<CuePoint>,
+}
+
+impl From<&Track> for TrackView {
+    fn from(track: &Track) -> Self {
+        Self {
+            position: track.position(),
+            title: track.title().map(str::to_string),
+            duration_seconds: track.duration_seconds(),
+            isrc: track.isrc().map(str::to_string),
+            explicit: track.explicit(),
+            disc_number: track.disc_number(),
+            track_number: track.track_number(),
+            cue_points: track.cue_points().to_vec(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct PlaylistView {
+    id: i32,
+    name: String,
+    tracks: Vec<TrackView>,
+}
+
+impl PlaylistView {
+    pub fn new(playlist: &Playlist, tracks: &[Track]) -> Self {
+        Self {
+            id: playlist.id(),
+            name: playlist.name().to_string(),
+            tracks: tracks.iter().map(TrackView::from).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct PageView<T> {
+    items: Vec<T>,
+    /// Cursor of the next page, absent on the last one
+    next_cursor: Option<i32>,
+}
+
+impl<T> PageView<T> {
+    pub fn new<E>(page: &Page<E>) -> Self
+    where
+        T: for<'e> From<&'e E>,
+    {
+        Self {
+            items: page.items.iter().map(T::from).collect(),
+            next_cursor: page.next_cursor,
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use sqlx::{
+    migrate::{MigrateError, Migrator},
     postgres::{PgConnectOptions, PgPoolOptions},
     PgPool,
 };
@@ -79,4 +80,15 @@ pub async fn create_pool(config: &DatabaseConfig) -> Result<PgPool, sqlx::Error>
     );
 
     Ok(pool)
-}
\ No newline at end of file
+}
+
+/// Schema migrations of `migrations/`, embedded at build time
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+/// Apply the migrations not applied yet.
+/// Fails when an applied migration no longer has the checksum it was applied with.
+pub async fn run_migrations(pool: &PgPool) -> Result<(), MigrateError> {
+    MIGRATOR.run(pool).await?;
+    tracing::info!(migrations = MIGRATOR.iter().count(), "Database migrated");
+    Ok(())
+}
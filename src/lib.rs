@@ -1,39 +1,61 @@
+use crate::media::{ByteRange, HttpOriginStore, MediaContent, MediaStore, MediaStoreConf, MediaStoreError};
+use crate::repository::api_key::{hash_api_key, ApiKey, ApiKeyConf, ApiKeyRepoT, ApiKeyScope};
 use crate::repository::artist::Artist;
+use crate::repository::artwork::Artwork;
+use crate::repository::drop::Drop;
+use crate::repository::drop_type::DropType;
+use crate::repository::import::ImportRepoT;
+use crate::repository::import_job::{FileImportReport, ImportJob, ImportJobState};
 use crate::repository::playlist::Playlist;
+use crate::repository::track::{Track, TrackRepoT};
 use crate::repository::{Repo, RepoByName};
-use crate::service::drop::DropService;
-use crate::service::DropServiceT;
+use crate::service::drop::{track_key, DropService, ImportPolicy};
+use crate::service::archive::extract_archive;
+use crate::service::artwork::{artwork_prefix, probe_artwork, ArtworkFormat, ArtworkSize, NO_ARTWORK_ID};
+use crate::service::audio::probe_audio_file;
+use crate::service::import::{import_drop_archive, ImportJobQueue, DEFAULT_IMPORT_WORKERS};
+use crate::service::watcher::DEFAULT_WATCH_STABLE_DELAY_MS;
+use crate::service::workspace::{ExtractionWorkspace, DEFAULT_QUARANTINE_RETENTION_HOURS};
+use axum::body::Body;
 use axum::extract::{ConnectInfo, Path, Request, State};
-use axum::http::header::SET_COOKIE;
-use axum::http::{HeaderValue, StatusCode};
+use axum::http::header::{ACCEPT_RANGES, AUTHORIZATION, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, LOCATION, RANGE, SET_COOKIE};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
 use axum::middleware::Next;
 use axum::response::{IntoResponse, Response};
-use axum::routing::get;
+use axum::routing::{get, post};
 use axum::{Json, Router};
-use chrono::NaiveDateTime;
+use chrono::{NaiveDateTime, Utc};
 use derive_new::new;
-use figment::providers::{Format, Toml};
+use figment::providers::{self, Format, Toml, Yaml};
 use figment::Figment;
-use flate2::read::GzDecoder;
+use http_body_util::BodyExt;
 use redis::Commands;
 use regex::Regex;
 use repository::RepoType;
 use serde::ser::SerializeStruct;
 use serde::{Deserialize, Serialize, Serializer};
-use service::drop::{DropRequest, ImportError};
+use service::drop::{find_drop_manifest, resolve_track_path, DropManifest, DropRequest, ImportError, ManifestError, ManifestFormat, DROP_MANIFEST_V1, DROP_MANIFEST_V2};
 use std::collections::HashMap;
 use std::fs;
 use std::fs::File;
 use std::net::{IpAddr, SocketAddr};
 use std::sync::{Arc, Mutex};
-use std::time::{SystemTime, UNIX_EPOCH};
-use tar::Archive;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
 use toml::de::Error;
 use uuid::Uuid;
 
 pub const TOKEN_NAME: &str = "dop_token";
 pub const TAG_ARCHIVE_PREFIX: &str = "drop_";
-
+pub const API_KEY_AUTH_SCHEME: &str = "Bearer ";
+pub const UPLOAD_STAGING_DIR: &str = ".staging";
+pub const SCRATCH_DIR: &str = ".scratch";
+pub const DEFAULT_MAX_UPLOAD_SIZE: u64 = 1024 * 1024 * 1024;
+pub const MACOS_RESOURCE_FORK_DIR: &str = "__MACOSX";
+
+pub mod admin;
+pub mod api;
+pub mod media;
 pub mod repository;
 pub mod service;
 pub mod config;
@@ -60,13 +82,35 @@ pub fn app(state: AppState) -> Router {
             "/track/{track_number}",
             get(track).route_layer(axum::middleware::from_fn_with_state(state.clone(), token_guard))
         )
+        .route(
+            "/track/{track_number}/content",
+            get(track_content).route_layer(axum::middleware::from_fn_with_state(state.clone(), token_guard))
+        )
+        .route(
+            "/artwork/{size}",
+            get(artwork).route_layer(axum::middleware::from_fn_with_state(state.clone(), token_guard))
+        )
+        .nest("/api", api::routes())
+        .nest("/admin", admin::routes(state.clone()))
         .route(
             "/{*path}",
             get(file).route_layer(axum::middleware::from_fn_with_state(state.clone(), token_guard))
         )
         .route(
             "/drop/import",
-            get(drop_import).route_layer(axum::middleware::from_fn_with_state(state.clone(), drop_import_guard)),
+            get(drop_import)
+                .post(drop_upload)
+                .route_layer(axum::middleware::from_fn_with_state(state.clone(), drop_import_guard)),
+        )
+        .route(
+            "/drop/import/jobs",
+            post(drop_import_job_create)
+                .route_layer(axum::middleware::from_fn_with_state(state.clone(), drop_import_guard)),
+        )
+        .route(
+            "/drop/import/jobs/{id}",
+            get(drop_import_job_get)
+                .route_layer(axum::middleware::from_fn_with_state(state.clone(), drop_import_guard)),
         )
         .route(
             "/",
@@ -80,9 +124,17 @@ pub fn app(state: AppState) -> Router {
 enum AppError {
     TagNotFound,
     Unauthorized,
+    Forbidden,
     InternalError,
     ResourceNotFound,
-    PlaylistNotFound
+    PlaylistNotFound,
+    PayloadTooLarge,
+    InvalidDropArchive,
+    DropArchiveAlreadyImported,
+    RangeNotSatisfiable,
+    Conflict,
+    ServiceUnavailable,
+    GatewayTimeout,
 }
 
 impl IntoResponse for AppError {
@@ -91,9 +143,31 @@ impl IntoResponse for AppError {
         match &self {
             AppError::TagNotFound => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
             AppError::Unauthorized => StatusCode::UNAUTHORIZED.into_response(),
+            AppError::Forbidden => StatusCode::FORBIDDEN.into_response(),
             AppError::InternalError => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
             AppError::ResourceNotFound => StatusCode::NOT_FOUND.into_response(),
             AppError::PlaylistNotFound => StatusCode::NOT_FOUND.into_response(),
+            AppError::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE.into_response(),
+            AppError::InvalidDropArchive => StatusCode::UNPROCESSABLE_ENTITY.into_response(),
+            AppError::DropArchiveAlreadyImported => StatusCode::CONFLICT.into_response(),
+            AppError::RangeNotSatisfiable => StatusCode::RANGE_NOT_SATISFIABLE.into_response(),
+            AppError::Conflict => StatusCode::CONFLICT.into_response(),
+            AppError::ServiceUnavailable => StatusCode::SERVICE_UNAVAILABLE.into_response(),
+            AppError::GatewayTimeout => StatusCode::GATEWAY_TIMEOUT.into_response(),
+        }
+    }
+}
+
+impl From<repository::RepositoryError> for AppError {
+    fn from(e: repository::RepositoryError) -> Self {
+        match e {
+            repository::RepositoryError::EntityNotFound => AppError::ResourceNotFound,
+            repository::RepositoryError::VersionConflict
+            | repository::RepositoryError::UniqueViolation
+            | repository::RepositoryError::ForeignKeyViolation => AppError::Conflict,
+            repository::RepositoryError::Timeout => AppError::GatewayTimeout,
+            repository::RepositoryError::Unavailable => AppError::ServiceUnavailable,
+            _ => AppError::InternalError,
         }
     }
 }
@@ -124,6 +198,11 @@ pub struct AppState {
     pub token_repo: Arc<dyn TokenRepo>,
     pub tag_repo: Arc<dyn TagRepo>,
     pub ip_repo: Arc<dyn IpRepo>,
+    pub api_key_repo: Arc<dyn ApiKeyRepoT>,
+    pub import_repo: Arc<dyn ImportRepoT>,
+    pub import_job_queue: Option<ImportJobQueue>,
+    /// Store the tag routes read the published media from
+    pub media_store: Arc<dyn MediaStore>,
     pub conf: Conf,
     pub entity_repositories: Vec<RepoType>,
     pub service_conf: ServiceConf
@@ -143,14 +222,11 @@ async fn tag(
             tag: tag_extracted.clone(),
         });
 
-        let mut uri_new = String::from(state.conf.redirect_uri);
-        uri_new.push_str("/tag/");
-        uri_new.push_str(&tag_extracted);
-        uri_new.push_str("/index.html");
-        println!("calling url {uri_new}");
-        return match reqwest::get(uri_new).await {
-            Ok(resp) => {
-                let mut response = resp.bytes().await.unwrap().into_response().into_body().into_response();
+        let key = format!("tag/{tag_extracted}/index.html");
+        println!("reading {key}");
+        return match state.media_store.get(&key).await {
+            Ok(content) => {
+                let mut response = content.into_response();
                 let header_value_str = format!("{}={}", TOKEN_NAME, uuid);
                 match HeaderValue::from_str(header_value_str.as_str()) {
                     Ok(header_value) => {
@@ -175,91 +251,144 @@ async fn tag(
     Err(AppError::TagNotFound)
 }
 
+// Former synchronous import, the archives are now queued as a job whose state is at `Location`
 async fn drop_import(
+    state: State<AppState>
+) -> Result<Response, AppError> {
+    let mut response = drop_import_job_create(state).await?;
+    response.headers_mut().insert("deprecation", HeaderValue::from_static("true"));
+    Ok(response)
+}
+
+// Queue the archives found in import_path, the import runs in background
+async fn drop_import_job_create(
     State(state): State<AppState>
 ) -> Result<Response, AppError> {
-    // check dir
-    let import_path = state.conf.import_path;
-    if import_path.is_empty() {
-        println!("import_path not set, can't import");
+    let Some(import_job_queue) = state.import_job_queue.as_ref() else {
+        println!("import job queue not started, can't import");
         return Ok(StatusCode::FAILED_DEPENDENCY.into_response());
-    }
-    let path = std::path::Path::new(&import_path);
+    };
+    let path = std::path::Path::new(state.conf.import_path());
     if !path.is_dir() {
         println!("import_path is not a directory, can't import");
         return Ok(StatusCode::FAILED_DEPENDENCY.into_response());
     }
-    // look for files
-    let files_to_import = look_for_drop_files_at_path(&path);
-    if files_to_import.is_empty() {
-        println!("no files to import at import path");
-        let response = Response::builder()
-            .status(StatusCode::OK)
-            .body("{imported: 0}");
-        return Ok(StatusCode::OK.into_response());
-    }
-    // check files
-    for file in files_to_import {
-        if let Ok((drop_import_path, drop_request)) = check_drop_file(&file) {
-            state.service_conf.drop_service.create_drop(
-                &drop_import_path,
-                drop_request,
-                &state.conf.web_server_path.as_ref().unwrap()
-            )
-                .await
-                .or(Err(AppError::InternalError))?;
-        }
-    }
-
-    Ok(StatusCode::OK.into_response())
-}
-
-pub fn check_drop_file(file: &str) -> Result<(String, DropRequest), ImportError> {
-    if !file.ends_with(".tar.gz") {
-        println!("file is not a tar.gz file");
-        return Err(ImportError::InvalidFileExtension)
-    }
-    // create temporary dir
-    let file_path = std::path::Path::new(file);
-    let file_parent_option = file_path.parent();
-    if file_parent_option.is_none() {
-        return Err(ImportError::NoFileParentDirectory);
-    }
-    let file_parent_path = file_parent_option.unwrap();
-    let in_ms = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .or(Err(ImportError::InvalidUnixEpoch))?
-        .as_millis();
-    let untar_path_str = file_parent_path.to_str()
-        .ok_or(ImportError::InvalidParentDirectory)?;
-    let mut untar_path_string = String::from(untar_path_str);
-    untar_path_string.push('_');
-    untar_path_string.push_str(&*in_ms.to_string());
-    fs::create_dir(&untar_path_string).or(Err(ImportError::CantCreateDropUntarDirectory))?;
-
-    /*// copy to untar directory
-    let mut copy_path_string = untar_path_string.clone();
-    copy_path_string.push_str("/");
-    copy_path_string.push_str(file_path.file_name().unwrap().to_str().unwrap());
-    fs::copy(file, &copy_path_string).or(Err(ImportError::CantCopyToUntarDirectory))?;
-*/
-    // untar
-    let tar_gz = File::open(file_path)
-        .or(Err(ImportError::CantOpenDropFile))?;
-    let tar = GzDecoder::new(tar_gz);
-    let mut archive = Archive::new(tar);
-    archive.unpack(&untar_path_string.as_str()).or(Err(ImportError::CantUnpackDropFile))?;
+    let import_job = import_job_queue.enqueue(look_for_drop_files_at_path(path))
+        .await?;
+    let location = format!("/drop/import/jobs/{}", import_job.id());
+    Ok((StatusCode::ACCEPTED, [(LOCATION, location)], Json(import_job)).into_response())
+}
+
+async fn drop_import_job_get(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Response, AppError> {
+    let Some(import_job_queue) = state.import_job_queue.as_ref() else {
+        return Err(AppError::ResourceNotFound);
+    };
+    let import_job = import_job_queue.get(id).await?;
+    Ok((import_job_status(&import_job), Json(import_job)).into_response())
+}
+
+// 200 while the job runs and when no archive failed, 207 when some did, otherwise the status of the failures
+fn import_job_status(import_job: &ImportJob) -> StatusCode {
+    match import_job.state() {
+        ImportJobState::PartiallySucceeded => StatusCode::MULTI_STATUS,
+        ImportJobState::Failed if import_job.files().iter().any(FileImportReport::server_error) => StatusCode::INTERNAL_SERVER_ERROR,
+        ImportJobState::Failed => StatusCode::UNPROCESSABLE_ENTITY,
+        _ => StatusCode::OK,
+    }
+}
+
+// Upload of a drop archive as raw request body, the archive is streamed to the staging
+// directory then goes through the same checks and import as the files found in import_path
+async fn drop_upload(
+    State(state): State<AppState>,
+    req: Request,
+) -> Result<Response, AppError> {
+    let Some(web_server_path) = state.conf.web_server_path.as_ref() else {
+        println!("web_server_path not set, can't import");
+        return Ok(StatusCode::FAILED_DEPENDENCY.into_response());
+    };
+    let max_upload_size = state.conf.max_upload_size();
+    if let Some(content_length) = req.headers().get(CONTENT_LENGTH)
+        && let Ok(content_length) = content_length.to_str()
+        && let Ok(content_length) = content_length.parse::<u64>()
+        && content_length > max_upload_size {
+        return Err(AppError::PayloadTooLarge);
+    }
+
+    let staging_path = state.conf.staging_path();
+    tokio::fs::create_dir_all(&staging_path).await.or(Err(AppError::InternalError))?;
+    // the archive format is detected from its content, the staged file has no extension
+    let staged_file = format!("{}/{}upload_{}", staging_path, TAG_ARCHIVE_PREFIX, Uuid::new_v4().simple());
+    let upload_result = stream_body_to_file(req.into_body(), &staged_file, max_upload_size).await;
+    let import_result = match upload_result {
+        Ok(()) => import_drop_archive(
+            &state.service_conf.drop_service,
+            state.import_repo.as_ref(),
+            &state.conf.extraction_workspace(),
+            &staged_file,
+            state.conf.import_path(),
+            web_server_path
+        )
+            .await
+            .map_err(|import_error| match import_error {
+                ImportError::DropFileAlreadyImported => AppError::DropArchiveAlreadyImported,
+                ImportError::DuplicateEntity | ImportError::MissingReferencedEntity => AppError::Conflict,
+                ImportError::DatabaseTimeout => AppError::GatewayTimeout,
+                ImportError::DatabaseUnavailable => AppError::ServiceUnavailable,
+                _ if import_error.is_create_phase_error() => AppError::InternalError,
+                _ => {
+                    println!("uploaded drop archive is invalid: {:?}", import_error);
+                    AppError::InvalidDropArchive
+                }
+            }),
+        Err(app_error) => Err(app_error),
+    };
+    // an imported archive has been moved to the processed or failed directory
+    if tokio::fs::try_exists(&staged_file).await.unwrap_or(false)
+        && tokio::fs::remove_file(&staged_file).await.is_err() {
+        println!("can't remove staged drop archive {staged_file}");
+    }
+
+    let created_drop = import_result?;
+    Ok((StatusCode::CREATED, Json(created_drop)).into_response())
+}
+
+async fn stream_body_to_file(mut body: Body, file_path: &str, max_size: u64) -> Result<(), AppError> {
+    let mut file = tokio::fs::File::create(file_path).await.or(Err(AppError::InternalError))?;
+    let mut written: u64 = 0;
+    while let Some(frame) = body.frame().await {
+        let frame = frame.or(Err(AppError::InternalError))?;
+        if let Ok(data) = frame.into_data() {
+            written += data.len() as u64;
+            if written > max_size {
+                return Err(AppError::PayloadTooLarge);
+            }
+            file.write_all(&data).await.or(Err(AppError::InternalError))?;
+        }
+    }
+    file.flush().await.or(Err(AppError::InternalError))
+}
+
+/// Unpack the archive `file` into `untar_dir`, which belongs to the caller, then check the drop it holds
+pub fn check_drop_file(file: &str, untar_dir: &std::path::Path) -> Result<(String, DropManifest), ImportError> {
+    let untar_path_str = untar_dir.to_str().ok_or(ImportError::InvalidParentDirectory)?;
+
+    // unpack, whatever the archive format
+    let archive_format = extract_archive(std::path::Path::new(file), untar_dir)?;
+    println!("{file} unpacked as {:?}", archive_format);
 
     // check files in untar dir
-    check_unarchived_drop_files(&untar_path_string)
+    check_unarchived_drop_files(untar_path_str)
 }
 
-pub fn check_unarchived_drop_files(untar_path_string: &str) -> Result<(String, DropRequest), ImportError> {
-    // check if drop.txt is present
-    let drop_txt_path = untar_path_string.to_owned() + "/drop.txt";
-    let mut drop_result = create_drop_request_from_toml_file(&drop_txt_path);
+pub fn check_unarchived_drop_files(untar_path_string: &str) -> Result<(String, DropManifest), ImportError> {
+    // check if a manifest is present
     let mut untar_path_string = String::from(untar_path_string);
-    if drop_result.is_err() {
+    let mut manifest_result = find_drop_manifest(std::path::Path::new(&untar_path_string));
+    if matches!(manifest_result, Ok(None)) {
         fs::read_dir(&untar_path_string)
             .or(Err(ImportError::CantReadUntarDirectory))?
             .for_each(|dir_entry| {
@@ -267,7 +396,8 @@ pub fn check_unarchived_drop_files(untar_path_string: &str) -> Result<(String, D
                 if let Ok(file_type) = dir_entry.file_type() {
                     if file_type.is_dir() {
                         if let Some(file_name) = dir_entry.file_name().to_str() {
-                            if !file_name.starts_with(".") {
+                            // zips made by macOS Finder carry their resource forks in __MACOSX
+                            if !file_name.starts_with(".") && file_name != MACOS_RESOURCE_FORK_DIR {
                                 if let Some(dir_entry_path) = dir_entry.path().to_str() {
                                     untar_path_string = String::from(dir_entry_path);
                                 }
@@ -277,19 +407,60 @@ pub fn check_unarchived_drop_files(untar_path_string: &str) -> Result<(String, D
                 }
             }
         });
-        let drop_txt_path = untar_path_string.to_owned() + "/drop.txt";
-        drop_result = create_drop_request_from_toml_file(&drop_txt_path);
-        if drop_result.is_err() {
-            return Err(ImportError::NoDropDescriptionFileFound);
-        }
+        manifest_result = find_drop_manifest(std::path::Path::new(&untar_path_string));
     }
-
-    let drop = drop_result.unwrap();
-    // check if tracks are present and valid files
+    let drop_result = match manifest_result {
+        Ok(Some((manifest_path, manifest_format))) => create_drop_manifest_from_file(&manifest_path, manifest_format),
+        Ok(None) => return Err(ImportError::NoDropDescriptionFileFound),
+        Err(manifest_error) => Err(manifest_error),
+    };
+
+    let mut drop = drop_result.map_err(|manifest_error| match manifest_error {
+        ManifestError::Unreadable(_) => ImportError::NoDropDescriptionFileFound,
+        ManifestError::UnsupportedVersion(version) => {
+            println!("manifest version {version} is not supported");
+            ImportError::UnsupportedDropManifestVersion
+        }
+        ManifestError::InvalidFields(field_errors) => {
+            for field_error in field_errors {
+                println!("invalid manifest: {field_error}");
+            }
+            ImportError::InvalidDropManifest
+        }
+        ManifestError::SeveralManifests(manifests) => {
+            println!("several manifests found: {}", manifests.join(", "));
+            ImportError::SeveralDropManifests
+        }
+    })?;
+    // check if tracks are present and audio files
+    let drop_dir = std::path::Path::new(&untar_path_string);
+    let mut audio_probes = Vec::with_capacity(drop.tracks().len());
     for track in drop.tracks() {
-        if File::open(untar_path_string.to_owned() + "/" + &*track).is_err() {
+        let track_path = resolve_track_path(drop_dir, track.file())?;
+        if File::open(&track_path).is_err() {
             return Err(ImportError::MissingTrackInDropArchive)
         }
+        let audio_probe = probe_audio_file(&track_path).inspect_err(|_| {
+            println!("{} is not a supported audio file", track.file());
+        })?;
+        audio_probes.push(audio_probe);
+    }
+    drop.fill_missing(&audio_probes);
+    // the fields read from the tracks are checked too, e.g. a probed duration against the cue points
+    drop.validate().map_err(|field_errors| {
+        for field_error in field_errors {
+            println!("invalid manifest once filled from the tracks: {field_error}");
+        }
+        ImportError::InvalidDropManifest
+    })?;
+    if let Some(artwork) = drop.artwork() {
+        let artwork_path = resolve_track_path(drop_dir, artwork)?;
+        if File::open(&artwork_path).is_err() {
+            return Err(ImportError::MissingArtworkInDropArchive)
+        }
+        probe_artwork(&artwork_path).inspect_err(|_| {
+            println!("{artwork} is not a supported image");
+        })?;
     }
     Ok((untar_path_string, drop))
 }
@@ -346,15 +517,81 @@ async fn tag_guard(
     AppError::TagNotFound.into_response()
 }
 
+// Route guard for /drop/import: loopback callers are trusted, other hosts need an api key
+// with the import scope. Without any key the endpoint stays hidden behind a 404.
 async fn drop_import_guard(
+    State(state): State<AppState>,
     ConnectInfo(connect_info): ConnectInfo<SocketAddr>,
     req: Request,
     next: Next
 ) -> Response {
-    if !connect_info.ip().to_string().starts_with("127") {
+    if connect_info.ip().to_string().starts_with("127") {
+        return next.run(req).await;
+    }
+    if extract_api_key(req.headers()).is_none() {
         return AppError::ResourceNotFound.into_response();
     }
-    next.run(req).await
+    if !check_ip(connect_info.ip(), &state.ip_repo, state.conf.max_attempts) {
+        return AppError::Unauthorized.into_response();
+    }
+    match check_api_key(&state, req.headers(), ApiKeyScope::Import).await {
+        Ok(_) => next.run(req).await,
+        Err(app_error) => {
+            increment_ip_nb_bad_attempts(&connect_info.ip(), &state.ip_repo);
+            app_error.into_response()
+        }
+    }
+}
+
+// Route guard for operator endpoints, the api key must be valid and grant `scope`
+pub async fn api_key_guard(
+    State((state, scope)): State<(AppState, ApiKeyScope)>,
+    ConnectInfo(connect_info): ConnectInfo<SocketAddr>,
+    req: Request,
+    next: Next
+) -> Response {
+    if !check_ip(connect_info.ip(), &state.ip_repo, state.conf.max_attempts) {
+        return AppError::Unauthorized.into_response();
+    }
+    match check_api_key(&state, req.headers(), scope).await {
+        Ok(_) => next.run(req).await,
+        Err(app_error) => {
+            increment_ip_nb_bad_attempts(&connect_info.ip(), &state.ip_repo);
+            app_error.into_response()
+        }
+    }
+}
+
+fn extract_api_key(headers: &HeaderMap) -> Option<&str> {
+    headers.get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix(API_KEY_AUTH_SCHEME)
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+}
+
+async fn check_api_key(state: &AppState, headers: &HeaderMap, scope: ApiKeyScope) -> Result<ApiKey, AppError> {
+    let key = extract_api_key(headers).ok_or(AppError::Unauthorized)?;
+    let api_key = state.api_key_repo.get_by_hash(&hash_api_key(key))
+        .await
+        .map_err(|e| match e {
+            repository::RepositoryError::EntityNotFound => AppError::Unauthorized,
+            e => AppError::from(e),
+        })?;
+    let now = Utc::now().naive_utc();
+    if api_key.is_expired(now) {
+        println!("api key {} is expired", api_key.name());
+        return Err(AppError::Unauthorized);
+    }
+    if !api_key.has_scope(scope) {
+        println!("api key {} doesn't have the {} scope", api_key.name(), scope.as_str());
+        return Err(AppError::Forbidden);
+    }
+    if state.api_key_repo.touch_last_used(api_key.id(), now).await.is_err() {
+        println!("can't update last use of api key {}", api_key.name());
+    }
+    Ok(api_key)
 }
 
 fn increment_ip_nb_bad_attempts(ip_addr: &IpAddr, ip_repo: &Arc<dyn IpRepo>) {
@@ -429,20 +666,9 @@ async fn play(
             if let Ok(token_uuid_requested) = Uuid::parse_str(token_str) {
                 let token_opt = state.token_repo.get_token(token_uuid_requested);
                 if let Some(token) = token_opt {
-                    let mut uri_new = String::from(state.conf.redirect_uri);
-                    uri_new.push_str("/tag/");
-                    uri_new.push_str(&token.tag);
-                    uri_new.push_str("/playlist.m3u8");
-                    println!("calling {uri_new}");
-                    return match reqwest::get(uri_new).await {
-                        Ok(resp) => {
-                            Ok(resp.bytes().await.unwrap().into_response())
-                        },
-                        Err(_) => {
-                            increment_ip_nb_bad_attempts(&connect_info.ip(), &state.ip_repo);
-                            Err(AppError::TagNotFound)
-                        },
-                    }
+                    let key = format!("tag/{}/playlist.m3u8", token.tag);
+                    println!("reading {key}");
+                    return media_response(state.media_store.as_ref(), &state, &connect_info, &key, None).await;
                 }
             }
         }
@@ -462,22 +688,16 @@ async fn track(
             if let Ok(token_uuid_requested) = Uuid::parse_str(token_str) {
                 let token_opt = state.token_repo.get_token(token_uuid_requested);
                 if let Some(token) = token_opt {
-                    let mut uri_new = String::from(state.conf.redirect_uri);
-                    uri_new.push_str("/tag/");
-                    uri_new.push_str(&token.tag);
-                    uri_new.push_str("/playlist_");
-                    uri_new.push_str(&track_number.to_string());
-                    uri_new.push_str(".m3u8");
-                    println!("calling {uri_new}");
-                    return match reqwest::get(uri_new).await {
-                        Ok(resp) => {
-                            Ok(resp.bytes().await.unwrap().into_response())
-                        },
-                        Err(_) => {
-                            increment_ip_nb_bad_attempts(&connect_info.ip(), &state.ip_repo);
-                            Err(AppError::TagNotFound)
-                        },
+                    // a tag bound to a drop is served the bytes of the tracks recorded at import
+                    if let Some(drop_id) = state.tag_repo.get(token.tag.clone()).and_then(|tag| tag.drop_id()) {
+                        let range = headers.get(RANGE)
+                            .and_then(|range| range.to_str().ok())
+                            .and_then(ByteRange::from_header);
+                        return drop_track_response(&state, &connect_info, drop_id, track_number, range).await;
                     }
+                    let key = format!("tag/{}/playlist_{track_number}.m3u8", token.tag);
+                    println!("reading {key}");
+                    return media_response(state.media_store.as_ref(), &state, &connect_info, &key, None).await;
                 }
             }
         }
@@ -506,25 +726,13 @@ async fn file(
             if let Ok(token_uuid_requested) = Uuid::parse_str(token_str) {
                 let token_opt = state.token_repo.get_token(token_uuid_requested);
                 if let Some(token) = token_opt {
-                    let mut uri_new = String::from(state.conf.redirect_uri);
-                    uri_new.push_str("/tag/");
-                    uri_new.push_str(&token.tag);
-                    uri_new.push('/');
-                    uri_new.push_str(path.as_str());
-
-                    println!("calling {uri_new}");
-                    return match reqwest::get(uri_new).await {
-                        Ok(resp) => {
-                            resp.headers().iter().for_each(|(header_name, header_value)| {
-                                println!("header: {:#?} - {:#?}", header_name, header_value);
-                            });
-                            Ok(resp.bytes().await.unwrap().into_response().into_body().into_response())
-                        },
-                        Err(_) => {
-                            increment_ip_nb_bad_attempts(&connect_info.ip(), &state.ip_repo);
-                            Err(AppError::TagNotFound)
-                        },
-                    }
+                    let key = format!("tag/{}/{path}", token.tag);
+                    // players seek in the tracks with range requests
+                    let range = headers.get(RANGE)
+                        .and_then(|range| range.to_str().ok())
+                        .and_then(ByteRange::from_header);
+                    println!("reading {key}");
+                    return media_response(state.media_store.as_ref(), &state, &connect_info, &key, range).await;
                 }
             }
         }
@@ -543,13 +751,19 @@ async fn playlist(
         && let Ok(token_uuid_requested) = Uuid::parse_str(token_str)
         && let Some(token) = state.token_repo.get_token(token_uuid_requested) {
 
-        let mut uri_new = String::from(&state.conf.redirect_uri);
-        uri_new.push_str("/tag/");
-        uri_new.push_str(&token.tag);
-        uri_new.push_str("/playlist.toml");
-        println!("checking if there is playlist info at uri: {uri_new}");
-        return if let Ok(resp) = reqwest::get(uri_new).await
-            && let Ok(text) = resp.text().await
+        // a tag bound to a drop is described by the database rather than by its playlist.toml
+        if let Some(drop_id) = state.tag_repo.get(token.tag.clone()).and_then(|tag| tag.drop_id()) {
+            return match drop_playlist_data(&state, drop_id).await {
+                Ok(playlist_data) => Json(playlist_data).into_response(),
+                Err(e) => e.into_response(),
+            }
+        }
+
+        let key = format!("tag/{}/playlist.toml", token.tag);
+        println!("checking if there is playlist info at {key}");
+        return if let Ok(content) = state.media_store.get(&key).await
+            && let Ok(content) = content.into_bytes().await
+            && let Ok(text) = String::from_utf8(content)
             && text.len() > 0
             && let Ok(playlist_data) = PlaylistData::create_from_toml_text(text.as_str()) {
             Json(playlist_data).into_response()
@@ -561,10 +775,176 @@ async fn playlist(
     AppError::Unauthorized.into_response()
 }
 
+// The media is streamed rather than read whole, with its length when the store tells it
+impl IntoResponse for MediaContent {
+    fn into_response(self) -> Response {
+        let mut response = (
+            [(CONTENT_TYPE, HeaderValue::from_static("application/octet-stream"))],
+            Body::from_stream(self.stream),
+        ).into_response();
+        if let Some(size) = self.size {
+            response.headers_mut().insert(CONTENT_LENGTH, HeaderValue::from(size));
+        }
+        response
+    }
+}
+
+// Media of `media_store`, the part of it in `range` when there is one
+async fn media_response(
+    media_store: &dyn MediaStore,
+    state: &AppState,
+    connect_info: &SocketAddr,
+    key: &str,
+    range: Option<ByteRange>,
+) -> Result<Response, AppError> {
+    let result = match range {
+        Some(range) => media_store.get_range(key, range).await.map(|ranged_content| {
+            (
+                StatusCode::PARTIAL_CONTENT,
+                [(CONTENT_RANGE, ranged_content.content_range()), (ACCEPT_RANGES, "bytes".to_string())],
+                ranged_content.content,
+            ).into_response()
+        }),
+        None => media_store.get(key).await.map(IntoResponse::into_response),
+    };
+    result.map_err(|e| {
+        println!("can't read {key}: {e}");
+        match e {
+            MediaStoreError::InvalidRange => AppError::RangeNotSatisfiable,
+            MediaStoreError::NotFound => {
+                increment_ip_nb_bad_attempts(&connect_info.ip(), &state.ip_repo);
+                AppError::ResourceNotFound
+            }
+            _ => {
+                increment_ip_nb_bad_attempts(&connect_info.ip(), &state.ip_repo);
+                AppError::TagNotFound
+            }
+        }
+    })
+}
+
+// Playlist data of a published drop from its playlist, its artist and its tracks
+async fn drop_playlist_data(state: &AppState, drop_id: i32) -> Result<PlaylistData, AppError> {
+    let drop_service = state.service_conf.drop_service();
+    let drop = drop_service.drop_repository().get(drop_id).await?;
+    if !drop.published() {
+        return Err(AppError::ResourceNotFound);
+    }
+    let playlist = drop_service.playlist_repository().get(drop.playlist_id()).await?;
+    let artist = drop_service.artist_repository().get(drop.artist_id()).await?;
+    let tracks = drop_service.track_repository().get_by_playlist(playlist.id()).await?;
+    let playlist_data = PlaylistData::new(
+        artist.name().to_string(),
+        playlist.name().to_string(),
+        // a track without title is named after its file in the archive
+        tracks.iter().map(|track| track.title().unwrap_or(track.source_file()).to_string()).collect()
+    );
+    Ok(match drop.drop_type() {
+        Some(drop_type) => playlist_data.with_drop_type(drop_type),
+        None => playlist_data,
+    })
+}
+
+// Track at `track_number` of the playlist of a published drop, with its drop
+async fn drop_track(state: &AppState, drop_id: i32, track_number: u8) -> Result<(Drop, Track), AppError> {
+    let drop_service = state.service_conf.drop_service();
+    let drop = drop_service.drop_repository().get(drop_id).await?;
+    // the content store keeps the bytes of an unpublished drop, they may be shared with a published one
+    if !drop.published() {
+        return Err(AppError::ResourceNotFound);
+    }
+    let track = drop_service
+        .track_repository()
+        .get_by_playlist(drop.playlist_id())
+        .await?
+        .into_iter()
+        .find(|track| track.position() == i32::from(track_number))
+        .ok_or(AppError::ResourceNotFound)?;
+    Ok((drop, track))
+}
+
+// Bytes of a track of the drop the token's tag is bound to, read from the store the import wrote them to
+async fn track_content(
+    Path(track_number): Path<u8>,
+    State(state): State<AppState>,
+    ConnectInfo(connect_info): ConnectInfo<SocketAddr>,
+    req: Request,
+) -> Result<Response, AppError> {
+    let token = req.headers().get(TOKEN_NAME)
+        .and_then(|header_token| header_token.to_str().ok())
+        .and_then(|token_str| Uuid::parse_str(token_str).ok())
+        .and_then(|token_uuid_requested| state.token_repo.get_token(token_uuid_requested))
+        .ok_or(AppError::Unauthorized)?;
+    let drop_id = state.tag_repo.get(token.tag)
+        .and_then(|tag| tag.drop_id())
+        .ok_or(AppError::ResourceNotFound)?;
+    let range = req.headers().get(RANGE)
+        .and_then(|range| range.to_str().ok())
+        .and_then(ByteRange::from_header);
+    drop_track_response(&state, &connect_info, drop_id, track_number, range).await
+}
+
+// Bytes of the track at `track_number` of a drop, at the key its `track` row gives
+async fn drop_track_response(
+    state: &AppState,
+    connect_info: &SocketAddr,
+    drop_id: i32,
+    track_number: u8,
+    range: Option<ByteRange>,
+) -> Result<Response, AppError> {
+    let web_server_path = state.conf.web_server_path().ok_or(AppError::ResourceNotFound)?;
+    let (drop, track) = drop_track(state, drop_id, track_number).await?;
+    let track_key = track_key(&drop, &track).map_err(|e| {
+        println!("can't read track {track_number} of drop {drop_id}: {e}");
+        AppError::InternalError
+    })?;
+    let media_store = state.service_conf.drop_service().media_store(web_server_path);
+    media_response(media_store.as_ref(), state, connect_info, &track_key, range).await
+}
+
+// Artwork of the drop the token's tag is bound to, in one of the sizes generated at import
+async fn artwork(
+    Path(size): Path<String>,
+    State(state): State<AppState>,
+    req: Request,
+) -> Result<Response, AppError> {
+    let artwork_size = ArtworkSize::from_name(&size).ok_or(AppError::ResourceNotFound)?;
+    let token = req.headers().get(TOKEN_NAME)
+        .and_then(|header_token| header_token.to_str().ok())
+        .and_then(|token_str| Uuid::parse_str(token_str).ok())
+        .and_then(|token_uuid_requested| state.token_repo.get_token(token_uuid_requested))
+        .ok_or(AppError::Unauthorized)?;
+    let drop_id = state.tag_repo.get(token.tag)
+        .and_then(|tag| tag.drop_id())
+        .ok_or(AppError::ResourceNotFound)?;
+    let web_server_path = state.conf.web_server_path().ok_or(AppError::ResourceNotFound)?;
+
+    // the artwork is read from the store the import wrote it to
+    let drop_service = state.service_conf.drop_service();
+    let drop = drop_service.drop_repository().get(drop_id).await?;
+    if !drop.published() || drop.artwork_id() == NO_ARTWORK_ID {
+        return Err(AppError::ResourceNotFound);
+    }
+    let artwork = drop_service.artwork_repository().get(drop.artwork_id()).await?;
+    let artwork_format = ArtworkFormat::from_mime_type(artwork.mime_type()).ok_or(AppError::InternalError)?;
+    let artwork_key = format!("{}{}", artwork_prefix(artwork.id()), artwork_size.file_name(artwork_format));
+    let content = drop_service.media_store(web_server_path).get(&artwork_key).await.map_err(|e| {
+        println!("can't read artwork {artwork_key}: {e}");
+        match e {
+            MediaStoreError::NotFound => AppError::ResourceNotFound,
+            _ => AppError::InternalError,
+        }
+    })?;
+    Ok(([(CONTENT_TYPE, artwork_size.format(artwork_format).mime_type())], content).into_response())
+}
+
 pub trait TokenRepo: Send + Sync {
     fn get_token(&self, id: Uuid) -> Option<Token>;
 
     fn save_token(&self, token: &Token);
+
+    /// Removes the tokens of a tag, gives how many there were
+    fn remove_by_tag(&self, tag: &str) -> usize;
 }
 
 #[derive(Debug, Clone, Default)]
@@ -599,6 +979,13 @@ impl TokenRepo for InMemoryTokenRepo {
     fn save_token(&self, token: &Token) {
         self.map.lock().unwrap().insert(token.id, token.clone());
     }
+
+    fn remove_by_tag(&self, tag: &str) -> usize {
+        let mut map = self.map.lock().unwrap();
+        let nb_tokens = map.len();
+        map.retain(|_, token| token.tag != tag);
+        nb_tokens - map.len()
+    }
 }
 
 impl TokenRepo for TokenRepoDB {
@@ -637,12 +1024,30 @@ impl TokenRepo for TokenRepoDB {
             );
         }
     }
+
+    fn remove_by_tag(&self, tag: &str) -> usize {
+        let mut conn = match self.client.get_connection() {
+            Ok(c) => c,
+            Err(_) => return 0,
+        };
+        let keys: Vec<String> = match conn.scan_match::<_, String>("token:*") {
+            Ok(keys) => keys.collect(),
+            Err(_) => return 0,
+        };
+        keys.iter()
+            .filter(|key| conn.hget::<_, _, Option<String>>(key.as_str(), "tag").ok().flatten().as_deref() == Some(tag)
+                && conn.del::<_, ()>(key.as_str()).is_ok())
+            .count()
+    }
 }
 
 pub trait TagRepo: Send + Sync {
     fn get(&self, tag: String) -> Option<Tag>;
 
     fn save(&self, tag: &Tag);
+
+    /// Removes the tags bound to a drop, gives their names
+    fn remove_by_drop(&self, drop_id: i32) -> Vec<String>;
 }
 
 #[derive(Debug, Clone, Default)]
@@ -658,6 +1063,16 @@ impl TagRepo for InMemoryTagRepo {
     fn save(&self, tag: &Tag) {
         self.map.lock().unwrap().insert(tag.id.clone(), tag.clone());
     }
+
+    fn remove_by_drop(&self, drop_id: i32) -> Vec<String> {
+        let mut map = self.map.lock().unwrap();
+        let tags: Vec<String> = map.values()
+            .filter(|tag| tag.drop_id == Some(drop_id))
+            .map(|tag| tag.id.clone())
+            .collect();
+        tags.iter().for_each(|tag| { map.remove(tag); });
+        tags
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -687,6 +1102,20 @@ impl TagRepoDB {
 pub struct Tag {
     id: String,
     create_date: NaiveDateTime,
+    #[new(default)]
+    drop_id: Option<i32>,
+}
+
+impl Tag {
+    /// Drop whose playlist, tracks and artwork are served to the tokens of this tag, only while it is published
+    pub fn drop_id(&self) -> Option<i32> {
+        self.drop_id
+    }
+
+    pub fn with_drop_id(mut self, drop_id: i32) -> Self {
+        self.drop_id = Some(drop_id);
+        self
+    }
 }
 
 impl TagRepo for TagRepoDB {
@@ -697,6 +1126,7 @@ impl TagRepo for TagRepoDB {
         };
         let key = format!("tag:{}", tag);
         let create_date_s: Option<String> = conn.hget(&key, "create_date").ok();
+        let drop_id: Option<i32> = conn.hget(&key, "drop_id").ok().flatten();
 
         match create_date_s {
             Some(cd_str) => {
@@ -704,6 +1134,7 @@ impl TagRepo for TagRepoDB {
                 Some(Tag {
                     id: tag,
                     create_date,
+                    drop_id,
                 })
             }
             _ => None,
@@ -720,8 +1151,28 @@ impl TagRepo for TagRepoDB {
                     ("create_date", tag.create_date.format("%Y-%m-%d %H:%M:%S").to_string())
                 ],
             );
+            let _: redis::RedisResult<()> = match tag.drop_id {
+                Some(drop_id) => conn.hset(&key, "drop_id", drop_id),
+                None => conn.hdel(&key, "drop_id"),
+            };
         }
     }
+
+    fn remove_by_drop(&self, drop_id: i32) -> Vec<String> {
+        let mut conn = match self.client.get_connection() {
+            Ok(c) => c,
+            Err(_) => return Vec::new(),
+        };
+        let keys: Vec<String> = match conn.scan_match::<_, String>("tag:*") {
+            Ok(keys) => keys.collect(),
+            Err(_) => return Vec::new(),
+        };
+        keys.iter()
+            .filter(|key| conn.hget::<_, _, Option<i32>>(key.as_str(), "drop_id").ok().flatten() == Some(drop_id)
+                && conn.del::<_, ()>(key.as_str()).is_ok())
+            .filter_map(|key| key.strip_prefix("tag:").map(str::to_string))
+            .collect()
+    }
 }
 
 #[derive(Debug, Clone, new)]
@@ -866,6 +1317,39 @@ pub struct Conf {
     import_path: String,
     db_conf: Option<DbConf>,
     web_server_path: Option<String>,
+    #[serde(default)]
+    #[new(default)]
+    api_keys: Vec<ApiKeyConf>,
+    #[serde(default)]
+    #[new(default)]
+    staging_path: Option<String>,
+    #[serde(default)]
+    #[new(default)]
+    max_upload_size: Option<u64>,
+    #[serde(default)]
+    #[new(default)]
+    import_workers: Option<usize>,
+    #[serde(default)]
+    #[new(default)]
+    watch_import_path: Option<bool>,
+    #[serde(default)]
+    #[new(default)]
+    watch_stable_delay_ms: Option<u64>,
+    #[serde(default)]
+    #[new(default)]
+    scratch_path: Option<String>,
+    #[serde(default)]
+    #[new(default)]
+    quarantine_retention_hours: Option<u64>,
+    #[serde(default)]
+    #[new(default)]
+    tag_drops: HashMap<String, i32>,
+    #[serde(default)]
+    #[new(default)]
+    import_policy: ImportPolicy,
+    #[serde(default)]
+    #[new(default)]
+    media_store: Option<MediaStoreConf>,
 }
 
 impl Conf {
@@ -888,9 +1372,102 @@ impl Conf {
 
     pub fn import_path(&self) -> &str { &self.import_path }
 
+    pub fn web_server_path(&self) -> Option<&str> {
+        self.web_server_path.as_deref()
+    }
+
     pub fn db_conf(&self) -> Option<&DbConf> {
         self.db_conf.as_ref()
     }
+
+    pub fn api_keys(&self) -> &Vec<ApiKeyConf> {
+        &self.api_keys
+    }
+
+    /// Drop each tag is bound to, e.g. `tag_drops = { jdznjevb = 12 }`
+    pub fn tag_drops(&self) -> &HashMap<String, i32> {
+        &self.tag_drops
+    }
+
+    /// Policy of the imports whose manifest gives none, strict by default
+    pub fn import_policy(&self) -> ImportPolicy {
+        self.import_policy
+    }
+
+    /// Directory receiving uploaded archives, defaults to `.staging` inside import_path
+    pub fn staging_path(&self) -> String {
+        match &self.staging_path {
+            Some(staging_path) => staging_path.clone(),
+            None => format!("{}/{}", self.import_path, UPLOAD_STAGING_DIR),
+        }
+    }
+
+    pub fn max_upload_size(&self) -> u64 {
+        self.max_upload_size.unwrap_or(DEFAULT_MAX_UPLOAD_SIZE)
+    }
+
+    pub fn import_workers(&self) -> usize {
+        self.import_workers.unwrap_or(DEFAULT_IMPORT_WORKERS)
+    }
+
+    pub fn watch_import_path(&self) -> bool {
+        self.watch_import_path.unwrap_or(false)
+    }
+
+    /// How long an archive size must not change before it is imported by the watcher
+    pub fn watch_stable_delay(&self) -> Duration {
+        Duration::from_millis(self.watch_stable_delay_ms.unwrap_or(DEFAULT_WATCH_STABLE_DELAY_MS))
+    }
+
+    /// Directory where archives are unpacked, defaults to `.scratch` inside import_path
+    pub fn scratch_path(&self) -> String {
+        match &self.scratch_path {
+            Some(scratch_path) => scratch_path.clone(),
+            None => format!("{}/{}", self.import_path, SCRATCH_DIR),
+        }
+    }
+
+    /// How long the extraction of a failed import is kept in quarantine
+    pub fn quarantine_retention(&self) -> Duration {
+        Duration::from_secs(3600 * self.quarantine_retention_hours.unwrap_or(DEFAULT_QUARANTINE_RETENTION_HOURS))
+    }
+
+    pub fn extraction_workspace(&self) -> ExtractionWorkspace {
+        ExtractionWorkspace::new(std::path::PathBuf::from(self.scratch_path()), self.quarantine_retention())
+    }
+
+    /// Store of the published media, the web server behind redirect_uri and web_server_path when not set
+    pub fn media_store(&self) -> Option<&MediaStoreConf> {
+        self.media_store.as_ref()
+    }
+
+    pub fn with_media_store(mut self, media_store: MediaStoreConf) -> Self {
+        self.media_store = Some(media_store);
+        self
+    }
+
+    pub fn with_scratch_path(mut self, scratch_path: &str) -> Self {
+        self.scratch_path = Some(scratch_path.to_string());
+        self
+    }
+
+    pub fn with_staging_path(mut self, staging_path: &str) -> Self {
+        self.staging_path = Some(staging_path.to_string());
+        self
+    }
+
+    pub fn with_max_upload_size(mut self, max_upload_size: u64) -> Self {
+        self.max_upload_size = Some(max_upload_size);
+        self
+    }
+}
+
+/// Store the tag routes read from, the configured one or else the web server behind redirect_uri
+pub fn create_media_store(conf: &Conf) -> Arc<dyn MediaStore> {
+    match conf.media_store() {
+        Some(media_store_conf) => media_store_conf.create_media_store(),
+        None => Arc::new(HttpOriginStore::new(conf.redirect_uri().to_string())),
+    }
 }
 
 pub fn create_conf_from_toml_file(relative_path: &str) -> figment::Result<Conf> {
@@ -905,12 +1482,44 @@ pub fn create_drop_request_from_toml_file(path: &str) -> figment::Result<DropReq
         .extract()
 }
 
+#[derive(Deserialize)]
+struct DropManifestVersion {
+    #[serde(default)]
+    version: Option<u32>,
+}
+
+/// Read and validate a drop manifest whatever its version, a manifest without `version` is a version 1
+pub fn create_drop_manifest_from_file(
+    path: &std::path::Path,
+    manifest_format: ManifestFormat,
+) -> Result<DropManifest, ManifestError> {
+    let figment = match manifest_format {
+        ManifestFormat::Toml => Figment::new().merge(Toml::file(path)),
+        ManifestFormat::Json => Figment::new().merge(providers::Json::file(path)),
+        ManifestFormat::Yaml => Figment::new().merge(Yaml::file(path)),
+    };
+    let unreadable = |e: figment::Error| ManifestError::Unreadable(e.to_string());
+    let version = figment.extract::<DropManifestVersion>()
+        .map_err(unreadable)?
+        .version
+        .unwrap_or(DROP_MANIFEST_V1);
+    let drop_manifest = match version {
+        DROP_MANIFEST_V1 => DropManifest::from(figment.extract::<DropRequest>().map_err(unreadable)?),
+        DROP_MANIFEST_V2 => figment.extract::<DropManifest>().map_err(unreadable)?,
+        version => return Err(ManifestError::UnsupportedVersion(version)),
+    };
+    drop_manifest.validate().map_err(ManifestError::InvalidFields)?;
+    Ok(drop_manifest)
+}
+
 #[derive(new)]
 pub struct ServiceConf {
     drop_service: DropService<
         Arc<dyn Repo<repository::drop::Drop>>,
         Arc<dyn RepoByName<Artist>>,
         Arc<dyn Repo<Playlist>>,
+        Arc<dyn Repo<Artwork>>,
+        Arc<dyn TrackRepoT>,
     >,
 }
 
@@ -929,6 +1538,8 @@ impl ServiceConf {
         Arc<dyn Repo<repository::drop::Drop>>,
         Arc<dyn RepoByName<Artist>>,
         Arc<dyn Repo<Playlist>>,
+        Arc<dyn Repo<Artwork>>,
+        Arc<dyn TrackRepoT>,
     > {
         &self.drop_service
     }
@@ -977,7 +1588,10 @@ impl DbConf {
 pub struct PlaylistData {
     artist_name: String,
     playlist_name: String,
-    tracks: Vec<String>
+    tracks: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[new(default)]
+    drop_type: Option<DropType>,
 }
 
 impl PlaylistData {
@@ -990,6 +1604,13 @@ impl PlaylistData {
     pub fn tracks(&self) -> &Vec<String> {
         &self.tracks
     }
+    pub fn drop_type(&self) -> Option<DropType> {
+        self.drop_type
+    }
+    pub fn with_drop_type(mut self, drop_type: DropType) -> Self {
+        self.drop_type = Some(drop_type);
+        self
+    }
     pub fn create_from_toml_text(toml_text: &str) -> Result<PlaylistData, Error> {
         toml::from_str(toml_text)
     }
@@ -1,13 +1,21 @@
 use chrono::NaiveDateTime;
-use drop_reverse_proxy::config::db::DatabaseConfig;
+use drop_reverse_proxy::config::db::{create_pool, run_migrations, DatabaseConfig};
 use drop_reverse_proxy::repository::drop::DropRepo;
-use drop_reverse_proxy::service::drop::DropService;
-use drop_reverse_proxy::{app, create_conf_from_toml_file, AppState, InMemoryIpRepo, InMemoryTagRepo, InMemoryTokenRepo, ServiceConf, Tag, TagRepo};
+use drop_reverse_proxy::service::drop::{DropAccess, DropService};
+use drop_reverse_proxy::{app, create_conf_from_toml_file, create_media_store, AppState, InMemoryIpRepo, InMemoryTagRepo, InMemoryTokenRepo, ServiceConf, Tag, TagRepo};
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Duration;
 use drop_reverse_proxy::repository::{Repo, RepoByName};
+use drop_reverse_proxy::repository::api_key::{ApiKeyRepo, ApiKeyRepoT, InMemoryApiKeyRepo};
+use drop_reverse_proxy::repository::import::{ImportRepo, ImportRepoT};
+use drop_reverse_proxy::repository::import_job::ImportJobRepo;
+use drop_reverse_proxy::service::import::ImportJobQueue;
+use drop_reverse_proxy::service::watcher::start_import_watcher;
 use drop_reverse_proxy::repository::artist::ArtistRepo;
+use drop_reverse_proxy::repository::track::{TrackRepo, TrackRepoT};
+use drop_reverse_proxy::repository::artwork::ArtworkRepo;
+use drop_reverse_proxy::repository::drop_type::DropTypeRepo;
 use drop_reverse_proxy::repository::playlist::PlaylistRepo;
 
 #[tokio::main]
@@ -15,7 +23,6 @@ async fn main() {
     let conf = create_conf_from_toml_file("app.toml")
         .expect("can't load conf from toml file");
 
-    let listener = tokio::net::TcpListener::bind(conf.bind_addr()).await.unwrap();
     let token_repo = InMemoryTokenRepo::default();
     let tag_repo = InMemoryTagRepo::default();
     ["jdznjevb", "xurnxenyoawltkky", "tag3", "playlist"].iter()
@@ -30,36 +37,180 @@ async fn main() {
         database: db_conf.db_name().to_string(),
         username: db_conf.db_user().to_string(),
         password: db_conf.db_password().to_string(),
-        max_connections: 10,
+        max_connections: db_conf.db_pool_size(),
         min_connections: 1,
         connect_timeout: Duration::from_secs(5),
         idle_timeout: Duration::from_secs(100),
         max_lifetime: Duration::from_secs(1800)
     };
 
-    if let Ok(drop_repository) = DropRepo::new(&db_config).await
-        && let Ok(playlist_repository) = PlaylistRepo::new(&db_config).await
-        && let Ok(artist_repository) = ArtistRepo::new(&db_config).await {
-        println!("Database connection successful");
+    // one pool is shared by the repositories, the schema is migrated before any of them uses it, `migrate` stops there
+    let pool = match create_pool(&db_config).await {
+        Ok(pool) => pool,
+        Err(e) => panic!("Database connection failed: {:?}", e),
+    };
+    println!("Database connection successful");
+    if let Err(e) = run_migrations(&pool).await {
+        panic!("can't migrate the database: {:?}", e);
+    }
+    // the normalized names are computed by the server, the artists saved before have none yet
+    match ArtistRepo::from_pool(pool.clone()).fill_normalized_names().await {
+        Ok(clashing_names) => {
+            for name in clashing_names {
+                println!("artist {name} has the normalized name of another artist, it is only found by id until they are merged");
+            }
+        }
+        Err(e) => panic!("can't fill the normalized artist names: {:?}", e),
+    }
+    if std::env::args().nth(1).as_deref() == Some("migrate") {
+        println!("Database migrated");
+        return;
+    }
 
-        let drop_service = DropService::new(
-            Arc::new(drop_repository) as Arc<dyn Repo<drop_reverse_proxy::repository::drop::Drop>>,
-            Arc::new(artist_repository) as Arc<dyn RepoByName<drop_reverse_proxy::repository::artist::Artist>>,
-            Arc::new(playlist_repository) as Arc<dyn Repo<drop_reverse_proxy::repository::playlist::Playlist>>,
-        );
-        let app_state = AppState {
-            token_repo: Arc::new(token_repo.clone()),
-            tag_repo: Arc::new(tag_repo.clone()),
-            ip_repo: Arc::new(ip_repo),
-            conf,
-            entity_repositories: Vec::new(),
-            service_conf: ServiceConf::new(drop_service),
-        };
-        axum::serve(
-            listener,
-            app(app_state).into_make_service_with_connect_info::<SocketAddr>()
-        ).await.unwrap();
+    let listener = tokio::net::TcpListener::bind(conf.bind_addr()).await.unwrap();
+
+    let drop_repository = DropRepo::from_pool(pool.clone());
+    let playlist_repository = PlaylistRepo::from_pool(pool.clone());
+    let artist_repository = ArtistRepo::from_pool(pool.clone());
+    let artwork_repository = ArtworkRepo::from_pool(pool.clone());
+    let track_repository = TrackRepo::from_pool(pool.clone());
+    let drop_type_repository = DropTypeRepo::from_pool(pool.clone());
+    let api_key_repository = ApiKeyRepo::from_pool(pool.clone());
+    let import_job_repository = ImportJobRepo::from_pool(pool.clone());
+    let import_repository = ImportRepo::from_pool(pool);
+
+    // tags bound to a drop serve it, the tags of a drop unpublished or deleted stay revoked
+    for (t, drop_id) in conf.tag_drops() {
+        match drop_repository.get(*drop_id).await {
+            Ok(drop) if drop.published() => tag_repo.save(&Tag::new(t.to_string(), NaiveDateTime::default()).with_drop_id(*drop_id)),
+            Ok(_) => println!("tag {t} is not bound, drop {drop_id} is unpublished"),
+            Err(e) => println!("tag {t} is not bound to drop {drop_id}: {:?}", e),
+        }
+    }
+
+    // api keys declared in app.toml take precedence over the ones stored in the database
+    let api_key_repo: Arc<dyn ApiKeyRepoT> = if conf.api_keys().is_empty() {
+        Arc::new(api_key_repository)
     } else {
-        panic!("Database connection failed");
+        Arc::new(InMemoryApiKeyRepo::from_conf(conf.api_keys()))
+    };
+
+    // the drop_type lookup table follows the types the server knows
+    if let Err(e) = drop_type_repository.sync().await {
+        println!("can't sync the drop types: {:?}", e);
+    }
+
+    // imports left running by a previous run can be retried
+    let import_repo: Arc<dyn ImportRepoT> = Arc::new(import_repository);
+    match import_repo.interrupt_running(chrono::Utc::now().naive_utc()).await {
+        Ok(nb_interrupted) if nb_interrupted > 0 => println!("{nb_interrupted} interrupted imports marked as failed"),
+        Ok(_) => {}
+        Err(e) => println!("can't mark interrupted imports as failed: {:?}", e),
+    }
+
+    let drop_service = DropService::new(
+        Arc::new(drop_repository) as Arc<dyn Repo<drop_reverse_proxy::repository::drop::Drop>>,
+        Arc::new(artist_repository) as Arc<dyn RepoByName<drop_reverse_proxy::repository::artist::Artist>>,
+        Arc::new(playlist_repository) as Arc<dyn Repo<drop_reverse_proxy::repository::playlist::Playlist>>,
+        Arc::new(artwork_repository) as Arc<dyn Repo<drop_reverse_proxy::repository::artwork::Artwork>>,
+        Arc::new(track_repository) as Arc<dyn TrackRepoT>,
+    )
+        .with_import_policy(conf.import_policy())
+        .with_drop_access(DropAccess::new(Arc::new(tag_repo.clone()), Arc::new(token_repo.clone())));
+    // without a configured store the imports write to web_server_path and the tag routes read redirect_uri
+    let media_store = create_media_store(&conf);
+    let drop_service = match conf.media_store() {
+        Some(_) => drop_service.with_media_store(media_store.clone()),
+        None => drop_service,
+    };
+    // `migrate-track-content` moves the tracks of the playlist directories to the content store then stops
+    if std::env::args().nth(1).as_deref() == Some("migrate-track-content") {
+        let web_server_path = conf.web_server_path().expect("web_server_path not found in app.toml");
+        match drop_service.migrate_track_content(web_server_path).await {
+            Ok(migration) => println!(
+                "{} tracks migrated to the content store, {} of them deduplicated, {} missing",
+                migration.migrated_tracks(),
+                migration.deduplicated_tracks(),
+                migration.missing_tracks()
+            ),
+            Err(e) => println!("can't migrate the tracks to the content store: {e}"),
+        }
+        return;
     }
+    // extractions left by a previous run are removed before any import starts
+    let extraction_workspace = conf.extraction_workspace();
+    match extraction_workspace.sweep(std::time::SystemTime::now()) {
+        Ok(sweep_report) => println!(
+            "{} leftover extractions and {} expired quarantines removed from {:?}",
+            sweep_report.leftover_extractions(),
+            sweep_report.expired_quarantines(),
+            extraction_workspace.scratch_path()
+        ),
+        Err(e) => println!("can't sweep {:?}: {:?}", extraction_workspace.scratch_path(), e),
+    }
+    // media a failed import couldn't delete have no row, nothing is written before the sweep ends
+    if let Some(web_server_path) = conf.web_server_path() {
+        match drop_service.sweep_orphaned_media(web_server_path).await {
+            Ok(sweep) => println!(
+                "{} track contents, {} artworks and {} playlist directories without row removed from the media store",
+                sweep.track_contents(),
+                sweep.artworks(),
+                sweep.playlists()
+            ),
+            Err(e) => println!("can't sweep the media store: {e}"),
+        }
+    }
+    let import_job_queue = match conf.web_server_path() {
+        Some(web_server_path) => {
+            let import_job_queue = ImportJobQueue::start(
+                Arc::new(import_job_repository),
+                import_repo.clone(),
+                Arc::new(drop_service.clone()),
+                extraction_workspace.clone(),
+                conf.import_path().to_string(),
+                web_server_path.to_string(),
+                conf.import_workers(),
+            );
+            match import_job_queue.resume_unfinished().await {
+                Ok(nb_resumed) => println!("{nb_resumed} import jobs resumed"),
+                Err(e) => println!("can't resume import jobs: {:?}", e),
+            }
+            Some(import_job_queue)
+        }
+        None => {
+            println!("web_server_path not set, import jobs are disabled");
+            None
+        }
+    };
+    // kept alive until the server stops
+    let _import_watcher = match &import_job_queue {
+        Some(import_job_queue) if conf.watch_import_path() => match start_import_watcher(
+            conf.import_path(),
+            import_job_queue.clone(),
+            conf.watch_stable_delay()
+        ) {
+            Ok(import_watcher) => Some(import_watcher),
+            Err(e) => {
+                println!("can't watch import_path: {:?}", e);
+                None
+            }
+        },
+        _ => None,
+    };
+    let app_state = AppState {
+        token_repo: Arc::new(token_repo.clone()),
+        tag_repo: Arc::new(tag_repo.clone()),
+        ip_repo: Arc::new(ip_repo),
+        api_key_repo,
+        import_repo,
+        import_job_queue,
+        media_store,
+        conf,
+        entity_repositories: Vec::new(),
+        service_conf: ServiceConf::new(drop_service),
+    };
+    axum::serve(
+        listener,
+        app(app_state).into_make_service_with_connect_info::<SocketAddr>()
+    ).await.unwrap();
 }
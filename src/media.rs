@@ -0,0 +1,214 @@
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::{Stream, StreamExt, TryStreamExt};
+use serde::Deserialize;
+use std::fmt::Debug;
+use std::io;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio_util::io::ReaderStream;
+
+pub mod http_origin;
+pub mod local;
+pub mod s3;
+
+pub use http_origin::HttpOriginStore;
+pub use local::LocalMediaStore;
+pub use s3::S3MediaStore;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MediaStoreError {
+    NotFound,
+    /// The key is empty, absolute or goes up a directory
+    InvalidKey,
+    /// The range starts after the end of the media
+    InvalidRange,
+    /// The store can't do this operation, e.g. writes to an HTTP origin
+    Unsupported,
+    /// The backend failed, the message tells why
+    Backend(String),
+}
+
+impl std::fmt::Display for MediaStoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MediaStoreError::NotFound => f.write_str("the media doesn't exist"),
+            MediaStoreError::InvalidKey => f.write_str("the media key is invalid"),
+            MediaStoreError::InvalidRange => f.write_str("the range is out of the media"),
+            MediaStoreError::Unsupported => f.write_str("the media store doesn't support this operation"),
+            MediaStoreError::Backend(message) => write!(f, "the media store failed: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for MediaStoreError {}
+
+/// Bytes of a media, read or written a chunk at a time
+pub type MediaStream = Pin<Box<dyn Stream<Item = Result<Bytes, MediaStoreError>> + Send>>;
+
+/// A media as a stream, so it is never held whole in memory
+pub struct MediaContent {
+    pub stream: MediaStream,
+    /// Number of bytes of the stream, when the store tells it
+    pub size: Option<u64>,
+}
+
+impl MediaContent {
+    pub fn new(stream: MediaStream, size: Option<u64>) -> Self {
+        Self { stream, size }
+    }
+
+    pub fn from_bytes(content: impl Into<Bytes>) -> Self {
+        let content = content.into();
+        let size = content.len() as u64;
+        Self::new(Box::pin(futures_util::stream::once(async move { Ok(content) })), Some(size))
+    }
+
+    /// Content of the file at `path`, it is read as the stream is polled
+    pub async fn from_file(path: &Path) -> io::Result<Self> {
+        let file = tokio::fs::File::open(path).await?;
+        let size = file.metadata().await?.len();
+        Ok(Self::from_reader(file, Some(size)))
+    }
+
+    pub fn from_reader(reader: impl tokio::io::AsyncRead + Send + 'static, size: Option<u64>) -> Self {
+        let stream = ReaderStream::new(reader)
+            .map_err(|e| MediaStoreError::Backend(e.to_string()));
+        Self::new(Box::pin(stream), size)
+    }
+
+    /// Reads the whole media, only for the small ones like a playlist.toml
+    pub async fn into_bytes(self) -> Result<Vec<u8>, MediaStoreError> {
+        let mut content = Vec::with_capacity(self.size.unwrap_or(0) as usize);
+        let mut stream = self.stream;
+        while let Some(chunk) = stream.next().await {
+            content.extend_from_slice(&chunk?);
+        }
+        Ok(content)
+    }
+}
+
+impl Debug for MediaContent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MediaContent").field("size", &self.size).finish_non_exhaustive()
+    }
+}
+
+/// Bytes from `start` to `end` included, to the end of the media when `end` is None
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: Option<u64>,
+}
+
+impl ByteRange {
+    /// Range of a `Range: bytes=start-end` header, suffix and multiple ranges are not supported
+    pub fn from_header(header: &str) -> Option<Self> {
+        let (start, end) = header.strip_prefix("bytes=")?.split_once('-')?;
+        let start = start.trim().parse().ok()?;
+        let end = match end.trim() {
+            "" => None,
+            end => Some(end.parse().ok()?),
+        };
+        match end {
+            Some(end) if end < start => None,
+            _ => Some(Self { start, end }),
+        }
+    }
+
+    pub fn header_value(&self) -> String {
+        match self.end {
+            Some(end) => format!("bytes={}-{end}", self.start),
+            None => format!("bytes={}-", self.start),
+        }
+    }
+}
+
+/// Part of a media read with a range
+#[derive(Debug)]
+pub struct RangedContent {
+    pub content: MediaContent,
+    /// Offset of the first byte of `content`
+    pub start: u64,
+    /// Offset of the last byte of `content`
+    pub end: u64,
+    /// Size of the whole media
+    pub total_size: u64,
+}
+
+impl RangedContent {
+    pub fn content_range(&self) -> String {
+        format!("bytes {}-{}/{}", self.start, self.end, self.total_size)
+    }
+}
+
+/// Storage of the published media, keys are relative paths like `playlist_3/track_1`
+#[async_trait]
+pub trait MediaStore: Debug + Send + Sync {
+    /// Writes the media, replacing the one with the same key
+    async fn put(&self, key: &str, content: MediaContent) -> Result<(), MediaStoreError>;
+
+    async fn get(&self, key: &str) -> Result<MediaContent, MediaStoreError>;
+
+    async fn get_range(&self, key: &str, range: ByteRange) -> Result<RangedContent, MediaStoreError>;
+
+    /// Keys starting with `prefix`, sorted
+    async fn list(&self, prefix: &str) -> Result<Vec<String>, MediaStoreError>;
+
+    /// Deleting a missing media is not an error
+    async fn delete(&self, key: &str) -> Result<(), MediaStoreError>;
+}
+
+/// Where the published media are stored, e.g. `media_store = { type = "s3", endpoint = "http://localhost:9000", ... }`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MediaStoreConf {
+    Local {
+        root: String,
+    },
+    S3 {
+        endpoint: String,
+        bucket: String,
+        region: String,
+        access_key_id: String,
+        secret_access_key: String,
+    },
+}
+
+impl MediaStoreConf {
+    pub fn create_media_store(&self) -> Arc<dyn MediaStore> {
+        match self {
+            MediaStoreConf::Local { root } => Arc::new(LocalMediaStore::new(root.into())),
+            MediaStoreConf::S3 { endpoint, bucket, region, access_key_id, secret_access_key } => Arc::new(
+                S3MediaStore::new(endpoint.clone(), bucket.clone(), region.clone(), access_key_id.clone(), secret_access_key.clone())
+            ),
+        }
+    }
+}
+
+/// Checks a key is a relative path without `.` or `..` segments
+pub fn validate_key(key: &str) -> Result<(), MediaStoreError> {
+    if key.is_empty() || key.starts_with('/') || key.split('/').any(|segment| matches!(segment, "" | "." | "..")) {
+        return Err(MediaStoreError::InvalidKey);
+    }
+    Ok(())
+}
+
+/// Moves each media under `from` to the same key under `to`, a media is deleted once copied
+pub async fn move_prefix(media_store: &dyn MediaStore, from: &str, to: &str) -> Result<(), MediaStoreError> {
+    for key in media_store.list(from).await? {
+        let content = media_store.get(&key).await?;
+        media_store.put(&format!("{to}{}", &key[from.len()..]), content).await?;
+        media_store.delete(&key).await?;
+    }
+    Ok(())
+}
+
+/// Deletes each media under `prefix`
+pub async fn delete_prefix(media_store: &dyn MediaStore, prefix: &str) -> Result<(), MediaStoreError> {
+    for key in media_store.list(prefix).await? {
+        media_store.delete(&key).await?;
+    }
+    Ok(())
+}
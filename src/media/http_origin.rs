@@ -0,0 +1,99 @@
+use crate::media::{validate_key, ByteRange, MediaContent, MediaStore, MediaStoreError, MediaStream, RangedContent};
+use async_trait::async_trait;
+use futures_util::TryStreamExt;
+use reqwest::header::{CONTENT_RANGE, RANGE};
+use reqwest::StatusCode;
+use tokio::io::AsyncReadExt;
+use tokio_util::io::StreamReader;
+
+/// Read-only media served by a web server, each key is a path under `base_uri`
+#[derive(Debug, Clone)]
+pub struct HttpOriginStore {
+    base_uri: String,
+    client: reqwest::Client,
+}
+
+impl HttpOriginStore {
+    pub fn new(base_uri: String) -> Self {
+        Self { base_uri, client: reqwest::Client::new() }
+    }
+
+    async fn send(&self, key: &str, range: Option<ByteRange>) -> Result<reqwest::Response, MediaStoreError> {
+        validate_key(key)?;
+        let mut request = self.client.get(format!("{}/{key}", self.base_uri.trim_end_matches('/')));
+        if let Some(range) = range {
+            request = request.header(RANGE, range.header_value());
+        }
+        let response = request.send().await.map_err(|e| MediaStoreError::Backend(e.to_string()))?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Err(MediaStoreError::NotFound),
+            StatusCode::RANGE_NOT_SATISFIABLE => Err(MediaStoreError::InvalidRange),
+            status if status.is_success() => Ok(response),
+            status => Err(MediaStoreError::Backend(format!("{key} answered {status}"))),
+        }
+    }
+}
+
+/// Start, end and total size of a `Content-Range: bytes start-end/total` header
+pub(crate) fn parse_content_range(content_range: &str) -> Option<(u64, u64, u64)> {
+    let (range, total_size) = content_range.strip_prefix("bytes ")?.split_once('/')?;
+    let (start, end) = range.split_once('-')?;
+    Some((start.parse().ok()?, end.parse().ok()?, total_size.parse().ok()?))
+}
+
+/// Body of a response, read as the stream is polled
+pub(crate) fn media_stream(response: reqwest::Response) -> MediaStream {
+    Box::pin(response.bytes_stream().map_err(|e| MediaStoreError::Backend(e.to_string())))
+}
+
+/// Content of a response, with its size when it has a `Content-Length`
+pub(crate) fn media_content(response: reqwest::Response) -> MediaContent {
+    let size = response.content_length();
+    MediaContent::new(media_stream(response), size)
+}
+
+/// Ranged content of a response, a server ignoring the range answers the whole media
+/// whose bytes before the range are skipped
+pub(crate) async fn ranged_content(response: reqwest::Response, range: ByteRange) -> Result<RangedContent, MediaStoreError> {
+    let partial = response.headers().get(CONTENT_RANGE)
+        .and_then(|content_range| content_range.to_str().ok())
+        .and_then(parse_content_range);
+    if let Some((start, end, total_size)) = partial {
+        return Ok(RangedContent { content: MediaContent::new(media_stream(response), Some(end - start + 1)), start, end, total_size });
+    }
+    let total_size = response.content_length()
+        .ok_or(MediaStoreError::Backend("the media has no content length".to_string()))?;
+    if range.start >= total_size {
+        return Err(MediaStoreError::InvalidRange);
+    }
+    let end = range.end.map_or(total_size - 1, |end| end.min(total_size - 1));
+    let mut reader = StreamReader::new(Box::pin(response.bytes_stream().map_err(std::io::Error::other)));
+    tokio::io::copy(&mut (&mut reader).take(range.start), &mut tokio::io::sink())
+        .await
+        .map_err(|e| MediaStoreError::Backend(e.to_string()))?;
+    let size = end - range.start + 1;
+    Ok(RangedContent { content: MediaContent::from_reader(reader.take(size), Some(size)), start: range.start, end, total_size })
+}
+
+#[async_trait]
+impl MediaStore for HttpOriginStore {
+    async fn put(&self, _key: &str, _content: MediaContent) -> Result<(), MediaStoreError> {
+        Err(MediaStoreError::Unsupported)
+    }
+
+    async fn get(&self, key: &str) -> Result<MediaContent, MediaStoreError> {
+        self.send(key, None).await.map(media_content)
+    }
+
+    async fn get_range(&self, key: &str, range: ByteRange) -> Result<RangedContent, MediaStoreError> {
+        ranged_content(self.send(key, Some(range)).await?, range).await
+    }
+
+    async fn list(&self, _prefix: &str) -> Result<Vec<String>, MediaStoreError> {
+        Err(MediaStoreError::Unsupported)
+    }
+
+    async fn delete(&self, _key: &str) -> Result<(), MediaStoreError> {
+        Err(MediaStoreError::Unsupported)
+    }
+}
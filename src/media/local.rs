@@ -0,0 +1,148 @@
+use crate::media::{validate_key, ByteRange, MediaContent, MediaStore, MediaStoreError, RangedContent};
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use std::io::{ErrorKind, SeekFrom};
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+/// Prefix of the file a media is written to before it is renamed to its key
+const PUT_FILE_PREFIX: &str = ".put_";
+
+/// Media stored as files under `root`, each key is a path relative to it
+#[derive(Debug, Clone)]
+pub struct LocalMediaStore {
+    root: PathBuf,
+}
+
+impl LocalMediaStore {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf, MediaStoreError> {
+        validate_key(key)?;
+        Ok(self.root.join(key))
+    }
+}
+
+fn backend_error(e: std::io::Error) -> MediaStoreError {
+    match e.kind() {
+        ErrorKind::NotFound => MediaStoreError::NotFound,
+        _ => MediaStoreError::Backend(e.to_string()),
+    }
+}
+
+#[async_trait]
+impl MediaStore for LocalMediaStore {
+    /// The media is written next to its path then renamed, so it never appears incomplete
+    async fn put(&self, key: &str, content: MediaContent) -> Result<(), MediaStoreError> {
+        let path = self.path(key)?;
+        let parent = path.parent().ok_or(MediaStoreError::InvalidKey)?.to_path_buf();
+        let temp_file = tokio::task::spawn_blocking(move || {
+            std::fs::create_dir_all(&parent)?;
+            let mut builder = tempfile::Builder::new();
+            // the web server reading the media is not its owner
+            #[cfg(unix)]
+            builder.permissions(std::os::unix::fs::PermissionsExt::from_mode(0o644));
+            builder.prefix(PUT_FILE_PREFIX).tempfile_in(&parent)
+        })
+            .await
+            .map_err(|e| MediaStoreError::Backend(e.to_string()))?
+            .map_err(backend_error)?;
+        // the temporary file is removed when it is dropped before being persisted
+        let mut file = tokio::fs::File::from_std(temp_file.as_file().try_clone().map_err(backend_error)?);
+        let mut stream = content.stream;
+        while let Some(chunk) = stream.next().await {
+            file.write_all(&chunk?).await.map_err(backend_error)?;
+        }
+        file.flush().await.map_err(backend_error)?;
+        drop(file);
+        tokio::task::spawn_blocking(move || temp_file.persist(&path).map_err(|e| e.error))
+            .await
+            .map_err(|e| MediaStoreError::Backend(e.to_string()))?
+            .map_err(backend_error)?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<MediaContent, MediaStoreError> {
+        MediaContent::from_file(&self.path(key)?).await.map_err(backend_error)
+    }
+
+    /// Only the bytes of the range are read, as the stream is polled
+    async fn get_range(&self, key: &str, range: ByteRange) -> Result<RangedContent, MediaStoreError> {
+        let mut file = tokio::fs::File::open(self.path(key)?).await.map_err(backend_error)?;
+        let total_size = file.metadata().await.map_err(backend_error)?.len();
+        if range.start >= total_size {
+            return Err(MediaStoreError::InvalidRange);
+        }
+        let end = range.end.map_or(total_size - 1, |end| end.min(total_size - 1));
+        file.seek(SeekFrom::Start(range.start)).await.map_err(backend_error)?;
+        let size = end - range.start + 1;
+        Ok(RangedContent {
+            content: MediaContent::from_reader(file.take(size), Some(size)),
+            start: range.start,
+            end,
+            total_size,
+        })
+    }
+
+    /// The files of a put in progress are not listed
+    async fn list(&self, prefix: &str) -> Result<Vec<String>, MediaStoreError> {
+        let root = self.root.clone();
+        let prefix = prefix.to_string();
+        tokio::task::spawn_blocking(move || {
+            let mut keys = Vec::new();
+            let mut dirs = vec![(root, String::new())];
+            while let Some((dir, dir_key)) = dirs.pop() {
+                let entries = match std::fs::read_dir(&dir) {
+                    Ok(entries) => entries,
+                    Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                    Err(e) => return Err(backend_error(e)),
+                };
+                for entry in entries {
+                    let entry = entry.map_err(backend_error)?;
+                    let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+                        continue;
+                    };
+                    if name.starts_with(PUT_FILE_PREFIX) {
+                        continue;
+                    }
+                    let key = format!("{dir_key}{name}");
+                    if entry.file_type().map_err(backend_error)?.is_dir() {
+                        // only the directories which can hold keys with the prefix are walked
+                        let dir_prefix = format!("{key}/");
+                        if dir_prefix.starts_with(&prefix) || prefix.starts_with(&dir_prefix) {
+                            dirs.push((entry.path(), dir_prefix));
+                        }
+                    } else if key.starts_with(&prefix) {
+                        keys.push(key);
+                    }
+                }
+            }
+            keys.sort();
+            Ok(keys)
+        })
+            .await
+            .map_err(|e| MediaStoreError::Backend(e.to_string()))?
+    }
+
+    /// The directories left empty are removed up to the root
+    async fn delete(&self, key: &str) -> Result<(), MediaStoreError> {
+        let path = self.path(key)?;
+        match tokio::fs::remove_file(&path).await {
+            Err(e) if e.kind() != ErrorKind::NotFound => return Err(backend_error(e)),
+            _ => {}
+        }
+        let mut dir = path.parent();
+        while let Some(parent) = dir
+            && parent != self.root
+            && tokio::fs::remove_dir(parent).await.is_ok() {
+            dir = parent.parent();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,208 @@
+use crate::media::http_origin::{media_content, ranged_content};
+use crate::media::{validate_key, ByteRange, MediaContent, MediaStore, MediaStoreError, RangedContent};
+use async_trait::async_trait;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use regex::Regex;
+use reqwest::header::{CONTENT_LENGTH, RANGE};
+use reqwest::{Method, StatusCode};
+use sha2::{Digest, Sha256};
+
+const SIGNING_ALGORITHM: &str = "AWS4-HMAC-SHA256";
+const SIGNED_HEADERS: &str = "host;x-amz-content-sha256;x-amz-date";
+/// Payload hash of a streamed body, it can't be hashed before it is sent
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+/// Media stored as the objects of a bucket of an S3 compatible service, e.g. MinIO.
+/// Requests are signed with AWS signature version 4 and address the bucket in the path.
+#[derive(Debug, Clone)]
+pub struct S3MediaStore {
+    endpoint: String,
+    bucket: String,
+    region: String,
+    access_key_id: String,
+    secret_access_key: String,
+    client: reqwest::Client,
+}
+
+impl S3MediaStore {
+    pub fn new(endpoint: String, bucket: String, region: String, access_key_id: String, secret_access_key: String) -> Self {
+        Self {
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            bucket,
+            region,
+            access_key_id,
+            secret_access_key,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Creates the bucket, a bucket which already exists is kept
+    pub async fn create_bucket(&self) -> Result<(), MediaStoreError> {
+        match self.send(Method::PUT, "", &[], None, None).await {
+            Ok(_) => Ok(()),
+            Err(MediaStoreError::Backend(message)) if message.contains("BucketAlreadyOwnedByYou") => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Sends a signed request on the object `key` or on the bucket when `key` is empty,
+    /// a `body` is streamed and must tell its size
+    async fn send(
+        &self,
+        method: Method,
+        key: &str,
+        query: &[(&str, &str)],
+        body: Option<MediaContent>,
+        range: Option<ByteRange>,
+    ) -> Result<reqwest::Response, MediaStoreError> {
+        let path = match key {
+            "" => format!("/{}", uri_encode(&self.bucket, false)),
+            key => format!("/{}/{}", uri_encode(&self.bucket, false), uri_encode(key, true)),
+        };
+        let mut query: Vec<(String, String)> = query.iter()
+            .map(|(name, value)| (uri_encode(name, false), uri_encode(value, false)))
+            .collect();
+        query.sort();
+        let canonical_query = query.iter()
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect::<Vec<_>>()
+            .join("&");
+        let url = match canonical_query.as_str() {
+            "" => format!("{}{path}", self.endpoint),
+            canonical_query => format!("{}{path}?{canonical_query}", self.endpoint),
+        };
+        let url = reqwest::Url::parse(&url).map_err(|e| MediaStoreError::Backend(e.to_string()))?;
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{host}:{port}"),
+            (Some(host), None) => host.to_string(),
+            (None, _) => return Err(MediaStoreError::Backend(format!("no host in endpoint {}", self.endpoint))),
+        };
+
+        let amz_date = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+        let payload_hash = match body {
+            Some(_) => UNSIGNED_PAYLOAD.to_string(),
+            None => hex::encode(Sha256::digest(b"")),
+        };
+        let authorization = self.authorization(&method, &path, &canonical_query, &host, &payload_hash, &amz_date);
+
+        let mut request = self.client.request(method, url)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("x-amz-date", amz_date)
+            .header("authorization", authorization);
+        if let Some(body) = body {
+            // S3 refuses a put without content length
+            let size = body.size.ok_or(MediaStoreError::Backend(format!("the size of {key} is unknown")))?;
+            request = request.header(CONTENT_LENGTH, size).body(reqwest::Body::wrap_stream(body.stream));
+        }
+        if let Some(range) = range {
+            request = request.header(RANGE, range.header_value());
+        }
+        let response = request.send().await.map_err(|e| MediaStoreError::Backend(e.to_string()))?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Err(MediaStoreError::NotFound),
+            StatusCode::RANGE_NOT_SATISFIABLE => Err(MediaStoreError::InvalidRange),
+            status if status.is_success() => Ok(response),
+            status => {
+                let message = response.text().await.unwrap_or_default();
+                Err(MediaStoreError::Backend(format!("{status} {message}")))
+            }
+        }
+    }
+
+    /// Authorization header of a request signed at `amz_date`, e.g. `20130524T000000Z`
+    fn authorization(&self, method: &Method, path: &str, canonical_query: &str, host: &str, payload_hash: &str, amz_date: &str) -> String {
+        let date = &amz_date[..8];
+        let canonical_request = format!(
+            "{method}\n{path}\n{canonical_query}\nhost:{host}\nx-amz-content-sha256:{payload_hash}\nx-amz-date:{amz_date}\n\n{SIGNED_HEADERS}\n{payload_hash}"
+        );
+        let scope = format!("{date}/{}/s3/aws4_request", self.region);
+        let string_to_sign = format!("{SIGNING_ALGORITHM}\n{amz_date}\n{scope}\n{}", hex::encode(Sha256::digest(canonical_request)));
+        let signing_key = [self.region.as_str(), "s3", "aws4_request"].iter()
+            .fold(hmac_sha256(format!("AWS4{}", self.secret_access_key).as_bytes(), date.as_bytes()), |key, part| {
+                hmac_sha256(&key, part.as_bytes())
+            });
+        let signature = hex::encode(hmac_sha256(&signing_key, string_to_sign.as_bytes()));
+        format!("{SIGNING_ALGORITHM} Credential={}/{scope}, SignedHeaders={SIGNED_HEADERS}, Signature={signature}", self.access_key_id)
+    }
+}
+
+fn hmac_sha256(key: &[u8], content: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any size");
+    mac.update(content);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Percent-encodes all but the unreserved characters, and the slashes of a key when `keep_slashes`
+fn uri_encode(value: &str, keep_slashes: bool) -> String {
+    value.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (byte as char).to_string(),
+            b'/' if keep_slashes => "/".to_string(),
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+fn xml_unescape(value: &str) -> String {
+    value.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[async_trait]
+impl MediaStore for S3MediaStore {
+    async fn put(&self, key: &str, content: MediaContent) -> Result<(), MediaStoreError> {
+        validate_key(key)?;
+        self.send(Method::PUT, key, &[], Some(content), None).await.map(|_| ())
+    }
+
+    async fn get(&self, key: &str) -> Result<MediaContent, MediaStoreError> {
+        validate_key(key)?;
+        self.send(Method::GET, key, &[], None, None).await.map(media_content)
+    }
+
+    async fn get_range(&self, key: &str, range: ByteRange) -> Result<RangedContent, MediaStoreError> {
+        validate_key(key)?;
+        ranged_content(self.send(Method::GET, key, &[], None, Some(range)).await?, range).await
+    }
+
+    /// Objects are listed a page of ListObjectsV2 at a time
+    async fn list(&self, prefix: &str) -> Result<Vec<String>, MediaStoreError> {
+        let key_regex = Regex::new(r"<Key>([^<]*)</Key>").unwrap();
+        let token_regex = Regex::new(r"<NextContinuationToken>([^<]*)</NextContinuationToken>").unwrap();
+        let mut keys = Vec::new();
+        let mut continuation_token: Option<String> = None;
+        loop {
+            let mut query = vec![("list-type", "2"), ("prefix", prefix)];
+            if let Some(continuation_token) = &continuation_token {
+                query.push(("continuation-token", continuation_token));
+            }
+            let listing = self.send(Method::GET, "", &query, None, None).await?
+                .text()
+                .await
+                .map_err(|e| MediaStoreError::Backend(e.to_string()))?;
+            keys.extend(key_regex.captures_iter(&listing).map(|captures| xml_unescape(&captures[1])));
+            continuation_token = match listing.contains("<IsTruncated>true</IsTruncated>") {
+                true => token_regex.captures(&listing).map(|captures| xml_unescape(&captures[1])),
+                false => None,
+            };
+            if continuation_token.is_none() {
+                break;
+            }
+        }
+        keys.sort();
+        Ok(keys)
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), MediaStoreError> {
+        validate_key(key)?;
+        match self.send(Method::DELETE, key, &[], None, None).await {
+            Ok(_) | Err(MediaStoreError::NotFound) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
@@ -1,9 +1,19 @@
 use async_trait::async_trait;
 use std::sync::Arc;
+use crate::repository::query::{Filter, Page, Queryable};
+use crate::repository::unit_of_work::UnitOfWork;
 
 pub mod drop;
+pub mod artwork;
+pub mod drop_type;
 pub mod artist;
 pub mod playlist;
+pub mod track;
+pub mod api_key;
+pub mod import;
+pub mod import_job;
+pub mod query;
+pub mod unit_of_work;
 
 pub trait Entity {
     fn id(&self) -> String;
@@ -12,12 +22,103 @@ pub trait Entity {
 pub enum RepositoryError {
     EntityNotFound,
     EntityNotSaved,
+    /// The entity was updated by another write since it was read
+    VersionConflict,
+    /// The repository doesn't offer the operation
+    Unsupported,
+    /// Another entity already has a value which must be unique, e.g. the sha256 of an import
+    UniqueViolation,
+    /// The entity references an entity which doesn't exist, or is referenced by one which does
+    ForeignKeyViolation,
+    /// No connection was available in time or the statement was cancelled by a timeout
+    Timeout,
+    /// The database can't be reached or is shutting down
+    Unavailable,
     DatabaseError(sqlx::Error),
 }
+
+/// SQLSTATE of a statement cancelled by `statement_timeout` and of a lock not acquired in `lock_timeout`
+const TIMEOUT_SQLSTATES: [&str; 2] = ["57014", "55P03"];
+/// SQLSTATE of too many connections, of an admin or crash shutdown and of a database starting up
+const UNAVAILABLE_SQLSTATES: [&str; 4] = ["53300", "57P01", "57P02", "57P03"];
+
+impl From<sqlx::Error> for RepositoryError {
+    fn from(e: sqlx::Error) -> Self {
+        match &e {
+            sqlx::Error::RowNotFound => RepositoryError::EntityNotFound,
+            sqlx::Error::PoolTimedOut => RepositoryError::Timeout,
+            sqlx::Error::Io(_) | sqlx::Error::Tls(_) | sqlx::Error::PoolClosed | sqlx::Error::WorkerCrashed => RepositoryError::Unavailable,
+            sqlx::Error::Database(database_error) => match database_error.kind() {
+                sqlx::error::ErrorKind::UniqueViolation => RepositoryError::UniqueViolation,
+                sqlx::error::ErrorKind::ForeignKeyViolation => RepositoryError::ForeignKeyViolation,
+                _ => match database_error.code().as_deref() {
+                    Some(code) if TIMEOUT_SQLSTATES.contains(&code) => RepositoryError::Timeout,
+                    // class 08 is made of the connection exceptions
+                    Some(code) if code.starts_with("08") || UNAVAILABLE_SQLSTATES.contains(&code) => RepositoryError::Unavailable,
+                    _ => RepositoryError::DatabaseError(e),
+                },
+            },
+            _ => RepositoryError::DatabaseError(e),
+        }
+    }
+}
+
+/// Error of a write, the untyped database errors are reported as `EntityNotSaved`
+pub(crate) fn not_saved(e: sqlx::Error) -> RepositoryError {
+    match RepositoryError::from(e) {
+        RepositoryError::EntityNotFound | RepositoryError::DatabaseError(_) => RepositoryError::EntityNotSaved,
+        e => e,
+    }
+}
+
+/// Result of an update by id and version, made of the id of the updated row
+/// and whether a row has the id, the version differs when it has but none was updated
+pub(crate) fn updated_id((updated_id, found): (Option<i32>, bool)) -> Result<i32, RepositoryError> {
+    match (updated_id, found) {
+        (Some(id), _) => Ok(id),
+        (None, true) => Err(RepositoryError::VersionConflict),
+        (None, false) => Err(RepositoryError::EntityNotFound),
+    }
+}
 #[async_trait]
 pub trait Repo<E: Entity>: Send + Sync {
     async fn get(&self, id: i32) -> Result<E, RepositoryError>;
     async fn save_or_update(&self, entity: &E) -> Result<i32, RepositoryError>;
+
+    /// Save or update as part of `unit_of_work`.
+    /// Repositories which can't join a unit of work write immediately.
+    async fn save_or_update_in(&self, entity: &E, _unit_of_work: &mut UnitOfWork) -> Result<i32, RepositoryError>
+    where
+        E: Sync,
+    {
+        self.save_or_update(entity).await
+    }
+
+    async fn list(&self, _filter: &Filter<E::Field>) -> Result<Page<E>, RepositoryError>
+    where
+        E: Queryable,
+    {
+        Err(RepositoryError::Unsupported)
+    }
+
+    /// `EntityNotFound` when no entity has the id
+    async fn delete(&self, _id: i32) -> Result<(), RepositoryError> {
+        Err(RepositoryError::Unsupported)
+    }
+
+    /// Delete as part of `unit_of_work`.
+    /// Repositories which can't join a unit of work delete immediately.
+    async fn delete_in(&self, id: i32, _unit_of_work: &mut UnitOfWork) -> Result<(), RepositoryError> {
+        self.delete(id).await
+    }
+
+    async fn exists(&self, id: i32) -> Result<bool, RepositoryError> {
+        match self.get(id).await {
+            Ok(_) => Ok(true),
+            Err(RepositoryError::EntityNotFound) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
 }
 
 #[async_trait]
@@ -29,6 +130,29 @@ impl<E: Entity + Sync> Repo<E> for Box<dyn Repo<E>> {
     async fn save_or_update(&self, entity: &E) -> Result<i32, RepositoryError> {
         self.as_ref().save_or_update(entity).await
     }
+
+    async fn save_or_update_in(&self, entity: &E, unit_of_work: &mut UnitOfWork) -> Result<i32, RepositoryError> {
+        self.as_ref().save_or_update_in(entity, unit_of_work).await
+    }
+
+    async fn list(&self, filter: &Filter<E::Field>) -> Result<Page<E>, RepositoryError>
+    where
+        E: Queryable,
+    {
+        self.as_ref().list(filter).await
+    }
+
+    async fn delete(&self, id: i32) -> Result<(), RepositoryError> {
+        self.as_ref().delete(id).await
+    }
+
+    async fn delete_in(&self, id: i32, unit_of_work: &mut UnitOfWork) -> Result<(), RepositoryError> {
+        self.as_ref().delete_in(id, unit_of_work).await
+    }
+
+    async fn exists(&self, id: i32) -> Result<bool, RepositoryError> {
+        self.as_ref().exists(id).await
+    }
 }
 
 #[async_trait]
@@ -40,6 +164,29 @@ impl<E: Entity + Sync> Repo<E> for Arc<dyn Repo<E>> {
     async fn save_or_update(&self, entity: &E) -> Result<i32, RepositoryError> {
         self.as_ref().save_or_update(entity).await
     }
+
+    async fn save_or_update_in(&self, entity: &E, unit_of_work: &mut UnitOfWork) -> Result<i32, RepositoryError> {
+        self.as_ref().save_or_update_in(entity, unit_of_work).await
+    }
+
+    async fn list(&self, filter: &Filter<E::Field>) -> Result<Page<E>, RepositoryError>
+    where
+        E: Queryable,
+    {
+        self.as_ref().list(filter).await
+    }
+
+    async fn delete(&self, id: i32) -> Result<(), RepositoryError> {
+        self.as_ref().delete(id).await
+    }
+
+    async fn delete_in(&self, id: i32, unit_of_work: &mut UnitOfWork) -> Result<(), RepositoryError> {
+        self.as_ref().delete_in(id, unit_of_work).await
+    }
+
+    async fn exists(&self, id: i32) -> Result<bool, RepositoryError> {
+        self.as_ref().exists(id).await
+    }
 }
 
 #[async_trait]
@@ -47,6 +194,41 @@ pub trait RepoByName<E: Entity>: Send + Sync {
     async fn get(&self, id: i32) -> Result<E, RepositoryError>;
     async fn save_or_update(&self, entity: &E) -> Result<i32, RepositoryError>;
     async fn get_by_name(&self, name: &str) -> Result<E, RepositoryError>;
+
+    /// Save or update as part of `unit_of_work`.
+    /// Repositories which can't join a unit of work write immediately.
+    async fn save_or_update_in(&self, entity: &E, _unit_of_work: &mut UnitOfWork) -> Result<i32, RepositoryError>
+    where
+        E: Sync,
+    {
+        self.save_or_update(entity).await
+    }
+
+    async fn list(&self, _filter: &Filter<E::Field>) -> Result<Page<E>, RepositoryError>
+    where
+        E: Queryable,
+    {
+        Err(RepositoryError::Unsupported)
+    }
+
+    /// `EntityNotFound` when no entity has the id
+    async fn delete(&self, _id: i32) -> Result<(), RepositoryError> {
+        Err(RepositoryError::Unsupported)
+    }
+
+    /// Delete as part of `unit_of_work`.
+    /// Repositories which can't join a unit of work delete immediately.
+    async fn delete_in(&self, id: i32, _unit_of_work: &mut UnitOfWork) -> Result<(), RepositoryError> {
+        self.delete(id).await
+    }
+
+    async fn exists(&self, id: i32) -> Result<bool, RepositoryError> {
+        match self.get(id).await {
+            Ok(_) => Ok(true),
+            Err(RepositoryError::EntityNotFound) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
 }
 
 #[async_trait]
@@ -61,6 +243,29 @@ impl<E: Entity + std::marker::Sync> RepoByName<E> for Arc<dyn RepoByName<E>> {
     async fn get_by_name(&self, name: &str) -> Result<E, RepositoryError> {
         self.as_ref().get_by_name(name).await
     }
+
+    async fn save_or_update_in(&self, entity: &E, unit_of_work: &mut UnitOfWork) -> Result<i32, RepositoryError> {
+        self.as_ref().save_or_update_in(entity, unit_of_work).await
+    }
+
+    async fn list(&self, filter: &Filter<E::Field>) -> Result<Page<E>, RepositoryError>
+    where
+        E: Queryable,
+    {
+        self.as_ref().list(filter).await
+    }
+
+    async fn delete(&self, id: i32) -> Result<(), RepositoryError> {
+        self.as_ref().delete(id).await
+    }
+
+    async fn delete_in(&self, id: i32, unit_of_work: &mut UnitOfWork) -> Result<(), RepositoryError> {
+        self.as_ref().delete_in(id, unit_of_work).await
+    }
+
+    async fn exists(&self, id: i32) -> Result<bool, RepositoryError> {
+        self.as_ref().exists(id).await
+    }
 }
 
 #[derive(Clone, Debug)]
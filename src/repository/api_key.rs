@@ -0,0 +1,337 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use chrono::{Duration, NaiveDateTime, Utc};
+use crate::config::db::{create_pool, DatabaseConfig};
+use crate::repository::{not_saved, Entity, Repo, RepositoryError};
+use derive_new::new;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use sqlx::{Pool, Postgres};
+use uuid::Uuid;
+
+pub const API_KEY_PREFIX: &str = "dop_";
+pub const API_KEY_DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum ApiKeyScope {
+    #[serde(rename = "import")]
+    Import,
+    #[serde(rename = "admin:read")]
+    AdminRead,
+    #[serde(rename = "admin:write")]
+    AdminWrite,
+}
+
+impl ApiKeyScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiKeyScope::Import => "import",
+            ApiKeyScope::AdminRead => "admin:read",
+            ApiKeyScope::AdminWrite => "admin:write",
+        }
+    }
+
+    pub fn parse(scope: &str) -> Option<ApiKeyScope> {
+        match scope {
+            "import" => Some(ApiKeyScope::Import),
+            "admin:read" => Some(ApiKeyScope::AdminRead),
+            "admin:write" => Some(ApiKeyScope::AdminWrite),
+            _ => None,
+        }
+    }
+}
+
+#[derive(sqlx::FromRow, Debug, Clone, PartialEq, new)]
+pub struct ApiKey {
+    id: i32,
+    name: String,
+    key_hash: String,
+    scopes: Vec<String>,
+    expires_at: Option<NaiveDateTime>,
+    last_used_at: Option<NaiveDateTime>,
+}
+
+impl ApiKey {
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn key_hash(&self) -> &str {
+        &self.key_hash
+    }
+
+    pub fn scopes(&self) -> Vec<ApiKeyScope> {
+        self.scopes.iter()
+            .filter_map(|scope| ApiKeyScope::parse(scope))
+            .collect()
+    }
+
+    pub fn expires_at(&self) -> Option<NaiveDateTime> {
+        self.expires_at
+    }
+
+    pub fn last_used_at(&self) -> Option<NaiveDateTime> {
+        self.last_used_at
+    }
+
+    pub fn has_scope(&self, scope: ApiKeyScope) -> bool {
+        self.scopes.iter().any(|s| s == scope.as_str())
+    }
+
+    pub fn is_expired(&self, now: NaiveDateTime) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+impl Entity for ApiKey {
+    fn id(&self) -> String {
+        self.id.to_string()
+    }
+}
+
+/// An api key declared in `app.toml`, only the hash of the key is stored
+#[derive(Clone, Deserialize, Debug, new)]
+pub struct ApiKeyConf {
+    name: String,
+    key_hash: String,
+    scopes: Vec<ApiKeyScope>,
+    expires_at: Option<String>,
+}
+
+impl ApiKeyConf {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn key_hash(&self) -> &str {
+        &self.key_hash
+    }
+
+    pub fn scopes(&self) -> &Vec<ApiKeyScope> {
+        &self.scopes
+    }
+
+    pub fn expires_at(&self) -> Option<&str> {
+        self.expires_at.as_deref()
+    }
+}
+
+/// Generate a new plain text api key, it is only shown once and must be stored hashed
+pub fn generate_api_key() -> String {
+    format!("{}{}{}", API_KEY_PREFIX, Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+pub fn hash_api_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+#[async_trait]
+pub trait ApiKeyRepoT: Repo<ApiKey> {
+    async fn get_by_hash(&self, key_hash: &str) -> Result<ApiKey, RepositoryError>;
+    async fn touch_last_used(&self, id: i32, last_used_at: NaiveDateTime) -> Result<(), RepositoryError>;
+}
+
+/// Replace the key `id` by a new one with the same name and scopes.
+/// The old key stays valid for `grace_period` so clients can switch without downtime.
+/// Returns the new key and its plain text value.
+pub async fn rotate_api_key(
+    api_key_repo: &dyn ApiKeyRepoT,
+    id: i32,
+    grace_period: Duration,
+) -> Result<(ApiKey, String), RepositoryError> {
+    let old_key = api_key_repo.get(id).await?;
+    let plain_key = generate_api_key();
+    let mut new_key = ApiKey::new(
+        0,
+        old_key.name.clone(),
+        hash_api_key(&plain_key),
+        old_key.scopes.clone(),
+        old_key.expires_at,
+        None,
+    );
+    new_key.id = api_key_repo.save_or_update(&new_key).await?;
+
+    let grace_end = Utc::now().naive_utc() + grace_period;
+    let old_expires_at = match old_key.expires_at {
+        Some(expires_at) if expires_at < grace_end => expires_at,
+        _ => grace_end,
+    };
+    api_key_repo.save_or_update(&ApiKey { expires_at: Some(old_expires_at), ..old_key }).await?;
+
+    Ok((new_key, plain_key))
+}
+
+#[derive(Debug, Clone)]
+pub struct ApiKeyRepo {
+    pub pool: Pool<Postgres>,
+}
+
+impl ApiKeyRepo {
+    pub async fn new(database_config: &DatabaseConfig) -> Result<ApiKeyRepo, RepositoryError> {
+        create_pool(database_config).await.map(Self::from_pool).map_err(RepositoryError::from)
+    }
+
+    pub fn from_pool(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl Repo<ApiKey> for ApiKeyRepo {
+    async fn get(&self, id: i32) -> Result<ApiKey, RepositoryError> {
+        sqlx::query_as::<_, ApiKey>("
+SELECT id, name, key_hash, scopes, expires_at, last_used_at
+FROM \"api_key\"
+WHERE id = $1
+LIMIT 1
+")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(RepositoryError::from)
+    }
+
+    async fn save_or_update(&self, api_key: &ApiKey) -> Result<i32, RepositoryError> {
+        if api_key.id == 0 {
+            return sqlx::query_scalar::<_, i32>("
+INSERT INTO \"api_key\" (name, key_hash, scopes, expires_at, last_used_at)
+VALUES ($1, $2, $3, $4, $5)
+RETURNING id
+    ")
+                .bind(&api_key.name)
+                .bind(&api_key.key_hash)
+                .bind(&api_key.scopes)
+                .bind(api_key.expires_at)
+                .bind(api_key.last_used_at)
+                .fetch_one(&self.pool)
+                .await
+                .map_err(not_saved);
+        }
+        sqlx::query_scalar::<_, i32>("
+UPDATE \"api_key\"
+SET name = $2, key_hash = $3, scopes = $4, expires_at = $5, last_used_at = $6
+WHERE id = $1
+RETURNING id
+    ")
+            .bind(api_key.id)
+            .bind(&api_key.name)
+            .bind(&api_key.key_hash)
+            .bind(&api_key.scopes)
+            .bind(api_key.expires_at)
+            .bind(api_key.last_used_at)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(not_saved)
+    }
+}
+
+#[async_trait]
+impl ApiKeyRepoT for ApiKeyRepo {
+    async fn get_by_hash(&self, key_hash: &str) -> Result<ApiKey, RepositoryError> {
+        sqlx::query_as::<_, ApiKey>("
+SELECT id, name, key_hash, scopes, expires_at, last_used_at
+FROM \"api_key\"
+WHERE key_hash = $1
+LIMIT 1
+")
+            .bind(key_hash)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(RepositoryError::from)
+    }
+
+    async fn touch_last_used(&self, id: i32, last_used_at: NaiveDateTime) -> Result<(), RepositoryError> {
+        sqlx::query("
+UPDATE \"api_key\"
+SET last_used_at = $2
+WHERE id = $1
+")
+            .bind(id)
+            .bind(last_used_at)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+            .map_err(not_saved)
+    }
+}
+
+/// Api keys declared in the configuration file, `last_used_at` is only kept in memory
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryApiKeyRepo {
+    map: Arc<RwLock<HashMap<i32, ApiKey>>>,
+}
+
+impl InMemoryApiKeyRepo {
+    pub fn from_conf(api_keys_conf: &[ApiKeyConf]) -> Self {
+        let repo = Self::default();
+        {
+            let mut map = repo.map.write().unwrap();
+            for (i, api_key_conf) in api_keys_conf.iter().enumerate() {
+                let expires_at = match api_key_conf.expires_at() {
+                    Some(expires_at) => match NaiveDateTime::parse_from_str(expires_at, API_KEY_DATE_FORMAT) {
+                        Ok(expires_at) => Some(expires_at),
+                        Err(_) => {
+                            println!("api key {} has an invalid expires_at, it is ignored", api_key_conf.name());
+                            continue;
+                        }
+                    },
+                    None => None,
+                };
+                let id = i as i32 + 1;
+                map.insert(id, ApiKey::new(
+                    id,
+                    api_key_conf.name().to_string(),
+                    api_key_conf.key_hash().to_lowercase(),
+                    api_key_conf.scopes().iter().map(|scope| scope.as_str().to_string()).collect(),
+                    expires_at,
+                    None,
+                ));
+            }
+        }
+        repo
+    }
+}
+
+#[async_trait]
+impl Repo<ApiKey> for InMemoryApiKeyRepo {
+    async fn get(&self, id: i32) -> Result<ApiKey, RepositoryError> {
+        self.map.read().unwrap().get(&id).cloned().ok_or(RepositoryError::EntityNotFound)
+    }
+
+    async fn save_or_update(&self, api_key: &ApiKey) -> Result<i32, RepositoryError> {
+        let mut map = self.map.write().unwrap();
+        let id = if api_key.id == 0 {
+            map.keys().max().copied().unwrap_or(0) + 1
+        } else {
+            api_key.id
+        };
+        map.insert(id, ApiKey { id, ..api_key.clone() });
+        Ok(id)
+    }
+}
+
+#[async_trait]
+impl ApiKeyRepoT for InMemoryApiKeyRepo {
+    async fn get_by_hash(&self, key_hash: &str) -> Result<ApiKey, RepositoryError> {
+        self.map.read().unwrap()
+            .values()
+            .find(|api_key| api_key.key_hash == key_hash)
+            .cloned()
+            .ok_or(RepositoryError::EntityNotFound)
+    }
+
+    async fn touch_last_used(&self, id: i32, last_used_at: NaiveDateTime) -> Result<(), RepositoryError> {
+        match self.map.write().unwrap().get_mut(&id) {
+            Some(api_key) => {
+                api_key.last_used_at = Some(last_used_at);
+                Ok(())
+            }
+            None => Err(RepositoryError::EntityNotFound)
+        }
+    }
+}
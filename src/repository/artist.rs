@@ -1,14 +1,19 @@
 use std::sync::Arc;
 use async_trait::async_trait;
 use crate::config::db::{create_pool, DatabaseConfig};
-use crate::repository::{Entity, RepoByName, RepositoryError};
+use crate::repository::query::{self, Field, Filter, Page, Queryable, Value};
+use crate::repository::unit_of_work::UnitOfWork;
+use crate::repository::{not_saved, updated_id, Entity, RepoByName, RepositoryError};
 use derive_new::new;
-use sqlx::{Pool, Postgres};
+use sqlx::{PgExecutor, Pool, Postgres};
+use unicode_normalization::UnicodeNormalization;
 
 #[derive(sqlx::FromRow, Debug, Clone, PartialEq, new)]
 pub struct Artist {
     id: i32,
     name: String,
+    #[new(default)]
+    version: i32,
 }
 
 impl Artist {
@@ -19,6 +24,16 @@ impl Artist {
     pub fn name(&self) -> &str {
         &self.name
     }
+
+    /// Incremented by each update
+    pub fn version(&self) -> i32 {
+        self.version
+    }
+
+    pub fn with_version(mut self, version: i32) -> Self {
+        self.version = version;
+        self
+    }
 }
 
 impl Entity for Artist {
@@ -27,6 +42,45 @@ impl Entity for Artist {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArtistField {
+    Id,
+    Name,
+}
+
+impl Field for ArtistField {
+    const ID: Self = ArtistField::Id;
+
+    fn column(&self) -> &'static str {
+        match self {
+            ArtistField::Id => "id",
+            ArtistField::Name => "name",
+        }
+    }
+}
+
+impl Queryable for Artist {
+    type Field = ArtistField;
+
+    fn value(&self, field: ArtistField) -> Value {
+        match field {
+            ArtistField::Id => self.id.into(),
+            ArtistField::Name => self.name.as_str().into(),
+        }
+    }
+}
+
+/// Form under which artist names are compared: NFKC, lowercase,
+/// without leading or trailing spaces and with inner spaces collapsed
+pub fn normalize_artist_name(name: &str) -> String {
+    name.nfkc()
+        .collect::<String>()
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 #[derive(Debug, Clone)]
 pub struct ArtistRepo {
     pub pool: Pool<Postgres>,
@@ -34,17 +88,41 @@ pub struct ArtistRepo {
 
 impl ArtistRepo {
     pub async fn new(database_config: &DatabaseConfig) -> Result<ArtistRepo, RepositoryError> {
-        match create_pool(database_config).await {
-            Ok(pool) => Ok(Self { pool }),
-            Err(err) => Err(RepositoryError::DatabaseError(err))
+        create_pool(database_config).await.map(Self::from_pool).map_err(RepositoryError::from)
+    }
+
+    pub fn from_pool(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+
+    /// Fills the normalized name of the artists saved before it was stored, gives the names
+    /// left without because another artist has the same normalized name
+    pub async fn fill_normalized_names(&self) -> Result<Vec<String>, RepositoryError> {
+        let artists = sqlx::query_as::<_, Artist>("SELECT id, name, version FROM \"artist\" WHERE normalized_name IS NULL ORDER BY id")
+            .fetch_all(&self.pool)
+            .await?;
+        let mut clashing_names = Vec::new();
+        for artist in artists {
+            let result = sqlx::query("UPDATE \"artist\" SET normalized_name = $2 WHERE id = $1")
+                .bind(artist.id)
+                .bind(normalize_artist_name(&artist.name))
+                .execute(&self.pool)
+                .await
+                .map_err(RepositoryError::from);
+            match result {
+                Ok(_) => {}
+                Err(RepositoryError::UniqueViolation) => clashing_names.push(artist.name),
+                Err(e) => return Err(e),
+            }
         }
+        Ok(clashing_names)
     }
 }
 #[async_trait]
 impl RepoByName<Artist> for ArtistRepo {
     async fn get(&self, id: i32) -> Result<Artist, RepositoryError> {
         sqlx::query_as::<_, Artist>("
-SELECT id, name
+SELECT id, name, version
 FROM \"artist\"
 WHERE id = $1
 LIMIT 1
@@ -52,33 +130,80 @@ LIMIT 1
             .bind(id)
             .fetch_one(&self.pool)
             .await
-            .map_err(|_| RepositoryError::EntityNotFound)
+            .map_err(RepositoryError::from)
     }
 
     async fn save_or_update(&self, artist: &Artist) -> Result<i32, RepositoryError> {
-        sqlx::query_scalar::<_, i32>("
-INSERT INTO \"artist\" (name)
-VALUES ($1)
-RETURNING id
-    ")
-            .bind(artist.name.clone())
-            .fetch_one(&self.pool)
-            .await
-            .map_err(|_| RepositoryError::EntityNotSaved)
+        save_artist(&self.pool, artist).await
     }
 
+    /// Matched on the normalized name stored with each artist
     async fn get_by_name(&self, name: &str) -> Result<Artist, RepositoryError> {
         sqlx::query_as::<_, Artist>("
-SELECT id, name
+SELECT id, name, version
 FROM \"artist\"
-WHERE name = $1
+WHERE normalized_name = $1
 LIMIT 1
 ")
-            .bind(name)
+            .bind(normalize_artist_name(name))
             .fetch_one(&self.pool)
             .await
-            .map_err(|_| RepositoryError::EntityNotFound)
+            .map_err(RepositoryError::from)
     }
+
+    async fn save_or_update_in(&self, artist: &Artist, unit_of_work: &mut UnitOfWork) -> Result<i32, RepositoryError> {
+        save_artist(unit_of_work.connection(&self.pool).await?, artist).await
+    }
+
+    async fn list(&self, filter: &Filter<ArtistField>) -> Result<Page<Artist>, RepositoryError> {
+        query::list(&self.pool, "artist", "id, name, version", filter).await
+    }
+
+    async fn delete(&self, id: i32) -> Result<(), RepositoryError> {
+        query::delete(&self.pool, "artist", id).await
+    }
+
+    async fn delete_in(&self, id: i32, unit_of_work: &mut UnitOfWork) -> Result<(), RepositoryError> {
+        query::delete(unit_of_work.connection(&self.pool).await?, "artist", id).await
+    }
+
+    async fn exists(&self, id: i32) -> Result<bool, RepositoryError> {
+        query::exists(&self.pool, "artist", id).await
+    }
+}
+
+/// An artist without id whose normalized name is taken gets the id of the existing one, which keeps its name
+async fn save_artist<'e>(executor: impl PgExecutor<'e>, artist: &Artist) -> Result<i32, RepositoryError> {
+    if artist.id == 0 {
+        return sqlx::query_scalar::<_, i32>("
+INSERT INTO \"artist\" (name, normalized_name)
+VALUES ($1, $2)
+ON CONFLICT (normalized_name) DO UPDATE SET normalized_name = EXCLUDED.normalized_name
+RETURNING id
+    ")
+            .bind(&artist.name)
+            .bind(normalize_artist_name(&artist.name))
+            .fetch_one(executor)
+            .await
+            .map_err(not_saved);
+    }
+    sqlx::query_as::<_, (Option<i32>, bool)>("
+WITH updated AS (
+    UPDATE \"artist\"
+    SET name = $2, normalized_name = $3, version = version + 1
+    WHERE id = $1 AND version = $4
+    RETURNING id
+)
+SELECT (SELECT id FROM updated), EXISTS (SELECT 1 FROM \"artist\" WHERE id = $1)
+    ")
+        .bind(artist.id)
+        .bind(&artist.name)
+        .bind(normalize_artist_name(&artist.name))
+        .bind(artist.version)
+        .fetch_one(executor)
+        .await
+        .map_err(not_saved)
+        .and_then(updated_id)
 }
 
 #[async_trait]
@@ -94,4 +219,24 @@ impl RepoByName<Artist> for Arc<ArtistRepo> {
     async fn get_by_name(&self, name: &str) -> Result<Artist, RepositoryError> {
         self.as_ref().get_by_name(name).await
     }
+
+    async fn save_or_update_in(&self, entity: &Artist, unit_of_work: &mut UnitOfWork) -> Result<i32, RepositoryError> {
+        self.as_ref().save_or_update_in(entity, unit_of_work).await
+    }
+
+    async fn list(&self, filter: &Filter<ArtistField>) -> Result<Page<Artist>, RepositoryError> {
+        self.as_ref().list(filter).await
+    }
+
+    async fn delete(&self, id: i32) -> Result<(), RepositoryError> {
+        self.as_ref().delete(id).await
+    }
+
+    async fn delete_in(&self, id: i32, unit_of_work: &mut UnitOfWork) -> Result<(), RepositoryError> {
+        self.as_ref().delete_in(id, unit_of_work).await
+    }
+
+    async fn exists(&self, id: i32) -> Result<bool, RepositoryError> {
+        self.as_ref().exists(id).await
+    }
 }
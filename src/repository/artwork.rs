@@ -0,0 +1,162 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use crate::config::db::{create_pool, DatabaseConfig};
+use crate::repository::query;
+use crate::repository::unit_of_work::UnitOfWork;
+use crate::repository::{not_saved, updated_id, Entity, Repo, RepositoryError};
+use derive_new::new;
+use sqlx::{PgExecutor, Pool, Postgres};
+
+/// Cover image of a drop, its resized variants are stored next to the playlists
+#[derive(sqlx::FromRow, Debug, Clone, PartialEq, new)]
+pub struct Artwork {
+    id: i32,
+    mime_type: String,
+    width: i32,
+    height: i32,
+    #[new(default)]
+    version: i32,
+}
+
+impl Artwork {
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    /// Mime type of the original image
+    pub fn mime_type(&self) -> &str {
+        &self.mime_type
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    /// Incremented by each update
+    pub fn version(&self) -> i32 {
+        self.version
+    }
+
+    pub fn with_version(mut self, version: i32) -> Self {
+        self.version = version;
+        self
+    }
+}
+
+impl Entity for Artwork {
+    fn id(&self) -> String {
+        self.id.to_string()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ArtworkRepo {
+    pool: Pool<Postgres>,
+}
+
+impl ArtworkRepo {
+    pub async fn new(database_config: &DatabaseConfig) -> Result<ArtworkRepo, RepositoryError> {
+        create_pool(database_config).await.map(Self::from_pool).map_err(RepositoryError::from)
+    }
+
+    pub fn from_pool(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+
+    pub fn pool(&self) -> &Pool<Postgres> {
+        &self.pool
+    }
+}
+
+#[async_trait]
+impl Repo<Artwork> for ArtworkRepo {
+    async fn get(&self, id: i32) -> Result<Artwork, RepositoryError> {
+        sqlx::query_as::<_, Artwork>("
+SELECT id, mime_type, width, height, version
+FROM \"artwork\"
+WHERE id = $1
+LIMIT 1
+")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(RepositoryError::from)
+    }
+
+    async fn save_or_update(&self, artwork: &Artwork) -> Result<i32, RepositoryError> {
+        save_artwork(&self.pool, artwork).await
+    }
+
+    async fn save_or_update_in(&self, artwork: &Artwork, unit_of_work: &mut UnitOfWork) -> Result<i32, RepositoryError> {
+        save_artwork(unit_of_work.connection(&self.pool).await?, artwork).await
+    }
+
+    async fn delete(&self, id: i32) -> Result<(), RepositoryError> {
+        query::delete(&self.pool, "artwork", id).await
+    }
+
+    async fn delete_in(&self, id: i32, unit_of_work: &mut UnitOfWork) -> Result<(), RepositoryError> {
+        query::delete(unit_of_work.connection(&self.pool).await?, "artwork", id).await
+    }
+}
+
+async fn save_artwork<'e>(executor: impl PgExecutor<'e>, artwork: &Artwork) -> Result<i32, RepositoryError> {
+    if artwork.id == 0 {
+        return sqlx::query_scalar::<_, i32>("
+INSERT INTO \"artwork\" (mime_type, width, height)
+VALUES ($1, $2, $3)
+RETURNING id
+    ")
+            .bind(&artwork.mime_type)
+            .bind(artwork.width)
+            .bind(artwork.height)
+            .fetch_one(executor)
+            .await
+            .map_err(not_saved);
+    }
+    sqlx::query_as::<_, (Option<i32>, bool)>("
+WITH updated AS (
+    UPDATE \"artwork\"
+    SET mime_type = $2, width = $3, height = $4, version = version + 1
+    WHERE id = $1 AND version = $5
+    RETURNING id
+)
+SELECT (SELECT id FROM updated), EXISTS (SELECT 1 FROM \"artwork\" WHERE id = $1)
+    ")
+        .bind(artwork.id)
+        .bind(&artwork.mime_type)
+        .bind(artwork.width)
+        .bind(artwork.height)
+        .bind(artwork.version)
+        .fetch_one(executor)
+        .await
+        .map_err(not_saved)
+        .and_then(updated_id)
+}
+
+#[async_trait]
+impl Repo<Artwork> for Arc<ArtworkRepo> {
+    async fn get(&self, id: i32) -> Result<Artwork, RepositoryError> {
+        self.as_ref().get(id).await
+    }
+
+    async fn save_or_update(&self, entity: &Artwork) -> Result<i32, RepositoryError> {
+        self.as_ref().save_or_update(entity).await
+    }
+
+    async fn save_or_update_in(&self, entity: &Artwork, unit_of_work: &mut UnitOfWork) -> Result<i32, RepositoryError> {
+        self.as_ref().save_or_update_in(entity, unit_of_work).await
+    }
+
+    async fn delete(&self, id: i32) -> Result<(), RepositoryError> {
+        self.as_ref().delete(id).await
+    }
+
+    async fn delete_in(&self, id: i32, unit_of_work: &mut UnitOfWork) -> Result<(), RepositoryError> {
+        self.as_ref().delete_in(id, unit_of_work).await
+    }
+}
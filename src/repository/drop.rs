@@ -1,9 +1,18 @@
 use std::sync::Arc;
 use async_trait::async_trait;
 use crate::config::db::{create_pool, DatabaseConfig};
-use crate::repository::{Entity, Repo, RepositoryError};
+use crate::repository::drop_type::DropType;
+use crate::repository::query::{self, Field, Filter, Page, Queryable, Value};
+use crate::repository::unit_of_work::UnitOfWork;
+use crate::repository::{not_saved, updated_id, Entity, Repo, RepositoryError};
+use chrono::NaiveDate;
 use derive_new::new;
-use sqlx::{Pool, Postgres};
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use sqlx::{PgExecutor, Pool, Postgres};
+
+// a drop without artwork has a NULL artwork_id in the table, 0 (NO_ARTWORK_ID) once read
+const DROP_COLUMNS: &str = "id, artist_id, type_id, COALESCE(artwork_id, 0) AS artwork_id, playlist_id, published, release_date, label, genre, description, credits, version";
 
 #[derive(sqlx::FromRow, Debug, Clone, PartialEq, new)]
 pub struct Drop {
@@ -11,6 +20,70 @@ pub struct Drop {
     artist_id: i32,
     type_id: i16,
     artwork_id: i32,
+    playlist_id: i32,
+    #[new(value = "true")]
+    published: bool,
+    #[sqlx(flatten)]
+    #[new(default)]
+    release: Release,
+    #[new(default)]
+    version: i32,
+}
+
+/// Release fields and credits of a drop, as given by its manifest
+#[derive(sqlx::FromRow, Debug, Clone, Default, PartialEq, Deserialize, Serialize, new)]
+pub struct Release {
+    release_date: Option<NaiveDate>,
+    label: Option<String>,
+    genre: Option<String>,
+    description: Option<String>,
+    #[serde(default)]
+    #[new(default)]
+    credits: Json<Vec<Credit>>,
+}
+
+impl Release {
+    pub fn release_date(&self) -> Option<NaiveDate> {
+        self.release_date
+    }
+
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    pub fn genre(&self) -> Option<&str> {
+        self.genre.as_deref()
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn credits(&self) -> &[Credit] {
+        &self.credits.0
+    }
+
+    pub fn with_credits(mut self, credits: Vec<Credit>) -> Self {
+        self.credits = Json(credits);
+        self
+    }
+}
+
+/// Who took part in a drop and how, e.g. `producer`
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, new)]
+pub struct Credit {
+    role: String,
+    name: String,
+}
+
+impl Credit {
+    pub fn role(&self) -> &str {
+        &self.role
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
 }
 
 impl Drop {
@@ -26,9 +99,46 @@ impl Drop {
         self.type_id
     }
 
+    pub fn drop_type(&self) -> Option<DropType> {
+        DropType::from_id(self.type_id)
+    }
+
     pub fn artwork_id(&self) -> i32 {
         self.artwork_id
     }
+
+    pub fn playlist_id(&self) -> i32 {
+        self.playlist_id
+    }
+
+    /// An unpublished drop is served neither by its tags nor by the catalog
+    pub fn published(&self) -> bool {
+        self.published
+    }
+
+    pub fn with_published(mut self, published: bool) -> Self {
+        self.published = published;
+        self
+    }
+
+    pub fn release(&self) -> &Release {
+        &self.release
+    }
+
+    pub fn with_release(mut self, release: Release) -> Self {
+        self.release = release;
+        self
+    }
+
+    /// Incremented by each update
+    pub fn version(&self) -> i32 {
+        self.version
+    }
+
+    pub fn with_version(mut self, version: i32) -> Self {
+        self.version = version;
+        self
+    }
 }
 
 impl Entity for Drop {
@@ -37,6 +147,46 @@ impl Entity for Drop {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DropField {
+    Id,
+    ArtistId,
+    TypeId,
+    ArtworkId,
+    PlaylistId,
+    Published,
+}
+
+impl Field for DropField {
+    const ID: Self = DropField::Id;
+
+    fn column(&self) -> &'static str {
+        match self {
+            DropField::Id => "id",
+            DropField::ArtistId => "artist_id",
+            DropField::TypeId => "type_id",
+            DropField::ArtworkId => "COALESCE(artwork_id, 0)",
+            DropField::PlaylistId => "playlist_id",
+            DropField::Published => "published",
+        }
+    }
+}
+
+impl Queryable for Drop {
+    type Field = DropField;
+
+    fn value(&self, field: DropField) -> Value {
+        match field {
+            DropField::Id => self.id.into(),
+            DropField::ArtistId => self.artist_id.into(),
+            DropField::TypeId => self.type_id.into(),
+            DropField::ArtworkId => self.artwork_id.into(),
+            DropField::PlaylistId => self.playlist_id.into(),
+            DropField::Published => self.published.into(),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct DropRepo {
     pool: Pool<Postgres>,
@@ -48,13 +198,14 @@ pub enum DropRepoError {
 }
 
 impl DropRepo {
-    pub async fn new(database_config: &DatabaseConfig) -> Result<DropRepo, RepositoryError>  {
-        match create_pool(database_config).await {
-            Ok(pool) => Ok(Self { pool }),
-            Err(err) => Err(RepositoryError::DatabaseError(err))
-        }
+    pub async fn new(database_config: &DatabaseConfig) -> Result<DropRepo, RepositoryError> {
+        create_pool(database_config).await.map(Self::from_pool).map_err(RepositoryError::from)
+    }
 
+    pub fn from_pool(pool: Pool<Postgres>) -> Self {
+        Self { pool }
     }
+
     pub fn pool(&self) -> &Pool<Postgres> {
         &self.pool
     }
@@ -63,36 +214,93 @@ impl DropRepo {
 #[async_trait]
 impl Repo<Drop> for DropRepo {
     async fn get(&self, id: i32) -> Result<Drop, RepositoryError> {
-        sqlx::query_as::<_, Drop>("
-SELECT id, artist_id, type_id, artwork_id
+        sqlx::query_as::<_, Drop>(&format!("
+SELECT {DROP_COLUMNS}
 FROM \"drop\"
 WHERE id = $1
 LIMIT 1
-")
+"))
             .bind(id)
             .fetch_one(&self.pool)
             .await
-            .map_err(|e| {
-                match e {
-                    sqlx::Error::RowNotFound => RepositoryError::EntityNotFound,
-                    _ => RepositoryError::DatabaseError(e),
-                }
-            })
+            .map_err(RepositoryError::from)
     }
 
     async fn save_or_update(&self, drop: &Drop) -> Result<i32, RepositoryError> {
-        sqlx::query_scalar::<_, i32>("
-INSERT INTO \"drop\" (artist_id, artwork_id, type_id)
-VALUES ($1, $2, $3)
+        save_drop(&self.pool, drop).await
+    }
+
+    async fn save_or_update_in(&self, drop: &Drop, unit_of_work: &mut UnitOfWork) -> Result<i32, RepositoryError> {
+        save_drop(unit_of_work.connection(&self.pool).await?, drop).await
+    }
+
+    async fn list(&self, filter: &Filter<DropField>) -> Result<Page<Drop>, RepositoryError> {
+        query::list(&self.pool, "drop", DROP_COLUMNS, filter).await
+    }
+
+    async fn delete(&self, id: i32) -> Result<(), RepositoryError> {
+        query::delete(&self.pool, "drop", id).await
+    }
+
+    async fn delete_in(&self, id: i32, unit_of_work: &mut UnitOfWork) -> Result<(), RepositoryError> {
+        query::delete(unit_of_work.connection(&self.pool).await?, "drop", id).await
+    }
+
+    async fn exists(&self, id: i32) -> Result<bool, RepositoryError> {
+        query::exists(&self.pool, "drop", id).await
+    }
+}
+
+/// A playlist has a single drop, a drop without id of a playlist which has one is a unique violation
+async fn save_drop<'e>(executor: impl PgExecutor<'e>, drop: &Drop) -> Result<i32, RepositoryError> {
+    if drop.id == 0 {
+        return sqlx::query_scalar::<_, i32>("
+INSERT INTO \"drop\" (artist_id, artwork_id, type_id, playlist_id, published, release_date, label, genre, description, credits)
+VALUES ($1, NULLIF($2, 0), $3, $4, $5, $6, $7, $8, $9, $10)
+ON CONFLICT (playlist_id) DO NOTHING
 RETURNING id
     ")
             .bind(drop.artist_id)
             .bind(drop.artwork_id)
             .bind(drop.type_id)
-            .fetch_one(&self.pool)
+            .bind(drop.playlist_id)
+            .bind(drop.published)
+            .bind(drop.release.release_date)
+            .bind(&drop.release.label)
+            .bind(&drop.release.genre)
+            .bind(&drop.release.description)
+            .bind(&drop.release.credits)
+            .fetch_optional(executor)
             .await
-            .map_err(|_| RepositoryError::EntityNotSaved)
+            .map_err(not_saved)?
+            .ok_or(RepositoryError::UniqueViolation);
     }
+    sqlx::query_as::<_, (Option<i32>, bool)>("
+WITH updated AS (
+    UPDATE \"drop\"
+    SET artist_id = $2, artwork_id = NULLIF($3, 0), type_id = $4, playlist_id = $5, published = $6,
+        release_date = $7, label = $8, genre = $9, description = $10, credits = $11, version = version + 1
+    WHERE id = $1 AND version = $12
+    RETURNING id
+)
+SELECT (SELECT id FROM updated), EXISTS (SELECT 1 FROM \"drop\" WHERE id = $1)
+    ")
+        .bind(drop.id)
+        .bind(drop.artist_id)
+        .bind(drop.artwork_id)
+        .bind(drop.type_id)
+        .bind(drop.playlist_id)
+        .bind(drop.published)
+        .bind(drop.release.release_date)
+        .bind(&drop.release.label)
+        .bind(&drop.release.genre)
+        .bind(&drop.release.description)
+        .bind(&drop.release.credits)
+        .bind(drop.version)
+        .fetch_one(executor)
+        .await
+        .map_err(not_saved)
+        .and_then(updated_id)
 }
 
 #[async_trait]
@@ -104,4 +312,24 @@ impl Repo<Drop> for Arc<DropRepo> {
     async fn save_or_update(&self, entity: &Drop) -> Result<i32, RepositoryError> {
         self.as_ref().save_or_update(entity).await
     }
+
+    async fn save_or_update_in(&self, entity: &Drop, unit_of_work: &mut UnitOfWork) -> Result<i32, RepositoryError> {
+        self.as_ref().save_or_update_in(entity, unit_of_work).await
+    }
+
+    async fn list(&self, filter: &Filter<DropField>) -> Result<Page<Drop>, RepositoryError> {
+        self.as_ref().list(filter).await
+    }
+
+    async fn delete(&self, id: i32) -> Result<(), RepositoryError> {
+        self.as_ref().delete(id).await
+    }
+
+    async fn delete_in(&self, id: i32, unit_of_work: &mut UnitOfWork) -> Result<(), RepositoryError> {
+        self.as_ref().delete_in(id, unit_of_work).await
+    }
+
+    async fn exists(&self, id: i32) -> Result<bool, RepositoryError> {
+        self.as_ref().exists(id).await
+    }
 }
@@ -0,0 +1,120 @@
+use crate::config::db::{create_pool, DatabaseConfig};
+use crate::repository::RepositoryError;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Postgres};
+
+/// Kind of release of a drop, its id is the row of the `drop_type` lookup table
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DropType {
+    Single,
+    Ep,
+    Album,
+    Mix,
+}
+
+impl DropType {
+    pub const ALL: [DropType; 4] = [DropType::Single, DropType::Ep, DropType::Album, DropType::Mix];
+
+    pub fn id(&self) -> i16 {
+        match self {
+            DropType::Single => 1,
+            DropType::Ep => 2,
+            DropType::Album => 3,
+            DropType::Mix => 4,
+        }
+    }
+
+    /// None for the drops created before types, stored with 0
+    pub fn from_id(id: i16) -> Option<Self> {
+        DropType::ALL.into_iter().find(|drop_type| drop_type.id() == id)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DropType::Single => "single",
+            DropType::Ep => "ep",
+            DropType::Album => "album",
+            DropType::Mix => "mix",
+        }
+    }
+
+    pub fn min_tracks(&self) -> usize {
+        match self {
+            DropType::Single => 1,
+            DropType::Ep => 4,
+            DropType::Album => 7,
+            DropType::Mix => 1,
+        }
+    }
+
+    pub fn max_tracks(&self) -> Option<usize> {
+        match self {
+            DropType::Single => Some(3),
+            DropType::Ep => Some(6),
+            DropType::Album | DropType::Mix => None,
+        }
+    }
+
+    pub fn accepts_track_count(&self, nb_tracks: usize) -> bool {
+        nb_tracks >= self.min_tracks() && self.max_tracks().is_none_or(|max_tracks| nb_tracks <= max_tracks)
+    }
+
+    /// A mix is a continuous recording, each of its tracks lists where the mixed tunes start
+    pub fn requires_cue_points(&self) -> bool {
+        matches!(self, DropType::Mix)
+    }
+
+    /// Type of a drop whose manifest gives none, a mix is never guessed
+    pub fn for_track_count(nb_tracks: usize) -> Self {
+        [DropType::Single, DropType::Ep].into_iter()
+            .find(|drop_type| drop_type.accepts_track_count(nb_tracks))
+            .unwrap_or(DropType::Album)
+    }
+}
+
+/// The `drop_type` lookup table, its rows mirror `DropType`
+#[derive(Debug, Clone)]
+pub struct DropTypeRepo {
+    pool: Pool<Postgres>,
+}
+
+impl DropTypeRepo {
+    pub async fn new(database_config: &DatabaseConfig) -> Result<DropTypeRepo, RepositoryError> {
+        create_pool(database_config).await.map(Self::from_pool).map_err(RepositoryError::from)
+    }
+
+    pub fn from_pool(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+
+    /// Insert the missing types and rename the changed ones
+    pub async fn sync(&self) -> Result<(), RepositoryError> {
+        for drop_type in DropType::ALL {
+            sqlx::query("
+INSERT INTO \"drop_type\" (id, name)
+VALUES ($1, $2)
+ON CONFLICT (id) DO UPDATE SET name = EXCLUDED.name
+")
+                .bind(drop_type.id())
+                .bind(drop_type.name())
+                .execute(&self.pool)
+                .await
+                .map_err(RepositoryError::from)?;
+        }
+        Ok(())
+    }
+
+    /// Types of the lookup table which `DropType` knows
+    pub async fn get_all(&self) -> Result<Vec<DropType>, RepositoryError> {
+        let ids = sqlx::query_scalar::<_, i16>("
+SELECT id
+FROM \"drop_type\"
+ORDER BY id
+")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(RepositoryError::from)?;
+        Ok(ids.into_iter().filter_map(DropType::from_id).collect())
+    }
+}
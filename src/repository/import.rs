@@ -0,0 +1,351 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use crate::config::db::{create_pool, DatabaseConfig};
+use crate::repository::unit_of_work::UnitOfWork;
+use crate::repository::{not_saved, Entity, Repo, RepositoryError};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::{PgExecutor, Pool, Postgres};
+use std::fs::File;
+use std::io;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportState {
+    Running,
+    Imported,
+    Failed,
+}
+
+impl ImportState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImportState::Running => "running",
+            ImportState::Imported => "imported",
+            ImportState::Failed => "failed",
+        }
+    }
+
+    pub fn parse(state: &str) -> Option<ImportState> {
+        match state {
+            "running" => Some(ImportState::Running),
+            "imported" => Some(ImportState::Imported),
+            "failed" => Some(ImportState::Failed),
+            _ => None,
+        }
+    }
+}
+
+/// A drop archive identified by the SHA-256 of its content, so it is imported only once
+#[derive(sqlx::FromRow, Debug, Clone, PartialEq, Serialize)]
+pub struct Import {
+    id: i32,
+    sha256: String,
+    file_name: String,
+    state: String,
+    drop_id: Option<i32>,
+    error: Option<String>,
+    created_at: NaiveDateTime,
+    updated_at: NaiveDateTime,
+}
+
+impl Import {
+    /// A new import in the running state
+    pub fn started(id: i32, sha256: String, file_name: String, started_at: NaiveDateTime) -> Self {
+        Self {
+            id,
+            sha256,
+            file_name,
+            state: ImportState::Running.as_str().to_string(),
+            drop_id: None,
+            error: None,
+            created_at: started_at,
+            updated_at: started_at,
+        }
+    }
+
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    pub fn sha256(&self) -> &str {
+        &self.sha256
+    }
+
+    pub fn file_name(&self) -> &str {
+        &self.file_name
+    }
+
+    pub fn state(&self) -> ImportState {
+        ImportState::parse(&self.state).unwrap_or(ImportState::Failed)
+    }
+
+    pub fn drop_id(&self) -> Option<i32> {
+        self.drop_id
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    pub fn created_at(&self) -> NaiveDateTime {
+        self.created_at
+    }
+
+    pub fn updated_at(&self) -> NaiveDateTime {
+        self.updated_at
+    }
+
+    pub fn set_imported(&mut self, drop_id: i32, updated_at: NaiveDateTime) {
+        self.state = ImportState::Imported.as_str().to_string();
+        self.drop_id = Some(drop_id);
+        self.error = None;
+        self.updated_at = updated_at;
+    }
+
+    pub fn set_failed(&mut self, error: String, updated_at: NaiveDateTime) {
+        self.state = ImportState::Failed.as_str().to_string();
+        self.drop_id = None;
+        self.error = Some(error);
+        self.updated_at = updated_at;
+    }
+}
+
+impl Entity for Import {
+    fn id(&self) -> String {
+        self.id.to_string()
+    }
+}
+
+/// SHA-256 of the file content, hex encoded
+pub fn sha256_of_file(file: &str) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(file)?, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+#[async_trait]
+pub trait ImportRepoT: Repo<Import> {
+    async fn get_by_sha256(&self, sha256: &str) -> Result<Import, RepositoryError>;
+
+    /// Record that the archive `sha256` is being imported. An archive which failed before can
+    /// be imported again. Returns None when it is already imported or being imported.
+    async fn start_import(
+        &self,
+        sha256: &str,
+        file_name: &str,
+        started_at: NaiveDateTime,
+    ) -> Result<Option<Import>, RepositoryError>;
+
+    /// Mark as failed the imports left running by a stopped server, so they can be retried
+    async fn interrupt_running(&self, updated_at: NaiveDateTime) -> Result<u64, RepositoryError>;
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportRepo {
+    pub pool: Pool<Postgres>,
+}
+
+impl ImportRepo {
+    pub async fn new(database_config: &DatabaseConfig) -> Result<ImportRepo, RepositoryError> {
+        create_pool(database_config).await.map(Self::from_pool).map_err(RepositoryError::from)
+    }
+
+    pub fn from_pool(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl Repo<Import> for ImportRepo {
+    async fn get(&self, id: i32) -> Result<Import, RepositoryError> {
+        sqlx::query_as::<_, Import>("
+SELECT id, sha256, file_name, state, drop_id, error, created_at, updated_at
+FROM \"import\"
+WHERE id = $1
+LIMIT 1
+")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(RepositoryError::from)
+    }
+
+    async fn save_or_update(&self, import: &Import) -> Result<i32, RepositoryError> {
+        save_import(&self.pool, import).await
+    }
+
+    async fn save_or_update_in(&self, import: &Import, unit_of_work: &mut UnitOfWork) -> Result<i32, RepositoryError> {
+        save_import(unit_of_work.connection(&self.pool).await?, import).await
+    }
+}
+
+async fn save_import<'e>(executor: impl PgExecutor<'e>, import: &Import) -> Result<i32, RepositoryError> {
+    if import.id == 0 {
+        return sqlx::query_scalar::<_, i32>("
+INSERT INTO \"import\" (sha256, file_name, state, drop_id, error, created_at, updated_at)
+VALUES ($1, $2, $3, $4, $5, $6, $7)
+RETURNING id
+    ")
+            .bind(&import.sha256)
+            .bind(&import.file_name)
+            .bind(&import.state)
+            .bind(import.drop_id)
+            .bind(&import.error)
+            .bind(import.created_at)
+            .bind(import.updated_at)
+            .fetch_one(executor)
+            .await
+            .map_err(not_saved);
+    }
+    sqlx::query_scalar::<_, i32>("
+UPDATE \"import\"
+SET file_name = $2, state = $3, drop_id = $4, error = $5, updated_at = $6
+WHERE id = $1
+RETURNING id
+    ")
+        .bind(import.id)
+        .bind(&import.file_name)
+        .bind(&import.state)
+        .bind(import.drop_id)
+        .bind(&import.error)
+        .bind(import.updated_at)
+        .fetch_one(executor)
+        .await
+        .map_err(not_saved)
+}
+
+#[async_trait]
+impl ImportRepoT for ImportRepo {
+    async fn get_by_sha256(&self, sha256: &str) -> Result<Import, RepositoryError> {
+        sqlx::query_as::<_, Import>("
+SELECT id, sha256, file_name, state, drop_id, error, created_at, updated_at
+FROM \"import\"
+WHERE sha256 = $1
+LIMIT 1
+")
+            .bind(sha256)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(RepositoryError::from)
+    }
+
+    async fn start_import(
+        &self,
+        sha256: &str,
+        file_name: &str,
+        started_at: NaiveDateTime,
+    ) -> Result<Option<Import>, RepositoryError> {
+        // the unique sha256 makes concurrent imports of the same archive wait for each other
+        sqlx::query_as::<_, Import>("
+INSERT INTO \"import\" (sha256, file_name, state, drop_id, error, created_at, updated_at)
+VALUES ($1, $2, 'running', NULL, NULL, $3, $3)
+ON CONFLICT (sha256) DO UPDATE
+SET file_name = EXCLUDED.file_name, state = 'running', error = NULL, updated_at = EXCLUDED.updated_at
+WHERE \"import\".state = 'failed'
+RETURNING id, sha256, file_name, state, drop_id, error, created_at, updated_at
+")
+            .bind(sha256)
+            .bind(file_name)
+            .bind(started_at)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(RepositoryError::from)
+    }
+
+    async fn interrupt_running(&self, updated_at: NaiveDateTime) -> Result<u64, RepositoryError> {
+        sqlx::query("
+UPDATE \"import\"
+SET state = 'failed', error = 'interrupted', updated_at = $1
+WHERE state = 'running'
+")
+            .bind(updated_at)
+            .execute(&self.pool)
+            .await
+            .map(|result| result.rows_affected())
+            .map_err(RepositoryError::from)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryImportRepo {
+    map: Arc<RwLock<HashMap<i32, Import>>>,
+}
+
+#[async_trait]
+impl Repo<Import> for InMemoryImportRepo {
+    async fn get(&self, id: i32) -> Result<Import, RepositoryError> {
+        self.map.read().unwrap().get(&id).cloned().ok_or(RepositoryError::EntityNotFound)
+    }
+
+    async fn save_or_update(&self, import: &Import) -> Result<i32, RepositoryError> {
+        let mut map = self.map.write().unwrap();
+        let id = if import.id == 0 {
+            map.keys().max().copied().unwrap_or(0) + 1
+        } else {
+            import.id
+        };
+        map.insert(id, Import { id, ..import.clone() });
+        Ok(id)
+    }
+
+    async fn save_or_update_in(&self, import: &Import, unit_of_work: &mut UnitOfWork) -> Result<i32, RepositoryError> {
+        let previous = self.map.read().unwrap().get(&import.id).cloned();
+        let id = self.save_or_update(import).await?;
+        let map = self.map.clone();
+        unit_of_work.on_rollback(move || {
+            let mut map = map.write().unwrap();
+            match previous {
+                Some(previous) => map.insert(id, previous),
+                None => map.remove(&id),
+            };
+        });
+        Ok(id)
+    }
+}
+
+#[async_trait]
+impl ImportRepoT for InMemoryImportRepo {
+    async fn get_by_sha256(&self, sha256: &str) -> Result<Import, RepositoryError> {
+        self.map.read().unwrap()
+            .values()
+            .find(|import| import.sha256 == sha256)
+            .cloned()
+            .ok_or(RepositoryError::EntityNotFound)
+    }
+
+    async fn start_import(
+        &self,
+        sha256: &str,
+        file_name: &str,
+        started_at: NaiveDateTime,
+    ) -> Result<Option<Import>, RepositoryError> {
+        let mut map = self.map.write().unwrap();
+        if let Some(import) = map.values_mut().find(|import| import.sha256 == sha256) {
+            if import.state() != ImportState::Failed {
+                return Ok(None);
+            }
+            import.file_name = file_name.to_string();
+            import.state = ImportState::Running.as_str().to_string();
+            import.error = None;
+            import.updated_at = started_at;
+            return Ok(Some(import.clone()));
+        }
+        let id = map.keys().max().copied().unwrap_or(0) + 1;
+        let import = Import::started(id, sha256.to_string(), file_name.to_string(), started_at);
+        map.insert(id, import.clone());
+        Ok(Some(import))
+    }
+
+    async fn interrupt_running(&self, updated_at: NaiveDateTime) -> Result<u64, RepositoryError> {
+        let mut nb_interrupted = 0;
+        for import in self.map.write().unwrap().values_mut() {
+            if import.state() == ImportState::Running {
+                import.set_failed("interrupted".to_string(), updated_at);
+                nb_interrupted += 1;
+            }
+        }
+        Ok(nb_interrupted)
+    }
+}
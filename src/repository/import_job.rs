@@ -0,0 +1,312 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use crate::config::db::{create_pool, DatabaseConfig};
+use crate::repository::{not_saved, Entity, Repo, RepositoryError};
+use derive_new::new;
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use sqlx::{Pool, Postgres};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportJobState {
+    Queued,
+    Running,
+    Succeeded,
+    PartiallySucceeded,
+    Failed,
+}
+
+impl ImportJobState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImportJobState::Queued => "queued",
+            ImportJobState::Running => "running",
+            ImportJobState::Succeeded => "succeeded",
+            ImportJobState::PartiallySucceeded => "partially_succeeded",
+            ImportJobState::Failed => "failed",
+        }
+    }
+
+    pub fn parse(state: &str) -> Option<ImportJobState> {
+        match state {
+            "queued" => Some(ImportJobState::Queued),
+            "running" => Some(ImportJobState::Running),
+            "succeeded" => Some(ImportJobState::Succeeded),
+            "partially_succeeded" => Some(ImportJobState::PartiallySucceeded),
+            "failed" => Some(ImportJobState::Failed),
+            _ => None,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        !matches!(self, ImportJobState::Queued | ImportJobState::Running)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileImportState {
+    Pending,
+    Running,
+    Imported,
+    Skipped,
+    Failed,
+}
+
+/// Progress of one archive of an import job
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, new)]
+pub struct FileImportReport {
+    file: String,
+    state: FileImportState,
+    #[new(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    drop_id: Option<i32>,
+    #[new(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    playlist_id: Option<i32>,
+    #[new(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    artist_id: Option<i32>,
+    #[new(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[new(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+    /// The archive was valid, the server failed to create its drop
+    #[new(default)]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    server_error: bool,
+}
+
+impl FileImportReport {
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    pub fn state(&self) -> FileImportState {
+        self.state
+    }
+
+    pub fn drop_id(&self) -> Option<i32> {
+        self.drop_id
+    }
+
+    pub fn playlist_id(&self) -> Option<i32> {
+        self.playlist_id
+    }
+
+    pub fn artist_id(&self) -> Option<i32> {
+        self.artist_id
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    pub fn server_error(&self) -> bool {
+        self.server_error
+    }
+
+    pub fn set_running(&mut self) {
+        self.state = FileImportState::Running;
+    }
+
+    pub fn set_imported(&mut self, drop_id: i32, playlist_id: i32, artist_id: i32) {
+        self.state = FileImportState::Imported;
+        self.drop_id = Some(drop_id);
+        self.playlist_id = Some(playlist_id);
+        self.artist_id = Some(artist_id);
+        self.error = None;
+        self.message = None;
+        self.server_error = false;
+    }
+
+    /// The archive was not imported, without it being an error
+    pub fn set_skipped(&mut self, reason: String, message: String) {
+        self.state = FileImportState::Skipped;
+        self.error = Some(reason);
+        self.message = Some(message);
+    }
+
+    pub fn set_failed(&mut self, error: String, message: String, server_error: bool) {
+        self.state = FileImportState::Failed;
+        self.error = Some(error);
+        self.message = Some(message);
+        self.server_error = server_error;
+    }
+}
+
+#[derive(sqlx::FromRow, Debug, Clone, PartialEq, Serialize, new)]
+pub struct ImportJob {
+    id: i32,
+    state: String,
+    files: Json<Vec<FileImportReport>>,
+    created_at: NaiveDateTime,
+    updated_at: NaiveDateTime,
+}
+
+impl ImportJob {
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    pub fn state(&self) -> ImportJobState {
+        ImportJobState::parse(&self.state).unwrap_or(ImportJobState::Failed)
+    }
+
+    pub fn files(&self) -> &Vec<FileImportReport> {
+        &self.files.0
+    }
+
+    pub fn files_mut(&mut self) -> &mut Vec<FileImportReport> {
+        &mut self.files.0
+    }
+
+    pub fn created_at(&self) -> NaiveDateTime {
+        self.created_at
+    }
+
+    pub fn updated_at(&self) -> NaiveDateTime {
+        self.updated_at
+    }
+
+    pub fn set_id(&mut self, id: i32) {
+        self.id = id;
+    }
+
+    pub fn set_state(&mut self, state: ImportJobState, updated_at: NaiveDateTime) {
+        self.state = state.as_str().to_string();
+        self.updated_at = updated_at;
+    }
+}
+
+impl Entity for ImportJob {
+    fn id(&self) -> String {
+        self.id.to_string()
+    }
+}
+
+#[async_trait]
+pub trait ImportJobRepoT: Repo<ImportJob> {
+    /// Jobs which were queued or running, used to resume them after a restart
+    async fn list_unfinished(&self) -> Result<Vec<ImportJob>, RepositoryError>;
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportJobRepo {
+    pub pool: Pool<Postgres>,
+}
+
+impl ImportJobRepo {
+    pub async fn new(database_config: &DatabaseConfig) -> Result<ImportJobRepo, RepositoryError> {
+        create_pool(database_config).await.map(Self::from_pool).map_err(RepositoryError::from)
+    }
+
+    pub fn from_pool(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl Repo<ImportJob> for ImportJobRepo {
+    async fn get(&self, id: i32) -> Result<ImportJob, RepositoryError> {
+        sqlx::query_as::<_, ImportJob>("
+SELECT id, state, files, created_at, updated_at
+FROM \"import_job\"
+WHERE id = $1
+LIMIT 1
+")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(RepositoryError::from)
+    }
+
+    async fn save_or_update(&self, import_job: &ImportJob) -> Result<i32, RepositoryError> {
+        if import_job.id == 0 {
+            return sqlx::query_scalar::<_, i32>("
+INSERT INTO \"import_job\" (state, files, created_at, updated_at)
+VALUES ($1, $2, $3, $4)
+RETURNING id
+    ")
+                .bind(&import_job.state)
+                .bind(&import_job.files)
+                .bind(import_job.created_at)
+                .bind(import_job.updated_at)
+                .fetch_one(&self.pool)
+                .await
+                .map_err(not_saved);
+        }
+        sqlx::query_scalar::<_, i32>("
+UPDATE \"import_job\"
+SET state = $2, files = $3, updated_at = $4
+WHERE id = $1
+RETURNING id
+    ")
+            .bind(import_job.id)
+            .bind(&import_job.state)
+            .bind(&import_job.files)
+            .bind(import_job.updated_at)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(not_saved)
+    }
+}
+
+#[async_trait]
+impl ImportJobRepoT for ImportJobRepo {
+    async fn list_unfinished(&self) -> Result<Vec<ImportJob>, RepositoryError> {
+        sqlx::query_as::<_, ImportJob>("
+SELECT id, state, files, created_at, updated_at
+FROM \"import_job\"
+WHERE state IN ('queued', 'running')
+ORDER BY id
+")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(RepositoryError::from)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryImportJobRepo {
+    map: Arc<RwLock<HashMap<i32, ImportJob>>>,
+}
+
+#[async_trait]
+impl Repo<ImportJob> for InMemoryImportJobRepo {
+    async fn get(&self, id: i32) -> Result<ImportJob, RepositoryError> {
+        self.map.read().unwrap().get(&id).cloned().ok_or(RepositoryError::EntityNotFound)
+    }
+
+    async fn save_or_update(&self, import_job: &ImportJob) -> Result<i32, RepositoryError> {
+        let mut map = self.map.write().unwrap();
+        let id = if import_job.id == 0 {
+            map.keys().max().copied().unwrap_or(0) + 1
+        } else {
+            import_job.id
+        };
+        map.insert(id, ImportJob { id, ..import_job.clone() });
+        Ok(id)
+    }
+}
+
+#[async_trait]
+impl ImportJobRepoT for InMemoryImportJobRepo {
+    async fn list_unfinished(&self) -> Result<Vec<ImportJob>, RepositoryError> {
+        let mut import_jobs: Vec<ImportJob> = self.map.read().unwrap()
+            .values()
+            .filter(|import_job| !import_job.state().is_finished())
+            .cloned()
+            .collect();
+        import_jobs.sort_by_key(|import_job| import_job.id);
+        Ok(import_jobs)
+    }
+}
@@ -1,20 +1,38 @@
 use std::sync::Arc;
 use async_trait::async_trait;
 use crate::config::db::{create_pool, DatabaseConfig};
-use crate::repository::{Entity, Repo, RepositoryError};
+use crate::repository::query::{self, Field, Filter, Page, Queryable, Value};
+use crate::repository::unit_of_work::UnitOfWork;
+use crate::repository::{not_saved, updated_id, Entity, Repo, RepositoryError};
 use derive_new::new;
-use sqlx::{Pool, Postgres};
+use sqlx::{PgExecutor, Pool, Postgres};
 
 #[derive(sqlx::FromRow, Debug, Clone, PartialEq, new)]
 pub struct Playlist {
     pub id: i32,
     pub name: String,
+    #[new(default)]
+    pub version: i32,
 }
 
 impl Playlist {
     pub fn id(&self) -> i32 {
         self.id
     }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Incremented by each update
+    pub fn version(&self) -> i32 {
+        self.version
+    }
+
+    pub fn with_version(mut self, version: i32) -> Self {
+        self.version = version;
+        self
+    }
 }
 
 impl Entity for Playlist {
@@ -23,6 +41,34 @@ impl Entity for Playlist {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlaylistField {
+    Id,
+    Name,
+}
+
+impl Field for PlaylistField {
+    const ID: Self = PlaylistField::Id;
+
+    fn column(&self) -> &'static str {
+        match self {
+            PlaylistField::Id => "id",
+            PlaylistField::Name => "name",
+        }
+    }
+}
+
+impl Queryable for Playlist {
+    type Field = PlaylistField;
+
+    fn value(&self, field: PlaylistField) -> Value {
+        match field {
+            PlaylistField::Id => self.id.into(),
+            PlaylistField::Name => self.name.as_str().into(),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct PlaylistRepo {
     pub pool: Pool<Postgres>,
@@ -30,10 +76,11 @@ pub struct PlaylistRepo {
 
 impl PlaylistRepo {
     pub async fn new(database_config: &DatabaseConfig) -> Result<PlaylistRepo, RepositoryError> {
-        match create_pool(database_config).await {
-            Ok(pool) => Ok(Self { pool }),
-            Err(err) => Err(RepositoryError::DatabaseError(err))
-        }
+        create_pool(database_config).await.map(Self::from_pool).map_err(RepositoryError::from)
+    }
+
+    pub fn from_pool(pool: Pool<Postgres>) -> Self {
+        Self { pool }
     }
 }
 
@@ -41,7 +88,7 @@ impl PlaylistRepo {
 impl Repo<Playlist> for PlaylistRepo {
     async fn get(&self, id: i32) -> Result<Playlist, RepositoryError> {
         sqlx::query_as::<_, Playlist>("
-SELECT id, name
+SELECT id, name, version
 FROM \"playlist\"
 WHERE id = $1
 LIMIT 1
@@ -49,20 +96,62 @@ LIMIT 1
             .bind(id)
             .fetch_one(&self.pool)
             .await
-            .map_err(|_| RepositoryError::EntityNotFound)
+            .map_err(RepositoryError::from)
     }
 
     async fn save_or_update(&self, playlist: &Playlist) -> Result<i32, RepositoryError> {
-        sqlx::query_scalar::<_, i32>("
+        save_playlist(&self.pool, playlist).await
+    }
+
+    async fn save_or_update_in(&self, playlist: &Playlist, unit_of_work: &mut UnitOfWork) -> Result<i32, RepositoryError> {
+        save_playlist(unit_of_work.connection(&self.pool).await?, playlist).await
+    }
+
+    async fn list(&self, filter: &Filter<PlaylistField>) -> Result<Page<Playlist>, RepositoryError> {
+        query::list(&self.pool, "playlist", "id, name, version", filter).await
+    }
+
+    async fn delete(&self, id: i32) -> Result<(), RepositoryError> {
+        query::delete(&self.pool, "playlist", id).await
+    }
+
+    async fn delete_in(&self, id: i32, unit_of_work: &mut UnitOfWork) -> Result<(), RepositoryError> {
+        query::delete(unit_of_work.connection(&self.pool).await?, "playlist", id).await
+    }
+
+    async fn exists(&self, id: i32) -> Result<bool, RepositoryError> {
+        query::exists(&self.pool, "playlist", id).await
+    }
+}
+
+async fn save_playlist<'e>(executor: impl PgExecutor<'e>, playlist: &Playlist) -> Result<i32, RepositoryError> {
+    if playlist.id == 0 {
+        return sqlx::query_scalar::<_, i32>("
 INSERT INTO \"playlist\" (name)
 VALUES ($1)
 RETURNING id
     ")
-            .bind(playlist.name.clone())
-            .fetch_one(&self.pool)
+            .bind(&playlist.name)
+            .fetch_one(executor)
             .await
-            .map_err(|_| RepositoryError::EntityNotSaved)
+            .map_err(not_saved);
     }
+    sqlx::query_as::<_, (Option<i32>, bool)>("
+WITH updated AS (
+    UPDATE \"playlist\"
+    SET name = $2, version = version + 1
+    WHERE id = $1 AND version = $3
+    RETURNING id
+)
+SELECT (SELECT id FROM updated), EXISTS (SELECT 1 FROM \"playlist\" WHERE id = $1)
+    ")
+        .bind(playlist.id)
+        .bind(&playlist.name)
+        .bind(playlist.version)
+        .fetch_one(executor)
+        .await
+        .map_err(not_saved)
+        .and_then(updated_id)
 }
 
 #[async_trait]
@@ -74,5 +163,25 @@ impl Repo<Playlist> for Arc<PlaylistRepo> {
     async fn save_or_update(&self, entity: &Playlist) -> Result<i32, RepositoryError> {
         self.as_ref().save_or_update(entity).await
     }
+
+    async fn save_or_update_in(&self, entity: &Playlist, unit_of_work: &mut UnitOfWork) -> Result<i32, RepositoryError> {
+        self.as_ref().save_or_update_in(entity, unit_of_work).await
+    }
+
+    async fn list(&self, filter: &Filter<PlaylistField>) -> Result<Page<Playlist>, RepositoryError> {
+        self.as_ref().list(filter).await
+    }
+
+    async fn delete(&self, id: i32) -> Result<(), RepositoryError> {
+        self.as_ref().delete(id).await
+    }
+
+    async fn delete_in(&self, id: i32, unit_of_work: &mut UnitOfWork) -> Result<(), RepositoryError> {
+        self.as_ref().delete_in(id, unit_of_work).await
+    }
+
+    async fn exists(&self, id: i32) -> Result<bool, RepositoryError> {
+        self.as_ref().exists(id).await
+    }
 }
 
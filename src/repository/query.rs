@@ -0,0 +1,255 @@
+use crate::repository::{Entity, RepositoryError};
+use serde::Deserialize;
+use sqlx::postgres::PgRow;
+use sqlx::{FromRow, PgExecutor, Pool, Postgres, QueryBuilder};
+use std::cmp::Ordering;
+use std::fmt::Debug;
+
+/// Page size of a listing without limit
+pub const DEFAULT_LIMIT: i64 = 50;
+
+/// A field an entity can be filtered and sorted on
+pub trait Field: Copy + Debug + Send + Sync + 'static {
+    /// The id field, it breaks ties between entities of the same sort value
+    const ID: Self;
+
+    fn column(&self) -> &'static str;
+}
+
+/// An entity which can be listed with a `Filter`
+pub trait Queryable: Entity {
+    type Field: Field;
+
+    fn value(&self, field: Self::Field) -> Value;
+}
+
+/// A value compared with a field of an entity
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub enum Value {
+    Bool(bool),
+    Int(i32),
+    Text(String),
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(value: i32) -> Self {
+        Value::Int(value)
+    }
+}
+
+impl From<i16> for Value {
+    fn from(value: i16) -> Self {
+        Value::Int(value.into())
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::Text(value.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::Text(value)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Order {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// Conditions, sort and page of a listing.
+/// Entities are sorted by id when no sort is given, the id breaks ties otherwise.
+/// A page starts after the entity whose id is the cursor, a cursor whose entity
+/// was deleted ends the listing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter<F: Field> {
+    conditions: Vec<(F, Value)>,
+    sort: (F, Order),
+    after: Option<i32>,
+    limit: i64,
+}
+
+impl<F: Field> Default for Filter<F> {
+    fn default() -> Self {
+        Self {
+            conditions: Vec::new(),
+            sort: (F::ID, Order::Asc),
+            after: None,
+            limit: DEFAULT_LIMIT,
+        }
+    }
+}
+
+impl<F: Field> Filter<F> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keeps the entities whose `field` equals `value`
+    pub fn eq(mut self, field: F, value: impl Into<Value>) -> Self {
+        self.conditions.push((field, value.into()));
+        self
+    }
+
+    pub fn sort_by(mut self, field: F, order: Order) -> Self {
+        self.sort = (field, order);
+        self
+    }
+
+    /// Starts after the entity of id `cursor`, the `next_cursor` of the previous page
+    pub fn after(mut self, cursor: Option<i32>) -> Self {
+        self.after = cursor;
+        self
+    }
+
+    pub fn limit(mut self, limit: i64) -> Self {
+        self.limit = limit.max(1);
+        self
+    }
+
+    pub fn conditions(&self) -> &[(F, Value)] {
+        &self.conditions
+    }
+
+    pub fn sort(&self) -> (F, Order) {
+        self.sort
+    }
+
+    pub fn cursor(&self) -> Option<i32> {
+        self.after
+    }
+
+    pub fn page_limit(&self) -> i64 {
+        self.limit
+    }
+
+    /// Page of the entities kept by the filter, for repositories which are not backed by the database
+    pub fn page<E: Queryable<Field = F>>(&self, entities: impl IntoIterator<Item = E>) -> Page<E> {
+        let (field, order) = self.sort;
+        let key = |entity: &E| (entity.value(field), entity.value(F::ID));
+        let mut entities: Vec<E> = entities.into_iter()
+            .filter(|entity| self.conditions.iter().all(|(field, value)| entity.value(*field) == *value))
+            .collect();
+        let cursor_key = self.after.map(|cursor| {
+            entities.iter()
+                .find(|entity| entity.value(F::ID) == Value::Int(cursor))
+                .map(key)
+        });
+        entities.sort_by(|a, b| {
+            let ordering = key(a).partial_cmp(&key(b)).unwrap_or(Ordering::Equal);
+            match order {
+                Order::Asc => ordering,
+                Order::Desc => ordering.reverse(),
+            }
+        });
+        let entities: Vec<E> = match cursor_key {
+            None => entities,
+            Some(None) => Vec::new(),
+            Some(Some(cursor_key)) => entities.into_iter()
+                .filter(|entity| match order {
+                    Order::Asc => key(entity) > cursor_key,
+                    Order::Desc => key(entity) < cursor_key,
+                })
+                .collect(),
+        };
+        Page::from_rows(entities, self.limit)
+    }
+}
+
+/// Entities of a listing, `next_cursor` is set when more follow
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page<E> {
+    pub items: Vec<E>,
+    pub next_cursor: Option<i32>,
+}
+
+impl<E: Queryable> Page<E> {
+    /// Page of the first `limit` rows, the rows are fetched with one more to tell whether more follow
+    fn from_rows(mut rows: Vec<E>, limit: i64) -> Self {
+        let limit = usize::try_from(limit).unwrap_or(usize::MAX);
+        if rows.len() <= limit {
+            return Self { items: rows, next_cursor: None };
+        }
+        rows.truncate(limit);
+        let next_cursor = rows.last().and_then(|entity| match entity.value(E::Field::ID) {
+            Value::Int(id) => Some(id),
+            _ => None,
+        });
+        Self { items: rows, next_cursor }
+    }
+}
+
+/// Lists the rows of `table` kept by `filter`, `columns` are the selected ones
+pub(crate) async fn list<E>(pool: &Pool<Postgres>, table: &str, columns: &str, filter: &Filter<E::Field>) -> Result<Page<E>, RepositoryError>
+where
+    E: Queryable + for<'r> FromRow<'r, PgRow> + Send + Unpin,
+{
+    let (sort_field, order) = filter.sort();
+    let (sort_column, id_column) = (sort_field.column(), E::Field::ID.column());
+    let mut builder = QueryBuilder::<Postgres>::new(format!("SELECT {columns} FROM \"{table}\" WHERE TRUE"));
+    for (field, value) in filter.conditions() {
+        builder.push(format!(" AND {} = ", field.column()));
+        push_value(&mut builder, value);
+    }
+    if let Some(cursor) = filter.cursor() {
+        let comparison = match order {
+            Order::Asc => ">",
+            Order::Desc => "<",
+        };
+        builder.push(format!(" AND ({sort_column}, {id_column}) {comparison} (SELECT {sort_column}, {id_column} FROM \"{table}\" WHERE {id_column} = "));
+        builder.push_bind(cursor);
+        builder.push(")");
+    }
+    let direction = match order {
+        Order::Asc => "ASC",
+        Order::Desc => "DESC",
+    };
+    builder.push(format!(" ORDER BY {sort_column} {direction}, {id_column} {direction} LIMIT "));
+    builder.push_bind(filter.page_limit() + 1);
+    let rows = builder.build_query_as::<E>()
+        .fetch_all(pool)
+        .await
+        .map_err(RepositoryError::from)?;
+    Ok(Page::from_rows(rows, filter.page_limit()))
+}
+
+/// Deletes the row of `table` with the id, `EntityNotFound` when there is none
+pub(crate) async fn delete<'e>(executor: impl PgExecutor<'e>, table: &str, id: i32) -> Result<(), RepositoryError> {
+    let result = sqlx::query(&format!("DELETE FROM \"{table}\" WHERE id = $1"))
+        .bind(id)
+        .execute(executor)
+        .await
+        .map_err(RepositoryError::from)?;
+    match result.rows_affected() {
+        0 => Err(RepositoryError::EntityNotFound),
+        _ => Ok(()),
+    }
+}
+
+pub(crate) async fn exists(pool: &Pool<Postgres>, table: &str, id: i32) -> Result<bool, RepositoryError> {
+    sqlx::query_scalar::<_, bool>(&format!("SELECT EXISTS (SELECT 1 FROM \"{table}\" WHERE id = $1)"))
+        .bind(id)
+        .fetch_one(pool)
+        .await
+        .map_err(RepositoryError::from)
+}
+
+fn push_value(builder: &mut QueryBuilder<Postgres>, value: &Value) {
+    match value {
+        Value::Bool(value) => builder.push_bind(*value),
+        Value::Int(value) => builder.push_bind(*value),
+        Value::Text(value) => builder.push_bind(value.clone()),
+    };
+}
@@ -0,0 +1,434 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use crate::config::db::{create_pool, DatabaseConfig};
+use crate::repository::unit_of_work::UnitOfWork;
+use crate::repository::{not_saved, updated_id, Entity, Repo, RepositoryError};
+use derive_new::new;
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use sqlx::{PgExecutor, Pool, Postgres};
+
+const TRACK_COLUMNS: &str = "id, playlist_id, position, source_file, title, duration_seconds, isrc, explicit, disc_number, track_number, cue_points, content_hash, version";
+
+/// A track of a playlist, its bytes are stored once under their SHA-256 `content_hash`.
+/// A track imported before has no content hash, its file is `track_{position}` in the playlist directory
+#[derive(sqlx::FromRow, Debug, Clone, PartialEq, new)]
+pub struct Track {
+    id: i32,
+    playlist_id: i32,
+    position: i32,
+    source_file: String,
+    title: Option<String>,
+    duration_seconds: Option<i32>,
+    #[new(default)]
+    isrc: Option<String>,
+    #[new(default)]
+    explicit: bool,
+    #[new(default)]
+    disc_number: Option<i32>,
+    #[new(default)]
+    track_number: Option<i32>,
+    #[new(default)]
+    cue_points: Json<Vec<CuePoint>>,
+    #[new(default)]
+    content_hash: Option<String>,
+    #[new(default)]
+    version: i32,
+}
+
+impl Track {
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    pub fn playlist_id(&self) -> i32 {
+        self.playlist_id
+    }
+
+    /// Starts at 1
+    pub fn position(&self) -> i32 {
+        self.position
+    }
+
+    /// Path of the track in the drop archive
+    pub fn source_file(&self) -> &str {
+        &self.source_file
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    pub fn duration_seconds(&self) -> Option<i32> {
+        self.duration_seconds
+    }
+
+    pub fn isrc(&self) -> Option<&str> {
+        self.isrc.as_deref()
+    }
+
+    pub fn with_isrc(mut self, isrc: Option<String>) -> Self {
+        self.isrc = isrc;
+        self
+    }
+
+    pub fn explicit(&self) -> bool {
+        self.explicit
+    }
+
+    pub fn with_explicit(mut self, explicit: bool) -> Self {
+        self.explicit = explicit;
+        self
+    }
+
+    pub fn disc_number(&self) -> Option<i32> {
+        self.disc_number
+    }
+
+    /// Number of the track given by the manifest, `position` is its place in the playlist
+    pub fn track_number(&self) -> Option<i32> {
+        self.track_number
+    }
+
+    pub fn with_numbering(mut self, disc_number: Option<i32>, track_number: Option<i32>) -> Self {
+        self.disc_number = disc_number;
+        self.track_number = track_number;
+        self
+    }
+
+    /// Where each tune of a mix starts, ordered by start
+    pub fn cue_points(&self) -> &[CuePoint] {
+        &self.cue_points.0
+    }
+
+    pub fn with_cue_points(mut self, cue_points: Vec<CuePoint>) -> Self {
+        self.cue_points = Json(cue_points);
+        self
+    }
+
+    /// Hex SHA-256 of the track bytes
+    pub fn content_hash(&self) -> Option<&str> {
+        self.content_hash.as_deref()
+    }
+
+    pub fn with_content_hash(mut self, content_hash: &str) -> Self {
+        self.content_hash = Some(content_hash.to_string());
+        self
+    }
+
+    /// Incremented by each update
+    pub fn version(&self) -> i32 {
+        self.version
+    }
+
+    pub fn with_version(mut self, version: i32) -> Self {
+        self.version = version;
+        self
+    }
+}
+
+/// Start of a tune within the recording of a mix
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, new)]
+pub struct CuePoint {
+    start_seconds: u32,
+    title: String,
+    #[serde(default)]
+    #[new(default)]
+    artist: Option<String>,
+}
+
+impl CuePoint {
+    pub fn start_seconds(&self) -> u32 {
+        self.start_seconds
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn artist(&self) -> Option<&str> {
+        self.artist.as_deref()
+    }
+
+    pub fn with_artist(mut self, artist: Option<String>) -> Self {
+        self.artist = artist;
+        self
+    }
+}
+
+impl Entity for Track {
+    fn id(&self) -> String {
+        self.id.to_string()
+    }
+}
+
+#[async_trait]
+pub trait TrackRepoT: Repo<Track> {
+    /// Tracks of a playlist ordered by position
+    async fn get_by_playlist(&self, playlist_id: i32) -> Result<Vec<Track>, RepositoryError>;
+
+    /// Deletes the tracks of a playlist as part of `unit_of_work`, gives how many there were
+    async fn delete_by_playlist_in(&self, playlist_id: i32, unit_of_work: &mut UnitOfWork) -> Result<u64, RepositoryError>;
+
+    /// Adds a reference to the content, recording it when it is new, gives its reference count.
+    /// A count of 1 means the bytes of the content are yet to be stored
+    async fn retain_content_in(&self, content_hash: &str, size: i64, unit_of_work: &mut UnitOfWork) -> Result<i32, RepositoryError>;
+
+    /// Removes a reference to the content, gives the references left
+    async fn release_content_in(&self, content_hash: &str, unit_of_work: &mut UnitOfWork) -> Result<i32, RepositoryError>;
+
+    /// Forgets the content when nothing references it, its bytes can then be deleted before `unit_of_work` is committed
+    async fn delete_unreferenced_content_in(&self, content_hash: &str, unit_of_work: &mut UnitOfWork) -> Result<bool, RepositoryError>;
+
+    /// Whether the content is recorded, a content retained by an uncommitted unit of work is not
+    async fn content_exists(&self, content_hash: &str) -> Result<bool, RepositoryError>;
+}
+
+#[derive(Debug, Clone)]
+pub struct TrackRepo {
+    pool: Pool<Postgres>,
+}
+
+impl TrackRepo {
+    pub async fn new(database_config: &DatabaseConfig) -> Result<TrackRepo, RepositoryError> {
+        create_pool(database_config).await.map(Self::from_pool).map_err(RepositoryError::from)
+    }
+
+    pub fn from_pool(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl Repo<Track> for TrackRepo {
+    async fn get(&self, id: i32) -> Result<Track, RepositoryError> {
+        sqlx::query_as::<_, Track>(&format!("
+SELECT {TRACK_COLUMNS}
+FROM \"track\"
+WHERE id = $1
+LIMIT 1
+"))
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(RepositoryError::from)
+    }
+
+    async fn save_or_update(&self, track: &Track) -> Result<i32, RepositoryError> {
+        save_track(&self.pool, track).await
+    }
+
+    async fn save_or_update_in(&self, track: &Track, unit_of_work: &mut UnitOfWork) -> Result<i32, RepositoryError> {
+        save_track(unit_of_work.connection(&self.pool).await?, track).await
+    }
+}
+
+#[async_trait]
+impl TrackRepoT for TrackRepo {
+    async fn get_by_playlist(&self, playlist_id: i32) -> Result<Vec<Track>, RepositoryError> {
+        sqlx::query_as::<_, Track>(&format!("
+SELECT {TRACK_COLUMNS}
+FROM \"track\"
+WHERE playlist_id = $1
+ORDER BY position
+"))
+            .bind(playlist_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(RepositoryError::from)
+    }
+
+    async fn delete_by_playlist_in(&self, playlist_id: i32, unit_of_work: &mut UnitOfWork) -> Result<u64, RepositoryError> {
+        sqlx::query("DELETE FROM \"track\" WHERE playlist_id = $1")
+            .bind(playlist_id)
+            .execute(unit_of_work.connection(&self.pool).await?)
+            .await
+            .map(|result| result.rows_affected())
+            .map_err(RepositoryError::from)
+    }
+
+    async fn retain_content_in(&self, content_hash: &str, size: i64, unit_of_work: &mut UnitOfWork) -> Result<i32, RepositoryError> {
+        sqlx::query_scalar::<_, i32>("
+INSERT INTO \"track_content\" (content_hash, size, ref_count)
+VALUES ($1, $2, 1)
+ON CONFLICT (content_hash) DO UPDATE
+SET ref_count = \"track_content\".ref_count + 1
+RETURNING ref_count
+")
+            .bind(content_hash)
+            .bind(size)
+            .fetch_one(unit_of_work.connection(&self.pool).await?)
+            .await
+            .map_err(RepositoryError::from)
+    }
+
+    async fn release_content_in(&self, content_hash: &str, unit_of_work: &mut UnitOfWork) -> Result<i32, RepositoryError> {
+        sqlx::query_scalar::<_, i32>("
+UPDATE \"track_content\"
+SET ref_count = ref_count - 1
+WHERE content_hash = $1 AND ref_count > 0
+RETURNING ref_count
+")
+            .bind(content_hash)
+            .fetch_optional(unit_of_work.connection(&self.pool).await?)
+            .await
+            .map_err(RepositoryError::from)?
+            .ok_or(RepositoryError::EntityNotFound)
+    }
+
+    // the deleted row stays locked until the commit, a concurrent retain waits for it then stores the bytes again
+    async fn delete_unreferenced_content_in(&self, content_hash: &str, unit_of_work: &mut UnitOfWork) -> Result<bool, RepositoryError> {
+        sqlx::query("DELETE FROM \"track_content\" WHERE content_hash = $1 AND ref_count = 0")
+            .bind(content_hash)
+            .execute(unit_of_work.connection(&self.pool).await?)
+            .await
+            .map(|result| result.rows_affected() > 0)
+            .map_err(RepositoryError::from)
+    }
+
+    async fn content_exists(&self, content_hash: &str) -> Result<bool, RepositoryError> {
+        sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM \"track_content\" WHERE content_hash = $1)")
+            .bind(content_hash)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(RepositoryError::from)
+    }
+}
+
+/// A track without id at a taken position of its playlist is a unique violation
+async fn save_track<'e>(executor: impl PgExecutor<'e>, track: &Track) -> Result<i32, RepositoryError> {
+    if track.id == 0 {
+        return sqlx::query_scalar::<_, i32>("
+INSERT INTO \"track\" (playlist_id, position, source_file, title, duration_seconds, isrc, explicit, disc_number, track_number, cue_points, content_hash)
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+ON CONFLICT (playlist_id, position) DO NOTHING
+RETURNING id
+    ")
+            .bind(track.playlist_id)
+            .bind(track.position)
+            .bind(&track.source_file)
+            .bind(&track.title)
+            .bind(track.duration_seconds)
+            .bind(&track.isrc)
+            .bind(track.explicit)
+            .bind(track.disc_number)
+            .bind(track.track_number)
+            .bind(&track.cue_points)
+            .bind(&track.content_hash)
+            .fetch_optional(executor)
+            .await
+            .map_err(not_saved)?
+            .ok_or(RepositoryError::UniqueViolation);
+    }
+    sqlx::query_as::<_, (Option<i32>, bool)>("
+WITH updated AS (
+    UPDATE \"track\"
+    SET playlist_id = $2, position = $3, source_file = $4, title = $5, duration_seconds = $6,
+        isrc = $7, explicit = $8, disc_number = $9, track_number = $10, cue_points = $11, content_hash = $12, version = version + 1
+    WHERE id = $1 AND version = $13
+    RETURNING id
+)
+SELECT (SELECT id FROM updated), EXISTS (SELECT 1 FROM \"track\" WHERE id = $1)
+    ")
+        .bind(track.id)
+        .bind(track.playlist_id)
+        .bind(track.position)
+        .bind(&track.source_file)
+        .bind(&track.title)
+        .bind(track.duration_seconds)
+        .bind(&track.isrc)
+        .bind(track.explicit)
+        .bind(track.disc_number)
+        .bind(track.track_number)
+        .bind(&track.cue_points)
+        .bind(&track.content_hash)
+        .bind(track.version)
+        .fetch_one(executor)
+        .await
+        .map_err(not_saved)
+        .and_then(updated_id)
+}
+
+#[async_trait]
+impl Repo<Track> for Arc<TrackRepo> {
+    async fn get(&self, id: i32) -> Result<Track, RepositoryError> {
+        self.as_ref().get(id).await
+    }
+
+    async fn save_or_update(&self, entity: &Track) -> Result<i32, RepositoryError> {
+        self.as_ref().save_or_update(entity).await
+    }
+
+    async fn save_or_update_in(&self, entity: &Track, unit_of_work: &mut UnitOfWork) -> Result<i32, RepositoryError> {
+        self.as_ref().save_or_update_in(entity, unit_of_work).await
+    }
+}
+
+#[async_trait]
+impl TrackRepoT for Arc<TrackRepo> {
+    async fn get_by_playlist(&self, playlist_id: i32) -> Result<Vec<Track>, RepositoryError> {
+        self.as_ref().get_by_playlist(playlist_id).await
+    }
+
+    async fn delete_by_playlist_in(&self, playlist_id: i32, unit_of_work: &mut UnitOfWork) -> Result<u64, RepositoryError> {
+        self.as_ref().delete_by_playlist_in(playlist_id, unit_of_work).await
+    }
+
+    async fn retain_content_in(&self, content_hash: &str, size: i64, unit_of_work: &mut UnitOfWork) -> Result<i32, RepositoryError> {
+        self.as_ref().retain_content_in(content_hash, size, unit_of_work).await
+    }
+
+    async fn release_content_in(&self, content_hash: &str, unit_of_work: &mut UnitOfWork) -> Result<i32, RepositoryError> {
+        self.as_ref().release_content_in(content_hash, unit_of_work).await
+    }
+
+    async fn delete_unreferenced_content_in(&self, content_hash: &str, unit_of_work: &mut UnitOfWork) -> Result<bool, RepositoryError> {
+        self.as_ref().delete_unreferenced_content_in(content_hash, unit_of_work).await
+    }
+
+    async fn content_exists(&self, content_hash: &str) -> Result<bool, RepositoryError> {
+        self.as_ref().content_exists(content_hash).await
+    }
+}
+
+#[async_trait]
+impl Repo<Track> for Arc<dyn TrackRepoT> {
+    async fn get(&self, id: i32) -> Result<Track, RepositoryError> {
+        self.as_ref().get(id).await
+    }
+
+    async fn save_or_update(&self, entity: &Track) -> Result<i32, RepositoryError> {
+        self.as_ref().save_or_update(entity).await
+    }
+
+    async fn save_or_update_in(&self, entity: &Track, unit_of_work: &mut UnitOfWork) -> Result<i32, RepositoryError> {
+        self.as_ref().save_or_update_in(entity, unit_of_work).await
+    }
+}
+
+#[async_trait]
+impl TrackRepoT for Arc<dyn TrackRepoT> {
+    async fn get_by_playlist(&self, playlist_id: i32) -> Result<Vec<Track>, RepositoryError> {
+        self.as_ref().get_by_playlist(playlist_id).await
+    }
+
+    async fn delete_by_playlist_in(&self, playlist_id: i32, unit_of_work: &mut UnitOfWork) -> Result<u64, RepositoryError> {
+        self.as_ref().delete_by_playlist_in(playlist_id, unit_of_work).await
+    }
+
+    async fn retain_content_in(&self, content_hash: &str, size: i64, unit_of_work: &mut UnitOfWork) -> Result<i32, RepositoryError> {
+        self.as_ref().retain_content_in(content_hash, size, unit_of_work).await
+    }
+
+    async fn release_content_in(&self, content_hash: &str, unit_of_work: &mut UnitOfWork) -> Result<i32, RepositoryError> {
+        self.as_ref().release_content_in(content_hash, unit_of_work).await
+    }
+
+    async fn delete_unreferenced_content_in(&self, content_hash: &str, unit_of_work: &mut UnitOfWork) -> Result<bool, RepositoryError> {
+        self.as_ref().delete_unreferenced_content_in(content_hash, unit_of_work).await
+    }
+
+    async fn content_exists(&self, content_hash: &str) -> Result<bool, RepositoryError> {
+        self.as_ref().content_exists(content_hash).await
+    }
+}
@@ -0,0 +1,64 @@
+use crate::repository::RepositoryError;
+use sqlx::{PgConnection, Pool, Postgres, Transaction};
+
+/// Undo of a write made by a repository which is not backed by the database
+pub type Rollback = Box<dyn FnOnce() + Send>;
+
+/// Writes of several repositories committed or rolled back together.
+/// Database repositories join the sqlx transaction, started by the first of them,
+/// other repositories register how to undo their writes.
+/// A unit of work dropped without commit is rolled back.
+#[derive(Default)]
+pub struct UnitOfWork {
+    transaction: Option<Transaction<'static, Postgres>>,
+    rollbacks: Vec<Rollback>,
+}
+
+impl UnitOfWork {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Connection of the transaction, it is started on `pool` on first use
+    pub async fn connection(&mut self, pool: &Pool<Postgres>) -> Result<&mut PgConnection, RepositoryError> {
+        let transaction = match self.transaction.take() {
+            Some(transaction) => transaction,
+            None => pool.begin().await.map_err(RepositoryError::from)?,
+        };
+        Ok(&mut **self.transaction.insert(transaction))
+    }
+
+    pub fn on_rollback(&mut self, rollback: impl FnOnce() + Send + 'static) {
+        self.rollbacks.push(Box::new(rollback));
+    }
+
+    pub async fn commit(mut self) -> Result<(), RepositoryError> {
+        self.rollbacks.clear();
+        match self.transaction.take() {
+            Some(transaction) => transaction.commit().await.map_err(RepositoryError::from),
+            None => Ok(()),
+        }
+    }
+
+    pub async fn rollback(mut self) -> Result<(), RepositoryError> {
+        self.run_rollbacks();
+        match self.transaction.take() {
+            Some(transaction) => transaction.rollback().await.map_err(RepositoryError::from),
+            None => Ok(()),
+        }
+    }
+
+    fn run_rollbacks(&mut self) {
+        // undo in reverse order of the writes
+        while let Some(rollback) = self.rollbacks.pop() {
+            rollback();
+        }
+    }
+}
+
+impl std::ops::Drop for UnitOfWork {
+    fn drop(&mut self) {
+        // the sqlx transaction rolls back by itself when dropped
+        self.run_rollbacks();
+    }
+}
@@ -1,9 +1,16 @@
 use crate::repository::artist::Artist;
+use crate::repository::import::{Import, ImportRepoT};
 use crate::repository::{Repo, RepoByName};
-use crate::service::drop::{DropRequest, ImportError};
+use crate::service::drop::{CreatedDrop, DropError, DropRequest, DropUpdate, ImportError, TrackMetadata};
 use async_trait::async_trait;
 
+pub mod archive;
+pub mod artwork;
+pub mod audio;
 pub mod drop;
+pub mod import;
+pub mod watcher;
+pub mod workspace;
 
 pub trait ArtistRepoTrait: Repo<Artist> + RepoByName<Artist> + Send + Sync {}
 impl<T: Repo<Artist> + RepoByName<Artist> + Send + Sync> ArtistRepoTrait for T {}
@@ -12,8 +19,36 @@ impl<T: Repo<Artist> + RepoByName<Artist> + Send + Sync> ArtistRepoTrait for T {
 pub trait DropServiceT {
     async fn create_drop(
         &self,
-        drop_import_path: &String,
+        drop_import_path: &str,
         drop_request: DropRequest,
-        web_server_path: &String
-    ) -> Result<(), ImportError>;
+        web_server_path: &str
+    ) -> Result<CreatedDrop, ImportError>;
+
+    /// Creates the drop of an archive, `import` is marked imported by the commit which creates it
+    async fn create_imported_drop(
+        &self,
+        drop_import_path: &str,
+        drop_request: DropRequest,
+        web_server_path: &str,
+        import_repo: &dyn ImportRepoT,
+        import: &mut Import
+    ) -> Result<CreatedDrop, ImportError>;
+
+    async fn update_drop(&self, drop_id: i32, drop_update: DropUpdate) -> Result<(), DropError>;
+
+    /// The tracks of the drop become `tracks`, read from `drop_import_path`
+    async fn replace_tracks(
+        &self,
+        drop_id: i32,
+        drop_import_path: &str,
+        tracks: Vec<String>,
+        track_metadata: Vec<TrackMetadata>,
+        web_server_path: &str
+    ) -> Result<(), DropError>;
+
+    /// Takes the drop down, its rows and files are kept and the access to it is revoked
+    async fn unpublish_drop(&self, drop_id: i32, web_server_path: &str) -> Result<(), DropError>;
+
+    /// Deletes the drop with its playlist, tracks and artwork, the artist is kept
+    async fn delete_drop(&self, drop_id: i32, web_server_path: &str) -> Result<(), DropError>;
 }
@@ -0,0 +1,279 @@
+use crate::service::drop::ImportError;
+use flate2::read::GzDecoder;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+use tar::{Archive, EntryType};
+use xz2::read::XzDecoder;
+use zip::ZipArchive;
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+const XZ_MAGIC: &[u8] = &[0xfd, b'7', b'z', b'X', b'Z', 0x00];
+const ZIP_MAGIC: &[u8] = &[b'P', b'K', 0x03, 0x04];
+const ZIP_EMPTY_MAGIC: &[u8] = &[b'P', b'K', 0x05, 0x06];
+const TAR_MAGIC: &[u8] = b"ustar";
+const TAR_MAGIC_OFFSET: usize = 257;
+
+pub const DEFAULT_MAX_UNPACKED_SIZE: u64 = 2 * 1024 * 1024 * 1024;
+pub const DEFAULT_MAX_ARCHIVE_ENTRIES: u64 = 1000;
+pub const DEFAULT_MAX_COMPRESSION_RATIO: u64 = 100;
+
+const UNPACK_BUFFER_SIZE: usize = 64 * 1024;
+
+const UNIX_FILE_TYPE_MASK: u32 = 0o170000;
+const UNIX_REGULAR_FILE: u32 = 0o100000;
+const UNIX_DIRECTORY: u32 = 0o040000;
+
+/// Format of a drop archive, detected from its first bytes whatever its file name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+    TarZst,
+    TarXz,
+}
+
+impl ArchiveFormat {
+    pub fn detect(header: &[u8]) -> Option<ArchiveFormat> {
+        if header.starts_with(GZIP_MAGIC) {
+            Some(ArchiveFormat::TarGz)
+        } else if header.starts_with(ZSTD_MAGIC) {
+            Some(ArchiveFormat::TarZst)
+        } else if header.starts_with(XZ_MAGIC) {
+            Some(ArchiveFormat::TarXz)
+        } else if header.starts_with(ZIP_MAGIC) || header.starts_with(ZIP_EMPTY_MAGIC) {
+            Some(ArchiveFormat::Zip)
+        } else if header.len() >= TAR_MAGIC_OFFSET + TAR_MAGIC.len()
+            && &header[TAR_MAGIC_OFFSET..TAR_MAGIC_OFFSET + TAR_MAGIC.len()] == TAR_MAGIC {
+            Some(ArchiveFormat::Tar)
+        } else {
+            None
+        }
+    }
+
+    /// Read the header of `file` then rewind it
+    pub fn detect_file(file: &mut File) -> io::Result<Option<ArchiveFormat>> {
+        let mut header = Vec::with_capacity(TAR_MAGIC_OFFSET + TAR_MAGIC.len());
+        Read::by_ref(file).take((TAR_MAGIC_OFFSET + TAR_MAGIC.len()) as u64).read_to_end(&mut header)?;
+        file.seek(SeekFrom::Start(0))?;
+        Ok(ArchiveFormat::detect(&header))
+    }
+
+    pub fn extractor(&self) -> Box<dyn ArchiveExtractor> {
+        match self {
+            ArchiveFormat::Zip => Box::new(ZipExtractor),
+            ArchiveFormat::Tar => Box::new(TarExtractor::new(TarCompression::None)),
+            ArchiveFormat::TarGz => Box::new(TarExtractor::new(TarCompression::Gzip)),
+            ArchiveFormat::TarZst => Box::new(TarExtractor::new(TarCompression::Zstd)),
+            ArchiveFormat::TarXz => Box::new(TarExtractor::new(TarCompression::Xz)),
+        }
+    }
+}
+
+/// Bounds of an unpacked archive, they protect the import path from archive bombs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtractionLimits {
+    max_total_size: u64,
+    max_entries: u64,
+    max_compression_ratio: u64,
+}
+
+impl ExtractionLimits {
+    pub fn new(max_total_size: u64, max_entries: u64, max_compression_ratio: u64) -> Self {
+        Self { max_total_size, max_entries, max_compression_ratio }
+    }
+
+    pub fn max_total_size(&self) -> u64 {
+        self.max_total_size
+    }
+
+    pub fn max_entries(&self) -> u64 {
+        self.max_entries
+    }
+
+    pub fn max_compression_ratio(&self) -> u64 {
+        self.max_compression_ratio
+    }
+}
+
+impl Default for ExtractionLimits {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_UNPACKED_SIZE, DEFAULT_MAX_ARCHIVE_ENTRIES, DEFAULT_MAX_COMPRESSION_RATIO)
+    }
+}
+
+/// Entries and bytes unpacked so far, checked against the limits
+struct ExtractionBudget {
+    limits: ExtractionLimits,
+    archive_size: u64,
+    entries: u64,
+    total_size: u64,
+}
+
+impl ExtractionBudget {
+    fn new(limits: ExtractionLimits, archive: &File) -> Result<Self, ImportError> {
+        let archive_size = archive.metadata().or(Err(ImportError::CantOpenDropFile))?.len();
+        Ok(Self { limits, archive_size, entries: 0, total_size: 0 })
+    }
+
+    fn add_entry(&mut self) -> Result<(), ImportError> {
+        self.entries += 1;
+        if self.entries > self.limits.max_entries {
+            return Err(ImportError::TooManyArchiveEntries);
+        }
+        Ok(())
+    }
+
+    fn add_size(&mut self, size: u64) -> Result<(), ImportError> {
+        self.total_size += size;
+        if self.total_size > self.limits.max_total_size {
+            return Err(ImportError::ArchiveTooLarge);
+        }
+        if self.total_size > self.archive_size.saturating_mul(self.limits.max_compression_ratio) {
+            return Err(ImportError::ArchiveCompressionRatioTooHigh);
+        }
+        Ok(())
+    }
+
+    /// Write the content of an entry to `destination`, the limits are checked on the bytes
+    /// actually decompressed rather than on the sizes the archive declares
+    fn unpack_file(&mut self, reader: &mut dyn Read, destination: &Path) -> Result<(), ImportError> {
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent).or(Err(ImportError::CantUnpackDropFile))?;
+        }
+        let mut file = File::create(destination).or(Err(ImportError::CantUnpackDropFile))?;
+        let mut buffer = [0u8; UNPACK_BUFFER_SIZE];
+        loop {
+            let read = reader.read(&mut buffer).or(Err(ImportError::CantUnpackDropFile))?;
+            if read == 0 {
+                return Ok(());
+            }
+            self.add_size(read as u64)?;
+            file.write_all(&buffer[..read]).or(Err(ImportError::CantUnpackDropFile))?;
+        }
+    }
+}
+
+/// Path of an entry relative to the destination, absolute and `..` paths are refused
+fn safe_entry_path(path: &Path) -> Result<PathBuf, ImportError> {
+    let mut relative_path = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => relative_path.push(name),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                return Err(ImportError::UnsafeArchiveEntry)
+            }
+        }
+    }
+    Ok(relative_path)
+}
+
+/// Unpack every entry of an archive into a directory
+pub trait ArchiveExtractor {
+    fn extract(&self, archive: File, destination: &Path, limits: ExtractionLimits) -> Result<(), ImportError>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TarCompression {
+    None,
+    Gzip,
+    Zstd,
+    Xz,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TarExtractor {
+    compression: TarCompression,
+}
+
+impl TarExtractor {
+    pub fn new(compression: TarCompression) -> Self {
+        Self { compression }
+    }
+}
+
+impl ArchiveExtractor for TarExtractor {
+    fn extract(&self, archive: File, destination: &Path, limits: ExtractionLimits) -> Result<(), ImportError> {
+        let mut budget = ExtractionBudget::new(limits, &archive)?;
+        let reader: Box<dyn Read> = match self.compression {
+            TarCompression::None => Box::new(archive),
+            TarCompression::Gzip => Box::new(GzDecoder::new(archive)),
+            TarCompression::Zstd => Box::new(zstd::Decoder::new(archive).or(Err(ImportError::CantUnpackDropFile))?),
+            TarCompression::Xz => Box::new(XzDecoder::new(archive)),
+        };
+        let mut tar_archive = Archive::new(reader);
+        for entry in tar_archive.entries().or(Err(ImportError::CantUnpackDropFile))? {
+            let mut entry = entry.or(Err(ImportError::CantUnpackDropFile))?;
+            budget.add_entry()?;
+            let entry_path = safe_entry_path(&entry.path().or(Err(ImportError::UnsafeArchiveEntry))?)?;
+            match entry.header().entry_type() {
+                EntryType::Directory => {
+                    fs::create_dir_all(destination.join(entry_path)).or(Err(ImportError::CantUnpackDropFile))?
+                }
+                EntryType::Regular | EntryType::Continuous if entry_path.as_os_str().is_empty() => {
+                    return Err(ImportError::UnsafeArchiveEntry)
+                }
+                EntryType::Regular | EntryType::Continuous => {
+                    budget.unpack_file(&mut entry, &destination.join(entry_path))?
+                }
+                // pax global headers only carry metadata
+                EntryType::XGlobalHeader => {}
+                EntryType::Symlink | EntryType::Link | EntryType::Char | EntryType::Block | EntryType::Fifo => {
+                    return Err(ImportError::UnsafeArchiveEntry)
+                }
+                _ => return Err(ImportError::CantUnpackDropFile),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ZipExtractor;
+
+impl ArchiveExtractor for ZipExtractor {
+    fn extract(&self, archive: File, destination: &Path, limits: ExtractionLimits) -> Result<(), ImportError> {
+        let mut budget = ExtractionBudget::new(limits, &archive)?;
+        let mut zip_archive = ZipArchive::new(archive).or(Err(ImportError::CantUnpackDropFile))?;
+        for i in 0..zip_archive.len() {
+            budget.add_entry()?;
+            let mut zip_file = zip_archive.by_index(i).or(Err(ImportError::CantUnpackDropFile))?;
+            // the unix mode tells links and device files apart, zips made elsewhere have none
+            if zip_file.is_symlink() || zip_file.unix_mode()
+                .map(|mode| mode & UNIX_FILE_TYPE_MASK)
+                .is_some_and(|file_type| file_type != 0 && file_type != UNIX_REGULAR_FILE && file_type != UNIX_DIRECTORY) {
+                return Err(ImportError::UnsafeArchiveEntry);
+            }
+            let entry_path = safe_entry_path(Path::new(zip_file.name()))?;
+            if zip_file.is_dir() {
+                fs::create_dir_all(destination.join(entry_path)).or(Err(ImportError::CantUnpackDropFile))?;
+            } else if entry_path.as_os_str().is_empty() {
+                return Err(ImportError::UnsafeArchiveEntry);
+            } else {
+                budget.unpack_file(&mut zip_file, &destination.join(entry_path))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Detect the format of the archive `file` then unpack it into `destination` within the default limits
+pub fn extract_archive(file: &Path, destination: &Path) -> Result<ArchiveFormat, ImportError> {
+    extract_archive_with_limits(file, destination, ExtractionLimits::default())
+}
+
+pub fn extract_archive_with_limits(
+    file: &Path,
+    destination: &Path,
+    limits: ExtractionLimits,
+) -> Result<ArchiveFormat, ImportError> {
+    let mut archive = File::open(file).or(Err(ImportError::CantOpenDropFile))?;
+    let archive_format = ArchiveFormat::detect_file(&mut archive)
+        .or(Err(ImportError::CantOpenDropFile))?
+        .ok_or(ImportError::UnsupportedArchiveFormat)?;
+    archive_format.extractor().extract(archive, destination, limits)?;
+    Ok(archive_format)
+}
@@ -0,0 +1,193 @@
+use crate::service::drop::ImportError;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat, ImageReader};
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::Path;
+
+pub const ARTWORK_DIR_PREFIX: &str = "artwork_";
+pub const ARTWORK_STAGING_DIR_PREFIX: &str = ".staging_artwork_";
+/// `artwork_id` of a drop without artwork
+pub const NO_ARTWORK_ID: i32 = 0;
+pub const MAX_ARTWORK_DIMENSION: u32 = 6000;
+const ARTWORK_JPEG_QUALITY: u8 = 85;
+
+/// Formats accepted for the original artwork
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArtworkFormat {
+    Jpeg,
+    Png,
+    WebP,
+}
+
+impl ArtworkFormat {
+    pub fn from_mime_type(mime_type: &str) -> Option<Self> {
+        match mime_type {
+            "image/jpeg" => Some(ArtworkFormat::Jpeg),
+            "image/png" => Some(ArtworkFormat::Png),
+            "image/webp" => Some(ArtworkFormat::WebP),
+            _ => None,
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ArtworkFormat::Jpeg => "image/jpeg",
+            ArtworkFormat::Png => "image/png",
+            ArtworkFormat::WebP => "image/webp",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ArtworkFormat::Jpeg => "jpg",
+            ArtworkFormat::Png => "png",
+            ArtworkFormat::WebP => "webp",
+        }
+    }
+
+    fn from_image_format(image_format: ImageFormat) -> Option<Self> {
+        match image_format {
+            ImageFormat::Jpeg => Some(ArtworkFormat::Jpeg),
+            ImageFormat::Png => Some(ArtworkFormat::Png),
+            ImageFormat::WebP => Some(ArtworkFormat::WebP),
+            _ => None,
+        }
+    }
+}
+
+/// Sizes served by `/artwork/{size}`, the original as imported and the variants generated from it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArtworkSize {
+    Original,
+    Large,
+    Medium,
+    Small,
+    Thumbnail,
+}
+
+impl ArtworkSize {
+    pub const VARIANTS: [ArtworkSize; 4] = [ArtworkSize::Large, ArtworkSize::Medium, ArtworkSize::Small, ArtworkSize::Thumbnail];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "original" => Some(ArtworkSize::Original),
+            "large" => Some(ArtworkSize::Large),
+            "medium" => Some(ArtworkSize::Medium),
+            "small" => Some(ArtworkSize::Small),
+            "thumbnail" => Some(ArtworkSize::Thumbnail),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ArtworkSize::Original => "original",
+            ArtworkSize::Large => "large",
+            ArtworkSize::Medium => "medium",
+            ArtworkSize::Small => "small",
+            ArtworkSize::Thumbnail => "thumbnail",
+        }
+    }
+
+    /// Longest side in pixels, a smaller original is not enlarged
+    pub fn max_dimension(&self) -> Option<u32> {
+        match self {
+            ArtworkSize::Original => None,
+            ArtworkSize::Large => Some(1200),
+            ArtworkSize::Medium => Some(600),
+            ArtworkSize::Small => Some(300),
+            ArtworkSize::Thumbnail => Some(150),
+        }
+    }
+
+    /// The original keeps its format, the thumbnail is a WebP and the other variants are JPEGs
+    pub fn format(&self, original_format: ArtworkFormat) -> ArtworkFormat {
+        match self {
+            ArtworkSize::Original => original_format,
+            ArtworkSize::Thumbnail => ArtworkFormat::WebP,
+            _ => ArtworkFormat::Jpeg,
+        }
+    }
+
+    pub fn file_name(&self, original_format: ArtworkFormat) -> String {
+        format!("{}.{}", self.name(), self.format(original_format).extension())
+    }
+}
+
+/// Format and dimensions of an original artwork
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArtworkImage {
+    format: ArtworkFormat,
+    width: u32,
+    height: u32,
+}
+
+impl ArtworkImage {
+    pub fn format(&self) -> ArtworkFormat {
+        self.format
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+/// Key prefix of the original and the variants of an artwork in the media store
+pub fn artwork_prefix(artwork_id: i32) -> String {
+    format!("{ARTWORK_DIR_PREFIX}{artwork_id}/")
+}
+
+/// Read the format and dimensions of an artwork without decoding it,
+/// its content decides of the format rather than its file name
+pub fn probe_artwork(path: &Path) -> Result<ArtworkImage, ImportError> {
+    let image_reader = ImageReader::open(path)
+        .and_then(|image_reader| image_reader.with_guessed_format())
+        .or(Err(ImportError::MissingArtworkInDropArchive))?;
+    let format = image_reader.format()
+        .and_then(ArtworkFormat::from_image_format)
+        .ok_or(ImportError::InvalidArtwork)?;
+    let (width, height) = image_reader.into_dimensions().or(Err(ImportError::InvalidArtwork))?;
+    if width == 0 || height == 0 || width > MAX_ARTWORK_DIMENSION || height > MAX_ARTWORK_DIMENSION {
+        return Err(ImportError::InvalidArtwork);
+    }
+    Ok(ArtworkImage { format, width, height })
+}
+
+/// Copy the original artwork to `destination` then write each of the resized variants
+pub fn write_artwork_variants(source: &Path, artwork_image: &ArtworkImage, destination: &Path) -> Result<(), ImportError> {
+    fs::copy(source, destination.join(ArtworkSize::Original.file_name(artwork_image.format)))
+        .or(Err(ImportError::CantWriteArtworkVariants))?;
+    let image = ImageReader::open(source)
+        .and_then(|image_reader| image_reader.with_guessed_format())
+        .or(Err(ImportError::CantWriteArtworkVariants))?
+        .decode()
+        .or(Err(ImportError::InvalidArtwork))?;
+    for artwork_size in ArtworkSize::VARIANTS {
+        let variant = resize_within(&image, artwork_size.max_dimension().unwrap_or(u32::MAX));
+        let file = File::create(destination.join(artwork_size.file_name(artwork_image.format)))
+            .or(Err(ImportError::CantWriteArtworkVariants))?;
+        let mut writer = BufWriter::new(file);
+        let written = match artwork_size.format(artwork_image.format) {
+            // JPEG has no alpha channel
+            ArtworkFormat::Jpeg => variant.to_rgb8()
+                .write_with_encoder(JpegEncoder::new_with_quality(&mut writer, ARTWORK_JPEG_QUALITY)),
+            _ => variant.to_rgba8().write_with_encoder(WebPEncoder::new_lossless(&mut writer)),
+        };
+        written.or(Err(ImportError::CantWriteArtworkVariants))?;
+    }
+    Ok(())
+}
+
+fn resize_within(image: &DynamicImage, max_dimension: u32) -> DynamicImage {
+    if image.width() <= max_dimension && image.height() <= max_dimension {
+        return image.clone();
+    }
+    image.resize(max_dimension, max_dimension, FilterType::Lanczos3)
+}
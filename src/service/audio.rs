@@ -0,0 +1,191 @@
+use crate::service::drop::ImportError;
+use serde::Serialize;
+use std::fs::File;
+use std::path::Path;
+use std::time::Duration;
+use symphonia::core::codecs::{CODEC_TYPE_AAC, CODEC_TYPE_FLAC, CODEC_TYPE_MP3, CODEC_TYPE_OPUS, CODEC_TYPE_VORBIS};
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::{MetadataOptions, MetadataRevision, StandardTagKey};
+use symphonia::core::probe::Hint;
+
+const PCM_CODEC_PREFIX: &str = "pcm_";
+
+/// Codec of a track, read from its container and codec headers rather than its file name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum AudioFormat {
+    Mp3,
+    Flac,
+    OggVorbis,
+    OggOpus,
+    Wav,
+    Aac,
+}
+
+/// Tags embedded in a track, ID3 for MP3, Vorbis comments for FLAC and OGG, INFO for WAV
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AudioTags {
+    title: Option<String>,
+    artist: Option<String>,
+    album: Option<String>,
+    genre: Option<String>,
+    label: Option<String>,
+    isrc: Option<String>,
+    track_number: Option<u32>,
+    disc_number: Option<u32>,
+}
+
+impl AudioTags {
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    pub fn artist(&self) -> Option<&str> {
+        self.artist.as_deref()
+    }
+
+    pub fn album(&self) -> Option<&str> {
+        self.album.as_deref()
+    }
+
+    pub fn genre(&self) -> Option<&str> {
+        self.genre.as_deref()
+    }
+
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    pub fn isrc(&self) -> Option<&str> {
+        self.isrc.as_deref()
+    }
+
+    pub fn track_number(&self) -> Option<u32> {
+        self.track_number
+    }
+
+    pub fn disc_number(&self) -> Option<u32> {
+        self.disc_number
+    }
+
+    /// Tags read first are kept, e.g. ID3 ones before those of the container
+    fn add(&mut self, metadata_revision: &MetadataRevision) {
+        for tag in metadata_revision.tags() {
+            let value = tag.value.to_string().trim_matches(|c: char| c == '\0' || c.is_whitespace()).to_string();
+            if value.is_empty() {
+                continue;
+            }
+            let field = match tag.std_key {
+                Some(StandardTagKey::TrackTitle) => &mut self.title,
+                Some(StandardTagKey::Artist) => &mut self.artist,
+                Some(StandardTagKey::Album) => &mut self.album,
+                Some(StandardTagKey::Genre) => &mut self.genre,
+                Some(StandardTagKey::Label) => &mut self.label,
+                Some(StandardTagKey::IdentIsrc) => &mut self.isrc,
+                Some(StandardTagKey::TrackNumber) => {
+                    self.track_number = self.track_number.or(parse_position(&value));
+                    continue;
+                }
+                Some(StandardTagKey::DiscNumber) => {
+                    self.disc_number = self.disc_number.or(parse_position(&value));
+                    continue;
+                }
+                _ => continue,
+            };
+            field.get_or_insert(value);
+        }
+    }
+}
+
+/// Track and disc numbers may be written `3/12`
+fn parse_position(value: &str) -> Option<u32> {
+    value.split('/').next()?.trim().parse().ok()
+}
+
+/// What probing a track found out about it
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioProbe {
+    format: AudioFormat,
+    duration: Option<Duration>,
+    bitrate: Option<u32>,
+    sample_rate: Option<u32>,
+    tags: AudioTags,
+}
+
+impl AudioProbe {
+    pub fn format(&self) -> AudioFormat {
+        self.format
+    }
+
+    pub fn duration(&self) -> Option<Duration> {
+        self.duration
+    }
+
+    /// Average bitrate in bits per second
+    pub fn bitrate(&self) -> Option<u32> {
+        self.bitrate
+    }
+
+    pub fn sample_rate(&self) -> Option<u32> {
+        self.sample_rate
+    }
+
+    pub fn tags(&self) -> &AudioTags {
+        &self.tags
+    }
+}
+
+/// Probe the container and codec headers of a track, anything else than MP3, FLAC,
+/// OGG Vorbis or Opus, WAV and AAC is refused
+pub fn probe_audio_file(path: &Path) -> Result<AudioProbe, ImportError> {
+    let file = File::open(path).or(Err(ImportError::MissingTrackInDropArchive))?;
+    let file_size = file.metadata().or(Err(ImportError::MissingTrackInDropArchive))?.len();
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|extension| extension.to_str()) {
+        hint.with_extension(extension);
+    }
+    let mut probe_result = symphonia::default::get_probe()
+        .format(
+            &hint,
+            MediaSourceStream::new(Box::new(file), Default::default()),
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .or(Err(ImportError::InvalidAudioTrack))?;
+
+    let track = probe_result.format.default_track().ok_or(ImportError::InvalidAudioTrack)?;
+    let codec_params = &track.codec_params;
+    let format = match codec_params.codec {
+        CODEC_TYPE_MP3 => AudioFormat::Mp3,
+        CODEC_TYPE_FLAC => AudioFormat::Flac,
+        CODEC_TYPE_VORBIS => AudioFormat::OggVorbis,
+        CODEC_TYPE_OPUS => AudioFormat::OggOpus,
+        CODEC_TYPE_AAC => AudioFormat::Aac,
+        // only the wav reader is enabled among the readers of PCM
+        codec => match symphonia::default::get_codecs().get_codec(codec) {
+            Some(codec_descriptor) if codec_descriptor.short_name.starts_with(PCM_CODEC_PREFIX) => AudioFormat::Wav,
+            _ => return Err(ImportError::InvalidAudioTrack),
+        },
+    };
+    let duration = match (codec_params.time_base, codec_params.n_frames) {
+        (Some(time_base), Some(n_frames)) => {
+            let time = time_base.calc_time(n_frames);
+            Some(Duration::from_secs(time.seconds) + Duration::from_secs_f64(time.frac))
+        }
+        _ => None,
+    };
+    let bitrate = duration
+        .filter(|duration| !duration.is_zero())
+        .map(|duration| (file_size as f64 * 8.0 / duration.as_secs_f64()) as u32);
+    let sample_rate = codec_params.sample_rate;
+
+    let mut tags = AudioTags::default();
+    if let Some(metadata) = probe_result.metadata.get()
+        && let Some(metadata_revision) = metadata.current() {
+        tags.add(metadata_revision);
+    }
+    if let Some(metadata_revision) = probe_result.format.metadata().current() {
+        tags.add(metadata_revision);
+    }
+    Ok(AudioProbe { format, duration, bitrate, sample_rate, tags })
+}
@@ -1,17 +1,49 @@
-use crate::repository::artist::Artist;
-use crate::repository::drop::Drop;
+use crate::repository::artist::{normalize_artist_name, Artist};
+use crate::repository::artwork::Artwork;
+use crate::repository::drop::{Drop, Release};
+use crate::repository::drop_type::DropType;
+use crate::repository::import::{Import, ImportRepoT};
 use crate::repository::playlist::Playlist;
-use crate::repository::{Repo, RepoByName};
+use crate::repository::query::Filter;
+use crate::repository::track::{Track, TrackRepoT};
+use crate::repository::unit_of_work::UnitOfWork;
+use crate::repository::{Repo, RepoByName, RepositoryError};
+use crate::media::{delete_prefix, move_prefix, LocalMediaStore, MediaContent, MediaStore, MediaStoreError};
+use crate::service::artwork::{artwork_prefix, probe_artwork, write_artwork_variants, ARTWORK_DIR_PREFIX, ARTWORK_STAGING_DIR_PREFIX, NO_ARTWORK_ID};
+use crate::service::audio::AudioProbe;
+use crate::{TagRepo, TokenRepo};
+pub use crate::repository::drop::Credit;
+pub use crate::repository::track::CuePoint;
 pub use crate::service::DropServiceT;
 use async_trait::async_trait;
-use serde::Deserialize;
+use chrono::{NaiveDate, Utc};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
 use derive_new::new;
+use uuid::Uuid;
 
 pub const PLAYLIST_DIR_PREFIX: &str = "playlist_";
 pub const TRACK_FILE_PREFIX: &str = "track_";
+pub const UNPUBLISHED_PLAYLIST_DIR_PREFIX: &str = ".unpublished_playlist_";
+pub const REPLACED_PLAYLIST_DIR_PREFIX: &str = ".replaced_playlist_";
+pub const TRACK_CONTENT_PREFIX: &str = "track_content/";
 
-#[derive(Debug)]
+/// What an import does when `artist_name` matches no artist
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportPolicy {
+    /// The import fails with `CantCreateArtistFromArtistName`
+    #[default]
+    Strict,
+    /// The artist is created along with the drop
+    CreateMissing,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
 pub enum ImportError {
     InvalidFileExtension,
     InvalidUnixEpoch,
@@ -34,6 +66,121 @@ pub enum ImportError {
     CantCreatePlaylistFromPlaylistName,
     CantCreatPlaylistDirectoryInWebServer,
     CantCopyTrackFileToPlaylistDirectory,
+    DropFileAlreadyImported,
+    CantRecordImport,
+    CantCommitDropCreation,
+    UnsupportedArchiveFormat,
+    UnsafeArchiveEntry,
+    ArchiveTooLarge,
+    TooManyArchiveEntries,
+    ArchiveCompressionRatioTooHigh,
+    TrackPathOutsideDropDirectory,
+    InvalidDropManifest,
+    UnsupportedDropManifestVersion,
+    MissingArtworkInDropArchive,
+    SeveralDropManifests,
+    InvalidAudioTrack,
+    InvalidArtwork,
+    CantCreateArtwork,
+    CantWriteArtworkVariants,
+    CantCreateMissingArtist,
+    CantCreateTrack,
+    /// A write conflicts with an entity saved by another import, e.g. the same missing artist
+    DuplicateEntity,
+    /// An entity the drop references was deleted during the import
+    MissingReferencedEntity,
+    DatabaseTimeout,
+    DatabaseUnavailable,
+}
+
+impl ImportError {
+    /// Errors raised while creating the drop, the archive itself was valid
+    pub fn is_create_phase_error(&self) -> bool {
+        matches!(
+            self,
+            ImportError::DropRepositoryIsNone
+            | ImportError::ArtistRepositoryIsNone
+            | ImportError::PlaylistRepositoryIsNone
+            | ImportError::CantCreateArtistFromArtistName
+            | ImportError::CantCreateDropFromDropRequest
+            | ImportError::CantCreatePlaylistFromPlaylistName
+            | ImportError::CantCreatPlaylistDirectoryInWebServer
+            | ImportError::CantCopyTrackFileToPlaylistDirectory
+            | ImportError::CantRecordImport
+            | ImportError::CantCommitDropCreation
+            | ImportError::CantCreateArtwork
+            | ImportError::CantWriteArtworkVariants
+            | ImportError::CantCreateMissingArtist
+            | ImportError::CantCreateTrack
+            | ImportError::DuplicateEntity
+            | ImportError::MissingReferencedEntity
+            | ImportError::DatabaseTimeout
+            | ImportError::DatabaseUnavailable
+        )
+    }
+
+    /// Error of a repository call of the import, `otherwise` when the database tells nothing more
+    pub fn from_repository_error(e: RepositoryError, otherwise: ImportError) -> ImportError {
+        match e {
+            RepositoryError::UniqueViolation => ImportError::DuplicateEntity,
+            RepositoryError::ForeignKeyViolation => ImportError::MissingReferencedEntity,
+            RepositoryError::Timeout => ImportError::DatabaseTimeout,
+            RepositoryError::Unavailable => ImportError::DatabaseUnavailable,
+            _ => otherwise,
+        }
+    }
+}
+
+impl std::fmt::Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            ImportError::InvalidFileExtension => "the file is not a supported drop archive",
+            ImportError::InvalidUnixEpoch => "the system clock is before the unix epoch",
+            ImportError::NoFileParentDirectory => "the archive has no parent directory",
+            ImportError::InvalidParentDirectory => "the archive parent directory is not a valid path",
+            ImportError::CantCreateDropUntarDirectory => "the directory to unpack the archive can't be created",
+            ImportError::CantCopyToUntarDirectory => "the archive can't be copied to the unpack directory",
+            ImportError::CantOpenDropFile => "the archive can't be opened",
+            ImportError::CantUnpackDropFile => "the archive can't be unpacked",
+            ImportError::CantReadUntarDirectory => "the unpacked archive can't be read",
+            ImportError::NoDropDescriptionFileFound => "the archive contains no valid drop.toml, drop.json, drop.yaml or drop.txt",
+            ImportError::MissingTrackInDropArchive => "a track listed in the manifest is missing from the archive",
+            ImportError::ArtistIdAndArtistNameAreBothPresent => "the manifest gives both artist_id and artist_name",
+            ImportError::InvalidArtistId => "the artist_id of the manifest doesn't exist",
+            ImportError::DropRepositoryIsNone => "the drop repository is not set",
+            ImportError::ArtistRepositoryIsNone => "the artist repository is not set",
+            ImportError::PlaylistRepositoryIsNone => "the playlist repository is not set",
+            ImportError::CantCreateArtistFromArtistName => "the artist_name of the manifest doesn't match an artist",
+            ImportError::CantCreateDropFromDropRequest => "the drop can't be saved",
+            ImportError::CantCreatePlaylistFromPlaylistName => "the playlist can't be saved",
+            ImportError::CantCreatPlaylistDirectoryInWebServer => "the playlist directory can't be created in the web server",
+            ImportError::CantCopyTrackFileToPlaylistDirectory => "a track can't be copied to the playlist directory",
+            ImportError::DropFileAlreadyImported => "an archive with the same content was already imported",
+            ImportError::CantRecordImport => "the import can't be recorded",
+            ImportError::CantCommitDropCreation => "the drop creation can't be committed",
+            ImportError::UnsupportedArchiveFormat => "the archive is not a zip, tar, tar.gz, tar.zst or tar.xz",
+            ImportError::UnsafeArchiveEntry => "the archive contains an absolute path, a `..` path, a link or a device file",
+            ImportError::ArchiveTooLarge => "the unpacked archive is larger than allowed",
+            ImportError::TooManyArchiveEntries => "the archive contains more entries than allowed",
+            ImportError::ArchiveCompressionRatioTooHigh => "the archive compression ratio is higher than allowed",
+            ImportError::TrackPathOutsideDropDirectory => "a file listed in the manifest is outside the unpacked archive",
+            ImportError::InvalidDropManifest => "some fields of the manifest are invalid",
+            ImportError::UnsupportedDropManifestVersion => "the version of the manifest is not supported",
+            ImportError::MissingArtworkInDropArchive => "the artwork listed in the manifest is missing from the archive",
+            ImportError::SeveralDropManifests => "the archive contains more than one of drop.toml, drop.json, drop.yaml and drop.txt",
+            ImportError::InvalidAudioTrack => "a track is not a MP3, FLAC, OGG Vorbis, Opus, WAV or AAC audio file",
+            ImportError::InvalidArtwork => "the artwork is not a JPEG, PNG or WebP image within the allowed dimensions",
+            ImportError::CantCreateArtwork => "the artwork can't be saved",
+            ImportError::CantWriteArtworkVariants => "the artwork variants can't be written to the web server",
+            ImportError::CantCreateMissingArtist => "the artist_name of the manifest matches no artist and the artist can't be created",
+            ImportError::CantCreateTrack => "a track can't be saved",
+            ImportError::DuplicateEntity => "an entity of the drop was saved meanwhile by another import",
+            ImportError::MissingReferencedEntity => "an entity the drop references was deleted meanwhile",
+            ImportError::DatabaseTimeout => "the database didn't answer in time",
+            ImportError::DatabaseUnavailable => "the database can't be reached",
+        };
+        f.write_str(message)
+    }
 }
 
 #[derive(Clone, Deserialize, new)]
@@ -41,7 +188,22 @@ pub struct DropRequest {
     artist_id: Option<i32>,
     artist_name: Option<String>,
     playlist_name: String,
-    tracks: Vec<String>
+    tracks: Vec<String>,
+    #[serde(default)]
+    #[new(default)]
+    artwork: Option<String>,
+    #[serde(default, rename = "type")]
+    #[new(default)]
+    drop_type: Option<DropType>,
+    #[serde(default)]
+    #[new(default)]
+    import_policy: Option<ImportPolicy>,
+    #[serde(default)]
+    #[new(default)]
+    track_metadata: Vec<TrackMetadata>,
+    #[serde(default)]
+    #[new(default)]
+    release: Release,
 }
 
 impl DropRequest {
@@ -60,49 +222,798 @@ impl DropRequest {
     pub fn tracks(&self) -> &Vec<String> {
         &self.tracks
     }
+
+    pub fn artwork(&self) -> Option<&str> {
+        self.artwork.as_deref()
+    }
+
+    pub fn with_artwork(mut self, artwork: &str) -> Self {
+        self.artwork = Some(artwork.to_string());
+        self
+    }
+
+    pub fn drop_type(&self) -> Option<DropType> {
+        self.drop_type
+    }
+
+    pub fn with_drop_type(mut self, drop_type: DropType) -> Self {
+        self.drop_type = Some(drop_type);
+        self
+    }
+
+    /// None when the service policy applies
+    pub fn import_policy(&self) -> Option<ImportPolicy> {
+        self.import_policy
+    }
+
+    pub fn with_import_policy(mut self, import_policy: ImportPolicy) -> Self {
+        self.import_policy = Some(import_policy);
+        self
+    }
+
+    /// Metadata of the tracks in the order of `tracks`, the tracks without are left unknown
+    pub fn track_metadata(&self) -> &Vec<TrackMetadata> {
+        &self.track_metadata
+    }
+
+    pub fn with_track_metadata(mut self, track_metadata: Vec<TrackMetadata>) -> Self {
+        self.track_metadata = track_metadata;
+        self
+    }
+
+    /// Release fields and credits of the drop
+    pub fn release(&self) -> &Release {
+        &self.release
+    }
+
+    pub fn with_release(mut self, release: Release) -> Self {
+        self.release = release;
+        self
+    }
+}
+
+/// Title, duration, recording fields and cue points of a track of a drop request
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize, new)]
+pub struct TrackMetadata {
+    title: Option<String>,
+    duration_seconds: Option<u32>,
+    #[serde(default)]
+    #[new(default)]
+    isrc: Option<String>,
+    #[serde(default)]
+    #[new(default)]
+    explicit: bool,
+    #[serde(default)]
+    #[new(default)]
+    disc_number: Option<u32>,
+    #[serde(default)]
+    #[new(default)]
+    track_number: Option<u32>,
+    #[serde(default)]
+    #[new(default)]
+    cue_points: Vec<CuePoint>,
+}
+
+impl TrackMetadata {
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    pub fn duration_seconds(&self) -> Option<u32> {
+        self.duration_seconds
+    }
+
+    pub fn isrc(&self) -> Option<&str> {
+        self.isrc.as_deref()
+    }
+
+    pub fn explicit(&self) -> bool {
+        self.explicit
+    }
+
+    pub fn disc_number(&self) -> Option<u32> {
+        self.disc_number
+    }
+
+    pub fn track_number(&self) -> Option<u32> {
+        self.track_number
+    }
+
+    pub fn cue_points(&self) -> &Vec<CuePoint> {
+        &self.cue_points
+    }
+}
+
+impl From<&TrackManifest> for TrackMetadata {
+    fn from(track: &TrackManifest) -> Self {
+        Self {
+            title: track.title.clone(),
+            duration_seconds: track.duration_seconds,
+            isrc: track.isrc(),
+            explicit: track.explicit,
+            disc_number: track.disc_number,
+            track_number: track.track_number,
+            cue_points: track.cue_points.clone(),
+        }
+    }
+}
+
+impl From<&DropManifest> for DropRequest {
+    fn from(drop_manifest: &DropManifest) -> Self {
+        let drop_request = DropRequest::new(
+            drop_manifest.artist_id,
+            drop_manifest.artist_name.clone(),
+            drop_manifest.playlist_name.clone(),
+            drop_manifest.tracks.iter().map(|track| track.file.clone()).collect(),
+        )
+            .with_drop_type(drop_manifest.drop_type())
+            .with_track_metadata(drop_manifest.tracks.iter().map(TrackMetadata::from).collect())
+            .with_release(Release::new(
+                drop_manifest.release_date(),
+                drop_manifest.label.clone(),
+                drop_manifest.genre.clone(),
+                drop_manifest.description.clone(),
+            ).with_credits(drop_manifest.credits.clone()));
+        let drop_request = match drop_manifest.import_policy {
+            Some(import_policy) => drop_request.with_import_policy(import_policy),
+            None => drop_request,
+        };
+        match &drop_manifest.artwork {
+            Some(artwork) => drop_request.with_artwork(artwork),
+            None => drop_request,
+        }
+    }
+}
+
+pub const DROP_MANIFEST_V1: u32 = 1;
+pub const DROP_MANIFEST_V2: u32 = 2;
+const RELEASE_DATE_FORMAT: &str = "%Y-%m-%d";
+const ISRC_LENGTH: usize = 12;
+
+/// A track of a v2 manifest, only `file` is required
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, new)]
+pub struct TrackManifest {
+    file: String,
+    #[serde(default)]
+    #[new(default)]
+    title: Option<String>,
+    #[serde(default)]
+    #[new(default)]
+    duration_seconds: Option<u32>,
+    #[serde(default)]
+    #[new(default)]
+    isrc: Option<String>,
+    #[serde(default)]
+    #[new(default)]
+    explicit: bool,
+    #[serde(default)]
+    #[new(default)]
+    disc_number: Option<u32>,
+    #[serde(default)]
+    #[new(default)]
+    track_number: Option<u32>,
+    #[serde(default)]
+    #[new(default)]
+    cue_points: Vec<CuePoint>,
+}
+
+impl TrackManifest {
+    /// Fill the fields missing from the manifest with what the audio file tells
+    pub fn fill_missing(&mut self, audio_probe: &AudioProbe) {
+        let tags = audio_probe.tags();
+        if self.title.is_none() {
+            self.title = tags.title().map(str::to_string);
+        }
+        if self.duration_seconds.is_none() {
+            self.duration_seconds = audio_probe.duration()
+                .map(|duration| duration.as_secs_f64().round() as u32)
+                .filter(|duration_seconds| *duration_seconds > 0);
+        }
+        if self.isrc.is_none() {
+            self.isrc = tags.isrc()
+                .map(|isrc| isrc.replace('-', "").to_uppercase())
+                .filter(|isrc| is_valid_isrc(isrc));
+        }
+        if self.disc_number.is_none() {
+            self.disc_number = tags.disc_number().filter(|disc_number| *disc_number > 0);
+        }
+        if self.track_number.is_none() {
+            self.track_number = tags.track_number().filter(|track_number| *track_number > 0);
+        }
+    }
+
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    pub fn duration_seconds(&self) -> Option<u32> {
+        self.duration_seconds
+    }
+
+    /// ISRC without the optional hyphens, e.g. `USRC17607839`
+    pub fn isrc(&self) -> Option<String> {
+        self.isrc.as_ref().map(|isrc| isrc.replace('-', "").to_uppercase())
+    }
+
+    pub fn explicit(&self) -> bool {
+        self.explicit
+    }
+
+    pub fn disc_number(&self) -> Option<u32> {
+        self.disc_number
+    }
+
+    pub fn track_number(&self) -> Option<u32> {
+        self.track_number
+    }
+
+    pub fn cue_points(&self) -> &Vec<CuePoint> {
+        &self.cue_points
+    }
+}
+
+/// Content of the drop manifest. Version 1 is a `DropRequest`, version 2 adds release
+/// fields, credits, an artwork and metadata for each track.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct DropManifest {
+    version: u32,
+    #[serde(default)]
+    artist_id: Option<i32>,
+    #[serde(default)]
+    artist_name: Option<String>,
+    playlist_name: String,
+    #[serde(default, rename = "type")]
+    drop_type: Option<DropType>,
+    #[serde(default)]
+    release_date: Option<String>,
+    #[serde(default)]
+    label: Option<String>,
+    #[serde(default)]
+    genre: Option<String>,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    credits: Vec<Credit>,
+    #[serde(default)]
+    artwork: Option<String>,
+    #[serde(default)]
+    import_policy: Option<ImportPolicy>,
+    tracks: Vec<TrackManifest>,
+}
+
+impl From<DropRequest> for DropManifest {
+    fn from(drop_request: DropRequest) -> Self {
+        Self {
+            version: DROP_MANIFEST_V1,
+            artist_id: drop_request.artist_id,
+            artist_name: drop_request.artist_name,
+            playlist_name: drop_request.playlist_name,
+            drop_type: drop_request.drop_type,
+            release_date: drop_request.release.release_date()
+                .map(|release_date| release_date.format(RELEASE_DATE_FORMAT).to_string()),
+            label: drop_request.release.label().map(str::to_string),
+            genre: drop_request.release.genre().map(str::to_string),
+            description: drop_request.release.description().map(str::to_string),
+            credits: drop_request.release.credits().to_vec(),
+            artwork: drop_request.artwork,
+            import_policy: drop_request.import_policy,
+            tracks: drop_request.tracks.into_iter()
+                .enumerate()
+                .map(|(i, file)| {
+                    let track_metadata = drop_request.track_metadata.get(i).cloned().unwrap_or_default();
+                    TrackManifest {
+                        title: track_metadata.title,
+                        duration_seconds: track_metadata.duration_seconds,
+                        isrc: track_metadata.isrc,
+                        explicit: track_metadata.explicit,
+                        disc_number: track_metadata.disc_number,
+                        track_number: track_metadata.track_number,
+                        cue_points: track_metadata.cue_points,
+                        ..TrackManifest::new(file)
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+impl DropManifest {
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn artist_id(&self) -> Option<i32> {
+        self.artist_id
+    }
+
+    pub fn artist_name(&self) -> Option<&str> {
+        self.artist_name.as_deref()
+    }
+
+    pub fn playlist_name(&self) -> &str {
+        &self.playlist_name
+    }
+
+    /// The type given by the manifest, otherwise the one its number of tracks suggests
+    pub fn drop_type(&self) -> DropType {
+        self.drop_type.unwrap_or_else(|| DropType::for_track_count(self.tracks.len()))
+    }
+
+    /// Only valid once `validate` passed
+    pub fn release_date(&self) -> Option<NaiveDate> {
+        self.release_date.as_ref()
+            .and_then(|release_date| NaiveDate::parse_from_str(release_date, RELEASE_DATE_FORMAT).ok())
+    }
+
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    pub fn genre(&self) -> Option<&str> {
+        self.genre.as_deref()
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn credits(&self) -> &Vec<Credit> {
+        &self.credits
+    }
+
+    pub fn artwork(&self) -> Option<&str> {
+        self.artwork.as_deref()
+    }
+
+    pub fn import_policy(&self) -> Option<ImportPolicy> {
+        self.import_policy
+    }
+
+    pub fn tracks(&self) -> &Vec<TrackManifest> {
+        &self.tracks
+    }
+
+    /// Fill the fields missing from the manifest with the probes of its tracks, given in the same order
+    pub fn fill_missing(&mut self, audio_probes: &[AudioProbe]) {
+        for (track, audio_probe) in self.tracks.iter_mut().zip(audio_probes) {
+            track.fill_missing(audio_probe);
+        }
+        if self.genre.is_none() {
+            self.genre = audio_probes.iter().find_map(|audio_probe| audio_probe.tags().genre()).map(str::to_string);
+        }
+        if self.label.is_none() {
+            self.label = audio_probes.iter().find_map(|audio_probe| audio_probe.tags().label()).map(str::to_string);
+        }
+    }
+
+    /// Check every field and report all the invalid ones
+    pub fn validate(&self) -> Result<(), Vec<ManifestFieldError>> {
+        let mut errors = Vec::new();
+        if self.playlist_name.trim().is_empty() {
+            errors.push(ManifestFieldError::new("playlist_name", "must not be empty"));
+        }
+        if let Some(release_date) = &self.release_date
+            && NaiveDate::parse_from_str(release_date, RELEASE_DATE_FORMAT).is_err() {
+            errors.push(ManifestFieldError::new("release_date", "must be a date formatted as YYYY-MM-DD"));
+        }
+        for (field, value) in [("label", &self.label), ("genre", &self.genre), ("description", &self.description)] {
+            if value.as_ref().is_some_and(|value| value.trim().is_empty()) {
+                errors.push(ManifestFieldError::new(field, "must not be empty when present"));
+            }
+        }
+        for (i, credit) in self.credits.iter().enumerate() {
+            if credit.role().trim().is_empty() {
+                errors.push(ManifestFieldError::new(&format!("credits[{i}].role"), "must not be empty"));
+            }
+            if credit.name().trim().is_empty() {
+                errors.push(ManifestFieldError::new(&format!("credits[{i}].name"), "must not be empty"));
+            }
+        }
+        if self.artwork.as_ref().is_some_and(|artwork| artwork.trim().is_empty()) {
+            errors.push(ManifestFieldError::new("artwork", "must not be empty when present"));
+        }
+        if self.tracks.is_empty() {
+            errors.push(ManifestFieldError::new("tracks", "must list at least one track"));
+        } else if let Some(drop_type) = self.drop_type
+            && !drop_type.accepts_track_count(self.tracks.len()) {
+            let message = match drop_type.max_tracks() {
+                Some(max_tracks) => format!("must list {} to {max_tracks} tracks for type {}", drop_type.min_tracks(), drop_type.name()),
+                None => format!("must list at least {} tracks for type {}", drop_type.min_tracks(), drop_type.name()),
+            };
+            errors.push(ManifestFieldError::new("tracks", &message));
+        }
+        let mut positions = Vec::new();
+        for (i, track) in self.tracks.iter().enumerate() {
+            if track.file.trim().is_empty() {
+                errors.push(ManifestFieldError::new(&format!("tracks[{i}].file"), "must not be empty"));
+            }
+            if track.title.as_ref().is_some_and(|title| title.trim().is_empty()) {
+                errors.push(ManifestFieldError::new(&format!("tracks[{i}].title"), "must not be empty when present"));
+            }
+            if track.duration_seconds == Some(0) {
+                errors.push(ManifestFieldError::new(&format!("tracks[{i}].duration_seconds"), "must be positive"));
+            }
+            if let Some(isrc) = track.isrc()
+                && !is_valid_isrc(&isrc) {
+                errors.push(ManifestFieldError::new(&format!("tracks[{i}].isrc"), "must be 12 characters like CCXXXYYNNNNN"));
+            }
+            if track.disc_number == Some(0) {
+                errors.push(ManifestFieldError::new(&format!("tracks[{i}].disc_number"), "must start at 1"));
+            }
+            if track.track_number == Some(0) {
+                errors.push(ManifestFieldError::new(&format!("tracks[{i}].track_number"), "must start at 1"));
+            }
+            if let Some(track_number) = track.track_number {
+                let position = (track.disc_number.unwrap_or(1), track_number);
+                if positions.contains(&position) {
+                    errors.push(ManifestFieldError::new(&format!("tracks[{i}].track_number"), "is already used on this disc"));
+                }
+                positions.push(position);
+            }
+            if track.cue_points.is_empty() && self.drop_type.is_some_and(|drop_type| drop_type.requires_cue_points()) {
+                errors.push(ManifestFieldError::new(&format!("tracks[{i}].cue_points"), "must list where each tune of the mix starts"));
+            }
+            let mut previous_start = None;
+            for (j, cue_point) in track.cue_points.iter().enumerate() {
+                if cue_point.title().trim().is_empty() {
+                    errors.push(ManifestFieldError::new(&format!("tracks[{i}].cue_points[{j}].title"), "must not be empty"));
+                }
+                if previous_start.is_some_and(|previous_start| cue_point.start_seconds() <= previous_start) {
+                    errors.push(ManifestFieldError::new(&format!("tracks[{i}].cue_points[{j}].start_seconds"), "must be after the previous cue point"));
+                } else if track.duration_seconds.is_some_and(|duration_seconds| cue_point.start_seconds() >= duration_seconds) {
+                    errors.push(ManifestFieldError::new(&format!("tracks[{i}].cue_points[{j}].start_seconds"), "must be within the track duration"));
+                }
+                previous_start = Some(cue_point.start_seconds());
+            }
+        }
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+}
+
+/// Country code, registrant code, year of reference then designation code
+fn is_valid_isrc(isrc: &str) -> bool {
+    let bytes = isrc.as_bytes();
+    bytes.len() == ISRC_LENGTH
+        && bytes[..2].iter().all(u8::is_ascii_uppercase)
+        && bytes[2..5].iter().all(|b| b.is_ascii_uppercase() || b.is_ascii_digit())
+        && bytes[5..].iter().all(u8::is_ascii_digit)
+}
+
+/// A manifest field which failed validation, e.g. `tracks[2].isrc`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ManifestFieldError {
+    field: String,
+    message: String,
+}
+
+impl ManifestFieldError {
+    pub fn new(field: &str, message: &str) -> Self {
+        Self { field: field.to_string(), message: message.to_string() }
+    }
+
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl std::fmt::Display for ManifestFieldError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.field, self.message)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ManifestError {
+    /// The file is missing or is not a manifest
+    Unreadable(String),
+    UnsupportedVersion(u32),
+    InvalidFields(Vec<ManifestFieldError>),
+    /// Manifests found in the same directory
+    SeveralManifests(Vec<String>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ManifestFormat {
+    Toml,
+    Json,
+    Yaml,
+}
+
+/// Accepted manifest file names, drop.txt is the legacy TOML one
+pub const DROP_MANIFEST_FILES: [(&str, ManifestFormat); 4] = [
+    ("drop.toml", ManifestFormat::Toml),
+    ("drop.json", ManifestFormat::Json),
+    ("drop.yaml", ManifestFormat::Yaml),
+    ("drop.txt", ManifestFormat::Toml),
+];
+
+/// The manifest of `dir`, None when there is none
+pub fn find_drop_manifest(dir: &Path) -> Result<Option<(PathBuf, ManifestFormat)>, ManifestError> {
+    let manifests: Vec<(PathBuf, ManifestFormat)> = DROP_MANIFEST_FILES.iter()
+        .map(|(file_name, manifest_format)| (dir.join(file_name), *manifest_format))
+        .filter(|(path, _)| path.is_file())
+        .collect();
+    if manifests.len() > 1 {
+        return Err(ManifestError::SeveralManifests(
+            manifests.iter().map(|(path, _)| path.to_string_lossy().to_string()).collect()
+        ));
+    }
+    Ok(manifests.into_iter().next())
+}
+
+/// Path of a file listed in the drop manifest, it must stay inside the unpacked drop directory
+pub fn resolve_track_path(drop_dir: &Path, track: &str) -> Result<PathBuf, ImportError> {
+    let track_path = Path::new(track);
+    if track_path.components().any(|component| !matches!(component, Component::Normal(_) | Component::CurDir)) {
+        return Err(ImportError::TrackPathOutsideDropDirectory);
+    }
+    let path = drop_dir.join(track_path);
+    // a symbolic link left in the directory could still point outside of it
+    if let (Ok(canonical_dir), Ok(canonical_path)) = (drop_dir.canonicalize(), path.canonicalize())
+        && !canonical_path.starts_with(canonical_dir) {
+        return Err(ImportError::TrackPathOutsideDropDirectory);
+    }
+    Ok(path)
+}
+
+/// Ids of the entities created by a drop import
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, new)]
+pub struct CreatedDrop {
+    drop_id: i32,
+    playlist_id: i32,
+    artist_id: i32,
+}
+
+impl CreatedDrop {
+    pub fn drop_id(&self) -> i32 {
+        self.drop_id
+    }
+
+    pub fn playlist_id(&self) -> i32 {
+        self.playlist_id
+    }
+
+    pub fn artist_id(&self) -> i32 {
+        self.artist_id
+    }
+}
+
+/// Errors of the operations on an existing drop
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum DropError {
+    DropNotFound,
+    InvalidArtistId,
+    /// The drop or its playlist was updated by another write since it was read
+    VersionConflict,
+    CantUpdateDrop,
+    CantSaveTracks,
+    CantWritePlaylistDirectory,
+    CantDeleteDrop,
+    CantCommit,
+    InvalidTrack(ImportError),
+}
+
+impl std::fmt::Display for DropError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DropError::DropNotFound => f.write_str("the drop doesn't exist"),
+            DropError::InvalidArtistId => f.write_str("the artist_id doesn't exist"),
+            DropError::VersionConflict => f.write_str("the drop was updated by another write"),
+            DropError::CantUpdateDrop => f.write_str("the drop can't be saved"),
+            DropError::CantSaveTracks => f.write_str("the tracks can't be saved"),
+            DropError::CantWritePlaylistDirectory => f.write_str("the playlist directory can't be written in the web server"),
+            DropError::CantDeleteDrop => f.write_str("the drop can't be deleted"),
+            DropError::CantCommit => f.write_str("the changes to the drop can't be committed"),
+            DropError::InvalidTrack(import_error) => import_error.fmt(f),
+        }
+    }
+}
+
+fn update_error(e: RepositoryError) -> DropError {
+    match e {
+        RepositoryError::EntityNotFound => DropError::DropNotFound,
+        RepositoryError::VersionConflict => DropError::VersionConflict,
+        _ => DropError::CantUpdateDrop,
+    }
+}
+
+/// Fields of a drop to change, the ones left to None are kept
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct DropUpdate {
+    artist_id: Option<i32>,
+    playlist_name: Option<String>,
+    drop_type: Option<DropType>,
+}
+
+impl DropUpdate {
+    pub fn with_artist_id(mut self, artist_id: i32) -> Self {
+        self.artist_id = Some(artist_id);
+        self
+    }
+
+    pub fn with_playlist_name(mut self, playlist_name: &str) -> Self {
+        self.playlist_name = Some(playlist_name.to_string());
+        self
+    }
+
+    pub fn with_drop_type(mut self, drop_type: DropType) -> Self {
+        self.drop_type = Some(drop_type);
+        self
+    }
+}
+
+/// Tags and tokens which give access to the drops
+#[derive(Clone)]
+pub struct DropAccess {
+    tag_repo: Arc<dyn TagRepo>,
+    token_repo: Arc<dyn TokenRepo>,
+}
+
+impl DropAccess {
+    pub fn new(tag_repo: Arc<dyn TagRepo>, token_repo: Arc<dyn TokenRepo>) -> Self {
+        Self { tag_repo, token_repo }
+    }
+
+    /// Removes the tags bound to the drop and their tokens, gives how many tags there were
+    pub fn revoke(&self, drop_id: i32) -> usize {
+        let tags = self.tag_repo.remove_by_drop(drop_id);
+        for tag in &tags {
+            self.token_repo.remove_by_tag(tag);
+        }
+        tags.len()
+    }
+}
+
+impl std::fmt::Debug for DropAccess {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DropAccess").finish_non_exhaustive()
+    }
+}
+
+/// Key prefix of the tracks of a drop, they are out of the web server's reach while the drop is unpublished
+pub fn playlist_prefix(drop: &Drop) -> String {
+    match drop.published() {
+        true => format!("{PLAYLIST_DIR_PREFIX}{}/", drop.playlist_id()),
+        false => format!("{UNPUBLISHED_PLAYLIST_DIR_PREFIX}{}/", drop.playlist_id()),
+    }
+}
+
+/// Key of the track bytes whose hex SHA-256 is `content_hash`, spread in directories by its first two characters.
+/// A hash which is not 64 lowercase hex characters, e.g. a damaged `track` row, has no key
+pub fn track_content_key(content_hash: &str) -> Result<String, MediaStoreError> {
+    if content_hash.len() != 64 || !content_hash.bytes().all(|byte| matches!(byte, b'0'..=b'9' | b'a'..=b'f')) {
+        return Err(MediaStoreError::InvalidKey);
+    }
+    Ok(format!("{TRACK_CONTENT_PREFIX}{}/{content_hash}", &content_hash[..2]))
+}
+
+/// Key of the bytes of a track, a track imported before the content store is in the playlist directory of the drop
+pub fn track_key(drop: &Drop, track: &Track) -> Result<String, MediaStoreError> {
+    match track.content_hash() {
+        Some(content_hash) => track_content_key(content_hash),
+        None => Ok(format!("{}{TRACK_FILE_PREFIX}{}", playlist_prefix(drop), track.position())),
+    }
+}
+
+/// Tracks moved by `migrate_track_content` from their playlist directory to the content store
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TrackContentMigration {
+    migrated_tracks: usize,
+    deduplicated_tracks: usize,
+    missing_tracks: usize,
+}
+
+impl TrackContentMigration {
+    pub fn migrated_tracks(&self) -> usize {
+        self.migrated_tracks
+    }
+
+    /// Migrated tracks whose bytes were already in the store
+    pub fn deduplicated_tracks(&self) -> usize {
+        self.deduplicated_tracks
+    }
+
+    /// Tracks without hash left as they are, their file is not in the playlist directory
+    pub fn missing_tracks(&self) -> usize {
+        self.missing_tracks
+    }
+}
+
+/// Media deleted by `sweep_orphaned_media` because no row references them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OrphanedMediaSweep {
+    track_contents: usize,
+    artworks: usize,
+    playlists: usize,
+}
+
+impl OrphanedMediaSweep {
+    pub fn track_contents(&self) -> usize {
+        self.track_contents
+    }
+
+    pub fn artworks(&self) -> usize {
+        self.artworks
+    }
+
+    /// Playlist directories of tracks imported before the content store
+    pub fn playlists(&self) -> usize {
+        self.playlists
+    }
 }
 
 #[derive(Debug, Deserialize,)]
-pub struct DropService<T, U, V>
+pub struct DropService<T, U, V, W, X>
 where
     T: Repo<Drop> + Send + Sync,
     U: RepoByName<Artist> + Send + Sync,
     V: Repo<Playlist> + Send + Sync,
+    W: Repo<Artwork> + Send + Sync,
+    X: TrackRepoT + Send + Sync,
 {
     drop_repository: T,
     artist_repository: U,
     playlist_repository: V,
+    artwork_repository: W,
+    track_repository: X,
+    import_policy: ImportPolicy,
+    #[serde(skip)]
+    drop_access: Option<DropAccess>,
+    #[serde(skip)]
+    media_store: Option<Arc<dyn MediaStore>>,
 }
 
-impl<T, U, V> Clone for DropService<T, U, V>
+impl<T, U, V, W, X> Clone for DropService<T, U, V, W, X>
 where
     T: Repo<Drop> + Send + Sync + Clone,
     U: RepoByName<Artist> + Send + Sync + Clone,
-    V: Repo<Playlist> + Send + Sync + Clone, {
+    V: Repo<Playlist> + Send + Sync + Clone,
+    W: Repo<Artwork> + Send + Sync + Clone,
+    X: TrackRepoT + Send + Sync + Clone, {
     fn clone(&self) -> Self {
-        DropService::new(
-            self.drop_repository.clone(),
-            self.artist_repository.clone(),
-            self.playlist_repository.clone()
-        )
+        DropService {
+            drop_access: self.drop_access.clone(),
+            media_store: self.media_store.clone(),
+            ..DropService::new(
+                self.drop_repository.clone(),
+                self.artist_repository.clone(),
+                self.playlist_repository.clone(),
+                self.artwork_repository.clone(),
+                self.track_repository.clone()
+            ).with_import_policy(self.import_policy)
+        }
     }
 }
 
-impl<T, U, V> DropService<T, U, V>
+impl<T, U, V, W, X> DropService<T, U, V, W, X>
 where
     T: Repo<Drop> + Send + Sync,
     U: RepoByName<Artist> + Send + Sync,
     V: Repo<Playlist> + Send + Sync,
+    W: Repo<Artwork> + Send + Sync,
+    X: TrackRepoT + Send + Sync,
 {
     pub fn new(
         drop_repository: T,
         artist_repository: U,
         playlist_repository: V,
-    ) -> DropService<T, U, V>
+        artwork_repository: W,
+        track_repository: X,
+    ) -> DropService<T, U, V, W, X>
     where
         T: Sized,
         U: Sized,
         V: Sized,
+        W: Sized,
+        X: Sized,
     {
         /*if drop_repository.drop() {
             println!("drop repository not set, can't create drop");
@@ -121,9 +1032,20 @@ where
             drop_repository,
             artist_repository,
             playlist_repository,
+            artwork_repository,
+            track_repository,
+            import_policy: ImportPolicy::default(),
+            drop_access: None,
+            media_store: None,
         }
     }
 
+    /// Policy of the imports whose manifest gives none
+    pub fn with_import_policy(mut self, import_policy: ImportPolicy) -> Self {
+        self.import_policy = import_policy;
+        self
+    }
+
     pub fn drop_repository(&self) -> &T {
         &self.drop_repository
     }
@@ -135,72 +1057,663 @@ where
     pub fn playlist_repository(&self) -> &V {
         &self.playlist_repository
     }
-}
 
-#[async_trait]
-impl<T, U, V> DropServiceT for DropService<T, U, V>
-where
-    T: Repo<Drop> + Send + Sync,
-    U: RepoByName<Artist> + Send + Sync,
-    V: Repo<Playlist> + Send + Sync,
-{
-    async fn create_drop(
+    pub fn artwork_repository(&self) -> &W {
+        &self.artwork_repository
+    }
+
+    pub fn track_repository(&self) -> &X {
+        &self.track_repository
+    }
+
+    pub fn import_policy(&self) -> ImportPolicy {
+        self.import_policy
+    }
+
+    /// Tags and tokens revoked when a drop is unpublished or deleted
+    pub fn with_drop_access(mut self, drop_access: DropAccess) -> Self {
+        self.drop_access = Some(drop_access);
+        self
+    }
+
+    /// Store the tracks and artworks are written to instead of the web server path
+    pub fn with_media_store(mut self, media_store: Arc<dyn MediaStore>) -> Self {
+        self.media_store = Some(media_store);
+        self
+    }
+
+    /// The store set with `with_media_store`, else the files under `web_server_path`
+    pub fn media_store(&self, web_server_path: &str) -> Arc<dyn MediaStore> {
+        match &self.media_store {
+            Some(media_store) => media_store.clone(),
+            None => Arc::new(LocalMediaStore::new(PathBuf::from(web_server_path))),
+        }
+    }
+
+    /// Puts the bytes of the drop's tracks in the content store as the tracks are recorded in the unit of work,
+    /// gives the hashes of the contents it stored. Bytes already in the store are only referenced again,
+    /// the ones it stored are deleted when one of the tracks fails
+    async fn put_tracks(
+        &self,
+        media_store: &dyn MediaStore,
+        drop: &Drop,
+        drop_import_path: &str,
+        tracks: &[String],
+        track_metadata: &[TrackMetadata],
+        unit_of_work: &mut UnitOfWork,
+    ) -> Result<Vec<String>, ImportError> {
+        let mut stored_hashes = Vec::new();
+        let result = async {
+            for (i, track) in tracks.iter().enumerate() {
+                let track_import_path = resolve_track_path(Path::new(drop_import_path), track)?;
+                // the file is read once to be hashed, then again to be stored, never whole in memory
+                let content = MediaContent::from_file(&track_import_path)
+                    .await
+                    .or(Err(ImportError::CantCopyTrackFileToPlaylistDirectory))?;
+                let (content_hash, size) = hash_content(content)
+                    .await
+                    .or(Err(ImportError::CantCopyTrackFileToPlaylistDirectory))?;
+                // the content row stays locked until the commit, a collection can't delete the bytes meanwhile
+                let ref_count = self.track_repository
+                    .retain_content_in(&content_hash, size as i64, unit_of_work)
+                    .await
+                    .map_err(|e| ImportError::from_repository_error(e, ImportError::CantCreateTrack))?;
+                if ref_count == 1 {
+                    stored_hashes.push(content_hash.clone());
+                    let content_key = track_content_key(&content_hash).or(Err(ImportError::CantCopyTrackFileToPlaylistDirectory))?;
+                    let content = MediaContent::from_file(&track_import_path)
+                        .await
+                        .or(Err(ImportError::CantCopyTrackFileToPlaylistDirectory))?;
+                    media_store.put(&content_key, content)
+                        .await
+                        .or(Err(ImportError::CantCopyTrackFileToPlaylistDirectory))?;
+                }
+                let track_metadata = track_metadata.get(i).cloned().unwrap_or_default();
+                self.track_repository
+                    .save_or_update_in(
+                        &Track::new(
+                            0,
+                            drop.playlist_id(),
+                            (i + 1) as i32,
+                            track.clone(),
+                            track_metadata.title,
+                            track_metadata.duration_seconds.map(|duration_seconds| duration_seconds as i32)
+                        )
+                            .with_isrc(track_metadata.isrc)
+                            .with_explicit(track_metadata.explicit)
+                            .with_numbering(
+                                track_metadata.disc_number.map(|disc_number| disc_number as i32),
+                                track_metadata.track_number.map(|track_number| track_number as i32)
+                            )
+                            .with_cue_points(track_metadata.cue_points)
+                            .with_content_hash(&content_hash),
+                        unit_of_work
+                    )
+                    .await
+                    .map_err(|e| ImportError::from_repository_error(e, ImportError::CantCreateTrack))?;
+            }
+            Ok(())
+        }.await;
+        match result {
+            Ok(()) => Ok(stored_hashes),
+            Err(e) => {
+                delete_track_contents(media_store, &stored_hashes).await;
+                Err(e)
+            }
+        }
+    }
+
+    /// Releases the contents of the tracks in the unit of work, gives the hashes left without reference
+    async fn release_track_contents(&self, tracks: &[Track], unit_of_work: &mut UnitOfWork) -> Result<Vec<String>, RepositoryError> {
+        let mut unreferenced_hashes = Vec::new();
+        for content_hash in tracks.iter().filter_map(Track::content_hash) {
+            if self.track_repository.release_content_in(content_hash, unit_of_work).await? == 0 {
+                unreferenced_hashes.push(content_hash.to_string());
+            }
+        }
+        Ok(unreferenced_hashes)
+    }
+
+    /// Deletes the bytes of the contents still without reference, each in its own unit of work
+    /// which keeps the content row locked until its bytes are gone. Failures are only reported
+    async fn collect_track_contents(&self, media_store: &dyn MediaStore, content_hashes: &[String]) {
+        for content_hash in content_hashes {
+            let mut unit_of_work = UnitOfWork::new();
+            match self.track_repository.delete_unreferenced_content_in(content_hash, &mut unit_of_work).await {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(e) => {
+                    println!("can't collect the track content {content_hash}: {:?}", e);
+                    continue;
+                }
+            }
+            // the content is kept without reference when its bytes can't be deleted
+            let deleted = match track_content_key(content_hash) {
+                Ok(content_key) => media_store.delete(&content_key).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = deleted {
+                println!("can't delete the track content {content_hash}: {e}");
+                continue;
+            }
+            if let Err(e) = unit_of_work.commit().await {
+                println!("can't commit the collection of the track content {content_hash}: {:?}", e);
+            }
+        }
+    }
+
+    /// Moves the tracks imported before the content store from the playlist directories to the store,
+    /// a drop at a time. Their files are deleted once the drop's tracks are committed with their hash
+    pub async fn migrate_track_content(&self, web_server_path: &str) -> Result<TrackContentMigration, DropError> {
+        let media_store = self.media_store(web_server_path);
+        let mut migration = TrackContentMigration::default();
+        let mut cursor = None;
+        loop {
+            let page = self.drop_repository
+                .list(&Filter::new().after(cursor))
+                .await
+                .or(Err(DropError::CantSaveTracks))?;
+            for drop in &page.items {
+                let drop_migration = self.migrate_drop_track_content(media_store.as_ref(), drop).await?;
+                migration.migrated_tracks += drop_migration.migrated_tracks;
+                migration.deduplicated_tracks += drop_migration.deduplicated_tracks;
+                migration.missing_tracks += drop_migration.missing_tracks;
+            }
+            cursor = page.next_cursor;
+            if cursor.is_none() {
+                return Ok(migration);
+            }
+        }
+    }
+
+    async fn migrate_drop_track_content(&self, media_store: &dyn MediaStore, drop: &Drop) -> Result<TrackContentMigration, DropError> {
+        let tracks = self.track_repository.get_by_playlist(drop.playlist_id()).await.or(Err(DropError::CantSaveTracks))?;
+        let mut migration = TrackContentMigration::default();
+        let mut migrated_keys = Vec::new();
+        let mut stored_hashes = Vec::new();
+        let mut unit_of_work = UnitOfWork::new();
+        let result = async {
+            for track in tracks.iter().filter(|track| track.content_hash().is_none()) {
+                let key = track_key(drop, track).or(Err(DropError::CantWritePlaylistDirectory))?;
+                let content = match media_store.get(&key).await {
+                    Ok(content) => content,
+                    Err(MediaStoreError::NotFound) => {
+                        migration.missing_tracks += 1;
+                        continue;
+                    }
+                    Err(_) => return Err(DropError::CantWritePlaylistDirectory),
+                };
+                let (content_hash, size) = hash_content(content)
+                    .await
+                    .or(Err(DropError::CantWritePlaylistDirectory))?;
+                let ref_count = self.track_repository
+                    .retain_content_in(&content_hash, size as i64, &mut unit_of_work)
+                    .await
+                    .or(Err(DropError::CantSaveTracks))?;
+                if ref_count == 1 {
+                    stored_hashes.push(content_hash.clone());
+                    let content_key = track_content_key(&content_hash).or(Err(DropError::CantWritePlaylistDirectory))?;
+                    // the media is read again to be stored rather than kept in memory
+                    let content = media_store.get(&key).await.or(Err(DropError::CantWritePlaylistDirectory))?;
+                    media_store.put(&content_key, content)
+                        .await
+                        .or(Err(DropError::CantWritePlaylistDirectory))?;
+                } else {
+                    migration.deduplicated_tracks += 1;
+                }
+                self.track_repository
+                    .save_or_update_in(&track.clone().with_content_hash(&content_hash), &mut unit_of_work)
+                    .await
+                    .map_err(|e| match e {
+                        RepositoryError::VersionConflict => DropError::VersionConflict,
+                        _ => DropError::CantSaveTracks,
+                    })?;
+                migrated_keys.push(key);
+                migration.migrated_tracks += 1;
+            }
+            Ok(())
+        }.await;
+        if let Err(e) = result {
+            delete_track_contents(media_store, &stored_hashes).await;
+            return Err(e);
+        }
+        if unit_of_work.commit().await.is_err() {
+            delete_track_contents(media_store, &stored_hashes).await;
+            return Err(DropError::CantCommit);
+        }
+        for key in &migrated_keys {
+            if let Err(e) = media_store.delete(key).await {
+                println!("can't delete the migrated track {key}: {e}");
+            }
+        }
+        Ok(migration)
+    }
+
+    /// Deletes the media left by the writes whose rollback or cleanup failed: the track contents,
+    /// artworks and playlist directories without their row. The media of a write in progress have no row
+    /// until it is committed, the sweep must run while nothing is imported or updated
+    pub async fn sweep_orphaned_media(&self, web_server_path: &str) -> Result<OrphanedMediaSweep, DropError> {
+        let media_store = self.media_store(web_server_path);
+        let mut sweep = OrphanedMediaSweep::default();
+        let content_keys = media_store.list(TRACK_CONTENT_PREFIX).await.or(Err(DropError::CantWritePlaylistDirectory))?;
+        for content_key in content_keys {
+            // a key which is not the one of its hash is not a content and is left alone
+            let content_hash = content_key.rsplit('/').next().unwrap_or_default();
+            if track_content_key(content_hash).ok().as_deref() != Some(content_key.as_str()) {
+                continue;
+            }
+            if !self.track_repository.content_exists(content_hash).await.or(Err(DropError::CantSaveTracks))? {
+                media_store.delete(&content_key).await.or(Err(DropError::CantWritePlaylistDirectory))?;
+                sweep.track_contents += 1;
+            }
+        }
+        for artwork_id in prefix_ids(media_store.as_ref(), ARTWORK_DIR_PREFIX).await? {
+            if !self.artwork_repository.exists(artwork_id).await.or(Err(DropError::CantSaveTracks))? {
+                delete_prefix(media_store.as_ref(), &artwork_prefix(artwork_id))
+                    .await
+                    .or(Err(DropError::CantWritePlaylistDirectory))?;
+                sweep.artworks += 1;
+            }
+        }
+        for dir_prefix in [PLAYLIST_DIR_PREFIX, UNPUBLISHED_PLAYLIST_DIR_PREFIX] {
+            for playlist_id in prefix_ids(media_store.as_ref(), dir_prefix).await? {
+                if !self.playlist_repository.exists(playlist_id).await.or(Err(DropError::CantSaveTracks))? {
+                    delete_prefix(media_store.as_ref(), &format!("{dir_prefix}{playlist_id}/"))
+                        .await
+                        .or(Err(DropError::CantWritePlaylistDirectory))?;
+                    sweep.playlists += 1;
+                }
+            }
+        }
+        Ok(sweep)
+    }
+
+    /// Creates the drop, the import it comes from, if any, is marked imported by the same commit
+    async fn create_drop_recording(
         &self,
-        drop_import_path: &String,
+        drop_import_path: &str,
         drop_request: DropRequest,
-        web_server_path: &String
-    ) -> Result<(), ImportError> {
+        web_server_path: &str,
+        import: Option<(&dyn ImportRepoT, &mut Import)>,
+    ) -> Result<CreatedDrop, ImportError> {
 
         // artist_id XOR artist_name
         if drop_request.artist_id.is_some() && drop_request.artist_name.is_some() {
             return Err(ImportError::ArtistIdAndArtistNameAreBothPresent)
         }
+        // database writes are committed together once the files are in place,
+        // returning early drops the unit of work which rolls them back
+        let mut unit_of_work = UnitOfWork::new();
+
         let mut drop_artist_id = 0;
         // artist_id exists
         if let Some(artist_id) = drop_request.artist_id {
             // check artist_id exists
             drop_artist_id = self.artist_repository.get(artist_id)
                 .await
-                .or(Err(ImportError::InvalidArtistId))?.id();
+                .map_err(|e| ImportError::from_repository_error(e, ImportError::InvalidArtistId))?.id();
         } else if let Some(artist_name) = drop_request.artist_name {
-            // check artist_name exists
-            drop_artist_id = self.artist_repository.get_by_name(&artist_name)
-                .await
-                .or(Err(ImportError::CantCreateArtistFromArtistName))?.id();
+            // artist names match whatever their case, spacing or unicode form
+            drop_artist_id = match self.artist_repository.get_by_name(&artist_name).await {
+                Ok(artist) => artist.id(),
+                Err(RepositoryError::EntityNotFound) => match drop_request.import_policy.unwrap_or(self.import_policy) {
+                    ImportPolicy::Strict => return Err(ImportError::CantCreateArtistFromArtistName),
+                    ImportPolicy::CreateMissing if normalize_artist_name(&artist_name).is_empty() => {
+                        return Err(ImportError::CantCreateArtistFromArtistName)
+                    }
+                    ImportPolicy::CreateMissing => self.artist_repository
+                        .save_or_update_in(&Artist::new(0, artist_name.trim().to_string()), &mut unit_of_work)
+                        .await
+                        .map_err(|e| ImportError::from_repository_error(e, ImportError::CantCreateMissingArtist))?,
+                },
+                Err(e) => return Err(ImportError::from_repository_error(e, ImportError::CantCreateArtistFromArtistName)),
+            };
         }
 
         // create playlist
         let playlist_id = self.playlist_repository
-            .save_or_update(&Playlist::new(0, drop_request.playlist_name))
+            .save_or_update_in(&Playlist::new(0, drop_request.playlist_name), &mut unit_of_work)
             .await
-            .or(Err(ImportError::CantCreatePlaylistFromPlaylistName))?;
+            .map_err(|e| ImportError::from_repository_error(e, ImportError::CantCreatePlaylistFromPlaylistName))?;
+
+        // create artwork, the original and its variants are written to a staging directory
+        let media_store = self.media_store(web_server_path);
+        let mut artwork = None;
+        if let Some(artwork_file) = &drop_request.artwork {
+            let artwork_import_path = resolve_track_path(Path::new(drop_import_path), artwork_file)?;
+            let artwork_staging_dir = tempfile::Builder::new()
+                .prefix(ARTWORK_STAGING_DIR_PREFIX)
+                .tempdir()
+                .or(Err(ImportError::CantWriteArtworkVariants))?;
+            let artwork_staging_path = artwork_staging_dir.path().to_path_buf();
+            // decoding and resizing the image would hold up the runtime
+            let artwork_image = tokio::task::spawn_blocking(move || {
+                let artwork_image = probe_artwork(&artwork_import_path)?;
+                write_artwork_variants(&artwork_import_path, &artwork_image, &artwork_staging_path)?;
+                Ok::<_, ImportError>(artwork_image)
+            })
+                .await
+                .or(Err(ImportError::CantWriteArtworkVariants))??;
+            let artwork_id = self.artwork_repository
+                .save_or_update_in(
+                    &Artwork::new(
+                        0,
+                        artwork_image.format().mime_type().to_string(),
+                        artwork_image.width() as i32,
+                        artwork_image.height() as i32
+                    ),
+                    &mut unit_of_work
+                )
+                .await
+                .map_err(|e| ImportError::from_repository_error(e, ImportError::CantCreateArtwork))?;
+            let artwork_prefix = artwork_prefix(artwork_id);
+            if !is_free(media_store.as_ref(), &artwork_prefix).await {
+                return Err(ImportError::CantWriteArtworkVariants);
+            }
+            artwork = Some((artwork_id, artwork_staging_dir, artwork_prefix));
+        }
+        let artwork_id = artwork.as_ref().map_or(NO_ARTWORK_ID, |(artwork_id, _, _)| *artwork_id);
 
         // create drop
-        self.drop_repository
-            .save_or_update(&Drop::new(0, drop_artist_id, 0, playlist_id))
+        let drop_type = drop_request.drop_type
+            .unwrap_or_else(|| DropType::for_track_count(drop_request.tracks.len()));
+        let drop = Drop::new(0, drop_artist_id, drop_type.id(), artwork_id, playlist_id)
+            .with_release(drop_request.release.clone());
+        let drop_id = self.drop_repository
+            .save_or_update_in(&drop, &mut unit_of_work)
             .await
-            .or(Err(ImportError::CantCreateDropFromDropRequest))?;
-
-        // create playlist directory in web server
-        let mut playlist_dir_path = web_server_path.clone();
-        playlist_dir_path.push_str("/");
-        playlist_dir_path.push_str(PLAYLIST_DIR_PREFIX);
-        playlist_dir_path.push_str(&playlist_id.to_string());
-        fs::create_dir(&playlist_dir_path).or(Err(ImportError::CantCreatePlaylistFromPlaylistName))?;
-        // move the files
-        let mut i = 1;
-        for track in drop_request.tracks.iter() {
-            let mut track_import_path = drop_import_path.clone();
-            track_import_path.push_str("/");
-            track_import_path.push_str(track);
-            let mut playlist_track_path = playlist_dir_path.clone();
-            playlist_track_path.push_str("/");
-            playlist_track_path.push_str(TRACK_FILE_PREFIX);
-            playlist_track_path.push_str(&i.to_string());
-            fs::copy(track_import_path, playlist_track_path)
-                .or(Err(ImportError::CantCopyTrackFileToPlaylistDirectory))?;
-            i += 1;
+            .map_err(|e| ImportError::from_repository_error(e, ImportError::CantCreateDropFromDropRequest))?;
+
+        // put the tracks in the content store, the bytes it stored are deleted if one of them fails
+        let stored_hashes = self.put_tracks(
+            media_store.as_ref(),
+            &drop,
+            drop_import_path,
+            &drop_request.tracks,
+            &drop_request.track_metadata,
+            &mut unit_of_work
+        ).await?;
+
+        let mut published_prefixes = Vec::new();
+        if let Some((_, artwork_staging_dir, artwork_prefix)) = artwork {
+            published_prefixes.push(artwork_prefix.clone());
+            if put_dir(media_store.as_ref(), artwork_staging_dir.path(), &artwork_prefix).await.is_err() {
+                delete_track_contents(media_store.as_ref(), &stored_hashes).await;
+                delete_published_media(media_store.as_ref(), &published_prefixes).await;
+                return Err(ImportError::CantWriteArtworkVariants);
+            }
+        }
+        // the archive is recorded as imported only if the drop is created
+        if let Some((import_repo, import)) = import {
+            import.set_imported(drop_id, Utc::now().naive_utc());
+            if let Err(e) = import_repo.save_or_update_in(import, &mut unit_of_work).await {
+                delete_track_contents(media_store.as_ref(), &stored_hashes).await;
+                delete_published_media(media_store.as_ref(), &published_prefixes).await;
+                return Err(ImportError::from_repository_error(e, ImportError::CantRecordImport));
+            }
         }
+        if let Err(e) = unit_of_work.commit().await {
+            delete_track_contents(media_store.as_ref(), &stored_hashes).await;
+            delete_published_media(media_store.as_ref(), &published_prefixes).await;
+            return Err(ImportError::from_repository_error(e, ImportError::CantCommitDropCreation));
+        }
+        Ok(CreatedDrop::new(drop_id, playlist_id, drop_artist_id))
+    }
+
+    fn revoke_access(&self, drop_id: i32) {
+        if let Some(drop_access) = &self.drop_access {
+            let nb_tags = drop_access.revoke(drop_id);
+            println!("{nb_tags} tags of drop {drop_id} revoked");
+        }
+    }
+
+    async fn get_drop(&self, drop_id: i32) -> Result<Drop, DropError> {
+        self.drop_repository.get(drop_id).await.map_err(|e| match e {
+            RepositoryError::EntityNotFound => DropError::DropNotFound,
+            _ => DropError::CantUpdateDrop,
+        })
+    }
+}
+
+#[async_trait]
+impl<T, U, V, W, X> DropServiceT for DropService<T, U, V, W, X>
+where
+    T: Repo<Drop> + Send + Sync,
+    U: RepoByName<Artist> + Send + Sync,
+    V: Repo<Playlist> + Send + Sync,
+    W: Repo<Artwork> + Send + Sync,
+    X: TrackRepoT + Send + Sync,
+{
+    async fn create_drop(
+        &self,
+        drop_import_path: &str,
+        drop_request: DropRequest,
+        web_server_path: &str
+    ) -> Result<CreatedDrop, ImportError> {
+        self.create_drop_recording(drop_import_path, drop_request, web_server_path, None).await
+    }
+
+    async fn create_imported_drop(
+        &self,
+        drop_import_path: &str,
+        drop_request: DropRequest,
+        web_server_path: &str,
+        import_repo: &dyn ImportRepoT,
+        import: &mut Import
+    ) -> Result<CreatedDrop, ImportError> {
+        self.create_drop_recording(drop_import_path, drop_request, web_server_path, Some((import_repo, import))).await
+    }
+
+    async fn update_drop(&self, drop_id: i32, drop_update: DropUpdate) -> Result<(), DropError> {
+        let drop = self.get_drop(drop_id).await?;
+        if let Some(artist_id) = drop_update.artist_id {
+            self.artist_repository.get(artist_id).await.or(Err(DropError::InvalidArtistId))?;
+        }
+        let mut unit_of_work = UnitOfWork::new();
+        if let Some(playlist_name) = drop_update.playlist_name {
+            let playlist = self.playlist_repository.get(drop.playlist_id()).await.map_err(update_error)?;
+            self.playlist_repository
+                .save_or_update_in(&Playlist::new(playlist.id(), playlist_name).with_version(playlist.version()), &mut unit_of_work)
+                .await
+                .map_err(update_error)?;
+        }
+        if drop_update.artist_id.is_some() || drop_update.drop_type.is_some() {
+            let updated_drop = Drop::new(
+                drop.id(),
+                drop_update.artist_id.unwrap_or(drop.artist_id()),
+                drop_update.drop_type.map_or(drop.type_id(), |drop_type| drop_type.id()),
+                drop.artwork_id(),
+                drop.playlist_id()
+            )
+                .with_published(drop.published())
+                .with_release(drop.release().clone())
+                .with_version(drop.version());
+            self.drop_repository.save_or_update_in(&updated_drop, &mut unit_of_work).await.map_err(update_error)?;
+        }
+        unit_of_work.commit().await.or(Err(DropError::CantCommit))
+    }
+
+    async fn replace_tracks(
+        &self,
+        drop_id: i32,
+        drop_import_path: &str,
+        tracks: Vec<String>,
+        track_metadata: Vec<TrackMetadata>,
+        web_server_path: &str
+    ) -> Result<(), DropError> {
+        let drop = self.get_drop(drop_id).await?;
+        let previous_tracks = self.track_repository.get_by_playlist(drop.playlist_id()).await.or(Err(DropError::CantSaveTracks))?;
+        let mut unit_of_work = UnitOfWork::new();
+        self.track_repository.delete_by_playlist_in(drop.playlist_id(), &mut unit_of_work)
+            .await
+            .or(Err(DropError::CantSaveTracks))?;
+
+        // the previous tracks of the playlist directory are set aside until the new ones are committed
+        let media_store = self.media_store(web_server_path);
+        let playlist_prefix = playlist_prefix(&drop);
+        let replaced_prefix = format!("{REPLACED_PLAYLIST_DIR_PREFIX}{}_{}/", drop.playlist_id(), Uuid::new_v4().simple());
+        let restore_previous_tracks = async || {
+            if move_prefix(media_store.as_ref(), &replaced_prefix, &playlist_prefix).await.is_err() {
+                println!("can't restore the tracks of drop {drop_id} from {replaced_prefix}");
+            }
+        };
+        if move_prefix(media_store.as_ref(), &playlist_prefix, &replaced_prefix).await.is_err() {
+            restore_previous_tracks().await;
+            return Err(DropError::CantWritePlaylistDirectory);
+        }
+        let put_tracks = self.put_tracks(
+            media_store.as_ref(),
+            &drop,
+            drop_import_path,
+            &tracks,
+            &track_metadata,
+            &mut unit_of_work
+        ).await;
+        let stored_hashes = match put_tracks {
+            Ok(stored_hashes) => stored_hashes,
+            Err(e) => {
+                restore_previous_tracks().await;
+                return Err(match e {
+                    ImportError::CantCopyTrackFileToPlaylistDirectory => DropError::CantWritePlaylistDirectory,
+                    ImportError::CantCreateTrack => DropError::CantSaveTracks,
+                    e => DropError::InvalidTrack(e),
+                });
+            }
+        };
+        // released after the new tracks are retained, the contents they share are not deleted
+        let unreferenced_hashes = match self.release_track_contents(&previous_tracks, &mut unit_of_work).await {
+            Ok(unreferenced_hashes) => unreferenced_hashes,
+            Err(_) => {
+                delete_track_contents(media_store.as_ref(), &stored_hashes).await;
+                restore_previous_tracks().await;
+                return Err(DropError::CantSaveTracks);
+            }
+        };
+        if unit_of_work.commit().await.is_err() {
+            delete_track_contents(media_store.as_ref(), &stored_hashes).await;
+            restore_previous_tracks().await;
+            return Err(DropError::CantCommit);
+        }
+        self.collect_track_contents(media_store.as_ref(), &unreferenced_hashes).await;
+        delete_published_media(media_store.as_ref(), std::slice::from_ref(&replaced_prefix)).await;
+        Ok(())
+    }
+
+    async fn unpublish_drop(&self, drop_id: i32, web_server_path: &str) -> Result<(), DropError> {
+        let drop = self.get_drop(drop_id).await?;
+        if drop.published() {
+            let media_store = self.media_store(web_server_path);
+            let unpublished_drop = drop.clone().with_published(false);
+            let (published_prefix, unpublished_prefix) = (playlist_prefix(&drop), playlist_prefix(&unpublished_drop));
+            let publish_again = async || {
+                if move_prefix(media_store.as_ref(), &unpublished_prefix, &published_prefix).await.is_err() {
+                    println!("can't publish again the tracks of drop {drop_id} from {unpublished_prefix}");
+                }
+            };
+            if move_prefix(media_store.as_ref(), &published_prefix, &unpublished_prefix).await.is_err() {
+                publish_again().await;
+                return Err(DropError::CantWritePlaylistDirectory);
+            }
+            if let Err(e) = self.drop_repository.save_or_update(&unpublished_drop).await {
+                publish_again().await;
+                return Err(update_error(e));
+            }
+        }
+        self.revoke_access(drop_id);
+        Ok(())
+    }
+
+    async fn delete_drop(&self, drop_id: i32, web_server_path: &str) -> Result<(), DropError> {
+        let drop = self.get_drop(drop_id).await?;
+        let tracks = self.track_repository.get_by_playlist(drop.playlist_id()).await.or(Err(DropError::CantDeleteDrop))?;
+        let mut unit_of_work = UnitOfWork::new();
+        self.track_repository.delete_by_playlist_in(drop.playlist_id(), &mut unit_of_work)
+            .await
+            .or(Err(DropError::CantDeleteDrop))?;
+        let unreferenced_hashes = self.release_track_contents(&tracks, &mut unit_of_work)
+            .await
+            .or(Err(DropError::CantDeleteDrop))?;
+        self.drop_repository.delete_in(drop.id(), &mut unit_of_work).await.or(Err(DropError::CantDeleteDrop))?;
+        self.playlist_repository.delete_in(drop.playlist_id(), &mut unit_of_work).await.or(Err(DropError::CantDeleteDrop))?;
+        if drop.artwork_id() != NO_ARTWORK_ID {
+            self.artwork_repository.delete_in(drop.artwork_id(), &mut unit_of_work).await.or(Err(DropError::CantDeleteDrop))?;
+        }
+        unit_of_work.commit().await.or(Err(DropError::CantCommit))?;
+        self.revoke_access(drop_id);
+
+        // the rows are gone, media left behind are only reported
+        let media_store = self.media_store(web_server_path);
+        self.collect_track_contents(media_store.as_ref(), &unreferenced_hashes).await;
+        let mut drop_prefixes = vec![playlist_prefix(&drop)];
+        if drop.artwork_id() != NO_ARTWORK_ID {
+            drop_prefixes.push(artwork_prefix(drop.artwork_id()));
+        }
+        delete_published_media(media_store.as_ref(), &drop_prefixes).await;
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// Whether no media has a key starting with `prefix`, a store which can't tell is not free
+async fn is_free(media_store: &dyn MediaStore, prefix: &str) -> bool {
+    media_store.list(prefix).await.is_ok_and(|keys| keys.is_empty())
+}
+
+/// Puts each file of `dir` under `prefix`
+async fn put_dir(media_store: &dyn MediaStore, dir: &Path, prefix: &str) -> Result<(), ImportError> {
+    let entries = fs::read_dir(dir).or(Err(ImportError::CantWriteArtworkVariants))?;
+    for entry in entries {
+        let path = entry.or(Err(ImportError::CantWriteArtworkVariants))?.path();
+        let file_name = path.file_name().and_then(|file_name| file_name.to_str()).ok_or(ImportError::CantWriteArtworkVariants)?;
+        let content = MediaContent::from_file(&path).await.or(Err(ImportError::CantWriteArtworkVariants))?;
+        media_store.put(&format!("{prefix}{file_name}"), content).await.or(Err(ImportError::CantWriteArtworkVariants))?;
+    }
+    Ok(())
+}
+
+/// Ids of the directories `{dir_prefix}{id}/` holding media
+async fn prefix_ids(media_store: &dyn MediaStore, dir_prefix: &str) -> Result<Vec<i32>, DropError> {
+    let keys = media_store.list(dir_prefix).await.or(Err(DropError::CantWritePlaylistDirectory))?;
+    let mut ids: Vec<i32> = keys
+        .iter()
+        .filter_map(|key| key[dir_prefix.len()..].split_once('/'))
+        .filter_map(|(id, _)| id.parse().ok())
+        .collect();
+    ids.dedup();
+    Ok(ids)
+}
+
+/// Hex SHA-256 and size of a media, read a chunk at a time
+async fn hash_content(content: MediaContent) -> Result<(String, u64), MediaStoreError> {
+    let mut hasher = Sha256::new();
+    let mut size = 0;
+    let mut stream = content.stream;
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        size += chunk.len() as u64;
+        hasher.update(&chunk);
+    }
+    Ok((hex::encode(hasher.finalize()), size))
+}
+
+/// Deletes the bytes of contents stored by a write which is rolled back, a failure is only reported.
+/// It runs before the unit of work which retained them is dropped, while their rows are still locked
+async fn delete_track_contents(media_store: &dyn MediaStore, content_hashes: &[String]) {
+    for content_hash in content_hashes {
+        let deleted = match track_content_key(content_hash) {
+            Ok(content_key) => media_store.delete(&content_key).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = deleted {
+            println!("can't delete the track content {content_hash}, it is left to the next startup sweep: {e}");
+        }
+    }
+}
+
+/// Removes the media of a drop which is rolled back or deleted
+async fn delete_published_media(media_store: &dyn MediaStore, published_prefixes: &[String]) {
+    for published_prefix in published_prefixes {
+        if let Err(e) = delete_prefix(media_store, published_prefix).await {
+            println!("can't remove the media {published_prefix} of the drop: {e}");
+        }
+    }
+}
@@ -0,0 +1,345 @@
+use crate::check_drop_file;
+use crate::repository::import::{sha256_of_file, Import, ImportRepoT, ImportState};
+use crate::repository::import_job::{FileImportReport, FileImportState, ImportJob, ImportJobRepoT, ImportJobState};
+use crate::repository::RepositoryError;
+use crate::service::drop::{CreatedDrop, DropRequest, ImportError};
+use crate::service::workspace::ExtractionWorkspace;
+use crate::service::DropServiceT;
+use chrono::{NaiveDateTime, Utc};
+use derive_new::new;
+use serde::Serialize;
+use sqlx::types::Json;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::{mpsc, Mutex};
+use uuid::Uuid;
+
+pub const DEFAULT_IMPORT_WORKERS: usize = 2;
+pub const PROCESSED_DIR: &str = "processed";
+pub const FAILED_DIR: &str = "failed";
+pub const ERROR_REPORT_SUFFIX: &str = ".error.json";
+
+/// Sidecar written next to an archive moved to the failed directory
+#[derive(Debug, Serialize, new)]
+struct ImportErrorReport {
+    file: String,
+    sha256: String,
+    error: String,
+    message: String,
+    failed_at: NaiveDateTime,
+}
+
+/// Check a drop archive then create the drop it describes, `import` is marked imported with it.
+/// The archive is unpacked in `extraction_workspace`, the extraction is removed afterwards or quarantined on failure.
+pub async fn import_drop_file(
+    drop_service: &(dyn DropServiceT + Send + Sync),
+    import_repo: &dyn ImportRepoT,
+    import: &mut Import,
+    extraction_workspace: &ExtractionWorkspace,
+    file: &str,
+    web_server_path: &str,
+) -> Result<CreatedDrop, ImportError> {
+    let extraction_dir = extraction_workspace.extraction_dir()
+        .or(Err(ImportError::CantCreateDropUntarDirectory))?;
+    let archive_file = file.to_string();
+    let untar_dir = extraction_dir.path().to_path_buf();
+    // untar is blocking, keep it away from the async workers
+    let check_result = tokio::task::spawn_blocking(move || check_drop_file(&archive_file, &untar_dir))
+        .await
+        .unwrap_or(Err(ImportError::CantUnpackDropFile));
+    let import_result = match check_result {
+        Ok((drop_import_path, drop_manifest)) => {
+            let drop_request = DropRequest::from(&drop_manifest);
+            drop_service.create_imported_drop(&drop_import_path, drop_request, web_server_path, import_repo, import).await
+        }
+        Err(import_error) => Err(import_error),
+    };
+
+    if import_result.is_ok() {
+        // removing a large extraction is blocking too
+        match tokio::task::spawn_blocking(move || extraction_dir.close()).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => println!("can't remove extraction of {file}: {:?}", e),
+            Err(e) => println!("can't remove extraction of {file}: {:?}", e),
+        }
+    } else {
+        match extraction_workspace.quarantine(extraction_dir) {
+            Ok(quarantined_dir) => println!("extraction of {file} quarantined in {:?}", quarantined_dir),
+            Err(e) => println!("can't quarantine extraction of {file}: {:?}", e),
+        }
+    }
+    import_result
+}
+
+/// Import a drop archive at most once, the archive is fingerprinted with SHA-256 and
+/// recorded through `ImportRepoT`. Afterwards it is moved to the processed or failed
+/// directory of `import_path`, a failure is explained by a sidecar error report.
+pub async fn import_drop_archive(
+    drop_service: &(dyn DropServiceT + Send + Sync),
+    import_repo: &dyn ImportRepoT,
+    extraction_workspace: &ExtractionWorkspace,
+    file: &str,
+    import_path: &str,
+    web_server_path: &str,
+) -> Result<CreatedDrop, ImportError> {
+    let file_path = Path::new(file);
+    let file_name = file_path.file_name()
+        .and_then(|file_name| file_name.to_str())
+        .ok_or(ImportError::CantOpenDropFile)?
+        .to_string();
+    let hashed_file = file.to_string();
+    let sha256 = tokio::task::spawn_blocking(move || sha256_of_file(&hashed_file))
+        .await
+        .or(Err(ImportError::CantOpenDropFile))?
+        .or(Err(ImportError::CantOpenDropFile))?;
+
+    let started_import = import_repo.start_import(&sha256, &file_name, Utc::now().naive_utc())
+        .await
+        .map_err(|e| {
+            println!("can't record import of {file}: {:?}", e);
+            ImportError::from_repository_error(e, ImportError::CantRecordImport)
+        })?;
+    let Some(mut import) = started_import else {
+        // an archive being imported by another worker is left where it is
+        if let Ok(import) = import_repo.get_by_sha256(&sha256).await
+            && import.state() == ImportState::Imported {
+            println!("{file} was already imported as import {}", import.id());
+            if let Err(e) = move_archive(file_path, import_path, PROCESSED_DIR) {
+                println!("can't move {file} to {PROCESSED_DIR}: {:?}", e);
+            }
+        }
+        return Err(ImportError::DropFileAlreadyImported);
+    };
+
+    // a created drop has already been recorded, with the import, by the same commit
+    let import_result = import_drop_file(drop_service, import_repo, &mut import, extraction_workspace, file, web_server_path).await;
+    let now = Utc::now().naive_utc();
+    if let Err(import_error) = &import_result {
+        import.set_failed(format!("{:?}", import_error), now);
+        if let Err(e) = import_repo.save_or_update(&import).await {
+            println!("can't save import {}: {:?}", import.id(), e);
+        }
+    }
+
+    match &import_result {
+        Ok(_) => {
+            if let Err(e) = move_archive(file_path, import_path, PROCESSED_DIR) {
+                println!("can't move {file} to {PROCESSED_DIR}: {:?}", e);
+            }
+        }
+        Err(import_error) => {
+            let report = ImportErrorReport::new(
+                file_name,
+                sha256,
+                format!("{:?}", import_error),
+                import_error.to_string(),
+                now
+            );
+            if let Err(e) = move_archive(file_path, import_path, FAILED_DIR)
+                .and_then(|failed_file| write_error_report(&failed_file, &report)) {
+                println!("can't move {file} to {FAILED_DIR}: {:?}", e);
+            }
+        }
+    }
+    import_result
+}
+
+/// Move the archive into `import_path/dir`, an archive with the same name already there is kept
+fn move_archive(file_path: &Path, import_path: &str, dir: &str) -> io::Result<PathBuf> {
+    let target_dir = Path::new(import_path).join(dir);
+    fs::create_dir_all(&target_dir)?;
+    let file_name = file_path.file_name()
+        .ok_or(io::Error::new(io::ErrorKind::InvalidInput, "archive has no file name"))?;
+    let mut target = target_dir.join(file_name);
+    if target.exists() {
+        target = target_dir.join(format!("{}_{}", Uuid::new_v4().simple(), file_name.to_string_lossy()));
+    }
+    // the staging directory may be on another filesystem
+    if fs::rename(file_path, &target).is_err() {
+        fs::copy(file_path, &target)?;
+        fs::remove_file(file_path)?;
+    }
+    Ok(target)
+}
+
+fn write_error_report(failed_file: &Path, report: &ImportErrorReport) -> io::Result<PathBuf> {
+    let mut report_path = failed_file.as_os_str().to_owned();
+    report_path.push(ERROR_REPORT_SUFFIX);
+    let report_path = PathBuf::from(report_path);
+    fs::write(&report_path, serde_json::to_vec_pretty(report)?)?;
+    Ok(report_path)
+}
+
+/// Record the outcome of an archive import in its report
+pub fn record_import_result(file_report: &mut FileImportReport, import_result: &Result<CreatedDrop, ImportError>) {
+    match import_result {
+        Ok(created_drop) => file_report.set_imported(
+            created_drop.drop_id(),
+            created_drop.playlist_id(),
+            created_drop.artist_id()
+        ),
+        Err(ImportError::DropFileAlreadyImported) => file_report.set_skipped(
+            format!("{:?}", ImportError::DropFileAlreadyImported),
+            ImportError::DropFileAlreadyImported.to_string()
+        ),
+        Err(import_error) => file_report.set_failed(
+            format!("{:?}", import_error),
+            import_error.to_string(),
+            import_error.is_create_phase_error()
+        ),
+    }
+}
+
+/// Queue of import jobs processed in the background by a pool of workers.
+/// Jobs are persisted through `ImportJobRepoT` so their state survives a restart.
+#[derive(Clone)]
+pub struct ImportJobQueue {
+    import_job_repo: Arc<dyn ImportJobRepoT>,
+    sender: mpsc::UnboundedSender<i32>,
+}
+
+impl ImportJobQueue {
+    pub fn start(
+        import_job_repo: Arc<dyn ImportJobRepoT>,
+        import_repo: Arc<dyn ImportRepoT>,
+        drop_service: Arc<dyn DropServiceT + Send + Sync>,
+        extraction_workspace: ExtractionWorkspace,
+        import_path: String,
+        web_server_path: String,
+        workers: usize,
+    ) -> ImportJobQueue {
+        let (sender, receiver) = mpsc::unbounded_channel::<i32>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for _ in 0..workers.max(1) {
+            let receiver = receiver.clone();
+            let import_job_repo = import_job_repo.clone();
+            let import_repo = import_repo.clone();
+            let drop_service = drop_service.clone();
+            let extraction_workspace = extraction_workspace.clone();
+            let import_path = import_path.clone();
+            let web_server_path = web_server_path.clone();
+            tokio::spawn(async move {
+                loop {
+                    let job_id = receiver.lock().await.recv().await;
+                    match job_id {
+                        Some(job_id) => run_import_job(
+                            job_id,
+                            import_job_repo.as_ref(),
+                            import_repo.as_ref(),
+                            drop_service.as_ref(),
+                            &extraction_workspace,
+                            &import_path,
+                            &web_server_path
+                        ).await,
+                        None => break,
+                    }
+                }
+            });
+        }
+        Self { import_job_repo, sender }
+    }
+
+    pub async fn enqueue(&self, files: Vec<String>) -> Result<ImportJob, RepositoryError> {
+        let now = Utc::now().naive_utc();
+        let mut import_job = ImportJob::new(
+            0,
+            ImportJobState::Queued.as_str().to_string(),
+            Json(files.into_iter()
+                .map(|file| FileImportReport::new(file, FileImportState::Pending))
+                .collect()),
+            now,
+            now,
+        );
+        let id = self.import_job_repo.save_or_update(&import_job).await?;
+        import_job.set_id(id);
+        if self.sender.send(id).is_err() {
+            println!("import workers are stopped, job {id} stays queued");
+        }
+        Ok(import_job)
+    }
+
+    pub async fn get(&self, id: i32) -> Result<ImportJob, RepositoryError> {
+        self.import_job_repo.get(id).await
+    }
+
+    /// Queue again the jobs which were not finished when the server stopped
+    pub async fn resume_unfinished(&self) -> Result<usize, RepositoryError> {
+        let import_jobs = self.import_job_repo.list_unfinished().await?;
+        for import_job in import_jobs.iter() {
+            println!("resuming import job {}", import_job.id());
+            if self.sender.send(import_job.id()).is_err() {
+                println!("import workers are stopped, job {} stays queued", import_job.id());
+            }
+        }
+        Ok(import_jobs.len())
+    }
+}
+
+async fn run_import_job(
+    job_id: i32,
+    import_job_repo: &dyn ImportJobRepoT,
+    import_repo: &dyn ImportRepoT,
+    drop_service: &(dyn DropServiceT + Send + Sync),
+    extraction_workspace: &ExtractionWorkspace,
+    import_path: &str,
+    web_server_path: &str,
+) {
+    let mut import_job = match import_job_repo.get(job_id).await {
+        Ok(import_job) => import_job,
+        Err(e) => {
+            println!("can't load import job {job_id}: {:?}", e);
+            return;
+        }
+    };
+    import_job.set_state(ImportJobState::Running, Utc::now().naive_utc());
+    save_import_job(import_job_repo, &import_job).await;
+
+    for i in 0..import_job.files().len() {
+        // files imported before a restart are not imported twice
+        if matches!(
+            import_job.files()[i].state(),
+            FileImportState::Imported | FileImportState::Skipped | FileImportState::Failed
+        ) {
+            continue;
+        }
+        import_job.files_mut()[i].set_running();
+        save_import_job(import_job_repo, &import_job).await;
+
+        let file = import_job.files()[i].file().to_string();
+        let import_result = import_drop_archive(
+            drop_service,
+            import_repo,
+            extraction_workspace,
+            &file,
+            import_path,
+            web_server_path
+        ).await;
+        if let Err(import_error) = &import_result {
+            println!("import of {file} failed: {:?}", import_error);
+        }
+        record_import_result(&mut import_job.files_mut()[i], &import_result);
+        import_job.set_state(ImportJobState::Running, Utc::now().naive_utc());
+        save_import_job(import_job_repo, &import_job).await;
+    }
+
+    let nb_failed = import_job.files().iter()
+        .filter(|file| file.state() == FileImportState::Failed)
+        .count();
+    let state = if nb_failed == 0 {
+        ImportJobState::Succeeded
+    } else if nb_failed == import_job.files().len() {
+        ImportJobState::Failed
+    } else {
+        ImportJobState::PartiallySucceeded
+    };
+    import_job.set_state(state, Utc::now().naive_utc());
+    save_import_job(import_job_repo, &import_job).await;
+}
+
+async fn save_import_job(import_job_repo: &dyn ImportJobRepoT, import_job: &ImportJob) {
+    if let Err(e) = import_job_repo.save_or_update(import_job).await {
+        println!("can't save import job {}: {:?}", import_job.id(), e);
+    }
+}
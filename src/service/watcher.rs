@@ -0,0 +1,195 @@
+use crate::service::import::ImportJobQueue;
+use crate::TAG_ARCHIVE_PREFIX;
+use notify::event::{AccessKind, AccessMode, ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+pub const DEFAULT_WATCH_STABLE_DELAY_MS: u64 = 2000;
+
+/// A file is ready once renamed into the watched directory, or when its size
+/// did not change for the stable delay while it was being copied.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum WatchedFileEvent {
+    Written,
+    RenamedInto,
+}
+
+#[derive(Debug)]
+struct CandidateFile {
+    size: u64,
+    last_change: Instant,
+}
+
+/// Debounce of the archives written in the watched directory
+#[derive(Debug, Default)]
+pub struct StableFileTracker {
+    candidates: HashMap<PathBuf, CandidateFile>,
+    recently_ready: HashMap<PathBuf, Instant>,
+}
+
+impl StableFileTracker {
+    /// Record that `path` has been written, its stable delay starts again
+    pub fn observe_written(&mut self, path: PathBuf, size: u64, now: Instant) {
+        self.candidates.insert(path, CandidateFile { size, last_change: now });
+    }
+
+    /// Record that `path` has been renamed into the watched directory, it is ready unless
+    /// it was already seen ready during `debounce` (a rename is reported by several events)
+    pub fn observe_renamed(&mut self, path: PathBuf, now: Instant, debounce: Duration) -> bool {
+        self.candidates.remove(&path);
+        if let Some(ready_at) = self.recently_ready.get(&path)
+            && now.duration_since(*ready_at) < debounce {
+            return false;
+        }
+        self.recently_ready.insert(path, now);
+        true
+    }
+
+    /// Files whose size did not change during `stable_delay`, they are not tracked anymore.
+    /// `size_of` returns None when the file disappeared.
+    pub fn take_stable_files(
+        &mut self,
+        now: Instant,
+        stable_delay: Duration,
+        size_of: impl Fn(&Path) -> Option<u64>,
+    ) -> Vec<PathBuf> {
+        let mut stable_files = Vec::new();
+        self.recently_ready.retain(|_, ready_at| now.duration_since(*ready_at) < stable_delay);
+        self.candidates.retain(|path, candidate| {
+            match size_of(path) {
+                None => false,
+                Some(size) if size != candidate.size => {
+                    candidate.size = size;
+                    candidate.last_change = now;
+                    true
+                }
+                Some(_) if now.duration_since(candidate.last_change) >= stable_delay => {
+                    self.recently_ready.insert(path.clone(), now);
+                    stable_files.push(path.clone());
+                    false
+                }
+                Some(_) => true,
+            }
+        });
+        stable_files.sort();
+        stable_files
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.candidates.is_empty() && self.recently_ready.is_empty()
+    }
+}
+
+/// Keeps the filesystem watcher alive, dropping it stops watching
+pub struct ImportWatcher {
+    _watcher: RecommendedWatcher,
+}
+
+/// Watch `import_path` and queue an import job for each new drop archive once fully written
+pub fn start_import_watcher(
+    import_path: &str,
+    import_job_queue: ImportJobQueue,
+    stable_delay: Duration,
+) -> Result<ImportWatcher, notify::Error> {
+    let (sender, mut receiver) = mpsc::unbounded_channel::<(PathBuf, WatchedFileEvent)>();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+        match event {
+            Ok(event) => {
+                for (path, watched_file_event) in watched_file_events(event) {
+                    let _ = sender.send((path, watched_file_event));
+                }
+            }
+            Err(e) => println!("import_path watch error: {:?}", e),
+        }
+    })?;
+    watcher.watch(Path::new(import_path), RecursiveMode::NonRecursive)?;
+    println!("watching {import_path} for new drop archives");
+
+    tokio::spawn(async move {
+        let mut tracker = StableFileTracker::default();
+        let mut interval = tokio::time::interval((stable_delay / 4).max(Duration::from_millis(50)));
+        loop {
+            tokio::select! {
+                watched = receiver.recv() => {
+                    let Some((path, watched_file_event)) = watched else {
+                        break;
+                    };
+                    if !is_drop_archive(&path) {
+                        continue;
+                    }
+                    match watched_file_event {
+                        WatchedFileEvent::RenamedInto => {
+                            if tracker.observe_renamed(path.clone(), Instant::now(), stable_delay) {
+                                enqueue_archive(&import_job_queue, &path).await;
+                            }
+                        }
+                        WatchedFileEvent::Written => {
+                            if let Some(size) = file_size(&path) {
+                                tracker.observe_written(path, size, Instant::now());
+                            }
+                        }
+                    }
+                }
+                _ = interval.tick() => {
+                    if tracker.is_empty() {
+                        continue;
+                    }
+                    for path in tracker.take_stable_files(Instant::now(), stable_delay, file_size) {
+                        enqueue_archive(&import_job_queue, &path).await;
+                    }
+                }
+            }
+        }
+    });
+
+    Ok(ImportWatcher { _watcher: watcher })
+}
+
+fn watched_file_events(event: Event) -> Vec<(PathBuf, WatchedFileEvent)> {
+    match event.kind {
+        EventKind::Modify(ModifyKind::Name(RenameMode::To)) => event.paths.into_iter()
+            .map(|path| (path, WatchedFileEvent::RenamedInto))
+            .collect(),
+        // paths are (from, to)
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => event.paths.into_iter()
+            .skip(1)
+            .map(|path| (path, WatchedFileEvent::RenamedInto))
+            .collect(),
+        // opening the archive during its import must not trigger it again
+        EventKind::Create(_)
+        | EventKind::Modify(ModifyKind::Data(_) | ModifyKind::Any)
+        | EventKind::Access(AccessKind::Close(AccessMode::Write)) => event.paths.into_iter()
+            .map(|path| (path, WatchedFileEvent::Written))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn is_drop_archive(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|file_name| file_name.to_str())
+        .is_some_and(|file_name| file_name.starts_with(TAG_ARCHIVE_PREFIX))
+}
+
+fn file_size(path: &Path) -> Option<u64> {
+    match std::fs::metadata(path) {
+        Ok(metadata) if metadata.is_file() => Some(metadata.len()),
+        _ => None,
+    }
+}
+
+async fn enqueue_archive(import_job_queue: &ImportJobQueue, path: &Path) {
+    let Some(file) = path.to_str() else {
+        return;
+    };
+    if file_size(path).is_none() {
+        return;
+    }
+    match import_job_queue.enqueue(vec![file.to_string()]).await {
+        Ok(import_job) => println!("import job {} queued for {file}", import_job.id()),
+        Err(e) => println!("can't queue import of {file}: {:?}", e),
+    }
+}
@@ -0,0 +1,115 @@
+use derive_new::new;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tempfile::TempDir;
+
+pub const EXTRACTION_DIR_PREFIX: &str = "extract_";
+pub const QUARANTINE_DIR: &str = "quarantine";
+pub const DEFAULT_QUARANTINE_RETENTION_HOURS: u64 = 72;
+
+/// Scratch root where drop archives are unpacked. An extraction directory is removed once
+/// its drop is created, after a failure it is kept in quarantine until the retention expires.
+#[derive(Debug, Clone, new)]
+pub struct ExtractionWorkspace {
+    scratch_path: PathBuf,
+    quarantine_retention: Duration,
+}
+
+/// Directories removed by a sweep of the scratch root
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SweepReport {
+    leftover_extractions: usize,
+    expired_quarantines: usize,
+}
+
+impl SweepReport {
+    pub fn leftover_extractions(&self) -> usize {
+        self.leftover_extractions
+    }
+
+    pub fn expired_quarantines(&self) -> usize {
+        self.expired_quarantines
+    }
+}
+
+impl ExtractionWorkspace {
+    pub fn scratch_path(&self) -> &Path {
+        &self.scratch_path
+    }
+
+    pub fn quarantine_path(&self) -> PathBuf {
+        self.scratch_path.join(QUARANTINE_DIR)
+    }
+
+    pub fn quarantine_retention(&self) -> Duration {
+        self.quarantine_retention
+    }
+
+    /// A new directory to unpack an archive into, it is removed when dropped
+    pub fn extraction_dir(&self) -> io::Result<TempDir> {
+        fs::create_dir_all(&self.scratch_path)?;
+        tempfile::Builder::new()
+            .prefix(EXTRACTION_DIR_PREFIX)
+            .tempdir_in(&self.scratch_path)
+    }
+
+    /// Keep the extraction of a failed import for inspection, expired quarantines are removed
+    pub fn quarantine(&self, mut extraction_dir: TempDir) -> io::Result<PathBuf> {
+        let quarantine_path = self.quarantine_path();
+        fs::create_dir_all(&quarantine_path)?;
+        let dir_name = extraction_dir.path().file_name()
+            .ok_or(io::Error::new(io::ErrorKind::InvalidInput, "extraction directory has no name"))?;
+        let quarantined_dir = quarantine_path.join(dir_name);
+        fs::rename(extraction_dir.path(), &quarantined_dir)?;
+        extraction_dir.disable_cleanup(true);
+        // the retention starts when the directory is quarantined
+        if let Err(e) = File::open(&quarantined_dir).and_then(|dir| dir.set_modified(SystemTime::now())) {
+            println!("can't date quarantined extraction {:?}: {:?}", quarantined_dir, e);
+        }
+        if let Err(e) = self.remove_expired_quarantines(SystemTime::now()) {
+            println!("can't remove expired quarantined extractions: {:?}", e);
+        }
+        Ok(quarantined_dir)
+    }
+
+    /// Remove the quarantined extractions older than the retention
+    pub fn remove_expired_quarantines(&self, now: SystemTime) -> io::Result<usize> {
+        let read_dir = match fs::read_dir(self.quarantine_path()) {
+            Ok(read_dir) => read_dir,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e),
+        };
+        let mut nb_removed = 0;
+        for dir_entry in read_dir {
+            let dir_entry = dir_entry?;
+            let quarantined_at = dir_entry.metadata()?.modified()?;
+            if quarantined_at + self.quarantine_retention <= now {
+                fs::remove_dir_all(dir_entry.path())?;
+                nb_removed += 1;
+            }
+        }
+        Ok(nb_removed)
+    }
+
+    /// Remove the extractions left by a stopped server and the expired quarantines,
+    /// to be called before any import starts
+    pub fn sweep(&self, now: SystemTime) -> io::Result<SweepReport> {
+        let read_dir = match fs::read_dir(&self.scratch_path) {
+            Ok(read_dir) => read_dir,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(SweepReport::default()),
+            Err(e) => return Err(e),
+        };
+        let mut sweep_report = SweepReport::default();
+        for dir_entry in read_dir {
+            let dir_entry = dir_entry?;
+            if dir_entry.file_name().to_string_lossy().starts_with(EXTRACTION_DIR_PREFIX) {
+                fs::remove_dir_all(dir_entry.path())?;
+                sweep_report.leftover_extractions += 1;
+            }
+        }
+        sweep_report.expired_quarantines = self.remove_expired_quarantines(now)?;
+        Ok(sweep_report)
+    }
+}
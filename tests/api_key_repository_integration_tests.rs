@@ -0,0 +1,47 @@
+use crate::utils::{create_default_db_config, start_postgres_container};
+use chrono::Utc;
+use drop_reverse_proxy::repository::api_key::{generate_api_key, hash_api_key, ApiKey, ApiKeyRepo, ApiKeyRepoT};
+use drop_reverse_proxy::repository::Repo;
+
+mod utils;
+
+#[tokio::test]
+async fn test_api_key_repo_integration() {
+    // 1. Start Postgres container
+    let db_name = "drop_of_culture";
+    let user = "drop_of_culture";
+    let password = "drop_of_culture";
+    let (_container_guard, host, port) = start_postgres_container(
+        db_name,
+        user,
+        password,
+    ).await.expect("Failed to start Postgres container");
+
+    // 2. Setup database pool
+    let db_config = create_default_db_config(host, port, db_name, user, password);
+
+    let pool = drop_reverse_proxy::config::db::create_pool(&db_config)
+        .await
+        .expect("Failed to create database pool");
+
+    // 3. Initialize schema
+    drop_reverse_proxy::config::db::run_migrations(&pool)
+        .await
+        .expect("Failed to run migrations");
+
+    let repo = ApiKeyRepo::new(&db_config).await.expect("Failed to create api key repository");
+
+    // 4. Test save_or_update
+    let plain_key = generate_api_key();
+    let api_key = ApiKey::new(0, "ci".to_string(), hash_api_key(&plain_key), vec!["import".to_string()], None, None);
+    let api_key_id = repo.save_or_update(&api_key).await.expect("Failed to save api key");
+
+    // 5. Test get_by_hash and touch_last_used
+    let saved_api_key = repo.get_by_hash(&hash_api_key(&plain_key)).await.expect("Failed to get api key");
+    assert_eq!(saved_api_key.id(), api_key_id);
+    assert_eq!(saved_api_key.name(), "ci");
+    assert!(saved_api_key.last_used_at().is_none());
+
+    repo.touch_last_used(api_key_id, Utc::now().naive_utc()).await.expect("Failed to touch api key");
+    assert!(repo.get(api_key_id).await.unwrap().last_used_at().is_some());
+}
@@ -1,6 +1,7 @@
 use crate::utils::{create_default_db_config, start_postgres_container};
-use drop_reverse_proxy::repository::artist::{Artist, ArtistRepo};
-use drop_reverse_proxy::repository::{RepoByName};
+use drop_reverse_proxy::repository::artist::{Artist, ArtistField, ArtistRepo};
+use drop_reverse_proxy::repository::query::{Filter, Order};
+use drop_reverse_proxy::repository::{RepoByName, RepositoryError};
 use std::sync::Arc;
 
 mod utils;
@@ -25,21 +26,12 @@ async fn test_artist_repo_integration() {
         .expect("Failed to create database pool");
 
     // 3. Initialize schema
-    sqlx::query(
-        r#"
-        CREATE TABLE "artist" (
-            id SERIAL PRIMARY KEY,
-            name VARCHAR(255) NOT NULL
-        )
-        "#
-    )
-    .execute(&pool)
-    .await
-    .expect("Failed to create table");
-
-    let repo = Arc::new(ArtistRepo::new(&db_config)
+    drop_reverse_proxy::config::db::run_migrations(&pool)
         .await
-        .expect("Failed to create artist repository"));
+        .expect("Failed to run migrations");
+
+    // repositories share the pool
+    let repo = Arc::new(ArtistRepo::from_pool(pool.clone()));
 
     // 4. Test save_or_update
     let new_artist = Artist::new(0, "Test Artist".to_string());
@@ -51,4 +43,45 @@ async fn test_artist_repo_integration() {
     
     assert_eq!(saved_artist.name(), "Test Artist");
     assert_eq!(saved_artist.id(), 1);
+
+    // names match whatever their case, spacing or unicode form
+    let found_artist = repo.get_by_name(" test  ARTIST ").await.expect("Failed to get artist by name");
+    assert_eq!(saved_artist, found_artist);
+    assert!(matches!(repo.get_by_name("Other Artist").await, Err(RepositoryError::EntityNotFound)));
+
+    // saving a taken name, whatever its form, gives the existing artist which keeps its name
+    assert_eq!(artist_id, repo.save_or_update(&Artist::new(0, "Test Artist".to_string())).await.expect("Failed to save artist"));
+    assert_eq!(artist_id, repo.save_or_update(&Artist::new(0, "test artist ".to_string())).await.expect("Failed to save artist"));
+    assert_eq!("Test Artist", repo.get(artist_id).await.expect("Failed to get artist").name());
+
+    // update by id, the version read must still be the stored one
+    let renamed_artist = Artist::new(artist_id, "Renamed Artist".to_string()).with_version(saved_artist.version());
+    assert_eq!(artist_id, repo.save_or_update(&renamed_artist).await.expect("Failed to update artist"));
+    assert_eq!("Renamed Artist", repo.get(artist_id).await.expect("Failed to get artist").name());
+    assert!(matches!(repo.save_or_update(&renamed_artist).await, Err(RepositoryError::VersionConflict)));
+    assert!(matches!(repo.save_or_update(&Artist::new(42, "Missing".to_string())).await, Err(RepositoryError::EntityNotFound)));
+
+    // list sorted by name, descending
+    let other_id = repo.save_or_update(&Artist::new(0, "Other Artist".to_string())).await.expect("Failed to save artist");
+    let page = repo.list(&Filter::new().sort_by(ArtistField::Name, Order::Desc).limit(1)).await.expect("Failed to list artists");
+    assert_eq!(vec![artist_id], page.items.iter().map(Artist::id).collect::<Vec<_>>());
+    let page = repo.list(&Filter::new().sort_by(ArtistField::Name, Order::Desc).limit(1).after(page.next_cursor)).await.expect("Failed to list artists");
+    assert_eq!(vec![other_id], page.items.iter().map(Artist::id).collect::<Vec<_>>());
+    assert_eq!(None, page.next_cursor);
+
+    // a name taken by another artist is a unique violation
+    let stored_artist = repo.get(artist_id).await.expect("Failed to get artist");
+    let clashing_artist = Artist::new(artist_id, "Other Artist".to_string()).with_version(stored_artist.version());
+    assert!(matches!(repo.save_or_update(&clashing_artist).await, Err(RepositoryError::UniqueViolation)));
+
+    repo.delete(other_id).await.expect("Failed to delete artist");
+    assert!(!repo.exists(other_id).await.expect("Failed to check artist"));
+    assert!(repo.exists(artist_id).await.expect("Failed to check artist"));
+
+    // artists saved before the normalized name get it filled, a clashing one is reported
+    for name in ["Legacy Artist", "RENAMED ARTIST"] {
+        sqlx::query("INSERT INTO \"artist\" (name) VALUES ($1)").bind(name).execute(&pool).await.expect("Failed to insert artist");
+    }
+    assert_eq!(vec!["RENAMED ARTIST".to_string()], repo.fill_normalized_names().await.expect("Failed to fill normalized names"));
+    assert_eq!("Legacy Artist", repo.get_by_name("legacy artist").await.expect("Failed to get artist by name").name());
 }
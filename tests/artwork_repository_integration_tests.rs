@@ -0,0 +1,51 @@
+use crate::utils::{create_default_db_config, start_postgres_container};
+use drop_reverse_proxy::repository::artwork::{Artwork, ArtworkRepo};
+use drop_reverse_proxy::repository::unit_of_work::UnitOfWork;
+use drop_reverse_proxy::repository::{Repo, RepositoryError};
+
+mod utils;
+
+#[tokio::test]
+async fn test_artwork_repo_integration() {
+    let db_name = "drop_of_culture";
+    let user = "drop_of_culture";
+    let password = "drop_of_culture";
+    let (_container_guard, host, port) = start_postgres_container(
+        db_name,
+        user,
+        password,
+    ).await.expect("Failed to start Postgres container");
+
+    let db_config = create_default_db_config(host, port, db_name, user, password);
+
+    let pool = drop_reverse_proxy::config::db::create_pool(&db_config)
+        .await
+        .expect("Failed to create database pool");
+
+    drop_reverse_proxy::config::db::run_migrations(&pool)
+        .await
+        .expect("Failed to run migrations");
+
+    let repo = ArtworkRepo::new(&db_config)
+        .await
+        .expect("Failed to create artwork repository");
+
+    let id = repo.save_or_update(&Artwork::new(0, "image/png".to_string(), 1400, 1400)).await.expect("Failed to save artwork");
+    let saved_artwork = repo.get(id).await.expect("Failed to get artwork");
+    assert_eq!(Artwork::new(id, "image/png".to_string(), 1400, 1400), saved_artwork);
+
+    // an artwork saved in a unit of work dropped without commit is rolled back
+    let rolled_back_id = {
+        let mut unit_of_work = UnitOfWork::new();
+        repo.save_or_update_in(&Artwork::new(0, "image/jpeg".to_string(), 600, 600), &mut unit_of_work)
+            .await
+            .expect("Failed to save artwork in unit of work")
+    };
+    assert!(matches!(repo.get(rolled_back_id).await, Err(RepositoryError::EntityNotFound)));
+
+    // update by id, the version read must still be the stored one
+    let updated_artwork = Artwork::new(id, "image/png".to_string(), 1200, 1200).with_version(saved_artwork.version());
+    assert_eq!(id, repo.save_or_update(&updated_artwork).await.expect("Failed to update artwork"));
+    assert_eq!(1200, repo.get(id).await.expect("Failed to get artwork").width());
+    assert!(matches!(repo.save_or_update(&updated_artwork).await, Err(RepositoryError::VersionConflict)));
+}
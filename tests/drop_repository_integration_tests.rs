@@ -1,6 +1,10 @@
 use crate::utils::{create_default_db_config, start_postgres_container};
-use drop_reverse_proxy::repository::drop::{Drop, DropRepo};
-use drop_reverse_proxy::repository::Repo;
+use chrono::NaiveDate;
+use drop_reverse_proxy::repository::drop::{Credit, Drop, DropField, DropRepo, Release};
+use drop_reverse_proxy::repository::query::Filter;
+use drop_reverse_proxy::repository::unit_of_work::UnitOfWork;
+use drop_reverse_proxy::repository::{Repo, RepositoryError};
+use drop_reverse_proxy::service::artwork::NO_ARTWORK_ID;
 
 mod utils;
 
@@ -24,24 +28,23 @@ async fn should_insert_data() {
         .expect("Failed to create database pool");
 
     // 3. Initialize schema
-    sqlx::query(
-        r#"
-        CREATE TABLE "drop" (
-            id SERIAL PRIMARY KEY,
-            artist_id INTEGER NOT NULL,
-            artwork_id INTEGER NOT NULL,
-            type_id SMALLINT NOT NULL
-        )
-        "#
-    )
-    .execute(&pool)
-    .await
-    .expect("Failed to create table");
+    drop_reverse_proxy::config::db::run_migrations(&pool)
+        .await
+        .expect("Failed to run migrations");
+
+    // rows the drops reference
+    for statement in [
+        "INSERT INTO \"artist\" (id, name) SELECT id, 'artist ' || id FROM generate_series(1, 9) id",
+        "INSERT INTO \"playlist\" (id, name) SELECT id, 'playlist ' || id FROM generate_series(1, 9) id",
+        "INSERT INTO \"artwork\" (id, mime_type, width, height) SELECT id, 'image/png', 1, 1 FROM generate_series(10, 11) id",
+    ] {
+        sqlx::query(statement).execute(&pool).await.expect("Failed to insert referenced rows");
+    }
 
     let repo = DropRepo::new(&db_config).await.expect("Failed to create drop repository");
 
     // 4. Test save_or_update
-    let new_drop = Drop::new(0, 1, 2, 10);
+    let new_drop = Drop::new(0, 1, 2, 10, 5);
 
     let drop_id = <DropRepo as Repo<Drop>>::save_or_update(&repo, &new_drop).await.expect("Failed to save drop");
 
@@ -53,4 +56,88 @@ async fn should_insert_data() {
     assert_eq!(saved_drop.artwork_id(), 10);
     assert_eq!(saved_drop.type_id(), 2);
     assert_eq!(saved_drop.id(), drop_id);
-}
\ No newline at end of file
+
+    // 6. Test save_or_update_in, a rolled back unit of work leaves no row
+    let mut unit_of_work = UnitOfWork::new();
+    let rolled_back_id = repo.save_or_update_in(&Drop::new(0, 3, 2, 10, 6), &mut unit_of_work).await.expect("Failed to save drop in unit of work");
+    unit_of_work.rollback().await.expect("Failed to roll back");
+    assert!(matches!(repo.get(rolled_back_id).await, Err(RepositoryError::EntityNotFound)));
+
+    let release = Release::new(NaiveDate::from_ymd_opt(2026, 5, 1), Some("Irie Records".to_string()), Some("Reggae".to_string()), None)
+        .with_credits(vec![Credit::new("producer".to_string(), "King Tubby".to_string())]);
+    let mut unit_of_work = UnitOfWork::new();
+    let committed_id = repo.save_or_update_in(&Drop::new(0, 4, 2, 10, 7).with_release(release.clone()), &mut unit_of_work).await.expect("Failed to save drop in unit of work");
+    unit_of_work.commit().await.expect("Failed to commit");
+    let committed_drop = repo.get(committed_id).await.expect("Failed to get committed drop");
+    assert_eq!(4, committed_drop.artist_id());
+    assert_eq!(&release, committed_drop.release());
+
+    // 7. Test a drop without id of a playlist which has one, the drop there is left as it was
+    assert!(matches!(repo.save_or_update(&Drop::new(0, 8, 3, 11, 5)).await, Err(RepositoryError::UniqueViolation)));
+    assert_eq!(saved_drop, repo.get(drop_id).await.expect("Failed to get drop"));
+
+    // 8. Test update by id, the version read must still be the stored one
+    let updated_drop = Drop::new(drop_id, 9, 3, 11, 5).with_version(saved_drop.version());
+    assert_eq!(drop_id, repo.save_or_update(&updated_drop).await.expect("Failed to update drop"));
+    let stored_drop = repo.get(drop_id).await.expect("Failed to get drop");
+    assert_eq!(9, stored_drop.artist_id());
+    assert!(stored_drop.published());
+    assert!(matches!(repo.save_or_update(&updated_drop).await, Err(RepositoryError::VersionConflict)));
+    assert!(matches!(repo.save_or_update(&Drop::new(42, 9, 3, 11, 42)).await, Err(RepositoryError::EntityNotFound)));
+
+    // 9. Test list filtered by artist, exists and delete
+    let page = repo.list(&Filter::new().eq(DropField::ArtistId, 4)).await.expect("Failed to list drops");
+    assert_eq!(vec![committed_id], page.items.iter().map(Drop::id).collect::<Vec<_>>());
+    assert_eq!(None, page.next_cursor);
+    let unpublished_drop = stored_drop.clone().with_published(false);
+    repo.save_or_update(&unpublished_drop).await.expect("Failed to unpublish drop");
+    assert!(!repo.get(drop_id).await.expect("Failed to get drop").published());
+    let published_of_artist = repo.list(&Filter::new().eq(DropField::ArtistId, 9).eq(DropField::Published, true)).await.expect("Failed to list drops");
+    assert!(published_of_artist.items.is_empty());
+    assert!(repo.exists(committed_id).await.expect("Failed to check drop"));
+    repo.delete(committed_id).await.expect("Failed to delete drop");
+    assert!(!repo.exists(committed_id).await.expect("Failed to check drop"));
+    assert!(matches!(repo.delete(committed_id).await, Err(RepositoryError::EntityNotFound)));
+
+    // 10. Test references, a drop without artwork is read with NO_ARTWORK_ID, a missing artist is refused
+    let drop_without_artwork_id = repo.save_or_update(&Drop::new(0, 1, 2, NO_ARTWORK_ID, 8)).await.expect("Failed to save drop");
+    assert_eq!(NO_ARTWORK_ID, repo.get(drop_without_artwork_id).await.expect("Failed to get drop").artwork_id());
+    assert!(matches!(repo.save_or_update(&Drop::new(0, 42, 2, NO_ARTWORK_ID, 9)).await, Err(RepositoryError::ForeignKeyViolation)));
+}
+#[tokio::test]
+async fn legacy_init_sql_database_is_migrated() {
+    let db_name = "drop_of_culture";
+    let user = "drop_of_culture";
+    let password = "drop_of_culture";
+    let (_container_guard, host, port) = start_postgres_container(
+        db_name,
+        user,
+        password,
+    ).await.expect("Failed to start Postgres container");
+    let db_config = create_default_db_config(host, port, db_name, user, password);
+    let pool = drop_reverse_proxy::config::db::create_pool(&db_config)
+        .await
+        .expect("Failed to create database pool");
+
+    // the schema of the former init.sql, its drop kept the playlist id in artwork_id
+    for statement in [
+        "CREATE TABLE \"artist\" (id SERIAL PRIMARY KEY, name VARCHAR(255) NOT NULL)",
+        "CREATE TABLE \"playlist\" (id SERIAL PRIMARY KEY, name VARCHAR(255) NOT NULL)",
+        "CREATE TABLE \"drop\" (id SERIAL PRIMARY KEY, artist_id INTEGER NOT NULL, artwork_id INTEGER NOT NULL, type_id SMALLINT NOT NULL)",
+        "INSERT INTO \"artist\" (name) VALUES ('Cool Rasta')",
+        "INSERT INTO \"playlist\" (name) VALUES ('Sunrise')",
+        "INSERT INTO \"drop\" (artist_id, artwork_id, type_id) VALUES (1, 1, 2)",
+    ] {
+        sqlx::query(statement).execute(&pool).await.expect("Failed to create the legacy schema");
+    }
+
+    drop_reverse_proxy::config::db::run_migrations(&pool)
+        .await
+        .expect("Failed to run migrations");
+
+    let repo = DropRepo::from_pool(pool);
+    let drop = repo.get(1).await.expect("Failed to get drop");
+    assert_eq!((1, 1, NO_ARTWORK_ID), (drop.artist_id(), drop.playlist_id(), drop.artwork_id()));
+    assert!(drop.published());
+    assert!(matches!(repo.save_or_update(&Drop::new(0, 1, 2, NO_ARTWORK_ID, 1)).await, Err(RepositoryError::UniqueViolation)));
+}
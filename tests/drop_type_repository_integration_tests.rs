@@ -0,0 +1,46 @@
+use crate::utils::{create_default_db_config, start_postgres_container};
+use drop_reverse_proxy::repository::drop_type::{DropType, DropTypeRepo};
+
+mod utils;
+
+#[tokio::test]
+async fn test_drop_type_repo_integration() {
+    let db_name = "drop_of_culture";
+    let user = "drop_of_culture";
+    let password = "drop_of_culture";
+    let (_container_guard, host, port) = start_postgres_container(
+        db_name,
+        user,
+        password,
+    ).await.expect("Failed to start Postgres container");
+
+    let db_config = create_default_db_config(host, port, db_name, user, password);
+
+    let pool = drop_reverse_proxy::config::db::create_pool(&db_config)
+        .await
+        .expect("Failed to create database pool");
+
+    drop_reverse_proxy::config::db::run_migrations(&pool)
+        .await
+        .expect("Failed to run migrations");
+
+    sqlx::query(r#"INSERT INTO "drop_type" (id, name) VALUES (3, 'lp')"#)
+        .execute(&pool)
+        .await
+        .expect("Failed to insert outdated type");
+
+    let repo = DropTypeRepo::new(&db_config)
+        .await
+        .expect("Failed to create drop type repository");
+
+    // syncing twice neither fails nor duplicates the types
+    repo.sync().await.expect("Failed to sync drop types");
+    repo.sync().await.expect("Failed to sync drop types again");
+
+    assert_eq!(DropType::ALL.to_vec(), repo.get_all().await.expect("Failed to get drop types"));
+    let album_name = sqlx::query_scalar::<_, String>(r#"SELECT name FROM "drop_type" WHERE id = 3"#)
+        .fetch_one(&pool)
+        .await
+        .expect("Failed to get album type");
+    assert_eq!("album", album_name);
+}
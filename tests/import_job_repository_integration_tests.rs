@@ -0,0 +1,57 @@
+use crate::utils::{create_default_db_config, start_postgres_container};
+use chrono::Utc;
+use drop_reverse_proxy::repository::import_job::{FileImportReport, FileImportState, ImportJob, ImportJobRepo, ImportJobRepoT, ImportJobState};
+use drop_reverse_proxy::repository::Repo;
+use sqlx::types::Json;
+
+mod utils;
+
+#[tokio::test]
+async fn test_import_job_repo_integration() {
+    // 1. Start Postgres container
+    let db_name = "drop_of_culture";
+    let user = "drop_of_culture";
+    let password = "drop_of_culture";
+    let (_container_guard, host, port) = start_postgres_container(
+        db_name,
+        user,
+        password,
+    ).await.expect("Failed to start Postgres container");
+
+    // 2. Setup database pool
+    let db_config = create_default_db_config(host, port, db_name, user, password);
+
+    let pool = drop_reverse_proxy::config::db::create_pool(&db_config)
+        .await
+        .expect("Failed to create database pool");
+
+    // 3. Initialize schema
+    drop_reverse_proxy::config::db::run_migrations(&pool)
+        .await
+        .expect("Failed to run migrations");
+
+    let repo = ImportJobRepo::new(&db_config).await.expect("Failed to create import job repository");
+
+    // 4. Test save_or_update
+    let now = Utc::now().naive_utc();
+    let mut import_job = ImportJob::new(
+        0,
+        ImportJobState::Queued.as_str().to_string(),
+        Json(vec![FileImportReport::new("drop_001.tar.gz".to_string(), FileImportState::Pending)]),
+        now,
+        now
+    );
+    let import_job_id = repo.save_or_update(&import_job).await.expect("Failed to save import job");
+    import_job.set_id(import_job_id);
+    assert_eq!(1, repo.list_unfinished().await.unwrap().len());
+
+    // 5. Test update and get
+    import_job.files_mut()[0].set_imported(1, 2, 3);
+    import_job.set_state(ImportJobState::Succeeded, Utc::now().naive_utc());
+    repo.save_or_update(&import_job).await.expect("Failed to update import job");
+
+    let saved_import_job = repo.get(import_job_id).await.expect("Failed to get import job");
+    assert_eq!(ImportJobState::Succeeded, saved_import_job.state());
+    assert_eq!(Some(2), saved_import_job.files()[0].playlist_id());
+    assert!(repo.list_unfinished().await.unwrap().is_empty());
+}
@@ -0,0 +1,59 @@
+use crate::utils::{create_default_db_config, start_postgres_container};
+use chrono::Utc;
+use drop_reverse_proxy::repository::import::{ImportRepo, ImportRepoT, ImportState};
+use drop_reverse_proxy::repository::Repo;
+
+mod utils;
+
+#[tokio::test]
+async fn test_import_repo_integration() {
+    // 1. Start Postgres container
+    let db_name = "drop_of_culture";
+    let user = "drop_of_culture";
+    let password = "drop_of_culture";
+    let (_container_guard, host, port) = start_postgres_container(
+        db_name,
+        user,
+        password,
+    ).await.expect("Failed to start Postgres container");
+
+    // 2. Setup database pool
+    let db_config = create_default_db_config(host, port, db_name, user, password);
+
+    let pool = drop_reverse_proxy::config::db::create_pool(&db_config)
+        .await
+        .expect("Failed to create database pool");
+
+    // 3. Initialize schema
+    drop_reverse_proxy::config::db::run_migrations(&pool)
+        .await
+        .expect("Failed to run migrations");
+
+    let repo = ImportRepo::new(&db_config).await.expect("Failed to create import repository");
+    let sha256 = "a".repeat(64);
+
+    // 4. Test start_import
+    let mut import = repo.start_import(&sha256, "drop_001.tar.gz", Utc::now().naive_utc())
+        .await
+        .expect("Failed to start import")
+        .expect("Import not started");
+    assert_eq!(ImportState::Running, import.state());
+    assert!(repo.start_import(&sha256, "drop_001.tar.gz", Utc::now().naive_utc()).await.unwrap().is_none());
+
+    // 5. Test interrupt_running then retry
+    assert_eq!(1, repo.interrupt_running(Utc::now().naive_utc()).await.unwrap());
+    assert_eq!(ImportState::Failed, repo.get(import.id()).await.unwrap().state());
+    let retried = repo.start_import(&sha256, "drop_001_retry.tar.gz", Utc::now().naive_utc())
+        .await
+        .unwrap()
+        .expect("Failed import not retried");
+    assert_eq!(import.id(), retried.id());
+
+    // 6. Test save_or_update and get_by_sha256
+    import.set_imported(12, Utc::now().naive_utc());
+    repo.save_or_update(&import).await.expect("Failed to update import");
+    let saved_import = repo.get_by_sha256(&sha256).await.expect("Failed to get import");
+    assert_eq!(ImportState::Imported, saved_import.state());
+    assert_eq!(Some(12), saved_import.drop_id());
+    assert!(repo.start_import(&sha256, "drop_001.tar.gz", Utc::now().naive_utc()).await.unwrap().is_none());
+}
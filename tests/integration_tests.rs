@@ -1,21 +1,38 @@
 use crate::mock::repository::artist::ArtistRepoMock;
+use crate::mock::repository::artwork::ArtworkRepoMock;
 use crate::mock::repository::drop::DropRepoMock;
 use crate::mock::repository::playlist::PlaylistRepoMock;
+use crate::mock::repository::track::TrackRepoMock;
 use crate::utils::{init_apache_http2_container, DockerGuard};
+use axum::body::Body;
 use axum::extract::ConnectInfo;
 use axum::http::{HeaderMap, Request, StatusCode};
-use chrono::NaiveDateTime;
-use drop_reverse_proxy::service::drop::DropService;
-use drop_reverse_proxy::{app, AppState, Conf, InMemoryIpRepo, InMemoryTagRepo, InMemoryTokenRepo, IpRepo, IpRepoDB, ServiceConf, Tag, TagRepo, TagRepoDB, Token, TokenRepo, TokenRepoDB, TOKEN_NAME};
-use http_body_util::Empty;
+use chrono::{NaiveDateTime, Utc};
+use drop_reverse_proxy::repository::api_key::{generate_api_key, hash_api_key, ApiKey, InMemoryApiKeyRepo};
+use drop_reverse_proxy::media::{LocalMediaStore, MediaContent, MediaStore};
+use drop_reverse_proxy::repository::Repo;
+use drop_reverse_proxy::repository::import::InMemoryImportRepo;
+use drop_reverse_proxy::repository::import_job::InMemoryImportJobRepo;
+use drop_reverse_proxy::service::drop::{track_content_key, DropService, TRACK_CONTENT_PREFIX};
+use drop_reverse_proxy::service::import::ImportJobQueue;
+use drop_reverse_proxy::service::workspace::ExtractionWorkspace;
+use drop_reverse_proxy::{app, create_media_store, AppState, Conf, InMemoryIpRepo, InMemoryTagRepo, InMemoryTokenRepo, IpRepo, IpRepoDB, ServiceConf, Tag, TagRepo, TagRepoDB, Token, TokenRepo, TokenRepoDB, TOKEN_NAME};
+use drop_reverse_proxy::repository::artist::Artist;
+use drop_reverse_proxy::repository::artwork::Artwork;
+use drop_reverse_proxy::repository::drop::{Drop, Release};
+use drop_reverse_proxy::repository::drop_type::DropType;
+use drop_reverse_proxy::repository::playlist::Playlist;
+use drop_reverse_proxy::repository::track::{CuePoint, Track, TrackRepoT};
+use http_body_util::{BodyExt, Empty};
 use regex::Regex;
-use reqwest::header::SET_COOKIE;
+use reqwest::header::{AUTHORIZATION, SET_COOKIE};
 use std::net::{IpAddr, SocketAddr};
 use std::process::Command;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
+use tempfile::TempDir;
 use tower::ServiceExt;
 use uuid::Uuid;
 
@@ -61,6 +78,10 @@ async fn get_tag() {
         token_repo: Arc::new(token_repo.clone()),
         tag_repo: Arc::new(tag_repo.clone()),
         ip_repo: Arc::new(ip_repo.clone()),
+        api_key_repo: Arc::new(InMemoryApiKeyRepo::default()),
+        import_repo: Arc::new(InMemoryImportRepo::default()),
+        import_job_queue: None,
+        media_store: create_media_store(&conf),
         conf,
         entity_repositories: Vec::new(),
         service_conf: ServiceConf::new(
@@ -68,6 +89,8 @@ async fn get_tag() {
                 Arc::new(DropRepoMock::new()),
                 Arc::new(ArtistRepoMock::new()),
                 Arc::new(PlaylistRepoMock::new()),
+                Arc::new(ArtworkRepoMock::new()),
+                Arc::new(TrackRepoMock::new()),
             )
         ),
     };
@@ -118,6 +141,10 @@ async fn get_tag_error() {
         token_repo: Arc::new(token_repo.clone()),
         tag_repo: Arc::new(tag_repo.clone()),
         ip_repo: Arc::new(ip_repo.clone()),
+        api_key_repo: Arc::new(InMemoryApiKeyRepo::default()),
+        import_repo: Arc::new(InMemoryImportRepo::default()),
+        import_job_queue: None,
+        media_store: create_media_store(&conf),
         conf,
         entity_repositories: Vec::new(),
         service_conf: ServiceConf::new(
@@ -125,6 +152,8 @@ async fn get_tag_error() {
                 Arc::new(DropRepoMock::new()),
                 Arc::new(ArtistRepoMock::new()),
                 Arc::new(PlaylistRepoMock::new()),
+                Arc::new(ArtworkRepoMock::new()),
+                Arc::new(TrackRepoMock::new()),
             )
         ),
     };
@@ -153,6 +182,10 @@ async fn tag_not_in_list_returns_500_and_no_token_header() {
         token_repo: Arc::new(token_repo.clone()),
         tag_repo: Arc::new(tag_repo.clone()),
         ip_repo: Arc::new(ip_repo.clone()),
+        api_key_repo: Arc::new(InMemoryApiKeyRepo::default()),
+        import_repo: Arc::new(InMemoryImportRepo::default()),
+        import_job_queue: None,
+        media_store: create_media_store(&conf),
         conf,
         entity_repositories: Vec::new(),
         service_conf: ServiceConf::new(
@@ -160,6 +193,8 @@ async fn tag_not_in_list_returns_500_and_no_token_header() {
                 Arc::new(DropRepoMock::new()),
                 Arc::new(ArtistRepoMock::new()),
                 Arc::new(PlaylistRepoMock::new()),
+                Arc::new(ArtworkRepoMock::new()),
+                Arc::new(TrackRepoMock::new()),
             )
         ),
     };
@@ -192,6 +227,10 @@ async fn save_and_get_token_from_repo() {
         token_repo: Arc::new(token_repo.clone()),
         tag_repo: Arc::new(tag_repo.clone()),
         ip_repo: Arc::new(ip_repo.clone()),
+        api_key_repo: Arc::new(InMemoryApiKeyRepo::default()),
+        import_repo: Arc::new(InMemoryImportRepo::default()),
+        import_job_queue: None,
+        media_store: create_media_store(&conf),
         conf,
         entity_repositories: Vec::new(),
         service_conf: ServiceConf::new(
@@ -199,6 +238,8 @@ async fn save_and_get_token_from_repo() {
                 Arc::new(DropRepoMock::new()),
                 Arc::new(ArtistRepoMock::new()),
                 Arc::new(PlaylistRepoMock::new()),
+                Arc::new(ArtworkRepoMock::new()),
+                Arc::new(TrackRepoMock::new()),
             )
         ),
     };
@@ -341,6 +382,10 @@ async fn save_and_get_token_from_db() {
         token_repo: Arc::new(token_repo.clone()),
         tag_repo: Arc::new(tag_repo.clone()),
         ip_repo: Arc::new(ip_repo.clone()),
+        api_key_repo: Arc::new(InMemoryApiKeyRepo::default()),
+        import_repo: Arc::new(InMemoryImportRepo::default()),
+        import_job_queue: None,
+        media_store: create_media_store(&conf),
         conf,
         entity_repositories: Vec::new(),
         service_conf: ServiceConf::new(
@@ -348,6 +393,8 @@ async fn save_and_get_token_from_db() {
                 Arc::new(DropRepoMock::new()),
                 Arc::new(ArtistRepoMock::new()),
                 Arc::new(PlaylistRepoMock::new()),
+                Arc::new(ArtworkRepoMock::new()),
+                Arc::new(TrackRepoMock::new()),
             )
         ),
     };
@@ -396,6 +443,10 @@ async fn get_tag_should_return_500_when_ip_max_attempts_reached() {
         token_repo: Arc::new(token_repo.clone()),
         tag_repo: Arc::new(tag_repo.clone()),
         ip_repo: Arc::new(ip_repo),
+        api_key_repo: Arc::new(InMemoryApiKeyRepo::default()),
+        import_repo: Arc::new(InMemoryImportRepo::default()),
+        import_job_queue: None,
+        media_store: create_media_store(&conf),
         conf,
         entity_repositories: Vec::new(),
         service_conf: ServiceConf::new(
@@ -403,6 +454,8 @@ async fn get_tag_should_return_500_when_ip_max_attempts_reached() {
                 Arc::new(DropRepoMock::new()),
                 Arc::new(ArtistRepoMock::new()),
                 Arc::new(PlaylistRepoMock::new()),
+                Arc::new(ArtworkRepoMock::new()),
+                Arc::new(TrackRepoMock::new()),
             )
         ),
     };
@@ -444,6 +497,10 @@ async fn get_play_is_authorized_token() {
         token_repo: Arc::new(token_repo.clone()),
         tag_repo: Arc::new(tag_repo.clone()),
         ip_repo: Arc::new(ip_repo),
+        api_key_repo: Arc::new(InMemoryApiKeyRepo::default()),
+        import_repo: Arc::new(InMemoryImportRepo::default()),
+        import_job_queue: None,
+        media_store: create_media_store(&conf),
         conf,
         entity_repositories: Vec::new(),
         service_conf: ServiceConf::new(
@@ -451,6 +508,8 @@ async fn get_play_is_authorized_token() {
                 Arc::new(DropRepoMock::new()),
                 Arc::new(ArtistRepoMock::new()),
                 Arc::new(PlaylistRepoMock::new()),
+                Arc::new(ArtworkRepoMock::new()),
+                Arc::new(TrackRepoMock::new()),
             )
         ),
     };
@@ -482,6 +541,10 @@ async fn get_play_is_not_authorized_token() {
         token_repo: Arc::new(token_repo.clone()),
         tag_repo: Arc::new(tag_repo.clone()),
         ip_repo: Arc::new(ip_repo),
+        api_key_repo: Arc::new(InMemoryApiKeyRepo::default()),
+        import_repo: Arc::new(InMemoryImportRepo::default()),
+        import_job_queue: None,
+        media_store: create_media_store(&conf),
         conf,
         entity_repositories: Vec::new(),
         service_conf: ServiceConf::new(
@@ -489,6 +552,8 @@ async fn get_play_is_not_authorized_token() {
                 Arc::new(DropRepoMock::new()),
                 Arc::new(ArtistRepoMock::new()),
                 Arc::new(PlaylistRepoMock::new()),
+                Arc::new(ArtworkRepoMock::new()),
+                Arc::new(TrackRepoMock::new()),
             )
         ),   
     };
@@ -517,6 +582,10 @@ async fn get_play_is_not_authorized_token_when_random_path_and_no_token_header()
         token_repo: Arc::new(token_repo.clone()),
         tag_repo: Arc::new(tag_repo.clone()),
         ip_repo: Arc::new(ip_repo),
+        api_key_repo: Arc::new(InMemoryApiKeyRepo::default()),
+        import_repo: Arc::new(InMemoryImportRepo::default()),
+        import_job_queue: None,
+        media_store: create_media_store(&conf),
         conf,
         entity_repositories: Vec::new(),
         service_conf: ServiceConf::new(
@@ -524,6 +593,8 @@ async fn get_play_is_not_authorized_token_when_random_path_and_no_token_header()
                 Arc::new(DropRepoMock::new()),
                 Arc::new(ArtistRepoMock::new()),
                 Arc::new(PlaylistRepoMock::new()),
+                Arc::new(ArtworkRepoMock::new()),
+                Arc::new(TrackRepoMock::new()),
             )
         ),
     };
@@ -571,6 +642,10 @@ async fn get_play_is_authorized_token_and_ip_is_allowed() {
         token_repo: Arc::new(token_repo.clone()),
         tag_repo: Arc::new(tag_repo.clone()),
         ip_repo: Arc::new(ip_repo),
+        api_key_repo: Arc::new(InMemoryApiKeyRepo::default()),
+        import_repo: Arc::new(InMemoryImportRepo::default()),
+        import_job_queue: None,
+        media_store: create_media_store(&conf),
         conf,
         entity_repositories: Vec::new(),
         service_conf: ServiceConf::new(
@@ -578,6 +653,8 @@ async fn get_play_is_authorized_token_and_ip_is_allowed() {
                 Arc::new(DropRepoMock::new()),
                 Arc::new(ArtistRepoMock::new()),
                 Arc::new(PlaylistRepoMock::new()),
+                Arc::new(ArtworkRepoMock::new()),
+                Arc::new(TrackRepoMock::new()),
             )
         ),
     };
@@ -616,6 +693,10 @@ async fn get_play_is_authorized_token_and_ip_is_not_allowed() {
         token_repo: Arc::new(token_repo.clone()),
         tag_repo: Arc::new(tag_repo.clone()),
         ip_repo: Arc::new(ip_repo.clone()),
+        api_key_repo: Arc::new(InMemoryApiKeyRepo::default()),
+        import_repo: Arc::new(InMemoryImportRepo::default()),
+        import_job_queue: None,
+        media_store: create_media_store(&conf),
         conf,
         entity_repositories: Vec::new(),
         service_conf: ServiceConf::new(
@@ -623,6 +704,8 @@ async fn get_play_is_authorized_token_and_ip_is_not_allowed() {
                 Arc::new(DropRepoMock::new()),
                 Arc::new(ArtistRepoMock::new()),
                 Arc::new(PlaylistRepoMock::new()),
+                Arc::new(ArtworkRepoMock::new()),
+                Arc::new(TrackRepoMock::new()),
             )
         ),
     };
@@ -690,6 +773,10 @@ async fn get_play_is_not_authorized_token_when_no_token() {
         token_repo: Arc::new(token_repo.clone()),
         tag_repo: Arc::new(tag_repo.clone()),
         ip_repo: Arc::new(ip_repo),
+        api_key_repo: Arc::new(InMemoryApiKeyRepo::default()),
+        import_repo: Arc::new(InMemoryImportRepo::default()),
+        import_job_queue: None,
+        media_store: create_media_store(&conf),
         conf,
         entity_repositories: Vec::new(),
         service_conf: ServiceConf::new(
@@ -697,6 +784,8 @@ async fn get_play_is_not_authorized_token_when_no_token() {
                 Arc::new(DropRepoMock::new()),
                 Arc::new(ArtistRepoMock::new()),
                 Arc::new(PlaylistRepoMock::new()),
+                Arc::new(ArtworkRepoMock::new()),
+                Arc::new(TrackRepoMock::new()),
             )
         ),
     };
@@ -713,17 +802,173 @@ async fn get_play_is_not_authorized_token_when_no_token() {
     assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
 }
 
+fn init_app_state_with_import_job_queue(artist_repo: ArtistRepoMock, import_path: &str, web_server_path: &str) -> AppState {
+    let drop_service = DropService::new(
+        Arc::new(DropRepoMock::new()) as Arc<dyn drop_reverse_proxy::repository::Repo<drop_reverse_proxy::repository::drop::Drop>>,
+        Arc::new(artist_repo) as Arc<dyn drop_reverse_proxy::repository::RepoByName<Artist>>,
+        Arc::new(PlaylistRepoMock::new()) as Arc<dyn drop_reverse_proxy::repository::Repo<drop_reverse_proxy::repository::playlist::Playlist>>,
+        Arc::new(ArtworkRepoMock::new()) as Arc<dyn drop_reverse_proxy::repository::Repo<drop_reverse_proxy::repository::artwork::Artwork>>,
+        Arc::new(TrackRepoMock::new()) as Arc<dyn TrackRepoT>,
+    );
+    let import_repo = Arc::new(InMemoryImportRepo::default());
+    let import_job_queue = ImportJobQueue::start(
+        Arc::new(InMemoryImportJobRepo::default()),
+        import_repo.clone(),
+        Arc::new(drop_service.clone()),
+        ExtractionWorkspace::new(format!("{import_path}/.scratch").into(), Duration::from_secs(3600)),
+        import_path.to_string(),
+        web_server_path.to_string(),
+        1
+    );
+    let conf = Conf::new(
+        String::from(""),
+        String::from("127.0.0.1:8000"),
+        10,
+        Vec::new(),
+        import_path.to_string(),
+        None,
+        Some(web_server_path.to_string())
+    );
+    AppState {
+        token_repo: Arc::new(InMemoryTokenRepo::default()),
+        tag_repo: Arc::new(InMemoryTagRepo::default()),
+        ip_repo: Arc::new(InMemoryIpRepo::default()),
+        api_key_repo: Arc::new(InMemoryApiKeyRepo::default()),
+        import_repo,
+        import_job_queue: Some(import_job_queue),
+        media_store: create_media_store(&conf),
+        conf,
+        entity_repositories: Vec::new(),
+        service_conf: ServiceConf::new(drop_service),
+    }
+}
+
+/// Polls the import job at `location` until it is finished, gives the status of its outcome with it
+async fn finished_import_job(app: &axum::Router, location: &str) -> (StatusCode, serde_json::Value) {
+    for _ in 0..100 {
+        let mut req = Request::builder()
+            .uri(location)
+            .body(Empty::new())
+            .unwrap();
+        req.extensions_mut().insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+        let response = app.clone().oneshot(req).await.unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        if !matches!(json["state"].as_str(), Some("queued" | "running")) {
+            return (status, json);
+        }
+        assert_eq!(StatusCode::OK, status);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("import job {location} is not finished");
+}
+
+#[tokio::test]
+async fn drop_import_queues_a_job_of_the_archives_of_import_path() {
+    let import_dir = TempDir::new().unwrap();
+    let web_server_dir = TempDir::new().unwrap();
+    let app = app(init_app_state_with_import_job_queue(
+        ArtistRepoMock::new(),
+        import_dir.path().to_str().unwrap(),
+        web_server_dir.path().to_str().unwrap()
+    ));
+
+    let mut req = Request::builder()
+        .uri("/drop/import")
+        .body(Empty::new())
+        .unwrap();
+    req.extensions_mut().insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+    let response = app.clone().oneshot(req).await.unwrap();
+
+    assert_eq!(StatusCode::ACCEPTED, response.status());
+    assert_eq!("true", response.headers()["deprecation"]);
+    let location = response.headers()["location"].to_str().unwrap().to_string();
+    let (status, job) = finished_import_job(&app, &location).await;
+    assert_eq!(StatusCode::OK, status);
+    assert_eq!(Some("succeeded"), job["state"].as_str());
+    assert_eq!(Some(0), job["files"].as_array().map(|files| files.len() as i64));
+}
+
+#[tokio::test]
+async fn drop_import_reports_each_archive_and_partial_failure() {
+    let import_dir = TempDir::new().unwrap();
+    std::fs::copy("tests/resources/import_path/correct_tar_gz/drop_ok.tar.gz", import_dir.path().join("drop_ok.tar.gz")).unwrap();
+    std::fs::write(import_dir.path().join("drop_invalid.tar.gz"), "not a tar.gz archive").unwrap();
+    let web_server_dir = TempDir::new().unwrap();
+    let artist_repo = ArtistRepoMock::new();
+    artist_repo.map_by_name().write().unwrap().insert("Cool Rasta".to_string(), Artist::new(7, "Cool Rasta".to_string()));
+    let app = app(init_app_state_with_import_job_queue(
+        artist_repo,
+        import_dir.path().to_str().unwrap(),
+        web_server_dir.path().to_str().unwrap()
+    ));
+
+    let mut req = Request::builder()
+        .uri("/drop/import")
+        .body(Empty::new())
+        .unwrap();
+    req.extensions_mut().insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+    let response = app.clone().oneshot(req).await.unwrap();
+
+    assert_eq!(StatusCode::ACCEPTED, response.status());
+    let location = response.headers()["location"].to_str().unwrap().to_string();
+    let (status, job) = finished_import_job(&app, &location).await;
+    assert_eq!(StatusCode::MULTI_STATUS, status);
+    assert_eq!(Some("partially_succeeded"), job["state"].as_str());
+    let files = job["files"].as_array().unwrap();
+    let imported = files.iter().find(|file| file["file"].as_str().unwrap().ends_with("drop_ok.tar.gz")).unwrap();
+    assert_eq!(Some("imported"), imported["state"].as_str());
+    assert_eq!(Some(7), imported["artist_id"].as_i64());
+    assert_eq!(Some(0), imported["playlist_id"].as_i64());
+    let failed = files.iter().find(|file| file["file"].as_str().unwrap().ends_with("drop_invalid.tar.gz")).unwrap();
+    assert_eq!(Some("failed"), failed["state"].as_str());
+    assert_eq!(Some("UnsupportedArchiveFormat"), failed["error"].as_str());
+    assert!(failed["message"].as_str().is_some());
+}
+
+#[tokio::test]
+async fn drop_import_job_of_invalid_archives_only_is_unprocessable() {
+    let import_dir = TempDir::new().unwrap();
+    std::fs::write(import_dir.path().join("drop_invalid.tar.gz"), "not a tar.gz archive").unwrap();
+    let web_server_dir = TempDir::new().unwrap();
+    let app = app(init_app_state_with_import_job_queue(
+        ArtistRepoMock::new(),
+        import_dir.path().to_str().unwrap(),
+        web_server_dir.path().to_str().unwrap()
+    ));
+
+    let mut req = Request::builder()
+        .method("POST")
+        .uri("/drop/import/jobs")
+        .body(Empty::new())
+        .unwrap();
+    req.extensions_mut().insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+    let response = app.clone().oneshot(req).await.unwrap();
+
+    assert_eq!(StatusCode::ACCEPTED, response.status());
+    let location = response.headers()["location"].to_str().unwrap().to_string();
+    let (status, job) = finished_import_job(&app, &location).await;
+    assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, status);
+    assert_eq!(Some("failed"), job["state"].as_str());
+    assert_eq!(None, job["files"][0]["server_error"].as_bool());
+}
+
 #[tokio::test]
-async fn drop_import_ok() {
+async fn tag_import_returns_not_found_when_called_with_ip_not_accepted() {
     // Arrange: use in-memory repo
     let token_repo = InMemoryTokenRepo::default();
     let tag_repo = InMemoryTagRepo::default();
     let ip_repo = InMemoryIpRepo::default();
-    let conf = Conf::new(String::from(""), String::from("127.0.0.1:8000"), 10, Vec::new(), String::from("tests/resources/import_path"), None, None);
+    let conf = Conf::new(String::from(""), String::from("127.0.0.1:8000"), 10, Vec::new(), String::from(""), None, None);
     let app_state = AppState {
         token_repo: Arc::new(token_repo.clone()),
         tag_repo: Arc::new(tag_repo.clone()),
         ip_repo: Arc::new(ip_repo.clone()),
+        api_key_repo: Arc::new(InMemoryApiKeyRepo::default()),
+        import_repo: Arc::new(InMemoryImportRepo::default()),
+        import_job_queue: None,
+        media_store: create_media_store(&conf),
         conf,
         entity_repositories: Vec::new(),
         service_conf: ServiceConf::new(
@@ -731,6 +976,8 @@ async fn drop_import_ok() {
                 Arc::new(DropRepoMock::new()),
                 Arc::new(ArtistRepoMock::new()),
                 Arc::new(PlaylistRepoMock::new()),
+                Arc::new(ArtworkRepoMock::new()),
+                Arc::new(TrackRepoMock::new()),
             )
         ),
     };
@@ -740,23 +987,22 @@ async fn drop_import_ok() {
         .uri("/drop/import")
         .body(Empty::new())
         .unwrap();
-    req.extensions_mut().insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+    req.extensions_mut().insert(ConnectInfo(SocketAddr::from(([12, 0, 0, 1], 12345))));
     let response = app.oneshot(req).await.unwrap();
 
-    assert_eq!(StatusCode::OK, response.status());
+    assert_eq!(StatusCode::NOT_FOUND, response.status());
 }
 
-#[tokio::test]
-async fn tag_import_returns_not_found_when_called_with_ip_not_accepted() {
-    // Arrange: use in-memory repo
-    let token_repo = InMemoryTokenRepo::default();
-    let tag_repo = InMemoryTagRepo::default();
-    let ip_repo = InMemoryIpRepo::default();
-    let conf = Conf::new(String::from(""), String::from("127.0.0.1:8000"), 10, Vec::new(), String::from(""), None, None);
-    let app_state = AppState {
-        token_repo: Arc::new(token_repo.clone()),
-        tag_repo: Arc::new(tag_repo.clone()),
-        ip_repo: Arc::new(ip_repo.clone()),
+fn init_app_state_with_api_keys(api_key_repo: InMemoryApiKeyRepo) -> AppState {
+    let conf = Conf::new(String::from(""), String::from("127.0.0.1:8000"), 10, Vec::new(), String::from("tests/resources/import_path"), None, None);
+    AppState {
+        token_repo: Arc::new(InMemoryTokenRepo::default()),
+        tag_repo: Arc::new(InMemoryTagRepo::default()),
+        ip_repo: Arc::new(InMemoryIpRepo::default()),
+        api_key_repo: Arc::new(api_key_repo),
+        import_repo: Arc::new(InMemoryImportRepo::default()),
+        import_job_queue: None,
+        media_store: create_media_store(&conf),
         conf,
         entity_repositories: Vec::new(),
         service_conf: ServiceConf::new(
@@ -764,17 +1010,697 @@ async fn tag_import_returns_not_found_when_called_with_ip_not_accepted() {
                 Arc::new(DropRepoMock::new()),
                 Arc::new(ArtistRepoMock::new()),
                 Arc::new(PlaylistRepoMock::new()),
+                Arc::new(ArtworkRepoMock::new()),
+                Arc::new(TrackRepoMock::new()),
             )
         ),
-    };
-    let app = app(app_state);
+    }
+}
+
+async fn save_api_key(api_key_repo: &InMemoryApiKeyRepo, scopes: Vec<&str>, expires_at: Option<NaiveDateTime>) -> (i32, String) {
+    let plain_key = generate_api_key();
+    let api_key = ApiKey::new(
+        0,
+        "automation".to_string(),
+        hash_api_key(&plain_key),
+        scopes.iter().map(|scope| scope.to_string()).collect(),
+        expires_at,
+        None
+    );
+    (api_key_repo.save_or_update(&api_key).await.unwrap(), plain_key)
+}
+
+#[tokio::test]
+async fn drop_import_is_allowed_from_other_host_with_import_api_key() {
+    let api_key_repo = InMemoryApiKeyRepo::default();
+    let (api_key_id, plain_key) = save_api_key(&api_key_repo, vec!["import"], None).await;
+    let app = app(init_app_state_with_api_keys(api_key_repo.clone()));
 
     let mut req = Request::builder()
         .uri("/drop/import")
+        .header(AUTHORIZATION, format!("Bearer {plain_key}"))
         .body(Empty::new())
         .unwrap();
     req.extensions_mut().insert(ConnectInfo(SocketAddr::from(([12, 0, 0, 1], 12345))));
     let response = app.oneshot(req).await.unwrap();
 
+    // the key is accepted, the import job queue is not started
+    assert_eq!(StatusCode::FAILED_DEPENDENCY, response.status());
+    assert!(api_key_repo.get(api_key_id).await.unwrap().last_used_at().is_some());
+}
+
+#[tokio::test]
+async fn drop_import_returns_forbidden_when_api_key_has_not_import_scope() {
+    let api_key_repo = InMemoryApiKeyRepo::default();
+    let (_, plain_key) = save_api_key(&api_key_repo, vec!["admin:read", "admin:write"], None).await;
+    let app = app(init_app_state_with_api_keys(api_key_repo));
+
+    let mut req = Request::builder()
+        .uri("/drop/import")
+        .header(AUTHORIZATION, format!("Bearer {plain_key}"))
+        .body(Empty::new())
+        .unwrap();
+    req.extensions_mut().insert(ConnectInfo(SocketAddr::from(([12, 0, 0, 1], 12345))));
+    let response = app.oneshot(req).await.unwrap();
+
+    assert_eq!(StatusCode::FORBIDDEN, response.status());
+}
+
+#[tokio::test]
+async fn drop_import_returns_unauthorized_when_api_key_is_expired_or_unknown() {
+    let api_key_repo = InMemoryApiKeyRepo::default();
+    let expired_at = Utc::now().naive_utc() - chrono::Duration::hours(1);
+    let (_, expired_key) = save_api_key(&api_key_repo, vec!["import"], Some(expired_at)).await;
+    let app = app(init_app_state_with_api_keys(api_key_repo));
+
+    for key in [expired_key, generate_api_key()] {
+        let mut req = Request::builder()
+            .uri("/drop/import")
+            .header(AUTHORIZATION, format!("Bearer {key}"))
+            .body(Empty::new())
+            .unwrap();
+        req.extensions_mut().insert(ConnectInfo(SocketAddr::from(([12, 0, 0, 1], 12345))));
+        let response = app.clone().oneshot(req).await.unwrap();
+
+        assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+    }
+}
+
+async fn admin_request(app: &axum::Router, method: &str, uri: &str, key: &str) -> (StatusCode, serde_json::Value) {
+    let mut req = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(AUTHORIZATION, format!("Bearer {key}"))
+        .body(Body::empty())
+        .unwrap();
+    req.extensions_mut().insert(ConnectInfo(SocketAddr::from(([12, 0, 0, 1], 12345))));
+    let response = app.clone().oneshot(req).await.unwrap();
+    let status = response.status();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    (status, serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null))
+}
+
+#[tokio::test]
+async fn admin_api_keys_need_their_admin_scope() {
+    let api_key_repo = InMemoryApiKeyRepo::default();
+    let (import_key_id, import_key) = save_api_key(&api_key_repo, vec!["import"], None).await;
+    let (_, read_key) = save_api_key(&api_key_repo, vec!["admin:read"], None).await;
+    let app = app(init_app_state_with_api_keys(api_key_repo));
+
+    let uri = format!("/admin/api-keys/{import_key_id}");
+    assert_eq!(StatusCode::FORBIDDEN, admin_request(&app, "GET", &uri, &import_key).await.0);
+    assert_eq!(StatusCode::UNAUTHORIZED, admin_request(&app, "GET", &uri, &generate_api_key()).await.0);
+    let (status, json) = admin_request(&app, "GET", &uri, &read_key).await;
+    assert_eq!(StatusCode::OK, status);
+    assert_eq!(serde_json::json!(["import"]), json["scopes"]);
+    assert!(json.get("key_hash").is_none());
+    assert_eq!(StatusCode::FORBIDDEN, admin_request(&app, "POST", &format!("{uri}/rotate"), &read_key).await.0);
+}
+
+#[tokio::test]
+async fn rotated_api_key_keeps_the_old_one_valid_during_the_grace_period() {
+    let api_key_repo = InMemoryApiKeyRepo::default();
+    let (admin_key_id, admin_key) = save_api_key(&api_key_repo, vec!["admin:read", "admin:write"], None).await;
+    let app = app(init_app_state_with_api_keys(api_key_repo.clone()));
+
+    let (status, json) = admin_request(&app, "POST", &format!("/admin/api-keys/{admin_key_id}/rotate"), &admin_key).await;
+    assert_eq!(StatusCode::CREATED, status);
+    let new_key = json["key"].as_str().unwrap();
+    assert_eq!(StatusCode::OK, admin_request(&app, "GET", &format!("/admin/api-keys/{}", json["id"]), new_key).await.0);
+    // the old key is still accepted until the end of the grace period
+    assert_eq!(StatusCode::OK, admin_request(&app, "GET", &format!("/admin/api-keys/{admin_key_id}"), &admin_key).await.0);
+    assert!(api_key_repo.get(admin_key_id).await.unwrap().expires_at().is_some());
+}
+
+fn init_app_state_for_upload(artist_repo: ArtistRepoMock, web_server_path: &str, import_path: &str, max_upload_size: u64) -> AppState {
+    let staging_path = format!("{import_path}/staging");
+    let conf = Conf::new(
+        String::from(""),
+        String::from("127.0.0.1:8000"),
+        10,
+        Vec::new(),
+        import_path.to_string(),
+        None,
+        Some(web_server_path.to_string())
+    )
+        .with_staging_path(&staging_path)
+        .with_max_upload_size(max_upload_size);
+    AppState {
+        token_repo: Arc::new(InMemoryTokenRepo::default()),
+        tag_repo: Arc::new(InMemoryTagRepo::default()),
+        ip_repo: Arc::new(InMemoryIpRepo::default()),
+        api_key_repo: Arc::new(InMemoryApiKeyRepo::default()),
+        import_repo: Arc::new(InMemoryImportRepo::default()),
+        import_job_queue: None,
+        media_store: create_media_store(&conf),
+        conf,
+        entity_repositories: Vec::new(),
+        service_conf: ServiceConf::new(
+            DropService::new(
+                Arc::new(DropRepoMock::new()),
+                Arc::new(artist_repo),
+                Arc::new(PlaylistRepoMock::new()),
+                Arc::new(ArtworkRepoMock::new()),
+                Arc::new(TrackRepoMock::new()),
+            )
+        ),
+    }
+}
+
+#[tokio::test]
+async fn drop_upload_creates_drop_and_returns_ids() {
+    let artist_repo = ArtistRepoMock::new();
+    artist_repo.map_by_name().write().unwrap().insert("Cool Rasta".to_string(), Artist::new(7, "Cool Rasta".to_string()));
+    let web_server_dir = TempDir::new().unwrap();
+    let import_dir = TempDir::new().unwrap();
+    let app = app(init_app_state_for_upload(
+        artist_repo,
+        web_server_dir.path().to_str().unwrap(),
+        import_dir.path().to_str().unwrap(),
+        1024 * 1024
+    ));
+
+    let archive = std::fs::read("tests/resources/import_path/correct_tar_gz/drop_ok.tar.gz").unwrap();
+    let mut req = Request::builder()
+        .method("POST")
+        .uri("/drop/import")
+        .body(Body::from(archive.clone()))
+        .unwrap();
+    req.extensions_mut().insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+    let response = app.clone().oneshot(req).await.unwrap();
+
+    assert_eq!(StatusCode::CREATED, response.status());
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(Some(7), json.get("artist_id").and_then(|v| v.as_i64()));
+    assert_eq!(Some(0), json.get("playlist_id").and_then(|v| v.as_i64()));
+    assert_eq!(Some(0), json.get("drop_id").and_then(|v| v.as_i64()));
+    let track_contents = LocalMediaStore::new(web_server_dir.path().to_path_buf()).list(TRACK_CONTENT_PREFIX).await.unwrap();
+    assert_eq!(3, track_contents.len());
+    // the staged archive is moved to the processed directory once imported
+    assert_eq!(0, std::fs::read_dir(import_dir.path().join("staging")).unwrap().count());
+    assert_eq!(1, std::fs::read_dir(import_dir.path().join("processed")).unwrap().count());
+
+    // the same archive is not imported twice
+    let mut req = Request::builder()
+        .method("POST")
+        .uri("/drop/import")
+        .body(Body::from(archive))
+        .unwrap();
+    req.extensions_mut().insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+    let response = app.oneshot(req).await.unwrap();
+
+    assert_eq!(StatusCode::CONFLICT, response.status());
+    assert_eq!(1, std::fs::read_dir(web_server_dir.path()).unwrap().count());
+}
+
+#[tokio::test]
+async fn drop_upload_returns_payload_too_large_when_archive_exceeds_max_upload_size() {
+    let web_server_dir = TempDir::new().unwrap();
+    let staging_dir = TempDir::new().unwrap();
+    let app = app(init_app_state_for_upload(
+        ArtistRepoMock::new(),
+        web_server_dir.path().to_str().unwrap(),
+        staging_dir.path().to_str().unwrap(),
+        16
+    ));
+
+    let archive = std::fs::read("tests/resources/import_path/correct_tar_gz/drop_ok.tar.gz").unwrap();
+    let mut req = Request::builder()
+        .method("POST")
+        .uri("/drop/import")
+        .body(Body::from(archive))
+        .unwrap();
+    req.extensions_mut().insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+    let response = app.oneshot(req).await.unwrap();
+
+    assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, response.status());
+    assert_eq!(0, std::fs::read_dir(staging_dir.path().join("staging")).unwrap().count());
+}
+
+#[tokio::test]
+async fn drop_upload_returns_unprocessable_entity_when_archive_is_invalid() {
+    let web_server_dir = TempDir::new().unwrap();
+    let staging_dir = TempDir::new().unwrap();
+    let app = app(init_app_state_for_upload(
+        ArtistRepoMock::new(),
+        web_server_dir.path().to_str().unwrap(),
+        staging_dir.path().to_str().unwrap(),
+        1024
+    ));
+
+    let mut req = Request::builder()
+        .method("POST")
+        .uri("/drop/import")
+        .body(Body::from("not a tar.gz archive"))
+        .unwrap();
+    req.extensions_mut().insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+    let response = app.oneshot(req).await.unwrap();
+
+    assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, response.status());
+}
+
+#[tokio::test]
+async fn drop_import_job_is_queued_and_its_state_is_reported() {
+    let import_dir = TempDir::new().unwrap();
+    let import_path = import_dir.path().join("import");
+    std::fs::create_dir(&import_path).unwrap();
+    std::fs::copy("tests/resources/import_path/correct_tar_gz/drop_ok.tar.gz", import_path.join("drop_ok.tar.gz")).unwrap();
+    let web_server_dir = TempDir::new().unwrap();
+    let artist_repo = ArtistRepoMock::new();
+    artist_repo.map_by_name().write().unwrap().insert("Cool Rasta".to_string(), Artist::new(7, "Cool Rasta".to_string()));
+    let app = app(init_app_state_with_import_job_queue(
+        artist_repo,
+        import_path.to_str().unwrap(),
+        web_server_dir.path().to_str().unwrap()
+    ));
+
+    let mut req = Request::builder()
+        .method("POST")
+        .uri("/drop/import/jobs")
+        .body(Empty::new())
+        .unwrap();
+    req.extensions_mut().insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+    let response = app.clone().oneshot(req).await.unwrap();
+
+    assert_eq!(StatusCode::ACCEPTED, response.status());
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let job_id = json.get("id").and_then(|v| v.as_i64()).unwrap();
+
+    let mut state = String::new();
+    for _ in 0..100 {
+        let mut req = Request::builder()
+            .uri(format!("/drop/import/jobs/{job_id}"))
+            .body(Empty::new())
+            .unwrap();
+        req.extensions_mut().insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+        let response = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::OK, response.status());
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        state = json.get("state").and_then(|v| v.as_str()).unwrap().to_string();
+        if state != "queued" && state != "running" {
+            assert_eq!(Some("imported"), json["files"][0]["state"].as_str());
+            assert_eq!(Some(7), json["files"][0]["artist_id"].as_i64());
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!("succeeded", state);
+
+    let mut req = Request::builder()
+        .uri("/drop/import/jobs/999")
+        .body(Empty::new())
+        .unwrap();
+    req.extensions_mut().insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+    let response = app.oneshot(req).await.unwrap();
+    assert_eq!(StatusCode::NOT_FOUND, response.status());
+}
+
+// tag1 is bound to the album 4 of the playlist 5 and whose artwork is 9, tag2 to no drop
+// SHA-256 of "0123456789", the bytes of the second track of the bound tag's drop
+const STORED_TRACK_HASH: &str = "84d89877f0d4041efb6bf91a16f0248f2fd573e6af05c19f96bedb9f882f7882";
+
+fn init_app_state_with_bound_tag(redirect_uri: &str, web_server_path: &str) -> (AppState, Uuid, Uuid) {
+    let token_repo = InMemoryTokenRepo::default();
+    let bound_token = Uuid::new_v4();
+    token_repo.save_token(&Token::new(bound_token, NaiveDateTime::default(), "tag1".to_string()));
+    let unbound_token = Uuid::new_v4();
+    token_repo.save_token(&Token::new(unbound_token, NaiveDateTime::default(), "tag2".to_string()));
+    let tag_repo = InMemoryTagRepo::default();
+    tag_repo.save(&Tag::new("tag1".to_string(), NaiveDateTime::default()).with_drop_id(4));
+    tag_repo.save(&Tag::new("tag2".to_string(), NaiveDateTime::default()));
+    let drop_repo = DropRepoMock::new();
+    drop_repo.map().write().unwrap().insert(
+        4,
+        Drop::new(4, 7, DropType::Album.id(), 9, 5).with_release(Release::new(None, Some("Irie Records".to_string()), None, None)),
+    );
+    let artist_repo = ArtistRepoMock::new();
+    artist_repo.map_by_id().write().unwrap().insert(7, Artist::new(7, "Cool Rasta".to_string()));
+    let playlist_repo = PlaylistRepoMock::new();
+    playlist_repo.map().write().unwrap().insert(5, Playlist::new(5, "Sunrise".to_string()));
+    let track_repo = TrackRepoMock::new();
+    track_repo.tracks().write().unwrap().extend([
+        Track::new(1, 5, 1, "tracks/01.flac".to_string(), Some("Dawn".to_string()), Some(180))
+            .with_isrc(Some("USRC17607839".to_string()))
+            .with_cue_points(vec![
+                CuePoint::new(0, "Dawn".to_string()),
+                CuePoint::new(95, "Morning".to_string()).with_artist(Some("Cool Rasta".to_string())),
+            ]),
+        Track::new(2, 5, 2, "tracks/02.flac".to_string(), None, None).with_content_hash(STORED_TRACK_HASH),
+    ]);
+    track_repo.contents().write().unwrap().insert(STORED_TRACK_HASH.to_string(), (10, 1));
+    let artwork_repo = ArtworkRepoMock::new();
+    artwork_repo.map().write().unwrap().insert(9, Artwork::new(9, "image/png".to_string(), 1400, 1400));
+    let conf = Conf::new(
+        redirect_uri.to_string(),
+        String::from("127.0.0.1:8000"),
+        10,
+        Vec::new(),
+        String::from(""),
+        None,
+        Some(web_server_path.to_string())
+    );
+    let app_state = AppState {
+        token_repo: Arc::new(token_repo),
+        tag_repo: Arc::new(tag_repo),
+        ip_repo: Arc::new(InMemoryIpRepo::default()),
+        api_key_repo: Arc::new(InMemoryApiKeyRepo::default()),
+        import_repo: Arc::new(InMemoryImportRepo::default()),
+        import_job_queue: None,
+        media_store: create_media_store(&conf),
+        conf,
+        entity_repositories: Vec::new(),
+        service_conf: ServiceConf::new(
+            DropService::new(
+                Arc::new(drop_repo),
+                Arc::new(artist_repo),
+                Arc::new(playlist_repo),
+                Arc::new(artwork_repo),
+                Arc::new(track_repo),
+            )
+        ),
+    };
+    (app_state, bound_token, unbound_token)
+}
+
+async fn get_artwork(app: &axum::Router, uri: &str, token: Option<Uuid>) -> axum::response::Response {
+    let mut req_builder = Request::builder().uri(uri);
+    if let Some(token) = token {
+        req_builder = req_builder.header(TOKEN_NAME, token.to_string());
+    }
+    let mut req = req_builder.body(Body::empty()).unwrap();
+    req.extensions_mut().insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+    app.clone().oneshot(req).await.unwrap()
+}
+
+#[tokio::test]
+async fn artwork_of_the_token_drop_is_served_in_each_size() {
+    let web_server_dir = TempDir::new().unwrap();
+    let artwork_dir = web_server_dir.path().join("artwork_9");
+    std::fs::create_dir(&artwork_dir).unwrap();
+    std::fs::write(artwork_dir.join("original.png"), "png content").unwrap();
+    std::fs::write(artwork_dir.join("medium.jpg"), "jpeg content").unwrap();
+    std::fs::write(artwork_dir.join("thumbnail.webp"), "webp content").unwrap();
+    let (app_state, bound_token, _) = init_app_state_with_bound_tag("", web_server_dir.path().to_str().unwrap());
+    let app = app(app_state);
+
+    for (size, content_type, content) in [
+        ("original", "image/png", "png content"),
+        ("medium", "image/jpeg", "jpeg content"),
+        ("thumbnail", "image/webp", "webp content"),
+    ] {
+        let response = get_artwork(&app, &format!("/artwork/{size}"), Some(bound_token)).await;
+        assert_eq!(StatusCode::OK, response.status(), "{size}");
+        assert_eq!(content_type, response.headers().get("content-type").unwrap().to_str().unwrap());
+        assert_eq!(content.as_bytes(), response.into_body().collect().await.unwrap().to_bytes());
+    }
+}
+
+#[tokio::test]
+async fn playlist_tracks_and_artwork_of_an_unpublished_drop_are_not_found() {
+    let web_server_dir = TempDir::new().unwrap();
+    let artwork_dir = web_server_dir.path().join("artwork_9");
+    std::fs::create_dir(&artwork_dir).unwrap();
+    std::fs::write(artwork_dir.join("original.png"), "png content").unwrap();
+    let media_store = LocalMediaStore::new(web_server_dir.path().to_path_buf());
+    media_store.put(&track_content_key(STORED_TRACK_HASH).unwrap(), MediaContent::from_bytes(&b"0123456789"[..])).await.unwrap();
+    // the tag stays bound, as after a restart which binds the tags of app.toml again
+    let (app_state, bound_token, _) = init_app_state_with_bound_tag("", web_server_dir.path().to_str().unwrap());
+    let drop_repo = app_state.service_conf.drop_service().drop_repository();
+    let drop = drop_repo.get(4).await.unwrap();
+    drop_repo.save_or_update(&drop.with_published(false)).await.unwrap();
+    let app = app(app_state);
+
+    assert_eq!(StatusCode::NOT_FOUND, get_artwork(&app, "/artwork/original", Some(bound_token)).await.status());
+    assert_eq!(StatusCode::NOT_FOUND, get_artwork(&app, "/playlist", Some(bound_token)).await.status());
+    assert_eq!(StatusCode::NOT_FOUND, get_with_token(&app, "/track/2", bound_token).await.status());
+    assert_eq!(StatusCode::NOT_FOUND, get_with_token(&app, "/track/2/content", bound_token).await.status());
+}
+
+#[tokio::test]
+async fn artwork_is_not_found_for_unknown_sizes_and_tags_without_drop() {
+    let web_server_dir = TempDir::new().unwrap();
+    let (app_state, bound_token, unbound_token) = init_app_state_with_bound_tag("", web_server_dir.path().to_str().unwrap());
+    let app = app(app_state);
+
+    assert_eq!(StatusCode::NOT_FOUND, get_artwork(&app, "/artwork/huge", Some(bound_token)).await.status());
+    assert_eq!(StatusCode::NOT_FOUND, get_artwork(&app, "/artwork/large", Some(unbound_token)).await.status());
+    // the variant files of the artwork are missing
+    assert_eq!(StatusCode::NOT_FOUND, get_artwork(&app, "/artwork/large", Some(bound_token)).await.status());
+    assert_eq!(StatusCode::UNAUTHORIZED, get_artwork(&app, "/artwork/large", None).await.status());
+    assert_eq!(StatusCode::UNAUTHORIZED, get_artwork(&app, "/artwork/large", Some(Uuid::new_v4())).await.status());
+}
+
+/// Web server answering the same playlist.toml and first track for every tag, in place of the apache container
+async fn start_playlist_web_server() -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let router = axum::Router::new()
+        .route(
+            "/tag/{tag}/playlist.toml",
+            axum::routing::get(|| async { "artist_name = \"Cool Rasta\"\nplaylist_name = \"Sunset\"\ntracks = [\"track_1\"]\n" })
+        )
+        .route("/tag/{tag}/playlist_1.m3u8", axum::routing::get(|| async { "#EXTM3U\n" }));
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+    format!("http://{addr}")
+}
+
+async fn get_with_token(app: &axum::Router, uri: &str, token: Uuid) -> axum::response::Response {
+    let mut req = Request::builder()
+        .uri(uri)
+        .header(TOKEN_NAME, token.to_string())
+        .body(Body::empty())
+        .unwrap();
+    req.extensions_mut().insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+    app.clone().oneshot(req).await.unwrap()
+}
+
+#[tokio::test]
+async fn playlist_of_a_bound_tag_comes_from_the_database() {
+    let redirect_uri = start_playlist_web_server().await;
+    let web_server_dir = TempDir::new().unwrap();
+    let (app_state, bound_token, unbound_token) = init_app_state_with_bound_tag(&redirect_uri, web_server_dir.path().to_str().unwrap());
+    let app = app(app_state);
+
+    let response = get_with_token(&app, "/playlist", bound_token).await;
+    assert_eq!(StatusCode::OK, response.status());
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(Some("Cool Rasta"), json["artist_name"].as_str());
+    assert_eq!(Some("Sunrise"), json["playlist_name"].as_str());
+    // the track without title is named after its file
+    assert_eq!(serde_json::json!(["Dawn", "tracks/02.flac"]), json["tracks"]);
+
+    // the other tags keep the playlist.toml of the web server
+    let response = get_with_token(&app, "/playlist", unbound_token).await;
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(Some("Sunset"), json["playlist_name"].as_str());
+}
+
+#[tokio::test]
+async fn track_of_a_bound_tag_is_read_from_its_track_row() {
+    let redirect_uri = start_playlist_web_server().await;
+    let web_server_dir = TempDir::new().unwrap();
+    let media_store = LocalMediaStore::new(web_server_dir.path().to_path_buf());
+    media_store.put(&track_content_key(STORED_TRACK_HASH).unwrap(), MediaContent::from_bytes(&b"0123456789"[..])).await.unwrap();
+    let (app_state, bound_token, unbound_token) = init_app_state_with_bound_tag(&redirect_uri, web_server_dir.path().to_str().unwrap());
+    let app = app(app_state);
+
+    // the bytes of the content store rather than the m3u8 of the web server
+    let response = get_with_token(&app, "/track/2", bound_token).await;
+    assert_eq!(StatusCode::OK, response.status());
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(&b"0123456789"[..], &body[..]);
+
+    // the other tags keep the track playlists of the web server
+    let response = get_with_token(&app, "/track/1", unbound_token).await;
+    assert_eq!(StatusCode::OK, response.status());
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(&b"#EXTM3U\n"[..], &body[..]);
+
+    // the drop has 2 tracks
+    let response = get_with_token(&app, "/track/3", bound_token).await;
+    assert_eq!(StatusCode::NOT_FOUND, response.status());
+}
+
+#[tokio::test]
+async fn track_parts_are_read_from_the_media_store_with_ranges() {
+    let media_dir = TempDir::new().unwrap();
+    std::fs::create_dir_all(media_dir.path().join("tag/tag1")).unwrap();
+    std::fs::write(media_dir.path().join("tag/tag1/track_1.ts"), "0123456789").unwrap();
+    let (mut app_state, bound_token, _) = init_app_state_with_bound_tag("", media_dir.path().to_str().unwrap());
+    app_state.media_store = Arc::new(LocalMediaStore::new(media_dir.path().to_path_buf()));
+    let app = app(app_state);
+
+    let response = get_with_token(&app, "/track/part/track_1.ts", bound_token).await;
+    assert_eq!(StatusCode::OK, response.status());
+    assert_eq!(&b"0123456789"[..], &response.into_body().collect().await.unwrap().to_bytes()[..]);
+
+    let mut req = Request::builder()
+        .uri("/track/part/track_1.ts")
+        .header(TOKEN_NAME, bound_token.to_string())
+        .header("range", "bytes=2-5")
+        .body(Body::empty())
+        .unwrap();
+    req.extensions_mut().insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+    let response = app.clone().oneshot(req).await.unwrap();
+    assert_eq!(StatusCode::PARTIAL_CONTENT, response.status());
+    assert_eq!("bytes 2-5/10", response.headers().get("content-range").unwrap().to_str().unwrap());
+    assert_eq!("4", response.headers().get("content-length").unwrap().to_str().unwrap());
+    assert_eq!(&b"2345"[..], &response.into_body().collect().await.unwrap().to_bytes()[..]);
+
+    let response = get_with_token(&app, "/track/part/track_2.ts", bound_token).await;
     assert_eq!(StatusCode::NOT_FOUND, response.status());
 }
+
+#[tokio::test]
+async fn track_contents_are_read_by_hash_or_from_the_playlist_directory() {
+    let web_server_dir = TempDir::new().unwrap();
+    let media_store = LocalMediaStore::new(web_server_dir.path().to_path_buf());
+    media_store.put(&track_content_key(STORED_TRACK_HASH).unwrap(), MediaContent::from_bytes(&b"0123456789"[..])).await.unwrap();
+    // the first track was imported before the content store
+    media_store.put("playlist_5/track_1", MediaContent::from_bytes(&b"legacy"[..])).await.unwrap();
+    let (app_state, bound_token, unbound_token) = init_app_state_with_bound_tag("", web_server_dir.path().to_str().unwrap());
+    let app = app(app_state);
+
+    let response = get_with_token(&app, "/track/1/content", bound_token).await;
+    assert_eq!(StatusCode::OK, response.status());
+    assert_eq!(&b"legacy"[..], &response.into_body().collect().await.unwrap().to_bytes()[..]);
+
+    let mut req = Request::builder()
+        .uri("/track/2/content")
+        .header(TOKEN_NAME, bound_token.to_string())
+        .header("range", "bytes=4-")
+        .body(Body::empty())
+        .unwrap();
+    req.extensions_mut().insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+    let response = app.clone().oneshot(req).await.unwrap();
+    assert_eq!(StatusCode::PARTIAL_CONTENT, response.status());
+    assert_eq!("bytes 4-9/10", response.headers().get("content-range").unwrap().to_str().unwrap());
+    assert_eq!(&b"456789"[..], &response.into_body().collect().await.unwrap().to_bytes()[..]);
+
+    assert_eq!(StatusCode::NOT_FOUND, get_with_token(&app, "/track/3/content", bound_token).await.status());
+    assert_eq!(StatusCode::NOT_FOUND, get_with_token(&app, "/track/1/content", unbound_token).await.status());
+}
+
+#[tokio::test]
+async fn track_content_with_a_damaged_hash_is_an_internal_error() {
+    let web_server_dir = TempDir::new().unwrap();
+    let (app_state, bound_token, _) = init_app_state_with_bound_tag("", web_server_dir.path().to_str().unwrap());
+    app_state.service_conf.drop_service().track_repository()
+        .save_or_update(&Track::new(0, 5, 3, "tracks/03.flac".to_string(), None, None).with_content_hash("8"))
+        .await
+        .unwrap();
+    let app = app(app_state);
+
+    assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, get_with_token(&app, "/track/3/content", bound_token).await.status());
+}
+
+#[tokio::test]
+async fn playlist_gives_the_type_of_the_tag_drop() {
+    let redirect_uri = start_playlist_web_server().await;
+    let web_server_dir = TempDir::new().unwrap();
+    let (app_state, bound_token, unbound_token) = init_app_state_with_bound_tag(&redirect_uri, web_server_dir.path().to_str().unwrap());
+    let app = app(app_state);
+
+    for (token, drop_type) in [(bound_token, Some("album")), (unbound_token, None)] {
+        let mut req = Request::builder()
+            .uri("/playlist")
+            .header(TOKEN_NAME, token.to_string())
+            .body(Body::empty())
+            .unwrap();
+        req.extensions_mut().insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+        let response = app.clone().oneshot(req).await.unwrap();
+
+        assert_eq!(StatusCode::OK, response.status());
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(drop_type, json.get("drop_type").and_then(|v| v.as_str()));
+    }
+}
+
+async fn get_api_json(app: &axum::Router, uri: &str) -> (StatusCode, serde_json::Value) {
+    let mut req = Request::builder().uri(uri).body(Body::empty()).unwrap();
+    req.extensions_mut().insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+    let response = app.clone().oneshot(req).await.unwrap();
+    let status = response.status();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    (status, serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null))
+}
+
+#[tokio::test]
+async fn catalog_api_serves_artists_drops_and_playlists_without_internal_fields() {
+    let (app_state, _, _) = init_app_state_with_bound_tag("", "");
+    let app = app(app_state);
+
+    let (status, json) = get_api_json(&app, "/api/artists/7").await;
+    assert_eq!(StatusCode::OK, status);
+    assert_eq!(serde_json::json!({"id": 7, "name": "Cool Rasta"}), json);
+
+    let (status, json) = get_api_json(&app, "/api/artists/7/drops").await;
+    assert_eq!(StatusCode::OK, status);
+    assert_eq!(
+        serde_json::json!({"items": [{
+            "id": 4, "artist_id": 7, "playlist_id": 5, "drop_type": "album", "has_artwork": true,
+            "release_date": null, "label": "Irie Records", "genre": null, "description": null, "credits": []
+        }], "next_cursor": null}),
+        json
+    );
+
+    let (status, json) = get_api_json(&app, "/api/drops/4").await;
+    assert_eq!(StatusCode::OK, status);
+    assert_eq!(None, json.get("artwork_id"));
+    assert_eq!(None, json.get("version"));
+
+    let (status, json) = get_api_json(&app, "/api/playlists/5").await;
+    assert_eq!(StatusCode::OK, status);
+    assert_eq!(
+        serde_json::json!({"id": 5, "name": "Sunrise", "tracks": [
+            {"position": 1, "title": "Dawn", "duration_seconds": 180, "isrc": "USRC17607839", "explicit": false, "disc_number": null, "track_number": null,
+             "cue_points": [{"start_seconds": 0, "title": "Dawn", "artist": null}, {"start_seconds": 95, "title": "Morning", "artist": "Cool Rasta"}]},
+            {"position": 2, "title": null, "duration_seconds": null, "isrc": null, "explicit": false, "disc_number": null, "track_number": null, "cue_points": []}
+        ]}),
+        json
+    );
+
+    for uri in ["/api/artists/8", "/api/artists/8/drops", "/api/drops/5", "/api/playlists/4"] {
+        assert_eq!(StatusCode::NOT_FOUND, get_api_json(&app, uri).await.0, "{uri}");
+    }
+}
+
+#[tokio::test]
+async fn catalog_api_hides_the_playlist_of_an_unpublished_drop() {
+    let (app_state, _, _) = init_app_state_with_bound_tag("", "");
+    let drop_repo = app_state.service_conf.drop_service().drop_repository();
+    let drop = drop_repo.get(4).await.unwrap();
+    drop_repo.save_or_update(&drop.with_published(false)).await.unwrap();
+    let app = app(app_state);
+
+    for uri in ["/api/drops/4", "/api/playlists/5"] {
+        assert_eq!(StatusCode::NOT_FOUND, get_api_json(&app, uri).await.0, "{uri}");
+    }
+}
+
+#[tokio::test]
+async fn catalog_api_pages_artists_in_the_requested_order() {
+    let (app_state, _, _) = init_app_state_with_bound_tag("", "");
+    let artist_repo = app_state.service_conf.drop_service().artist_repository();
+    for (id, name) in [(8, "Alpha Blondy"), (9, "Burning Spear")] {
+        drop_reverse_proxy::repository::RepoByName::save_or_update(artist_repo, &Artist::new(id, name.to_string())).await.unwrap();
+    }
+    let app = app(app_state);
+
+    let (status, json) = get_api_json(&app, "/api/artists?sort=name&order=desc&limit=2").await;
+    assert_eq!(StatusCode::OK, status);
+    assert_eq!(serde_json::json!([7, 9]), serde_json::json!(json["items"].as_array().unwrap().iter().map(|artist| &artist["id"]).collect::<Vec<_>>()));
+    assert_eq!(serde_json::json!(9), json["next_cursor"]);
+
+    let (_, json) = get_api_json(&app, "/api/artists?sort=name&order=desc&limit=2&cursor=9").await;
+    assert_eq!(serde_json::json!([{"id": 8, "name": "Alpha Blondy"}]), json["items"]);
+    assert_eq!(serde_json::Value::Null, json["next_cursor"]);
+
+    assert_eq!(StatusCode::BAD_REQUEST, get_api_json(&app, "/api/artists?sort=version").await.0);
+}
@@ -0,0 +1,60 @@
+use drop_reverse_proxy::media::{delete_prefix, ByteRange, MediaContent, MediaStore, MediaStoreError, S3MediaStore};
+use testcontainers::runners::AsyncRunner;
+use testcontainers::ContainerAsync;
+use testcontainers_modules::minio::MinIO;
+
+/// Starts MinIO, an S3 compatible service whose credentials are minioadmin/minioadmin, returns its endpoint
+async fn start_minio_container() -> Result<(ContainerAsync<MinIO>, String), Box<dyn std::error::Error>> {
+    let minio_container = MinIO::default().start().await?;
+    let host = minio_container.get_host().await?;
+    let port = minio_container.get_host_port_ipv4(9000).await?;
+    Ok((minio_container, format!("http://{host}:{port}")))
+}
+
+#[tokio::test]
+async fn test_s3_media_store_integration() {
+    // 1. Start MinIO container
+    let (_container_guard, endpoint) = start_minio_container()
+        .await
+        .expect("Failed to start MinIO container");
+
+    // 2. Create the bucket, creating it again keeps it
+    let media_store = S3MediaStore::new(
+        endpoint,
+        "drop-of-culture".to_string(),
+        "us-east-1".to_string(),
+        "minioadmin".to_string(),
+        "minioadmin".to_string(),
+    );
+    media_store.create_bucket().await.expect("Failed to create bucket");
+    media_store.create_bucket().await.expect("Failed to create bucket again");
+
+    // 3. Test put and get, keys with spaces are encoded
+    media_store.put("playlist_1/track_1", MediaContent::from_bytes(&b"hello world"[..])).await.expect("Failed to put media");
+    media_store.put("playlist_1/track 2", MediaContent::from_bytes(&b"second"[..])).await.expect("Failed to put media");
+    media_store.put("playlist_10/track_1", MediaContent::from_bytes(&b"other"[..])).await.expect("Failed to put media");
+    assert_eq!(b"hello world".to_vec(), media_store.get("playlist_1/track_1").await.expect("Failed to get media").into_bytes().await.unwrap());
+    assert_eq!(b"second".to_vec(), media_store.get("playlist_1/track 2").await.expect("Failed to get media").into_bytes().await.unwrap());
+    assert_eq!(MediaStoreError::NotFound, media_store.get("playlist_1/track_3").await.unwrap_err());
+
+    // 4. Test get_range
+    let ranged_content = media_store.get_range("playlist_1/track_1", ByteRange { start: 6, end: Some(100) })
+        .await
+        .expect("Failed to get range");
+    assert_eq!((6, 10, 11), (ranged_content.start, ranged_content.end, ranged_content.total_size));
+    assert_eq!(Some(5), ranged_content.content.size);
+    assert_eq!(b"world".to_vec(), ranged_content.content.into_bytes().await.unwrap());
+    assert_eq!(MediaStoreError::InvalidRange, media_store.get_range("playlist_1/track_1", ByteRange { start: 11, end: None }).await.unwrap_err());
+
+    // 5. Test list
+    assert_eq!(
+        vec!["playlist_1/track 2", "playlist_1/track_1"],
+        media_store.list("playlist_1/").await.expect("Failed to list media")
+    );
+    assert_eq!(3, media_store.list("").await.expect("Failed to list media").len());
+
+    // 6. Test delete, a missing media is not an error
+    delete_prefix(&media_store, "playlist_1/").await.expect("Failed to delete media");
+    media_store.delete("playlist_1/track_1").await.expect("Failed to delete missing media");
+    assert_eq!(vec!["playlist_10/track_1"], media_store.list("").await.expect("Failed to list media"));
+}
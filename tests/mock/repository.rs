@@ -3,4 +3,8 @@ pub mod drop;
 #[path = "repository/playlist.rs"]
 pub mod playlist;
 #[path = "repository/artist.rs"]
-pub mod artist;
\ No newline at end of file
+pub mod artist;
+#[path = "repository/artwork.rs"]
+pub mod artwork;
+#[path = "repository/track.rs"]
+pub mod track;
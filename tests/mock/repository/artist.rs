@@ -1,9 +1,11 @@
 use std::sync::{Arc, RwLock};
-use drop_reverse_proxy::repository::artist::Artist;
+use drop_reverse_proxy::repository::artist::{normalize_artist_name, Artist, ArtistField};
+use drop_reverse_proxy::repository::query::{Filter, Page};
+use drop_reverse_proxy::repository::unit_of_work::UnitOfWork;
 use drop_reverse_proxy::repository::{RepoByName, RepositoryError};
 use std::collections::HashMap;
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct ArtistRepoMock {
     map_by_id: Arc<RwLock<HashMap<i32, Artist>>>,
     map_by_name: Arc<RwLock<HashMap<String, Artist>>>,
@@ -38,6 +40,35 @@ impl RepoByName<Artist> for ArtistRepoMock {
     }
 
     async fn get_by_name(&self, name: &str) -> Result<Artist, RepositoryError> {
-        self.map_by_name.read().unwrap().get(name).cloned().ok_or(RepositoryError::EntityNotFound)
+        let normalized_name = normalize_artist_name(name);
+        self.map_by_name.read().unwrap().values()
+            .find(|artist| normalize_artist_name(artist.name()) == normalized_name)
+            .cloned()
+            .ok_or(RepositoryError::EntityNotFound)
+    }
+
+    async fn save_or_update_in(&self, entity: &Artist, unit_of_work: &mut UnitOfWork) -> Result<i32, RepositoryError> {
+        let id = self.save_or_update(entity).await?;
+        let name = entity.name().to_string();
+        let (map_by_id, map_by_name) = (self.map_by_id.clone(), self.map_by_name.clone());
+        unit_of_work.on_rollback(move || {
+            map_by_id.write().unwrap().remove(&id);
+            map_by_name.write().unwrap().remove(&name);
+        });
+        Ok(id)
+    }
+
+    async fn list(&self, filter: &Filter<ArtistField>) -> Result<Page<Artist>, RepositoryError> {
+        Ok(filter.page(self.map_by_id.read().unwrap().values().cloned()))
+    }
+
+    async fn delete(&self, id: i32) -> Result<(), RepositoryError> {
+        let artist = self.map_by_id.write().unwrap().remove(&id).ok_or(RepositoryError::EntityNotFound)?;
+        self.map_by_name.write().unwrap().remove(artist.name());
+        Ok(())
+    }
+
+    async fn exists(&self, id: i32) -> Result<bool, RepositoryError> {
+        Ok(self.map_by_id.read().unwrap().contains_key(&id))
     }
 }
\ No newline at end of file
@@ -0,0 +1,61 @@
+use async_trait::async_trait;
+use drop_reverse_proxy::repository::unit_of_work::UnitOfWork;
+use drop_reverse_proxy::repository::artwork::Artwork;
+use drop_reverse_proxy::repository::{Repo, RepositoryError};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+#[derive(Clone, Default)]
+pub struct ArtworkRepoMock {
+    map: Arc<RwLock<HashMap<i32, Artwork>>>
+}
+impl ArtworkRepoMock {
+    pub fn new() -> Self {
+        Self { map: Arc::new(RwLock::new(HashMap::new())) }
+    }
+
+    pub fn map(&self) -> &Arc<RwLock<HashMap<i32, Artwork>>> {
+        &self.map
+    }
+}
+
+#[async_trait]
+impl Repo<Artwork> for ArtworkRepoMock {
+    async fn get(&self, id: i32) -> Result<Artwork, RepositoryError> {
+        match self.map().read().unwrap().get(&id) {
+            Some(artwork) => Ok(artwork.clone()),
+            None => Err(RepositoryError::EntityNotFound)
+        }
+    }
+
+    async fn save_or_update(&self, entity: &Artwork) -> Result<i32, RepositoryError> {
+        self.map.write().unwrap().insert(entity.id(), entity.clone());
+        Ok(entity.id())
+    }
+
+    async fn save_or_update_in(&self, entity: &Artwork, unit_of_work: &mut UnitOfWork) -> Result<i32, RepositoryError> {
+        let id = entity.id();
+        let previous = self.map.write().unwrap().insert(id, entity.clone());
+        let map = self.map.clone();
+        unit_of_work.on_rollback(move || {
+            match previous {
+                Some(previous) => map.write().unwrap().insert(id, previous),
+                None => map.write().unwrap().remove(&id),
+            };
+        });
+        Ok(id)
+    }
+
+    async fn delete(&self, id: i32) -> Result<(), RepositoryError> {
+        self.map.write().unwrap().remove(&id).map(|_| ()).ok_or(RepositoryError::EntityNotFound)
+    }
+
+    async fn delete_in(&self, id: i32, unit_of_work: &mut UnitOfWork) -> Result<(), RepositoryError> {
+        let previous = self.map.write().unwrap().remove(&id).ok_or(RepositoryError::EntityNotFound)?;
+        let map = self.map.clone();
+        unit_of_work.on_rollback(move || {
+            map.write().unwrap().insert(id, previous);
+        });
+        Ok(())
+    }
+}
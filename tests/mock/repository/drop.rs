@@ -1,11 +1,13 @@
 use async_trait::async_trait;
-use drop_reverse_proxy::repository::drop::Drop;
+use drop_reverse_proxy::repository::unit_of_work::UnitOfWork;
+use drop_reverse_proxy::repository::drop::{Drop, DropField};
+use drop_reverse_proxy::repository::query::{Filter, Page};
 use drop_reverse_proxy::repository::Repo;
 use drop_reverse_proxy::repository::RepositoryError;
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct DropRepoMock {
     map: Arc<RwLock<HashMap<i32, Drop>>>
 }
@@ -32,6 +34,39 @@ impl Repo<Drop> for DropRepoMock {
         self.map.write().unwrap().insert(entity.id(), entity.clone());
         Ok(entity.id())
     }
+
+    async fn save_or_update_in(&self, entity: &Drop, unit_of_work: &mut UnitOfWork) -> Result<i32, RepositoryError> {
+        let id = entity.id();
+        let previous = self.map.write().unwrap().insert(id, entity.clone());
+        let map = self.map.clone();
+        unit_of_work.on_rollback(move || {
+            match previous {
+                Some(previous) => map.write().unwrap().insert(id, previous),
+                None => map.write().unwrap().remove(&id),
+            };
+        });
+        Ok(id)
+    }
+    async fn list(&self, filter: &Filter<DropField>) -> Result<Page<Drop>, RepositoryError> {
+        Ok(filter.page(self.map.read().unwrap().values().cloned()))
+    }
+
+    async fn delete(&self, id: i32) -> Result<(), RepositoryError> {
+        self.map.write().unwrap().remove(&id).map(|_| ()).ok_or(RepositoryError::EntityNotFound)
+    }
+
+    async fn delete_in(&self, id: i32, unit_of_work: &mut UnitOfWork) -> Result<(), RepositoryError> {
+        let previous = self.map.write().unwrap().remove(&id).ok_or(RepositoryError::EntityNotFound)?;
+        let map = self.map.clone();
+        unit_of_work.on_rollback(move || {
+            map.write().unwrap().insert(id, previous);
+        });
+        Ok(())
+    }
+
+    async fn exists(&self, id: i32) -> Result<bool, RepositoryError> {
+        Ok(self.map.read().unwrap().contains_key(&id))
+    }
 }
 
 #[cfg(test)]
@@ -51,7 +86,8 @@ mod tests {
             0,
             10,
             1,
-            2
+            2,
+            3
         );
         let save_result = drop_repo.save_or_update(&drop).await;
         assert!(save_result.is_ok());
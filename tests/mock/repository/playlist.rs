@@ -1,10 +1,12 @@
 use async_trait::async_trait;
-use drop_reverse_proxy::repository::playlist::Playlist;
+use drop_reverse_proxy::repository::unit_of_work::UnitOfWork;
+use drop_reverse_proxy::repository::playlist::{Playlist, PlaylistField};
+use drop_reverse_proxy::repository::query::{Filter, Page};
 use drop_reverse_proxy::repository::{Repo, RepositoryError};
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct PlaylistRepoMock {
     map: Arc<RwLock<HashMap<i32, Playlist>>>
 }
@@ -31,4 +33,37 @@ impl Repo<Playlist> for PlaylistRepoMock {
         self.map.write().unwrap().insert(entity.id(), entity.clone());
         Ok(entity.id())
     }
+
+    async fn save_or_update_in(&self, entity: &Playlist, unit_of_work: &mut UnitOfWork) -> Result<i32, RepositoryError> {
+        let id = entity.id();
+        let previous = self.map.write().unwrap().insert(id, entity.clone());
+        let map = self.map.clone();
+        unit_of_work.on_rollback(move || {
+            match previous {
+                Some(previous) => map.write().unwrap().insert(id, previous),
+                None => map.write().unwrap().remove(&id),
+            };
+        });
+        Ok(id)
+    }
+    async fn list(&self, filter: &Filter<PlaylistField>) -> Result<Page<Playlist>, RepositoryError> {
+        Ok(filter.page(self.map.read().unwrap().values().cloned()))
+    }
+
+    async fn delete(&self, id: i32) -> Result<(), RepositoryError> {
+        self.map.write().unwrap().remove(&id).map(|_| ()).ok_or(RepositoryError::EntityNotFound)
+    }
+
+    async fn delete_in(&self, id: i32, unit_of_work: &mut UnitOfWork) -> Result<(), RepositoryError> {
+        let previous = self.map.write().unwrap().remove(&id).ok_or(RepositoryError::EntityNotFound)?;
+        let map = self.map.clone();
+        unit_of_work.on_rollback(move || {
+            map.write().unwrap().insert(id, previous);
+        });
+        Ok(())
+    }
+
+    async fn exists(&self, id: i32) -> Result<bool, RepositoryError> {
+        Ok(self.map.read().unwrap().contains_key(&id))
+    }
 }
\ No newline at end of file
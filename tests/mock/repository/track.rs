@@ -0,0 +1,135 @@
+use async_trait::async_trait;
+use drop_reverse_proxy::repository::track::{Track, TrackRepoT};
+use drop_reverse_proxy::repository::unit_of_work::UnitOfWork;
+use drop_reverse_proxy::repository::{Repo, RepositoryError};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// Tracks share the id 0 once saved, they are kept in a list rather than a map by id.
+/// Contents are kept by hash with their size and reference count
+#[derive(Clone, Default)]
+pub struct TrackRepoMock {
+    tracks: Arc<RwLock<Vec<Track>>>,
+    contents: Arc<RwLock<HashMap<String, (i64, i32)>>>,
+}
+
+impl TrackRepoMock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn tracks(&self) -> &Arc<RwLock<Vec<Track>>> {
+        &self.tracks
+    }
+
+    pub fn contents(&self) -> &Arc<RwLock<HashMap<String, (i64, i32)>>> {
+        &self.contents
+    }
+
+    /// Restores the content as it was before a change, a content which didn't exist is removed
+    fn restore_content_on_rollback(&self, content_hash: &str, previous: Option<(i64, i32)>, unit_of_work: &mut UnitOfWork) {
+        let contents = self.contents.clone();
+        let content_hash = content_hash.to_string();
+        unit_of_work.on_rollback(move || {
+            let mut contents = contents.write().unwrap();
+            match previous {
+                Some(content) => contents.insert(content_hash, content),
+                None => contents.remove(&content_hash),
+            };
+        });
+    }
+}
+
+#[async_trait]
+impl Repo<Track> for TrackRepoMock {
+    async fn get(&self, id: i32) -> Result<Track, RepositoryError> {
+        self.tracks.read().unwrap().iter()
+            .find(|track| track.id() == id)
+            .cloned()
+            .ok_or(RepositoryError::EntityNotFound)
+    }
+
+    /// Like the unique index, a new track can't take the position of another one of the playlist
+    async fn save_or_update(&self, entity: &Track) -> Result<i32, RepositoryError> {
+        let mut tracks = self.tracks.write().unwrap();
+        let position_taken = tracks.iter()
+            .any(|track| track.playlist_id() == entity.playlist_id() && track.position() == entity.position());
+        if entity.id() == 0 && position_taken {
+            return Err(RepositoryError::UniqueViolation);
+        }
+        tracks.retain(|track| track.playlist_id() != entity.playlist_id() || track.position() != entity.position());
+        tracks.push(entity.clone());
+        Ok(entity.id())
+    }
+
+    async fn save_or_update_in(&self, entity: &Track, unit_of_work: &mut UnitOfWork) -> Result<i32, RepositoryError> {
+        let (playlist_id, position) = (entity.playlist_id(), entity.position());
+        let previous = self.tracks.read().unwrap().iter()
+            .find(|track| track.playlist_id() == playlist_id && track.position() == position)
+            .cloned();
+        let id = self.save_or_update(entity).await?;
+        let tracks = self.tracks.clone();
+        unit_of_work.on_rollback(move || {
+            let mut tracks = tracks.write().unwrap();
+            tracks.retain(|track| track.playlist_id() != playlist_id || track.position() != position);
+            tracks.extend(previous);
+        });
+        Ok(id)
+    }
+}
+
+#[async_trait]
+impl TrackRepoT for TrackRepoMock {
+    async fn get_by_playlist(&self, playlist_id: i32) -> Result<Vec<Track>, RepositoryError> {
+        let mut tracks: Vec<Track> = self.tracks.read().unwrap().iter()
+            .filter(|track| track.playlist_id() == playlist_id)
+            .cloned()
+            .collect();
+        tracks.sort_by_key(|track| track.position());
+        Ok(tracks)
+    }
+
+    async fn delete_by_playlist_in(&self, playlist_id: i32, unit_of_work: &mut UnitOfWork) -> Result<u64, RepositoryError> {
+        let mut tracks = self.tracks.write().unwrap();
+        let deleted: Vec<Track> = tracks.iter().filter(|track| track.playlist_id() == playlist_id).cloned().collect();
+        tracks.retain(|track| track.playlist_id() != playlist_id);
+        let nb_deleted = deleted.len() as u64;
+        let tracks = self.tracks.clone();
+        unit_of_work.on_rollback(move || {
+            tracks.write().unwrap().extend(deleted);
+        });
+        Ok(nb_deleted)
+    }
+
+    async fn retain_content_in(&self, content_hash: &str, size: i64, unit_of_work: &mut UnitOfWork) -> Result<i32, RepositoryError> {
+        let previous = self.contents.read().unwrap().get(content_hash).copied();
+        let ref_count = previous.map_or(0, |(_, ref_count)| ref_count) + 1;
+        self.contents.write().unwrap().insert(content_hash.to_string(), (size, ref_count));
+        self.restore_content_on_rollback(content_hash, previous, unit_of_work);
+        Ok(ref_count)
+    }
+
+    async fn release_content_in(&self, content_hash: &str, unit_of_work: &mut UnitOfWork) -> Result<i32, RepositoryError> {
+        let previous = self.contents.read().unwrap().get(content_hash).copied();
+        let Some((size, ref_count)) = previous.filter(|(_, ref_count)| *ref_count > 0) else {
+            return Err(RepositoryError::EntityNotFound);
+        };
+        self.contents.write().unwrap().insert(content_hash.to_string(), (size, ref_count - 1));
+        self.restore_content_on_rollback(content_hash, previous, unit_of_work);
+        Ok(ref_count - 1)
+    }
+
+    async fn delete_unreferenced_content_in(&self, content_hash: &str, unit_of_work: &mut UnitOfWork) -> Result<bool, RepositoryError> {
+        let previous = self.contents.read().unwrap().get(content_hash).copied();
+        if previous.is_none_or(|(_, ref_count)| ref_count > 0) {
+            return Ok(false);
+        }
+        self.contents.write().unwrap().remove(content_hash);
+        self.restore_content_on_rollback(content_hash, previous, unit_of_work);
+        Ok(true)
+    }
+
+    async fn content_exists(&self, content_hash: &str) -> Result<bool, RepositoryError> {
+        Ok(self.contents.read().unwrap().contains_key(content_hash))
+    }
+}
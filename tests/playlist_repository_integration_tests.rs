@@ -1,6 +1,7 @@
 use crate::utils::{create_default_db_config, start_postgres_container};
-use drop_reverse_proxy::repository::playlist::{Playlist, PlaylistRepo};
-use drop_reverse_proxy::repository::Repo;
+use drop_reverse_proxy::repository::playlist::{Playlist, PlaylistField, PlaylistRepo};
+use drop_reverse_proxy::repository::query::{Filter, Order};
+use drop_reverse_proxy::repository::{Repo, RepositoryError};
 
 mod utils;
 
@@ -24,27 +25,16 @@ async fn test_playlist_repo_integration() {
         .expect("Failed to create database pool");
 
     // 3. Initialize schema
-    sqlx::query(
-        r#"
-        CREATE TABLE "playlist" (
-            id SERIAL PRIMARY KEY,
-            name VARCHAR(255) NOT NULL
-        )
-        "#
-    )
-    .execute(&pool)
-    .await
-    .expect("Failed to create table");
+    drop_reverse_proxy::config::db::run_migrations(&pool)
+        .await
+        .expect("Failed to run migrations");
 
     let repo = PlaylistRepo::new(&db_config)
         .await
         .expect("Failed to create playlist repository");
 
     // 4. Test save_or_update
-    let new_playlist = Playlist {
-        id: 0,
-        name: "Test Playlist".to_string(),
-    };
+    let new_playlist = Playlist::new(0, "Test Playlist".to_string());
 
     <PlaylistRepo as Repo<Playlist>>::save_or_update(&repo, &new_playlist).await.expect("Failed to save playlist");
 
@@ -53,4 +43,32 @@ async fn test_playlist_repo_integration() {
     
     assert_eq!(saved_playlist.name, "Test Playlist");
     assert_eq!(saved_playlist.id, 1);
+
+    // 6. Test update by id, the version read must still be the stored one
+    let renamed_playlist = Playlist::new(1, "Renamed Playlist".to_string()).with_version(saved_playlist.version());
+    assert_eq!(1, repo.save_or_update(&renamed_playlist).await.expect("Failed to update playlist"));
+    let updated_playlist = repo.get(1).await.expect("Failed to get playlist");
+    assert_eq!("Renamed Playlist", updated_playlist.name());
+    assert_eq!(saved_playlist.version() + 1, updated_playlist.version());
+    assert!(matches!(repo.save_or_update(&renamed_playlist).await, Err(RepositoryError::VersionConflict)));
+    assert!(matches!(repo.save_or_update(&Playlist::new(42, "Missing".to_string())).await, Err(RepositoryError::EntityNotFound)));
+
+    // 7. Test list, pages follow the sort and end without cursor
+    for name in ["Charlie", "Alpha", "Bravo"] {
+        repo.save_or_update(&Playlist::new(0, name.to_string())).await.expect("Failed to save playlist");
+    }
+    let filter = Filter::new().sort_by(PlaylistField::Name, Order::Asc).limit(2);
+    let first_page = repo.list(&filter).await.expect("Failed to list playlists");
+    assert_eq!(vec!["Alpha", "Bravo"], first_page.items.iter().map(Playlist::name).collect::<Vec<_>>());
+    let second_page = repo.list(&filter.clone().after(first_page.next_cursor)).await.expect("Failed to list playlists");
+    assert_eq!(vec!["Charlie", "Renamed Playlist"], second_page.items.iter().map(Playlist::name).collect::<Vec<_>>());
+    assert_eq!(None, second_page.next_cursor);
+    let bravo_only = repo.list(&Filter::new().eq(PlaylistField::Name, "Bravo")).await.expect("Failed to list playlists");
+    assert_eq!(1, bravo_only.items.len());
+
+    // 8. Test exists and delete
+    assert!(repo.exists(1).await.expect("Failed to check playlist"));
+    repo.delete(1).await.expect("Failed to delete playlist");
+    assert!(!repo.exists(1).await.expect("Failed to check playlist"));
+    assert!(matches!(repo.delete(1).await, Err(RepositoryError::EntityNotFound)));
 }
@@ -1,4 +1,6 @@
 #[path = "service/drop_int.rs"]
 pub mod drop_int;
 #[path = "service/drop.rs"]
-pub mod drop;
\ No newline at end of file
+pub mod drop;
+#[path = "service/import.rs"]
+pub mod import;
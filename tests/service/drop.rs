@@ -1,14 +1,38 @@
 use mock::repository::artist::ArtistRepoMock;
+use mock::repository::artwork::ArtworkRepoMock;
 use mock::repository::drop::DropRepoMock;
 use mock::repository::playlist::PlaylistRepoMock;
+use mock::repository::track::TrackRepoMock;
 use drop_reverse_proxy::repository::artist::Artist;
-use drop_reverse_proxy::service::drop::{DropRequest, DropService, DropServiceT, ImportError, PLAYLIST_DIR_PREFIX, TRACK_FILE_PREFIX};
+use drop_reverse_proxy::repository::artwork::Artwork;
+use drop_reverse_proxy::repository::drop::{Credit, Drop, Release};
+use drop_reverse_proxy::repository::drop_type::DropType;
+use drop_reverse_proxy::repository::import::{ImportRepoT, ImportState, InMemoryImportRepo};
+use drop_reverse_proxy::service::artwork::{ARTWORK_DIR_PREFIX, NO_ARTWORK_ID};
+use drop_reverse_proxy::service::drop::{track_content_key, CreatedDrop, DropAccess, DropError, DropRequest, DropService, DropServiceT, DropUpdate, ImportError, ImportPolicy, TrackMetadata, PLAYLIST_DIR_PREFIX, TRACK_FILE_PREFIX};
+use drop_reverse_proxy::{InMemoryTagRepo, InMemoryTokenRepo, Tag, TagRepo, Token, TokenRepo};
+use chrono::{NaiveDate, Utc};
+use sha2::{Digest, Sha256};
 use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tempfile::TempDir;
-use drop_reverse_proxy::repository::Repo;
+use drop_reverse_proxy::repository::playlist::Playlist;
+use drop_reverse_proxy::repository::track::{CuePoint, Track, TrackRepoT};
+use drop_reverse_proxy::repository::unit_of_work::UnitOfWork;
+use drop_reverse_proxy::repository::{Repo, RepoByName};
 
 #[path = "../mock.rs"]
-mod mock;
+pub mod mock;
+
+fn content_hash(content: &str) -> String {
+    hex::encode(Sha256::digest(content))
+}
+
+// file of the content store holding `content`
+fn content_path(web_server_path: &Path, content: &str) -> PathBuf {
+    web_server_path.join(track_content_key(&content_hash(content)).unwrap())
+}
 
 #[tokio::test]
 async fn test_create_drop_success_with_artist_id() {
@@ -19,7 +43,7 @@ async fn test_create_drop_success_with_artist_id() {
     let artist_id = 10;
     artist_repo.map_by_id().write().unwrap().insert(artist_id, Artist::new(artist_id, "Artist Name".to_string()));
 
-    let service = DropService::new(drop_repo, artist_repo, playlist_repo);
+    let service = DropService::new(drop_repo, artist_repo, playlist_repo, ArtworkRepoMock::new(), TrackRepoMock::new());
 
     let temp_import_dir = TempDir::new().unwrap();
     let import_path = temp_import_dir.path().to_str().unwrap().to_string();
@@ -35,18 +59,83 @@ async fn test_create_drop_success_with_artist_id() {
         vec!["track1.mp3".to_string()]
     );
 
-    let result: Result<(), ImportError> = service.create_drop(&import_path, drop_request, &web_server_path).await;
+    let result: Result<CreatedDrop, ImportError> = service.create_drop(&import_path, drop_request, &web_server_path).await;
     assert!(result.is_ok());
+    assert_eq!(CreatedDrop::new(0, 0, artist_id), result.unwrap());
 
-    // Verify playlist directory and file
-    // PlaylistRepoMock returns entity.id() on save. Playlist::new(0, ...) has id 0.
-    let playlist_dir = temp_web_server_dir.path().join(format!("{}{}", PLAYLIST_DIR_PREFIX, 0));
-    assert!(playlist_dir.exists());
-    assert!(playlist_dir.join(format!("{}{}", TRACK_FILE_PREFIX, 1)).exists());
+    // the track is stored under its hash rather than in a playlist directory
+    assert_eq!("content1", fs::read_to_string(content_path(temp_web_server_dir.path(), "content1")).unwrap());
+    assert!(!temp_web_server_dir.path().join(format!("{}{}", PLAYLIST_DIR_PREFIX, 0)).exists());
 
     let drop_result = service.drop_repository().get(0).await;
     assert!(drop_result.is_ok());
-    assert_eq!(drop_result.unwrap().artist_id(), artist_id);
+    let drop = drop_result.unwrap();
+    assert_eq!(drop.artist_id(), artist_id);
+    // the request gives no type, one track makes a single
+    assert_eq!(Some(DropType::Single), drop.drop_type());
+}
+
+#[tokio::test]
+async fn test_create_drop_keeps_the_requested_type() {
+    let artist_repo = ArtistRepoMock::new();
+    let artist_id = 1;
+    artist_repo.map_by_id().write().unwrap().insert(artist_id, Artist::new(artist_id, "Artist".to_string()));
+    let service = DropService::new(DropRepoMock::new(), artist_repo, PlaylistRepoMock::new(), ArtworkRepoMock::new(), TrackRepoMock::new());
+
+    let temp_import_dir = TempDir::new().unwrap();
+    let import_path = temp_import_dir.path().to_str().unwrap().to_string();
+    fs::write(temp_import_dir.path().join("mix.mp3"), "content").unwrap();
+    let temp_web_server_dir = TempDir::new().unwrap();
+    let web_server_path = temp_web_server_dir.path().to_str().unwrap().to_string();
+
+    let drop_request = DropRequest::new(
+        Some(artist_id),
+        None,
+        "Sound system mix".to_string(),
+        vec!["mix.mp3".to_string()]
+    ).with_drop_type(DropType::Mix);
+
+    service.create_drop(&import_path, drop_request, &web_server_path).await.unwrap();
+    assert_eq!(DropType::Mix.id(), service.drop_repository().get(0).await.unwrap().type_id());
+}
+
+#[tokio::test]
+async fn test_create_drop_keeps_the_release_fields_track_metadata_and_cue_points() {
+    let artist_repo = ArtistRepoMock::new();
+    let artist_id = 1;
+    artist_repo.map_by_id().write().unwrap().insert(artist_id, Artist::new(artist_id, "Artist".to_string()));
+    let track_repo = TrackRepoMock::new();
+    let service = DropService::new(DropRepoMock::new(), artist_repo, PlaylistRepoMock::new(), ArtworkRepoMock::new(), track_repo.clone());
+
+    let temp_import_dir = TempDir::new().unwrap();
+    let import_path = temp_import_dir.path().to_str().unwrap().to_string();
+    fs::write(temp_import_dir.path().join("track1.mp3"), "content1").unwrap();
+    let temp_web_server_dir = TempDir::new().unwrap();
+    let web_server_path = temp_web_server_dir.path().to_str().unwrap().to_string();
+
+    let release = Release::new(NaiveDate::from_ymd_opt(2026, 5, 1), Some("Irie Records".to_string()), Some("Reggae".to_string()), None)
+        .with_credits(vec![Credit::new("producer".to_string(), "King Tubby".to_string())]);
+    let track_metadata: TrackMetadata = serde_json::from_value(serde_json::json!(
+        {"title": "Intro", "duration_seconds": 95, "isrc": "USRC17607839", "explicit": true, "disc_number": 1, "track_number": 1,
+         "cue_points": [{"start_seconds": 0, "title": "Intro"}, {"start_seconds": 40, "title": "Dub", "artist": "King Tubby"}]}
+    )).unwrap();
+    let drop_request = DropRequest::new(
+        Some(artist_id),
+        None,
+        "Playlist Name".to_string(),
+        vec!["track1.mp3".to_string()]
+    ).with_release(release.clone()).with_track_metadata(vec![track_metadata]);
+
+    service.create_drop(&import_path, drop_request, &web_server_path).await.unwrap();
+    assert_eq!(&release, service.drop_repository().get(0).await.unwrap().release());
+    let track = &track_repo.tracks().read().unwrap()[0];
+    assert_eq!(Some("USRC17607839"), track.isrc());
+    assert!(track.explicit());
+    assert_eq!((Some(1), Some(1)), (track.disc_number(), track.track_number()));
+    assert_eq!(
+        &[CuePoint::new(0, "Intro".to_string()), CuePoint::new(40, "Dub".to_string()).with_artist(Some("King Tubby".to_string()))],
+        track.cue_points()
+    );
 }
 
 #[tokio::test]
@@ -59,7 +148,7 @@ async fn test_create_drop_success_with_artist_name() {
     let artist_name = "Artist Name";
     artist_repo.map_by_name().write().unwrap().insert(artist_name.to_string(), Artist::new(artist_id, artist_name.to_string()));
 
-    let service = DropService::new(drop_repo, artist_repo, playlist_repo);
+    let service = DropService::new(drop_repo, artist_repo, playlist_repo, ArtworkRepoMock::new(), TrackRepoMock::new());
 
     let temp_import_dir = TempDir::new().unwrap();
     let import_path = temp_import_dir.path().to_str().unwrap().to_string();
@@ -75,11 +164,10 @@ async fn test_create_drop_success_with_artist_name() {
         vec!["track1.mp3".to_string()]
     );
 
-    let result: Result<(), ImportError> = service.create_drop(&import_path, drop_request, &web_server_path).await;
+    let result: Result<CreatedDrop, ImportError> = service.create_drop(&import_path, drop_request, &web_server_path).await;
     assert!(result.is_ok());
 
-    let playlist_dir = temp_web_server_dir.path().join(format!("{}{}", PLAYLIST_DIR_PREFIX, 0));
-    assert!(playlist_dir.exists());
+    assert!(content_path(temp_web_server_dir.path(), "content1").exists());
 }
 
 #[tokio::test]
@@ -88,7 +176,7 @@ async fn test_create_drop_error_both_artist_id_and_name() {
     let drop_repo = DropRepoMock::new();
     let playlist_repo = PlaylistRepoMock::new();
 
-    let service = DropService::new(drop_repo, artist_repo, playlist_repo);
+    let service = DropService::new(drop_repo, artist_repo, playlist_repo, ArtworkRepoMock::new(), TrackRepoMock::new());
 
     let drop_request = DropRequest::new(
         Some(1),
@@ -97,7 +185,7 @@ async fn test_create_drop_error_both_artist_id_and_name() {
         vec![]
     );
 
-    let result = service.create_drop(&"import".to_string(), drop_request, &"web".to_string()).await;
+    let result = service.create_drop("import", drop_request, "web").await;
     assert!(matches!(result, Err(ImportError::ArtistIdAndArtistNameAreBothPresent)));
 }
 
@@ -107,7 +195,7 @@ async fn test_create_drop_error_artist_id_not_found() {
     let drop_repo = DropRepoMock::new();
     let playlist_repo = PlaylistRepoMock::new();
 
-    let service = DropService::new(drop_repo, artist_repo, playlist_repo);
+    let service = DropService::new(drop_repo, artist_repo, playlist_repo, ArtworkRepoMock::new(), TrackRepoMock::new());
 
     let drop_request = DropRequest::new(
         Some(999),
@@ -116,7 +204,7 @@ async fn test_create_drop_error_artist_id_not_found() {
         vec![]
     );
 
-    let result = service.create_drop(&"import".to_string(), drop_request, &"web".to_string()).await;
+    let result = service.create_drop("import", drop_request, "web").await;
     assert!(matches!(result, Err(ImportError::InvalidArtistId)));
 }
 
@@ -126,7 +214,7 @@ async fn test_create_drop_error_artist_name_not_found() {
     let drop_repo = DropRepoMock::new();
     let playlist_repo = PlaylistRepoMock::new();
 
-    let service = DropService::new(drop_repo, artist_repo, playlist_repo);
+    let service = DropService::new(drop_repo, artist_repo, playlist_repo, ArtworkRepoMock::new(), TrackRepoMock::new());
 
     let drop_request = DropRequest::new(
         None,
@@ -135,8 +223,122 @@ async fn test_create_drop_error_artist_name_not_found() {
         vec![]
     );
 
-    let result = service.create_drop(&"import".to_string(), drop_request, &"web".to_string()).await;
+    let result = service.create_drop("import", drop_request, "web").await;
+    assert!(matches!(result, Err(ImportError::CantCreateArtistFromArtistName)));
+}
+
+#[tokio::test]
+async fn test_create_drop_matches_the_normalized_artist_name() {
+    let artist_repo = ArtistRepoMock::new();
+    let artist_id = 10;
+    artist_repo.map_by_name().write().unwrap().insert("Cool Rasta".to_string(), Artist::new(artist_id, "Cool Rasta".to_string()));
+    let service = DropService::new(DropRepoMock::new(), artist_repo, PlaylistRepoMock::new(), ArtworkRepoMock::new(), TrackRepoMock::new())
+        .with_import_policy(ImportPolicy::CreateMissing);
+
+    let temp_import_dir = TempDir::new().unwrap();
+    let import_path = temp_import_dir.path().to_str().unwrap().to_string();
+    fs::write(temp_import_dir.path().join("track1.mp3"), "content1").unwrap();
+    let temp_web_server_dir = TempDir::new().unwrap();
+    let web_server_path = temp_web_server_dir.path().to_str().unwrap().to_string();
+
+    let drop_request = DropRequest::new(
+        None,
+        Some(" cool  RASTA ".to_string()),
+        "Playlist Name".to_string(),
+        vec!["track1.mp3".to_string()]
+    );
+
+    let created_drop = service.create_drop(&import_path, drop_request, &web_server_path).await.unwrap();
+    assert_eq!(artist_id, created_drop.artist_id());
+    // the existing artist is used, no other is created
+    assert_eq!(1, service.artist_repository().map_by_name().read().unwrap().len());
+}
+
+#[tokio::test]
+async fn test_create_imported_drop_records_the_import_with_the_drop() {
+    let artist_repo = ArtistRepoMock::new();
+    artist_repo.map_by_id().write().unwrap().insert(10, Artist::new(10, "Artist Name".to_string()));
+    let service = DropService::new(DropRepoMock::new(), artist_repo, PlaylistRepoMock::new(), ArtworkRepoMock::new(), TrackRepoMock::new());
+    let import_repo = InMemoryImportRepo::default();
+    let mut import = import_repo.start_import("sha256", "drop_1.tar.gz", Utc::now().naive_utc()).await.unwrap().unwrap();
+
+    let temp_import_dir = TempDir::new().unwrap();
+    let import_path = temp_import_dir.path().to_str().unwrap();
+    fs::write(temp_import_dir.path().join("track1.mp3"), "content1").unwrap();
+    let temp_web_server_dir = TempDir::new().unwrap();
+    let web_server_path = temp_web_server_dir.path().to_str().unwrap();
+
+    // the import is left running by a drop which is not created
+    let drop_request = DropRequest::new(Some(10), None, "Playlist Name".to_string(), vec!["missing.mp3".to_string()]);
+    assert!(service.create_imported_drop(import_path, drop_request, web_server_path, &import_repo, &mut import).await.is_err());
+    assert_eq!(ImportState::Running, import_repo.get(import.id()).await.unwrap().state());
+
+    let drop_request = DropRequest::new(Some(10), None, "Playlist Name".to_string(), vec!["track1.mp3".to_string()]);
+    let created_drop = service.create_imported_drop(import_path, drop_request, web_server_path, &import_repo, &mut import).await.unwrap();
+    let recorded_import = import_repo.get(import.id()).await.unwrap();
+    assert_eq!(ImportState::Imported, recorded_import.state());
+    assert_eq!(Some(created_drop.drop_id()), recorded_import.drop_id());
+}
+
+#[tokio::test]
+async fn test_create_drop_creates_the_missing_artist() {
+    let service = DropService::new(DropRepoMock::new(), ArtistRepoMock::new(), PlaylistRepoMock::new(), ArtworkRepoMock::new(), TrackRepoMock::new())
+        .with_import_policy(ImportPolicy::CreateMissing);
+
+    let temp_import_dir = TempDir::new().unwrap();
+    let import_path = temp_import_dir.path().to_str().unwrap().to_string();
+    fs::write(temp_import_dir.path().join("track1.mp3"), "content1").unwrap();
+    let temp_web_server_dir = TempDir::new().unwrap();
+    let web_server_path = temp_web_server_dir.path().to_str().unwrap().to_string();
+
+    let drop_request = DropRequest::new(
+        None,
+        Some("New Artist ".to_string()),
+        "Playlist Name".to_string(),
+        vec!["track1.mp3".to_string()]
+    );
+
+    let created_drop = service.create_drop(&import_path, drop_request, &web_server_path).await.unwrap();
+    let artist = service.artist_repository().get(created_drop.artist_id()).await.unwrap();
+    assert_eq!("New Artist", artist.name());
+}
+
+#[tokio::test]
+async fn test_create_drop_request_policy_overrides_the_service_one() {
+    let service = DropService::new(DropRepoMock::new(), ArtistRepoMock::new(), PlaylistRepoMock::new(), ArtworkRepoMock::new(), TrackRepoMock::new())
+        .with_import_policy(ImportPolicy::CreateMissing);
+
+    let drop_request = DropRequest::new(
+        None,
+        Some("Unknown".to_string()),
+        "Playlist".to_string(),
+        vec![]
+    ).with_import_policy(ImportPolicy::Strict);
+
+    let result = service.create_drop("import", drop_request, "web").await;
     assert!(matches!(result, Err(ImportError::CantCreateArtistFromArtistName)));
+    assert!(service.artist_repository().map_by_name().read().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_create_drop_rolls_back_the_created_artist() {
+    let service = DropService::new(DropRepoMock::new(), ArtistRepoMock::new(), PlaylistRepoMock::new(), ArtworkRepoMock::new(), TrackRepoMock::new());
+
+    let temp_import_dir = TempDir::new().unwrap();
+    let import_path = temp_import_dir.path().to_str().unwrap().to_string();
+    let temp_web_server_dir = TempDir::new().unwrap();
+    let web_server_path = temp_web_server_dir.path().to_str().unwrap().to_string();
+
+    let drop_request = DropRequest::new(
+        None,
+        Some("New Artist".to_string()),
+        "Playlist".to_string(),
+        vec!["missing.mp3".to_string()]
+    ).with_import_policy(ImportPolicy::CreateMissing);
+
+    let result = service.create_drop(&import_path, drop_request, &web_server_path).await;
+    assert!(matches!(result, Err(ImportError::CantCopyTrackFileToPlaylistDirectory)));
+    assert!(service.artist_repository().map_by_name().read().unwrap().is_empty());
 }
 
 #[tokio::test]
@@ -148,7 +350,7 @@ async fn test_create_drop_error_missing_track_file() {
     let artist_id = 1;
     artist_repo.map_by_id().write().unwrap().insert(artist_id, Artist::new(artist_id, "Artist".to_string()));
 
-    let service = DropService::new(drop_repo, artist_repo, playlist_repo);
+    let service = DropService::new(drop_repo, artist_repo, playlist_repo, ArtworkRepoMock::new(), TrackRepoMock::new());
 
     let temp_import_dir = TempDir::new().unwrap();
     let import_path = temp_import_dir.path().to_str().unwrap().to_string();
@@ -166,4 +368,400 @@ async fn test_create_drop_error_missing_track_file() {
 
     let result = service.create_drop(&import_path, drop_request, &web_server_path).await;
     assert!(matches!(result, Err(ImportError::CantCopyTrackFileToPlaylistDirectory)));
+
+    // the playlist and drop are rolled back, no directory is left in the web server
+    assert!(service.playlist_repository().map().read().unwrap().is_empty());
+    assert!(service.drop_repository().map().read().unwrap().is_empty());
+    assert_eq!(0, fs::read_dir(temp_web_server_dir.path()).unwrap().count());
+}
+
+#[tokio::test]
+async fn test_create_drop_records_the_tracks() {
+    let artist_repo = ArtistRepoMock::new();
+    let artist_id = 1;
+    artist_repo.map_by_id().write().unwrap().insert(artist_id, Artist::new(artist_id, "Artist".to_string()));
+    let service = DropService::new(DropRepoMock::new(), artist_repo, PlaylistRepoMock::new(), ArtworkRepoMock::new(), TrackRepoMock::new());
+
+    let temp_import_dir = TempDir::new().unwrap();
+    let import_path = temp_import_dir.path().to_str().unwrap().to_string();
+    fs::create_dir(temp_import_dir.path().join("tracks")).unwrap();
+    fs::write(temp_import_dir.path().join("tracks/01.mp3"), "content1").unwrap();
+    fs::write(temp_import_dir.path().join("tracks/02.mp3"), "content2").unwrap();
+    let temp_web_server_dir = TempDir::new().unwrap();
+    let web_server_path = temp_web_server_dir.path().to_str().unwrap().to_string();
+
+    // the second track has no metadata
+    let drop_request = DropRequest::new(
+        Some(artist_id),
+        None,
+        "Playlist".to_string(),
+        vec!["tracks/01.mp3".to_string(), "tracks/02.mp3".to_string()]
+    ).with_track_metadata(vec![TrackMetadata::new(Some("Dawn".to_string()), Some(180))]);
+
+    let created_drop = service.create_drop(&import_path, drop_request, &web_server_path).await.unwrap();
+    assert_eq!(created_drop.playlist_id(), service.drop_repository().get(0).await.unwrap().playlist_id());
+    assert_eq!(
+        vec![
+            Track::new(0, 0, 1, "tracks/01.mp3".to_string(), Some("Dawn".to_string()), Some(180)).with_content_hash(&content_hash("content1")),
+            Track::new(0, 0, 2, "tracks/02.mp3".to_string(), None, None).with_content_hash(&content_hash("content2")),
+        ],
+        service.track_repository().get_by_playlist(created_drop.playlist_id()).await.unwrap()
+    );
+}
+
+#[tokio::test]
+async fn test_create_drop_rolls_back_the_tracks() {
+    let artist_repo = ArtistRepoMock::new();
+    let artist_id = 1;
+    artist_repo.map_by_id().write().unwrap().insert(artist_id, Artist::new(artist_id, "Artist".to_string()));
+    let service = DropService::new(DropRepoMock::new(), artist_repo, PlaylistRepoMock::new(), ArtworkRepoMock::new(), TrackRepoMock::new());
+
+    let temp_import_dir = TempDir::new().unwrap();
+    let import_path = temp_import_dir.path().to_str().unwrap().to_string();
+    fs::write(temp_import_dir.path().join("track1.mp3"), "content1").unwrap();
+    let temp_web_server_dir = TempDir::new().unwrap();
+    let web_server_path = temp_web_server_dir.path().to_str().unwrap().to_string();
+
+    // the first track is recorded before the second one is found missing
+    let drop_request = DropRequest::new(
+        Some(artist_id),
+        None,
+        "Playlist".to_string(),
+        vec!["track1.mp3".to_string(), "missing.mp3".to_string()]
+    );
+
+    let result = service.create_drop(&import_path, drop_request, &web_server_path).await;
+    assert!(matches!(result, Err(ImportError::CantCopyTrackFileToPlaylistDirectory)));
+    assert!(service.track_repository().tracks().read().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_create_drop_error_track_outside_import_directory() {
+    let artist_repo = ArtistRepoMock::new();
+    let artist_id = 1;
+    artist_repo.map_by_id().write().unwrap().insert(artist_id, Artist::new(artist_id, "Artist".to_string()));
+    let service = DropService::new(DropRepoMock::new(), artist_repo, PlaylistRepoMock::new(), ArtworkRepoMock::new(), TrackRepoMock::new());
+
+    let temp_dir = TempDir::new().unwrap();
+    let import_dir = temp_dir.path().join("import");
+    fs::create_dir(&import_dir).unwrap();
+    fs::write(temp_dir.path().join("secret.txt"), "secret").unwrap();
+    let temp_web_server_dir = TempDir::new().unwrap();
+    let web_server_path = temp_web_server_dir.path().to_str().unwrap().to_string();
+
+    let drop_request = DropRequest::new(
+        Some(artist_id),
+        None,
+        "Playlist".to_string(),
+        vec!["../secret.txt".to_string()]
+    );
+
+    let result = service.create_drop(import_dir.to_str().unwrap(), drop_request, &web_server_path).await;
+    assert!(matches!(result, Err(ImportError::TrackPathOutsideDropDirectory)));
+    assert!(service.drop_repository().map().read().unwrap().is_empty());
+    assert_eq!(0, fs::read_dir(temp_web_server_dir.path()).unwrap().count());
+}
+
+#[tokio::test]
+async fn test_create_drop_with_artwork_writes_its_variants() {
+    let artist_repo = ArtistRepoMock::new();
+    let artist_id = 1;
+    artist_repo.map_by_id().write().unwrap().insert(artist_id, Artist::new(artist_id, "Artist".to_string()));
+    let service = DropService::new(DropRepoMock::new(), artist_repo, PlaylistRepoMock::new(), ArtworkRepoMock::new(), TrackRepoMock::new());
+
+    let temp_import_dir = TempDir::new().unwrap();
+    let import_path = temp_import_dir.path().to_str().unwrap().to_string();
+    fs::write(temp_import_dir.path().join("track1.mp3"), "content1").unwrap();
+    image::RgbImage::new(320, 320).save(temp_import_dir.path().join("cover.png")).unwrap();
+    let temp_web_server_dir = TempDir::new().unwrap();
+    let web_server_path = temp_web_server_dir.path().to_str().unwrap().to_string();
+
+    let drop_request = DropRequest::new(
+        Some(artist_id),
+        None,
+        "Playlist".to_string(),
+        vec!["track1.mp3".to_string()]
+    ).with_artwork("cover.png");
+
+    service.create_drop(&import_path, drop_request, &web_server_path).await.unwrap();
+
+    // ArtworkRepoMock returns entity.id() on save, the artwork has id 0
+    assert_eq!(Some(&Artwork::new(0, "image/png".to_string(), 320, 320)), service.artwork_repository().map().read().unwrap().get(&0));
+    let artwork_dir = temp_web_server_dir.path().join(format!("{}{}", ARTWORK_DIR_PREFIX, 0));
+    for file_name in ["original.png", "large.jpg", "medium.jpg", "small.jpg", "thumbnail.webp"] {
+        assert!(artwork_dir.join(file_name).exists(), "{file_name}");
+    }
+    assert_eq!(2, fs::read_dir(temp_web_server_dir.path()).unwrap().count());
+}
+
+#[tokio::test]
+async fn test_create_drop_error_invalid_artwork() {
+    let artist_repo = ArtistRepoMock::new();
+    let artist_id = 1;
+    artist_repo.map_by_id().write().unwrap().insert(artist_id, Artist::new(artist_id, "Artist".to_string()));
+    let service = DropService::new(DropRepoMock::new(), artist_repo, PlaylistRepoMock::new(), ArtworkRepoMock::new(), TrackRepoMock::new());
+
+    let temp_import_dir = TempDir::new().unwrap();
+    let import_path = temp_import_dir.path().to_str().unwrap().to_string();
+    fs::write(temp_import_dir.path().join("track1.mp3"), "content1").unwrap();
+    fs::write(temp_import_dir.path().join("cover.png"), "not an image").unwrap();
+    let temp_web_server_dir = TempDir::new().unwrap();
+    let web_server_path = temp_web_server_dir.path().to_str().unwrap().to_string();
+
+    let drop_request = DropRequest::new(
+        Some(artist_id),
+        None,
+        "Playlist".to_string(),
+        vec!["track1.mp3".to_string()]
+    ).with_artwork("cover.png");
+
+    let result = service.create_drop(&import_path, drop_request, &web_server_path).await;
+    assert!(matches!(result, Err(ImportError::InvalidArtwork)));
+    assert!(service.playlist_repository().map().read().unwrap().is_empty());
+    assert!(service.artwork_repository().map().read().unwrap().is_empty());
+    assert_eq!(0, fs::read_dir(temp_web_server_dir.path()).unwrap().count());
+}
+
+#[tokio::test]
+async fn test_tracks_with_the_same_bytes_share_their_content() {
+    let artist_repo = ArtistRepoMock::new();
+    let artist_id = 1;
+    artist_repo.map_by_id().write().unwrap().insert(artist_id, Artist::new(artist_id, "Artist".to_string()));
+    let service = DropService::new(DropRepoMock::new(), artist_repo, PlaylistRepoMock::new(), ArtworkRepoMock::new(), TrackRepoMock::new());
+
+    let temp_import_dir = TempDir::new().unwrap();
+    let import_path = temp_import_dir.path().to_str().unwrap().to_string();
+    fs::write(temp_import_dir.path().join("track1.mp3"), "shared").unwrap();
+    fs::write(temp_import_dir.path().join("copy.mp3"), "shared").unwrap();
+    let temp_web_server_dir = TempDir::new().unwrap();
+    let web_server_path = temp_web_server_dir.path().to_str().unwrap().to_string();
+    let shared_hash = content_hash("shared");
+    let shared_path = content_path(temp_web_server_dir.path(), "shared");
+
+    let drop_request = DropRequest::new(Some(artist_id), None, "Playlist".to_string(), vec!["track1.mp3".to_string(), "copy.mp3".to_string()]);
+    service.create_drop(&import_path, drop_request, &web_server_path).await.unwrap();
+    assert_eq!(Some(&(6, 2)), service.track_repository().contents().read().unwrap().get(&shared_hash));
+    assert_eq!(1, fs::read_dir(shared_path.parent().unwrap()).unwrap().count());
+
+    // the new track references the bytes before the replaced ones release them
+    service.replace_tracks(0, &import_path, vec!["copy.mp3".to_string()], Vec::new(), &web_server_path).await.unwrap();
+    assert_eq!(Some(&(6, 1)), service.track_repository().contents().read().unwrap().get(&shared_hash));
+    assert_eq!("shared", fs::read_to_string(&shared_path).unwrap());
+
+    service.delete_drop(0, &web_server_path).await.unwrap();
+    assert!(service.track_repository().contents().read().unwrap().is_empty());
+    assert_eq!(0, fs::read_dir(temp_web_server_dir.path()).unwrap().count());
+}
+
+#[tokio::test]
+async fn test_unit_of_work_commit_keeps_writes_and_drop_rolls_back() {
+    let playlist_repo = PlaylistRepoMock::new();
+
+    let mut unit_of_work = UnitOfWork::new();
+    playlist_repo.save_or_update_in(&Playlist::new(1, "Committed".to_string()), &mut unit_of_work).await.unwrap();
+    unit_of_work.commit().await.unwrap();
+
+    {
+        let mut unit_of_work = UnitOfWork::new();
+        playlist_repo.save_or_update_in(&Playlist::new(1, "Overwritten".to_string()), &mut unit_of_work).await.unwrap();
+        playlist_repo.save_or_update_in(&Playlist::new(2, "Rolled back".to_string()), &mut unit_of_work).await.unwrap();
+        assert_eq!(2, playlist_repo.map().read().unwrap().len());
+    }
+
+    let map = playlist_repo.map().read().unwrap();
+    assert_eq!(1, map.len());
+    assert_eq!("Committed", map.get(&1).unwrap().name);
+}
+
+type MockDropService = DropService<DropRepoMock, ArtistRepoMock, PlaylistRepoMock, ArtworkRepoMock, TrackRepoMock>;
+
+// drop 0 of the artist 1, its playlist 0 has the track "first"
+async fn service_with_created_drop(web_server_path: &str) -> MockDropService {
+    let artist_repo = ArtistRepoMock::new();
+    artist_repo.map_by_id().write().unwrap().insert(1, Artist::new(1, "Artist".to_string()));
+    let service = DropService::new(DropRepoMock::new(), artist_repo, PlaylistRepoMock::new(), ArtworkRepoMock::new(), TrackRepoMock::new());
+    let temp_import_dir = TempDir::new().unwrap();
+    fs::write(temp_import_dir.path().join("first.mp3"), "first").unwrap();
+    let drop_request = DropRequest::new(Some(1), None, "Playlsit".to_string(), vec!["first.mp3".to_string()]);
+    service.create_drop(temp_import_dir.path().to_str().unwrap(), drop_request, web_server_path).await.unwrap();
+    service
+}
+
+#[tokio::test]
+async fn test_update_drop_changes_the_given_fields() {
+    let temp_web_server_dir = TempDir::new().unwrap();
+    let web_server_path = temp_web_server_dir.path().to_str().unwrap().to_string();
+    let service = service_with_created_drop(&web_server_path).await;
+    service.artist_repository().map_by_id().write().unwrap().insert(2, Artist::new(2, "Other Artist".to_string()));
+
+    let drop_update = DropUpdate::default().with_playlist_name("Playlist").with_artist_id(2).with_drop_type(DropType::Ep);
+    service.update_drop(0, drop_update).await.unwrap();
+    assert_eq!("Playlist", service.playlist_repository().get(0).await.unwrap().name());
+    let drop = service.drop_repository().get(0).await.unwrap();
+    assert_eq!(2, drop.artist_id());
+    assert_eq!(Some(DropType::Ep), drop.drop_type());
+
+    // the playlist alone keeps the other fields
+    service.update_drop(0, DropUpdate::default().with_playlist_name("Renamed")).await.unwrap();
+    assert_eq!(drop, service.drop_repository().get(0).await.unwrap());
+
+    assert_eq!(Err(DropError::InvalidArtistId), service.update_drop(0, DropUpdate::default().with_artist_id(42)).await);
+    assert_eq!(Err(DropError::DropNotFound), service.update_drop(42, DropUpdate::default()).await);
+}
+
+#[tokio::test]
+async fn test_replace_tracks_swaps_the_files_and_the_rows() {
+    let temp_web_server_dir = TempDir::new().unwrap();
+    let web_server_path = temp_web_server_dir.path().to_str().unwrap().to_string();
+    let service = service_with_created_drop(&web_server_path).await;
+    let temp_import_dir = TempDir::new().unwrap();
+    let import_path = temp_import_dir.path().to_str().unwrap().to_string();
+    fs::write(temp_import_dir.path().join("dawn.mp3"), "dawn").unwrap();
+    fs::write(temp_import_dir.path().join("dusk.mp3"), "dusk").unwrap();
+
+    // a missing track leaves the previous ones in place
+    let result = service.replace_tracks(0, &import_path, vec!["missing.mp3".to_string()], Vec::new(), &web_server_path).await;
+    assert_eq!(Err(DropError::CantWritePlaylistDirectory), result);
+    assert_eq!("first", fs::read_to_string(content_path(temp_web_server_dir.path(), "first")).unwrap());
+    assert_eq!(1, service.track_repository().get_by_playlist(0).await.unwrap().len());
+
+    service.replace_tracks(
+        0,
+        &import_path,
+        vec!["dawn.mp3".to_string(), "dusk.mp3".to_string()],
+        vec![TrackMetadata::new(Some("Dawn".to_string()), Some(180))],
+        &web_server_path
+    ).await.unwrap();
+    assert_eq!("dawn", fs::read_to_string(content_path(temp_web_server_dir.path(), "dawn")).unwrap());
+    assert_eq!("dusk", fs::read_to_string(content_path(temp_web_server_dir.path(), "dusk")).unwrap());
+    assert_eq!(
+        vec![
+            Track::new(0, 0, 1, "dawn.mp3".to_string(), Some("Dawn".to_string()), Some(180)).with_content_hash(&content_hash("dawn")),
+            Track::new(0, 0, 2, "dusk.mp3".to_string(), None, None).with_content_hash(&content_hash("dusk")),
+        ],
+        service.track_repository().get_by_playlist(0).await.unwrap()
+    );
+    // the replaced bytes are no longer referenced, neither they nor a replaced directory are left behind
+    assert!(!content_path(temp_web_server_dir.path(), "first").exists());
+    assert!(!service.track_repository().contents().read().unwrap().contains_key(&content_hash("first")));
+    assert_eq!(1, fs::read_dir(temp_web_server_dir.path()).unwrap().count());
+}
+
+fn drop_access_with_tags() -> (DropAccess, InMemoryTagRepo, InMemoryTokenRepo) {
+    let tag_repo = InMemoryTagRepo::default();
+    tag_repo.save(&Tag::new("bound".to_string(), chrono::NaiveDateTime::default()).with_drop_id(0));
+    tag_repo.save(&Tag::new("other".to_string(), chrono::NaiveDateTime::default()).with_drop_id(1));
+    let token_repo = InMemoryTokenRepo::default();
+    token_repo.save_token(&Token::new(uuid::Uuid::nil(), chrono::NaiveDateTime::default(), "bound".to_string()));
+    token_repo.save_token(&Token::new(uuid::Uuid::max(), chrono::NaiveDateTime::default(), "other".to_string()));
+    (DropAccess::new(Arc::new(tag_repo.clone()), Arc::new(token_repo.clone())), tag_repo, token_repo)
+}
+
+#[tokio::test]
+async fn test_unpublish_drop_keeps_the_rows_and_revokes_the_access() {
+    let temp_web_server_dir = TempDir::new().unwrap();
+    let web_server_path = temp_web_server_dir.path().to_str().unwrap().to_string();
+    let (drop_access, tag_repo, token_repo) = drop_access_with_tags();
+    let service = service_with_created_drop(&web_server_path).await.with_drop_access(drop_access);
+
+    service.unpublish_drop(0, &web_server_path).await.unwrap();
+    // the stored bytes stay referenced, the routes no longer serve them
+    assert!(content_path(temp_web_server_dir.path(), "first").exists());
+    assert!(!service.drop_repository().get(0).await.unwrap().published());
+    assert_eq!(1, service.track_repository().get_by_playlist(0).await.unwrap().len());
+    assert!(tag_repo.get("bound".to_string()).is_none());
+    assert!(token_repo.get_token(uuid::Uuid::nil()).is_none());
+    assert!(tag_repo.get("other".to_string()).is_some());
+    assert!(token_repo.get_token(uuid::Uuid::max()).is_some());
+
+    // unpublishing again changes nothing
+    service.unpublish_drop(0, &web_server_path).await.unwrap();
+    assert!(!service.drop_repository().get(0).await.unwrap().published());
+}
+
+#[tokio::test]
+async fn test_delete_drop_removes_its_rows_and_directories() {
+    let temp_web_server_dir = TempDir::new().unwrap();
+    let web_server_path = temp_web_server_dir.path().to_str().unwrap().to_string();
+    let (drop_access, tag_repo, _) = drop_access_with_tags();
+    let service = service_with_created_drop(&web_server_path).await.with_drop_access(drop_access);
+
+    service.delete_drop(0, &web_server_path).await.unwrap();
+    assert!(service.drop_repository().map().read().unwrap().is_empty());
+    assert!(service.playlist_repository().map().read().unwrap().is_empty());
+    assert!(service.track_repository().tracks().read().unwrap().is_empty());
+    assert!(service.artist_repository().get(1).await.is_ok());
+    assert_eq!(0, fs::read_dir(temp_web_server_dir.path()).unwrap().count());
+    assert!(tag_repo.get("bound".to_string()).is_none());
+
+    assert_eq!(Err(DropError::DropNotFound), service.delete_drop(0, &web_server_path).await);
+}
+
+#[tokio::test]
+async fn test_migrate_track_content_moves_the_playlist_files_to_the_content_store() {
+    let temp_web_server_dir = TempDir::new().unwrap();
+    let web_server_path = temp_web_server_dir.path().to_str().unwrap().to_string();
+    let service = DropService::new(DropRepoMock::new(), ArtistRepoMock::new(), PlaylistRepoMock::new(), ArtworkRepoMock::new(), TrackRepoMock::new());
+    service.drop_repository().map().write().unwrap().insert(3, Drop::new(3, 1, DropType::Album.id(), NO_ARTWORK_ID, 4));
+    // the files of tracks imported before the content store, the third one is missing
+    let playlist_dir = temp_web_server_dir.path().join(format!("{PLAYLIST_DIR_PREFIX}4"));
+    fs::create_dir(&playlist_dir).unwrap();
+    fs::write(playlist_dir.join(format!("{TRACK_FILE_PREFIX}1")), "same").unwrap();
+    fs::write(playlist_dir.join(format!("{TRACK_FILE_PREFIX}2")), "same").unwrap();
+    service.track_repository().tracks().write().unwrap().extend((1..=3).map(|position| {
+        Track::new(position, 4, position, format!("{position}.mp3"), None, None)
+    }));
+
+    let migration = service.migrate_track_content(&web_server_path).await.unwrap();
+    assert_eq!((2, 1, 1), (migration.migrated_tracks(), migration.deduplicated_tracks(), migration.missing_tracks()));
+    let same_hash = content_hash("same");
+    let tracks = service.track_repository().get_by_playlist(4).await.unwrap();
+    assert_eq!(
+        vec![Some(same_hash.as_str()), Some(same_hash.as_str()), None],
+        tracks.iter().map(Track::content_hash).collect::<Vec<_>>()
+    );
+    assert_eq!(Some(&(4, 2)), service.track_repository().contents().read().unwrap().get(&same_hash));
+    assert_eq!("same", fs::read_to_string(content_path(temp_web_server_dir.path(), "same")).unwrap());
+    assert!(!playlist_dir.exists());
+
+    // migrated tracks are not migrated again
+    let migration = service.migrate_track_content(&web_server_path).await.unwrap();
+    assert_eq!((0, 0, 1), (migration.migrated_tracks(), migration.deduplicated_tracks(), migration.missing_tracks()));
+}
+
+#[tokio::test]
+async fn test_sweep_orphaned_media_keeps_only_the_media_of_a_row() {
+    let temp_web_server_dir = TempDir::new().unwrap();
+    let web_server_path = temp_web_server_dir.path().to_str().unwrap().to_string();
+    let service = DropService::new(DropRepoMock::new(), ArtistRepoMock::new(), PlaylistRepoMock::new(), ArtworkRepoMock::new(), TrackRepoMock::new());
+    service.artwork_repository().map().write().unwrap().insert(1, Artwork::new(1, "image/png".to_string(), 320, 320));
+    service.playlist_repository().map().write().unwrap().insert(4, Playlist::new(4, "Kept".to_string()));
+    service.track_repository().contents().write().unwrap().insert(content_hash("kept"), (4, 1));
+    // the media of rows and the ones a failed rollback left
+    for (key, content) in [
+        (track_content_key(&content_hash("kept")).unwrap(), "kept"),
+        (track_content_key(&content_hash("orphan")).unwrap(), "orphan"),
+        (format!("{ARTWORK_DIR_PREFIX}1/original.png"), "kept"),
+        (format!("{ARTWORK_DIR_PREFIX}2/original.png"), "orphan"),
+        (format!("{ARTWORK_DIR_PREFIX}2/320.png"), "orphan"),
+        (format!("{PLAYLIST_DIR_PREFIX}4/{TRACK_FILE_PREFIX}1"), "kept"),
+        (format!("{PLAYLIST_DIR_PREFIX}5/{TRACK_FILE_PREFIX}1"), "orphan"),
+    ] {
+        let path = temp_web_server_dir.path().join(key);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    let sweep = service.sweep_orphaned_media(&web_server_path).await.unwrap();
+    assert_eq!((1, 1, 1), (sweep.track_contents(), sweep.artworks(), sweep.playlists()));
+    assert!(content_path(temp_web_server_dir.path(), "kept").exists());
+    assert!(!content_path(temp_web_server_dir.path(), "orphan").exists());
+    assert!(temp_web_server_dir.path().join(format!("{ARTWORK_DIR_PREFIX}1/original.png")).exists());
+    assert!(!temp_web_server_dir.path().join(format!("{ARTWORK_DIR_PREFIX}2")).exists());
+    assert!(temp_web_server_dir.path().join(format!("{PLAYLIST_DIR_PREFIX}4")).exists());
+    assert!(!temp_web_server_dir.path().join(format!("{PLAYLIST_DIR_PREFIX}5")).exists());
+
+    // nothing is left to sweep
+    let sweep = service.sweep_orphaned_media(&web_server_path).await.unwrap();
+    assert_eq!((0, 0, 0), (sweep.track_contents(), sweep.artworks(), sweep.playlists()));
 }
@@ -1,8 +1,11 @@
 use drop_reverse_proxy::repository::artist::{Artist, ArtistRepo};
+use drop_reverse_proxy::repository::artwork::ArtworkRepo;
 use drop_reverse_proxy::repository::drop::DropRepo;
 use drop_reverse_proxy::repository::playlist::PlaylistRepo;
+use drop_reverse_proxy::repository::track::{TrackRepo, TrackRepoT};
 use drop_reverse_proxy::repository::RepoByName;
-use drop_reverse_proxy::service::drop::{DropRequest, DropService, DropServiceT, PLAYLIST_DIR_PREFIX, TRACK_FILE_PREFIX};
+use drop_reverse_proxy::service::drop::{track_content_key, DropRequest, DropService, DropServiceT};
+use sha2::{Digest, Sha256};
 use std::fs;
 use std::sync::Arc;
 use tempfile::TempDir;
@@ -28,43 +31,9 @@ async fn setup_db() -> (drop_reverse_proxy::config::db::DatabaseConfig, Containe
         .await
         .expect("Failed to create database pool");
 
-    sqlx::query(
-        r#"
-        CREATE TABLE "artist" (
-            id SERIAL PRIMARY KEY,
-            name VARCHAR(255) NOT NULL
-        )
-        "#
-    )
-        .execute(&pool)
+    drop_reverse_proxy::config::db::run_migrations(&pool)
         .await
-        .expect("Failed to create artist table");
-
-    sqlx::query(
-        r#"
-        CREATE TABLE "playlist" (
-            id SERIAL PRIMARY KEY,
-            name VARCHAR(255) NOT NULL
-        )
-        "#
-    )
-        .execute(&pool)
-        .await
-        .expect("Failed to create playlist table");
-
-    sqlx::query(
-        r#"
-        CREATE TABLE "drop" (
-            id SERIAL PRIMARY KEY,
-            artist_id INTEGER NOT NULL,
-            artwork_id INTEGER NOT NULL,
-            type_id SMALLINT NOT NULL
-        )
-        "#
-    )
-        .execute(&pool)
-        .await
-        .expect("Failed to create drop table");
+        .expect("Failed to run migrations");
 
     (db_config, container)
 }
@@ -79,8 +48,10 @@ async fn test_create_drop_with_new_artist_name() {
     let artist_repo = Arc::new(ArtistRepo::new(&db_config).await.unwrap());
     let drop_repo = Arc::new(DropRepo::new(&db_config).await.unwrap());
     let playlist_repo = Arc::new(PlaylistRepo::new(&db_config).await.unwrap());
+    let artwork_repo = Arc::new(ArtworkRepo::new(&db_config).await.unwrap());
+    let track_repo = Arc::new(TrackRepo::new(&db_config).await.unwrap());
 
-    let service = DropService::new(drop_repo, artist_repo.clone(), playlist_repo);
+    let service = DropService::new(drop_repo, artist_repo.clone(), playlist_repo, artwork_repo, track_repo.clone());
 
     let temp_import_dir = TempDir::new().unwrap();
     let import_path = temp_import_dir.path().to_str().unwrap().to_string();
@@ -105,14 +76,16 @@ async fn test_create_drop_with_new_artist_name() {
 
     service.create_drop(&import_path, drop_request, &web_server_path).await.expect("Failed to create drop");
 
-    // Verify file system
-    // The playlist ID should be 1
-    let playlist_dir = temp_web_server_dir.path().join(format!("{}{}", PLAYLIST_DIR_PREFIX, 1));
-    assert!(playlist_dir.exists());
-    assert!(playlist_dir.join(format!("{}{}", TRACK_FILE_PREFIX, 1)).exists());
-    assert!(playlist_dir.join(format!("{}{}", TRACK_FILE_PREFIX, 2)).exists());
-
-    assert_eq!(fs::read_to_string(playlist_dir.join(format!("{}{}", TRACK_FILE_PREFIX, 1))).unwrap(), "fake mp3 content 1");
+    // the tracks are recorded in their order with the hash their bytes are stored under
+    let tracks = track_repo.get_by_playlist(1).await.unwrap();
+    assert_eq!(vec!["track1.mp3", "track2.mp3"], tracks.iter().map(|track| track.source_file()).collect::<Vec<_>>());
+    assert_eq!(vec![1, 2], tracks.iter().map(|track| track.position()).collect::<Vec<_>>());
+    let content_hash = hex::encode(Sha256::digest("fake mp3 content 1"));
+    assert_eq!(Some(content_hash.as_str()), tracks[0].content_hash());
+    assert_eq!(
+        fs::read_to_string(temp_web_server_dir.path().join(track_content_key(&content_hash).unwrap())).unwrap(),
+        "fake mp3 content 1"
+    );
 }
 
 #[tokio::test]
@@ -124,8 +97,10 @@ async fn test_create_drop_with_existing_artist_id() {
     let artist_repo = Arc::new(ArtistRepo::new(&db_config).await.unwrap());
     let drop_repo = Arc::new(DropRepo::new(&db_config).await.unwrap());
     let playlist_repo = Arc::new(PlaylistRepo::new(&db_config).await.unwrap());
+    let artwork_repo = Arc::new(ArtworkRepo::new(&db_config).await.unwrap());
+    let track_repo = Arc::new(TrackRepo::new(&db_config).await.unwrap());
 
-    let service = DropService::new(drop_repo, artist_repo.clone(), playlist_repo);
+    let service = DropService::new(drop_repo, artist_repo.clone(), playlist_repo, artwork_repo, track_repo.clone());
 
     let artist_id = artist_repo.save_or_update(&Artist::new(0, "Existing Artist".to_string())).await.unwrap();
 
@@ -145,8 +120,8 @@ async fn test_create_drop_with_existing_artist_id() {
 
     service.create_drop(&import_path, drop_request, &web_server_path).await.expect("Failed to create drop");
 
-    let playlist_dir = temp_web_server_dir.path().join(format!("{}{}", PLAYLIST_DIR_PREFIX, 1));
-    assert!(playlist_dir.exists());
+    let content_key = track_content_key(&hex::encode(Sha256::digest("c1"))).unwrap();
+    assert!(temp_web_server_dir.path().join(content_key).exists());
 }
 
 #[tokio::test]
@@ -156,8 +131,10 @@ async fn test_create_drop_error_both_id_and_name() {
     let artist_repo = Arc::new(ArtistRepo::new(&db_config).await.unwrap());
     let drop_repo = Arc::new(DropRepo::new(&db_config).await.unwrap());
     let playlist_repo = Arc::new(PlaylistRepo::new(&db_config).await.unwrap());
+    let artwork_repo = Arc::new(ArtworkRepo::new(&db_config).await.unwrap());
+    let track_repo = Arc::new(TrackRepo::new(&db_config).await.unwrap());
 
-    let service = DropService::new(drop_repo, artist_repo, playlist_repo);
+    let service = DropService::new(drop_repo, artist_repo, playlist_repo, artwork_repo, track_repo);
 
     let drop_request = DropRequest::new(
         Some(1),
@@ -166,7 +143,7 @@ async fn test_create_drop_error_both_id_and_name() {
         vec![],
     );
 
-    let result = service.create_drop(&".".to_string(), drop_request, &".".to_string()).await;
+    let result = service.create_drop(".", drop_request, ".").await;
     assert!(result.is_err());
     //assert_eq!(result.unwrap_err().to_string(), "Both artist_id and artist_name are set, but only one is allowed");
     // Should be ArtistIdAndArtistNameAreBothPresent
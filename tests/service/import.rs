@@ -0,0 +1,269 @@
+use super::drop::mock::repository::artist::ArtistRepoMock;
+use super::drop::mock::repository::drop::DropRepoMock;
+use super::drop::mock::repository::playlist::PlaylistRepoMock;
+use super::drop::mock::repository::artwork::ArtworkRepoMock;
+use super::drop::mock::repository::track::TrackRepoMock;
+use chrono::Utc;
+use drop_reverse_proxy::repository::artist::Artist;
+use drop_reverse_proxy::repository::import::{ImportRepoT, ImportState, InMemoryImportRepo};
+use drop_reverse_proxy::repository::import_job::{FileImportReport, FileImportState, ImportJob, ImportJobState, InMemoryImportJobRepo};
+use drop_reverse_proxy::repository::Repo;
+use drop_reverse_proxy::service::drop::{DropService, ImportError};
+use drop_reverse_proxy::service::import::{import_drop_archive, record_import_result, ImportJobQueue, ERROR_REPORT_SUFFIX, FAILED_DIR, PROCESSED_DIR};
+use drop_reverse_proxy::service::watcher::start_import_watcher;
+use drop_reverse_proxy::service::workspace::{ExtractionWorkspace, EXTRACTION_DIR_PREFIX};
+use sqlx::types::Json;
+use std::fs;
+use std::sync::Arc;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+use tempfile::TempDir;
+
+fn init_drop_service() -> DropService<DropRepoMock, ArtistRepoMock, PlaylistRepoMock, ArtworkRepoMock, TrackRepoMock> {
+    let artist_repo = ArtistRepoMock::new();
+    artist_repo.map_by_name().write().unwrap().insert("Cool Rasta".to_string(), Artist::new(3, "Cool Rasta".to_string()));
+    DropService::new(DropRepoMock::new(), artist_repo, PlaylistRepoMock::new(), ArtworkRepoMock::new(), TrackRepoMock::new())
+}
+
+fn init_extraction_workspace(dir: &Path) -> ExtractionWorkspace {
+    ExtractionWorkspace::new(dir.join(".scratch"), Duration::from_secs(3600))
+}
+
+async fn wait_for_finished_job(import_job_queue: &ImportJobQueue, id: i32) -> ImportJob {
+    for _ in 0..100 {
+        let import_job = import_job_queue.get(id).await.unwrap();
+        if import_job.state().is_finished() {
+            return import_job;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("import job {id} not finished");
+}
+
+#[tokio::test]
+async fn import_job_reports_each_file() {
+    let import_dir = TempDir::new().unwrap();
+    let archive_path = import_dir.path().join("import").join("drop_ok.tar.gz");
+    fs::create_dir(archive_path.parent().unwrap()).unwrap();
+    fs::copy("tests/resources/import_path/correct_tar_gz/drop_ok.tar.gz", &archive_path).unwrap();
+    let web_server_dir = TempDir::new().unwrap();
+
+    let import_job_queue = ImportJobQueue::start(
+        Arc::new(InMemoryImportJobRepo::default()),
+        Arc::new(InMemoryImportRepo::default()),
+        Arc::new(init_drop_service()),
+        init_extraction_workspace(import_dir.path()),
+        archive_path.parent().unwrap().to_str().unwrap().to_string(),
+        web_server_dir.path().to_str().unwrap().to_string(),
+        2
+    );
+    let missing_archive = import_dir.path().join("import").join("drop_missing.tar.gz");
+    let import_job = import_job_queue.enqueue(vec![
+        archive_path.to_str().unwrap().to_string(),
+        missing_archive.to_str().unwrap().to_string(),
+    ]).await.unwrap();
+    assert_eq!(ImportJobState::Queued, import_job.state());
+
+    let import_job = wait_for_finished_job(&import_job_queue, import_job.id()).await;
+
+    assert_eq!(ImportJobState::PartiallySucceeded, import_job.state());
+    let imported = &import_job.files()[0];
+    assert_eq!(FileImportState::Imported, imported.state());
+    assert_eq!(Some(3), imported.artist_id());
+    assert_eq!(Some(0), imported.playlist_id());
+    let failed = &import_job.files()[1];
+    assert_eq!(FileImportState::Failed, failed.state());
+    assert!(failed.error().is_some());
+}
+
+#[test]
+fn file_report_tells_the_failures_of_the_server_from_the_ones_of_the_archive() {
+    let mut file_report = FileImportReport::new("drop.tar.gz".to_string(), FileImportState::Running);
+    record_import_result(&mut file_report, &Err(ImportError::InvalidDropManifest));
+    assert_eq!(FileImportState::Failed, file_report.state());
+    assert!(!file_report.server_error());
+
+    record_import_result(&mut file_report, &Err(ImportError::CantCommitDropCreation));
+    assert_eq!(FileImportState::Failed, file_report.state());
+    assert!(file_report.server_error());
+    assert_eq!(Some(true), serde_json::to_value(&file_report).unwrap()["server_error"].as_bool());
+}
+
+#[tokio::test]
+async fn unfinished_import_jobs_are_resumed() {
+    let web_server_dir = TempDir::new().unwrap();
+    let import_job_repo = InMemoryImportJobRepo::default();
+    // a job interrupted by a restart while its first file was already imported
+    let mut imported = FileImportReport::new("tests/resources/import_path/drop_already_imported.tar.gz".to_string(), FileImportState::Pending);
+    imported.set_imported(1, 1, 3);
+    let now = Utc::now().naive_utc();
+    let interrupted_job = ImportJob::new(
+        0,
+        ImportJobState::Running.as_str().to_string(),
+        Json(vec![
+            imported,
+            FileImportReport::new("tests/resources/import_path/drop_missing.tar.gz".to_string(), FileImportState::Running),
+        ]),
+        now,
+        now
+    );
+    let id = import_job_repo.save_or_update(&interrupted_job).await.unwrap();
+
+    let import_job_queue = ImportJobQueue::start(
+        Arc::new(import_job_repo),
+        Arc::new(InMemoryImportRepo::default()),
+        Arc::new(init_drop_service()),
+        init_extraction_workspace(web_server_dir.path()),
+        web_server_dir.path().to_str().unwrap().to_string(),
+        web_server_dir.path().to_str().unwrap().to_string(),
+        1
+    );
+    assert_eq!(1, import_job_queue.resume_unfinished().await.unwrap());
+
+    let import_job = wait_for_finished_job(&import_job_queue, id).await;
+    assert_eq!(ImportJobState::PartiallySucceeded, import_job.state());
+    assert_eq!(FileImportState::Imported, import_job.files()[0].state());
+    assert_eq!(Some(1), import_job.files()[0].drop_id());
+    assert_eq!(FileImportState::Failed, import_job.files()[1].state());
+}
+
+#[tokio::test]
+async fn watcher_queues_archives_renamed_into_import_path() {
+    let import_dir = TempDir::new().unwrap();
+    let web_server_dir = TempDir::new().unwrap();
+    let import_job_repo = Arc::new(InMemoryImportJobRepo::default());
+    let import_job_queue = ImportJobQueue::start(
+        import_job_repo.clone(),
+        Arc::new(InMemoryImportRepo::default()),
+        Arc::new(init_drop_service()),
+        init_extraction_workspace(import_dir.path()),
+        import_dir.path().to_str().unwrap().to_string(),
+        web_server_dir.path().to_str().unwrap().to_string(),
+        1
+    );
+    let _import_watcher = start_import_watcher(
+        import_dir.path().to_str().unwrap(),
+        import_job_queue.clone(),
+        Duration::from_millis(200)
+    ).unwrap();
+
+    let partial_path = import_dir.path().join("upload.part");
+    fs::copy("tests/resources/import_path/correct_tar_gz/drop_ok.tar.gz", &partial_path).unwrap();
+    fs::rename(&partial_path, import_dir.path().join("drop_ok.tar.gz")).unwrap();
+
+    let mut import_job = None;
+    for _ in 0..100 {
+        if let Ok(queued) = import_job_repo.get(1).await {
+            import_job = Some(queued);
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    let import_job = wait_for_finished_job(&import_job_queue, import_job.expect("no import job queued").id()).await;
+    assert_eq!(ImportJobState::Succeeded, import_job.state());
+    assert_eq!(1, import_job.files().len());
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert!(import_job_repo.get(2).await.is_err());
+}
+
+#[tokio::test]
+async fn drop_archive_is_imported_once_then_moved_to_processed() {
+    let import_dir = TempDir::new().unwrap();
+    let web_server_dir = TempDir::new().unwrap();
+    let import_path = import_dir.path().to_str().unwrap();
+    let web_server_path = web_server_dir.path().to_str().unwrap();
+    let drop_service = init_drop_service();
+    let import_repo = InMemoryImportRepo::default();
+    let extraction_workspace = init_extraction_workspace(import_dir.path());
+    let archive_path = import_dir.path().join("drop_ok.tar.gz");
+    fs::copy("tests/resources/import_path/correct_tar_gz/drop_ok.tar.gz", &archive_path).unwrap();
+
+    let created_drop = import_drop_archive(&drop_service, &import_repo, &extraction_workspace, archive_path.to_str().unwrap(), import_path, web_server_path)
+        .await
+        .unwrap();
+
+    assert!(!archive_path.exists());
+    assert!(import_dir.path().join(PROCESSED_DIR).join("drop_ok.tar.gz").exists());
+    let import = import_repo.get(1).await.unwrap();
+    assert_eq!(ImportState::Imported, import.state());
+    assert_eq!(Some(created_drop.drop_id()), import.drop_id());
+    assert_eq!(64, import.sha256().len());
+    // the extraction is removed once the drop is created
+    assert_eq!(0, fs::read_dir(extraction_workspace.scratch_path()).unwrap().count());
+
+    // the same content under another name is recognised by its fingerprint
+    let copy_path = import_dir.path().join("drop_copy.tar.gz");
+    fs::copy("tests/resources/import_path/correct_tar_gz/drop_ok.tar.gz", &copy_path).unwrap();
+    let result = import_drop_archive(
+        &drop_service,
+        &import_repo,
+        &extraction_workspace,
+        copy_path.to_str().unwrap(),
+        import_path,
+        web_server_path
+    ).await;
+
+    assert!(matches!(result, Err(ImportError::DropFileAlreadyImported)));
+    assert!(!copy_path.exists());
+    assert!(import_dir.path().join(PROCESSED_DIR).join("drop_copy.tar.gz").exists());
+    assert_eq!(1, fs::read_dir(web_server_dir.path()).unwrap().count());
+}
+
+#[tokio::test]
+async fn failed_drop_archive_is_moved_to_failed_with_an_error_report() {
+    let import_dir = TempDir::new().unwrap();
+    let web_server_dir = TempDir::new().unwrap();
+    let import_repo = InMemoryImportRepo::default();
+    let extraction_workspace = init_extraction_workspace(import_dir.path());
+    let archive_path = import_dir.path().join("drop_invalid.tar.gz");
+    fs::write(&archive_path, "not a tar.gz archive").unwrap();
+
+    let result = import_drop_archive(
+        &init_drop_service(),
+        &import_repo,
+        &extraction_workspace,
+        archive_path.to_str().unwrap(),
+        import_dir.path().to_str().unwrap(),
+        web_server_dir.path().to_str().unwrap()
+    ).await;
+
+    assert!(matches!(result, Err(ImportError::UnsupportedArchiveFormat)));
+    let failed_dir = import_dir.path().join(FAILED_DIR);
+    assert!(failed_dir.join("drop_invalid.tar.gz").exists());
+    let report = fs::read_to_string(failed_dir.join(format!("drop_invalid.tar.gz{ERROR_REPORT_SUFFIX}"))).unwrap();
+    let report: serde_json::Value = serde_json::from_str(&report).unwrap();
+    assert_eq!(Some("UnsupportedArchiveFormat"), report["error"].as_str());
+    let import = import_repo.get_by_sha256(report["sha256"].as_str().unwrap()).await.unwrap();
+    assert_eq!(ImportState::Failed, import.state());
+    // the extraction is kept in quarantine
+    assert_eq!(1, fs::read_dir(extraction_workspace.quarantine_path()).unwrap().count());
+
+    // a failed archive put back in import_path is imported again
+    assert!(import_repo.start_import(import.sha256(), "drop_invalid.tar.gz", Utc::now().naive_utc()).await.unwrap().is_some());
+    assert!(import_repo.start_import(import.sha256(), "drop_invalid.tar.gz", Utc::now().naive_utc()).await.unwrap().is_none());
+}
+
+#[test]
+fn extraction_workspace_sweep_removes_leftovers_and_expired_quarantines() {
+    let scratch_dir = TempDir::new().unwrap();
+    let extraction_workspace = ExtractionWorkspace::new(scratch_dir.path().to_path_buf(), Duration::from_secs(3600));
+
+    // an extraction left by a stopped server
+    let leftover = extraction_workspace.extraction_dir().unwrap().keep();
+    fs::write(leftover.join("drop.txt"), "playlist_name = \"leftover\"").unwrap();
+    let quarantined = extraction_workspace.quarantine(extraction_workspace.extraction_dir().unwrap()).unwrap();
+    assert!(quarantined.starts_with(extraction_workspace.quarantine_path()));
+    assert!(quarantined.file_name().unwrap().to_str().unwrap().starts_with(EXTRACTION_DIR_PREFIX));
+
+    // the quarantine is kept within the retention
+    let sweep_report = extraction_workspace.sweep(SystemTime::now()).unwrap();
+    assert_eq!(1, sweep_report.leftover_extractions());
+    assert_eq!(0, sweep_report.expired_quarantines());
+    assert!(!leftover.exists());
+    assert!(quarantined.exists());
+
+    let sweep_report = extraction_workspace.sweep(SystemTime::now() + Duration::from_secs(3600)).unwrap();
+    assert_eq!(0, sweep_report.leftover_extractions());
+    assert_eq!(1, sweep_report.expired_quarantines());
+    assert!(!quarantined.exists());
+}
@@ -0,0 +1,118 @@
+use crate::utils::{create_default_db_config, start_postgres_container};
+use drop_reverse_proxy::repository::track::{CuePoint, Track, TrackRepo, TrackRepoT};
+use drop_reverse_proxy::repository::unit_of_work::UnitOfWork;
+use drop_reverse_proxy::repository::{Repo, RepositoryError};
+
+mod utils;
+
+#[tokio::test]
+async fn test_track_repo_integration() {
+    let db_name = "drop_of_culture";
+    let user = "drop_of_culture";
+    let password = "drop_of_culture";
+    let (_container_guard, host, port) = start_postgres_container(
+        db_name,
+        user,
+        password,
+    ).await.expect("Failed to start Postgres container");
+
+    let db_config = create_default_db_config(host, port, db_name, user, password);
+
+    let pool = drop_reverse_proxy::config::db::create_pool(&db_config)
+        .await
+        .expect("Failed to create database pool");
+
+    drop_reverse_proxy::config::db::run_migrations(&pool)
+        .await
+        .expect("Failed to run migrations");
+
+    let repo = TrackRepo::new(&db_config)
+        .await
+        .expect("Failed to create track repository");
+
+    // rows the tracks reference
+    for statement in [
+        "INSERT INTO \"playlist\" (id, name) SELECT id, 'playlist ' || id FROM generate_series(5, 9) id",
+        "INSERT INTO \"track_content\" (content_hash, size, ref_count) VALUES ('ab12', 10, 1)",
+    ] {
+        sqlx::query(statement).execute(&pool).await.expect("Failed to insert referenced rows");
+    }
+
+    // saved out of order, listed by position
+    let second_id = repo.save_or_update(&Track::new(0, 5, 2, "02.flac".to_string(), None, None))
+        .await
+        .expect("Failed to save track");
+    let first_id = repo.save_or_update(&Track::new(0, 5, 1, "01.flac".to_string(), Some("Dawn".to_string()), Some(180)))
+        .await
+        .expect("Failed to save track");
+    repo.save_or_update(&Track::new(0, 6, 1, "other.flac".to_string(), None, None))
+        .await
+        .expect("Failed to save track");
+
+    assert_eq!(
+        vec![
+            Track::new(first_id, 5, 1, "01.flac".to_string(), Some("Dawn".to_string()), Some(180)),
+            Track::new(second_id, 5, 2, "02.flac".to_string(), None, None),
+        ],
+        repo.get_by_playlist(5).await.expect("Failed to get tracks")
+    );
+
+    // a track without id at a taken position leaves the one there as it was
+    assert!(matches!(
+        repo.save_or_update(&Track::new(0, 5, 1, "again.flac".to_string(), None, None)).await,
+        Err(RepositoryError::UniqueViolation)
+    ));
+    assert_eq!("01.flac", repo.get(first_id).await.expect("Failed to get track").source_file());
+
+    // update by id, the version read must still be the stored one
+    let updated_track = Track::new(first_id, 5, 1, "again.flac".to_string(), Some("Again".to_string()), None);
+    assert_eq!(first_id, repo.save_or_update(&updated_track).await.expect("Failed to update track"));
+    assert!(matches!(repo.save_or_update(&updated_track).await, Err(RepositoryError::VersionConflict)));
+
+    // a track saved in a unit of work dropped without commit is rolled back
+    {
+        let mut unit_of_work = UnitOfWork::new();
+        repo.save_or_update_in(&Track::new(0, 7, 1, "rolled_back.flac".to_string(), None, None), &mut unit_of_work)
+            .await
+            .expect("Failed to save track in unit of work");
+    }
+    assert!(repo.get_by_playlist(7).await.expect("Failed to get tracks").is_empty());
+
+    // the hash of the bytes is kept with the track
+    let hashed_id = repo.save_or_update(&Track::new(0, 8, 1, "hashed.flac".to_string(), None, None).with_content_hash("ab12"))
+        .await
+        .expect("Failed to save track");
+    assert_eq!(Some("ab12"), repo.get(hashed_id).await.expect("Failed to get track").content_hash());
+    assert!(matches!(
+        repo.save_or_update(&Track::new(0, 8, 2, "unstored.flac".to_string(), None, None).with_content_hash("ef56")).await,
+        Err(RepositoryError::ForeignKeyViolation)
+    ));
+
+    // the recording fields and cue points of the manifest are kept with the track
+    let recorded_track = Track::new(0, 9, 1, "recorded.flac".to_string(), None, None)
+        .with_isrc(Some("USRC17607839".to_string()))
+        .with_explicit(true)
+        .with_numbering(Some(1), Some(3))
+        .with_cue_points(vec![CuePoint::new(0, "Intro".to_string()), CuePoint::new(40, "Dub".to_string())]);
+    let recorded_id = repo.save_or_update(&recorded_track).await.expect("Failed to save track");
+    let stored_track = repo.get(recorded_id).await.expect("Failed to get track");
+    assert_eq!(Some("USRC17607839"), stored_track.isrc());
+    assert!(stored_track.explicit());
+    assert_eq!((Some(1), Some(3)), (stored_track.disc_number(), stored_track.track_number()));
+    assert_eq!(recorded_track.cue_points(), stored_track.cue_points());
+
+    // a content is counted once per reference, it is deleted once unreferenced
+    let mut unit_of_work = UnitOfWork::new();
+    assert_eq!(1, repo.retain_content_in("cd34", 10, &mut unit_of_work).await.expect("Failed to retain content"));
+    assert_eq!(2, repo.retain_content_in("cd34", 10, &mut unit_of_work).await.expect("Failed to retain content"));
+    assert_eq!(1, repo.release_content_in("cd34", &mut unit_of_work).await.expect("Failed to release content"));
+    assert!(!repo.delete_unreferenced_content_in("cd34", &mut unit_of_work).await.expect("Failed to delete content"));
+    assert_eq!(0, repo.release_content_in("cd34", &mut unit_of_work).await.expect("Failed to release content"));
+    assert!(matches!(repo.release_content_in("cd34", &mut unit_of_work).await, Err(RepositoryError::EntityNotFound)));
+    assert!(repo.delete_unreferenced_content_in("cd34", &mut unit_of_work).await.expect("Failed to delete content"));
+    unit_of_work.commit().await.expect("Failed to commit");
+
+    // the tracks of a deleted playlist are deleted with it
+    sqlx::query("DELETE FROM \"playlist\" WHERE id = 6").execute(&pool).await.expect("Failed to delete playlist");
+    assert!(repo.get_by_playlist(6).await.expect("Failed to get tracks").is_empty());
+}
@@ -1,6 +1,22 @@
-use drop_reverse_proxy::{check_drop_file, check_unarchived_drop_files, create_conf_from_toml_file, create_drop_request_from_toml_file, look_for_drop_files_at_path, IpRepo};
+use drop_reverse_proxy::{check_drop_file, check_unarchived_drop_files, create_conf_from_toml_file, create_drop_manifest_from_file, create_drop_request_from_toml_file, look_for_drop_files_at_path, IpRepo};
+use drop_reverse_proxy::config::db::MIGRATOR;
+use drop_reverse_proxy::media::{move_prefix, ByteRange, HttpOriginStore, LocalMediaStore, MediaContent, MediaStore, MediaStoreError, RangedContent};
+use drop_reverse_proxy::repository::api_key::{hash_api_key, rotate_api_key, ApiKeyRepoT, ApiKeyScope, InMemoryApiKeyRepo};
+use drop_reverse_proxy::repository::{Repo, RepositoryError};
+use drop_reverse_proxy::repository::artist::normalize_artist_name;
+use drop_reverse_proxy::repository::drop::{Drop, DropField};
+use drop_reverse_proxy::repository::drop_type::DropType;
+use drop_reverse_proxy::repository::query::{Filter, Order};
+use drop_reverse_proxy::repository::track::Track;
+use drop_reverse_proxy::service::artwork::{probe_artwork, write_artwork_variants, ArtworkFormat, ArtworkSize};
+use drop_reverse_proxy::service::audio::{probe_audio_file, AudioFormat};
+use drop_reverse_proxy::service::archive::{extract_archive_with_limits, ArchiveFormat, ExtractionLimits};
+use drop_reverse_proxy::service::drop::{track_content_key, track_key, DropRequest, ImportError, ImportPolicy, ManifestError, ManifestFormat};
+use drop_reverse_proxy::service::watcher::StableFileTracker;
 use std::fs;
-use std::path::Path;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 #[test]
 fn ip_repo_save_or_update_when_not_exists() {
@@ -46,6 +62,7 @@ fn conf_is_ok() {
     assert_eq!("drop_of_culture", config.db_conf().unwrap().db_password());
     assert_eq!(10, config.db_conf().unwrap().db_pool_size());
     assert_eq!(10000, config.db_conf().unwrap().db_timeout());
+    assert_eq!(ImportPolicy::Strict, config.import_policy());
 }
 
 #[test]
@@ -80,9 +97,299 @@ fn create_drop_from_toml_file_should_return_drop_struct() {
     assert_eq!("Cool Rasta", drop.artist_name().as_ref().unwrap());
     assert_eq!("Rasta's playlist", drop.playlist_name());
     assert_eq!(3, drop.tracks().len());
-    assert!(drop.tracks().contains(&"track001.mp3".to_string()));
-    assert!(drop.tracks().contains(&"track002.mp3".to_string()));
-    assert!(drop.tracks().contains(&"track003.mp3".to_string()));
+    assert!(drop.tracks().contains(&"track001.wav".to_string()));
+    assert!(drop.tracks().contains(&"track002.wav".to_string()));
+    assert!(drop.tracks().contains(&"track003.wav".to_string()));
+}
+
+/// One second of 8 kHz noise without tags
+const FIXTURE_TRACK: &str = "tests/resources/import_path/untar_drop/ok/drop_ok/track002.wav";
+
+#[test]
+fn drop_manifest_without_version_is_read_as_version_1() {
+    let drop_manifest = create_drop_manifest_from_file(Path::new("tests/resources/import_path/untar_drop/ok/drop_ok/drop.txt"), ManifestFormat::Toml).unwrap();
+    assert_eq!(1, drop_manifest.version());
+    assert_eq!(Some("Cool Rasta"), drop_manifest.artist_name());
+    assert_eq!(3, drop_manifest.tracks().len());
+    assert_eq!("track001.wav", drop_manifest.tracks()[0].file());
+    assert_eq!(None, drop_manifest.tracks()[0].title());
+}
+
+#[test]
+fn drop_manifest_v2_has_release_fields_and_track_metadata() {
+    let drop_manifest = create_drop_manifest_from_file(Path::new("tests/resources/manifest/drop_v2.txt"), ManifestFormat::Toml).unwrap();
+    assert_eq!(2, drop_manifest.version());
+    assert_eq!(chrono::NaiveDate::from_ymd_opt(2026, 5, 1), drop_manifest.release_date());
+    assert_eq!(Some("Irie Records"), drop_manifest.label());
+    assert_eq!(Some("Reggae"), drop_manifest.genre());
+    assert_eq!(Some("cover.jpg"), drop_manifest.artwork());
+    assert_eq!("producer", drop_manifest.credits()[0].role());
+    assert_eq!("King Tubby", drop_manifest.credits()[0].name());
+    let track = &drop_manifest.tracks()[0];
+    assert_eq!(Some("Intro"), track.title());
+    assert_eq!(Some(95), track.duration_seconds());
+    assert_eq!(Some("USRC17607839".to_string()), track.isrc());
+    assert!(!track.explicit());
+    assert!(drop_manifest.tracks()[1].explicit());
+    assert_eq!(Some(2), drop_manifest.tracks()[1].track_number());
+}
+
+#[test]
+fn drop_request_of_a_v2_manifest_keeps_the_release_fields_and_track_metadata() {
+    let drop_manifest = create_drop_manifest_from_file(Path::new("tests/resources/manifest/drop_v2.txt"), ManifestFormat::Toml).unwrap();
+    let drop_request = DropRequest::from(&drop_manifest);
+    let release = drop_request.release();
+    assert_eq!(chrono::NaiveDate::from_ymd_opt(2026, 5, 1), release.release_date());
+    assert_eq!(Some("Irie Records"), release.label());
+    assert_eq!(Some("Reggae"), release.genre());
+    assert_eq!(Some("First drop of the summer"), release.description());
+    assert_eq!("King Tubby", release.credits()[0].name());
+    let track_metadata = &drop_request.track_metadata()[0];
+    assert_eq!(Some("USRC17607839"), track_metadata.isrc());
+    assert_eq!((Some(1), Some(1)), (track_metadata.disc_number(), track_metadata.track_number()));
+    assert!(drop_request.track_metadata()[1].explicit());
+}
+
+#[test]
+fn drop_manifest_v2_reports_every_invalid_field() {
+    let manifest_dir = tempfile::TempDir::new().unwrap();
+    let manifest_path = manifest_dir.path().join("drop.txt");
+    fs::write(&manifest_path, r#"
+version = 2
+playlist_name = " "
+release_date = "01/05/2026"
+[[credits]]
+role = "producer"
+name = ""
+[[tracks]]
+file = "track001.wav"
+isrc = "USRC1760"
+duration_seconds = 0
+track_number = 1
+[[tracks]]
+file = "track002.wav"
+track_number = 1
+"#).unwrap();
+
+    let result = create_drop_manifest_from_file(&manifest_path, ManifestFormat::Toml);
+
+    let Err(ManifestError::InvalidFields(field_errors)) = result else {
+        panic!("unexpected result {:?}", result);
+    };
+    let fields: Vec<&str> = field_errors.iter().map(|field_error| field_error.field()).collect();
+    assert_eq!(vec![
+        "playlist_name",
+        "release_date",
+        "credits[0].name",
+        "tracks[0].duration_seconds",
+        "tracks[0].isrc",
+        "tracks[1].track_number",
+    ], fields);
+}
+
+#[test]
+fn drop_type_rules_follow_the_number_of_tracks() {
+    assert!(DropType::Single.accepts_track_count(3));
+    assert!(!DropType::Single.accepts_track_count(4));
+    assert!(DropType::Ep.accepts_track_count(4));
+    assert!(!DropType::Ep.accepts_track_count(7));
+    assert!(!DropType::Album.accepts_track_count(6));
+    assert!(DropType::Album.accepts_track_count(40));
+    assert!(DropType::Mix.accepts_track_count(1));
+    assert!(DropType::Mix.requires_cue_points());
+    assert!(!DropType::Album.requires_cue_points());
+
+    assert_eq!(DropType::Single, DropType::for_track_count(2));
+    assert_eq!(DropType::Ep, DropType::for_track_count(5));
+    assert_eq!(DropType::Album, DropType::for_track_count(12));
+    for drop_type in DropType::ALL {
+        assert_eq!(Some(drop_type), DropType::from_id(drop_type.id()));
+    }
+    assert_eq!(None, DropType::from_id(0));
+}
+
+#[test]
+fn drop_manifest_type_is_read_or_guessed_from_the_tracks() {
+    let manifest_dir = tempfile::TempDir::new().unwrap();
+    let manifest_path = manifest_dir.path().join("drop.txt");
+    fs::write(&manifest_path, r#"
+version = 2
+artist_name = "Cool Rasta"
+playlist_name = "Sunrise"
+type = "single"
+[[tracks]]
+file = "track001.wav"
+"#).unwrap();
+    let drop_manifest = create_drop_manifest_from_file(&manifest_path, ManifestFormat::Toml).unwrap();
+    assert_eq!(DropType::Single, drop_manifest.drop_type());
+    assert_eq!(Some(DropType::Single), DropRequest::from(&drop_manifest).drop_type());
+
+    // a v1 manifest gives no type, 3 tracks make a single
+    let drop_manifest = create_drop_manifest_from_file(
+        Path::new("tests/resources/import_path/untar_drop/ok/drop_ok/drop.txt"),
+        ManifestFormat::Toml
+    ).unwrap();
+    assert_eq!(DropType::Single, drop_manifest.drop_type());
+}
+
+#[test]
+fn artist_names_are_normalized() {
+    assert_eq!("cool rasta", normalize_artist_name("Cool Rasta"));
+    assert_eq!("cool rasta", normalize_artist_name(" cool \t RASTA  "));
+    // compatibility forms such as full width letters
+    assert_eq!("cool rasta", normalize_artist_name("Ｃｏｏｌ Ｒａｓｔａ"));
+    // composed and decomposed accents
+    assert_eq!(normalize_artist_name("Beyonc\u{e9}"), normalize_artist_name("BEYONCE\u{301}"));
+    assert_eq!("", normalize_artist_name("   "));
+}
+
+#[test]
+fn drop_manifest_gives_the_import_policy() {
+    let manifest_dir = tempfile::TempDir::new().unwrap();
+    let manifest_path = manifest_dir.path().join("drop.txt");
+    fs::write(&manifest_path, r#"
+version = 2
+artist_name = "Cool Rasta"
+playlist_name = "Sunrise"
+import_policy = "create_missing"
+[[tracks]]
+file = "track001.wav"
+"#).unwrap();
+    let drop_manifest = create_drop_manifest_from_file(&manifest_path, ManifestFormat::Toml).unwrap();
+    assert_eq!(Some(ImportPolicy::CreateMissing), drop_manifest.import_policy());
+    assert_eq!(Some(ImportPolicy::CreateMissing), DropRequest::from(&drop_manifest).import_policy());
+
+    // without policy the one of the conf applies
+    let drop_manifest = create_drop_manifest_from_file(
+        Path::new("tests/resources/import_path/untar_drop/ok/drop_ok/drop.txt"),
+        ManifestFormat::Toml
+    ).unwrap();
+    assert_eq!(None, DropRequest::from(&drop_manifest).import_policy());
+}
+
+#[test]
+fn drop_manifest_reports_type_and_cue_point_errors() {
+    let manifest_dir = tempfile::TempDir::new().unwrap();
+    let manifest_path = manifest_dir.path().join("drop.txt");
+    fs::write(&manifest_path, r#"
+version = 2
+artist_name = "Cool Rasta"
+playlist_name = "Dub sessions"
+type = "ep"
+[[tracks]]
+file = "track001.wav"
+"#).unwrap();
+    let Err(ManifestError::InvalidFields(field_errors)) = create_drop_manifest_from_file(&manifest_path, ManifestFormat::Toml) else {
+        panic!("an ep of one track is invalid");
+    };
+    assert_eq!("tracks", field_errors[0].field());
+    assert_eq!("must list 4 to 6 tracks for type ep", field_errors[0].message());
+
+    fs::write(&manifest_path, r#"
+version = 2
+artist_name = "Cool Rasta"
+playlist_name = "Sound system mix"
+type = "mix"
+[[tracks]]
+file = "part1.wav"
+duration_seconds = 600
+cue_points = [
+    { start_seconds = 0, title = "Intro" },
+    { start_seconds = 0, title = "Dub" },
+    { start_seconds = 700, title = "" },
+]
+[[tracks]]
+file = "part2.wav"
+"#).unwrap();
+    let Err(ManifestError::InvalidFields(field_errors)) = create_drop_manifest_from_file(&manifest_path, ManifestFormat::Toml) else {
+        panic!("the cue points of the mix are invalid");
+    };
+    let fields: Vec<&str> = field_errors.iter().map(|field_error| field_error.field()).collect();
+    assert_eq!(vec![
+        "tracks[0].cue_points[1].start_seconds",
+        "tracks[0].cue_points[2].title",
+        "tracks[0].cue_points[2].start_seconds",
+        "tracks[1].cue_points",
+    ], fields);
+}
+
+#[test]
+fn drop_manifest_with_unknown_version_is_rejected() {
+    let drop_dir = tempfile::TempDir::new().unwrap();
+    fs::write(drop_dir.path().join("drop.txt"), "version = 3\nplaylist_name = \"p\"\ntracks = []\n").unwrap();
+
+    let result = create_drop_manifest_from_file(&drop_dir.path().join("drop.txt"), ManifestFormat::Toml);
+    assert_eq!(Err(ManifestError::UnsupportedVersion(3)), result);
+
+    let result = check_unarchived_drop_files(drop_dir.path().to_str().unwrap());
+    assert!(matches!(result, Err(ImportError::UnsupportedDropManifestVersion)));
+}
+
+#[test]
+fn check_unarchived_drop_files_requires_the_artwork_of_a_v2_manifest() {
+    let drop_dir = tempfile::TempDir::new().unwrap();
+    fs::copy("tests/resources/manifest/drop_v2.txt", drop_dir.path().join("drop.txt")).unwrap();
+    fs::copy(FIXTURE_TRACK, drop_dir.path().join("track001.wav")).unwrap();
+    fs::copy(FIXTURE_TRACK, drop_dir.path().join("track002.wav")).unwrap();
+
+    let result = check_unarchived_drop_files(drop_dir.path().to_str().unwrap());
+    assert!(matches!(result, Err(ImportError::MissingArtworkInDropArchive)));
+
+    image::RgbImage::new(64, 64).save_with_format(drop_dir.path().join("cover.jpg"), image::ImageFormat::Jpeg).unwrap();
+    let (_, drop_manifest) = check_unarchived_drop_files(drop_dir.path().to_str().unwrap()).unwrap();
+    assert_eq!(2, drop_manifest.tracks().len());
+}
+
+#[test]
+fn check_unarchived_drop_files_validates_the_fields_filled_from_the_tracks() {
+    let drop_dir = tempfile::TempDir::new().unwrap();
+    fs::write(drop_dir.path().join("drop.txt"), r#"version = 2
+artist_name = "Cool Rasta"
+playlist_name = "Sound system mix"
+
+[[tracks]]
+file = "track001.wav"
+cue_points = [{ start_seconds = 30, title = "Second tune" }]
+"#).unwrap();
+    fs::copy(FIXTURE_TRACK, drop_dir.path().join("track001.wav")).unwrap();
+
+    // the manifest gives no duration, the probed one second ends before the cue point
+    let result = check_unarchived_drop_files(drop_dir.path().to_str().unwrap());
+    assert!(matches!(result, Err(ImportError::InvalidDropManifest)));
+}
+
+#[test]
+fn check_unarchived_drop_files_reads_json_and_yaml_manifests() {
+    let json_manifest = r#"{
+  "version": 2,
+  "artist_name": "Cool Rasta",
+  "playlist_name": "Rasta's playlist",
+  "tracks": [{ "file": "track001.wav", "title": "Intro", "track_number": 1 }]
+}"#;
+    let yaml_manifest = "artist_name: Cool Rasta\nplaylist_name: Rasta's playlist\ntracks:\n  - track001.wav\n";
+    for (file_name, manifest, version) in [("drop.json", json_manifest, 2), ("drop.yaml", yaml_manifest, 1)] {
+        let drop_dir = tempfile::TempDir::new().unwrap();
+        fs::write(drop_dir.path().join(file_name), manifest).unwrap();
+        fs::copy(FIXTURE_TRACK, drop_dir.path().join("track001.wav")).unwrap();
+
+        let (_, drop_manifest) = check_unarchived_drop_files(drop_dir.path().to_str().unwrap())
+            .unwrap_or_else(|e| panic!("{file_name} not read: {:?}", e));
+
+        assert_eq!(version, drop_manifest.version(), "{file_name}");
+        assert_eq!("Rasta's playlist", drop_manifest.playlist_name(), "{file_name}");
+        assert_eq!("track001.wav", drop_manifest.tracks()[0].file(), "{file_name}");
+    }
+}
+
+#[test]
+fn check_unarchived_drop_files_rejects_several_manifests() {
+    let drop_dir = tempfile::TempDir::new().unwrap();
+    fs::copy("tests/resources/import_path/untar_drop/ok/drop_ok/drop.txt", drop_dir.path().join("drop.txt")).unwrap();
+    fs::copy("tests/resources/import_path/untar_drop/ok/drop_ok/drop.txt", drop_dir.path().join("drop.toml")).unwrap();
+
+    let result = check_unarchived_drop_files(drop_dir.path().to_str().unwrap());
+
+    assert!(matches!(result, Err(ImportError::SeveralDropManifests)));
 }
 
 #[test]
@@ -100,14 +407,602 @@ fn check_unarchived_drop_files_should_return_untar_directory_path() {
 #[test]
 fn check_drop_file_should_return_untar_directory_path() {
     let tar_gz_path = "tests/resources/import_path/correct_tar_gz/drop_ok.tar.gz";
-    let result = check_drop_file(&tar_gz_path);
+    let untar_dir = tempfile::TempDir::new().unwrap();
+    let result = check_drop_file(&tar_gz_path, untar_dir.path());
     assert!(result.is_ok());
     let directory_path = result.unwrap().0;
     let path = Path::new(&directory_path);
     assert!(path.is_dir());
-    assert!(path.parent().unwrap().file_name().unwrap().to_str().unwrap().starts_with("correct_tar_gz_"));
+    assert_eq!(untar_dir.path(), path.parent().unwrap());
     assert_eq!(path.file_name().unwrap().to_str().unwrap(), "drop_ok");
+    // nothing is unpacked next to the archive
+    assert_eq!(1, fs::read_dir("tests/resources/import_path/correct_tar_gz").unwrap().count());
+}
+
+const UNTAR_DROP_PATH: &str = "tests/resources/import_path/untar_drop/ok/drop_ok";
+
+fn write_drop_tar<W: Write>(writer: W) -> W {
+    let mut builder = tar::Builder::new(writer);
+    builder.append_dir_all("drop_ok", UNTAR_DROP_PATH).unwrap();
+    builder.into_inner().unwrap()
+}
+
+fn write_drop_zip(file: fs::File) {
+    let mut zip_writer = zip::ZipWriter::new(file);
+    zip_writer.add_directory("drop_ok/", zip::write::SimpleFileOptions::default()).unwrap();
+    for dir_entry in fs::read_dir(UNTAR_DROP_PATH).unwrap() {
+        let dir_entry = dir_entry.unwrap();
+        zip_writer.start_file(
+            format!("drop_ok/{}", dir_entry.file_name().to_str().unwrap()),
+            zip::write::SimpleFileOptions::default()
+        ).unwrap();
+        zip_writer.write_all(&fs::read(dir_entry.path()).unwrap()).unwrap();
+    }
+    zip_writer.finish().unwrap();
+}
+
+fn write_drop_archive(archive_format: ArchiveFormat, path: &Path) {
+    let file = fs::File::create(path).unwrap();
+    match archive_format {
+        ArchiveFormat::Zip => write_drop_zip(file),
+        ArchiveFormat::Tar => {
+            write_drop_tar(file);
+        }
+        ArchiveFormat::TarGz => {
+            write_drop_tar(flate2::write::GzEncoder::new(file, flate2::Compression::default())).finish().unwrap();
+        }
+        ArchiveFormat::TarZst => {
+            write_drop_tar(zstd::Encoder::new(file, 0).unwrap()).finish().unwrap();
+        }
+        ArchiveFormat::TarXz => {
+            write_drop_tar(xz2::write::XzEncoder::new(file, 6)).finish().unwrap();
+        }
+    }
+}
+
+#[test]
+fn archive_format_is_detected_from_magic_bytes() {
+    assert_eq!(Some(ArchiveFormat::TarGz), ArchiveFormat::detect(&[0x1f, 0x8b, 0x08]));
+    assert_eq!(Some(ArchiveFormat::TarZst), ArchiveFormat::detect(&[0x28, 0xb5, 0x2f, 0xfd, 0x00]));
+    assert_eq!(Some(ArchiveFormat::TarXz), ArchiveFormat::detect(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]));
+    assert_eq!(Some(ArchiveFormat::Zip), ArchiveFormat::detect(b"PK\x03\x04"));
+    let mut tar_header = vec![0u8; 512];
+    tar_header[257..262].copy_from_slice(b"ustar");
+    assert_eq!(Some(ArchiveFormat::Tar), ArchiveFormat::detect(&tar_header));
+    assert_eq!(None, ArchiveFormat::detect(b"artist_name = \"Cool Rasta\""));
+}
+
+#[test]
+fn check_drop_file_unpacks_every_supported_archive_format() {
+    for archive_format in [ArchiveFormat::Zip, ArchiveFormat::Tar, ArchiveFormat::TarGz, ArchiveFormat::TarZst, ArchiveFormat::TarXz] {
+        let import_dir = tempfile::TempDir::new().unwrap();
+        // the file name doesn't tell the format
+        let archive_path = import_dir.path().join("drop_archive");
+        write_drop_archive(archive_format, &archive_path);
+
+        let untar_dir = import_dir.path().join("untar");
+        fs::create_dir(&untar_dir).unwrap();
+
+        let (directory_path, drop_request) = check_drop_file(archive_path.to_str().unwrap(), &untar_dir)
+            .unwrap_or_else(|e| panic!("{:?} not unpacked: {:?}", archive_format, e));
+
+        assert!(directory_path.ends_with("drop_ok"), "{:?}", archive_format);
+        assert_eq!(3, drop_request.tracks().len());
+    }
+}
+
+#[test]
+fn check_drop_file_rejects_unknown_archive_format() {
+    let import_dir = tempfile::TempDir::new().unwrap();
+    let archive_path = import_dir.path().join("drop_archive.tar.gz");
+    fs::write(&archive_path, "not an archive").unwrap();
+
+    let result = check_drop_file(archive_path.to_str().unwrap(), import_dir.path());
+
+    assert!(matches!(result, Err(ImportError::UnsupportedArchiveFormat)));
+}
+
+/// A tar holding one entry named `name`, written raw since tar::Builder refuses unsafe paths
+fn write_tar_entry(path: &Path, name: &[u8], entry_type: tar::EntryType, link_name: Option<&str>) {
+    let mut header = tar::Header::new_gnu();
+    header.as_old_mut().name[..name.len()].copy_from_slice(name);
+    header.set_entry_type(entry_type);
+    header.set_mode(0o644);
+    header.set_size(0);
+    if let Some(link_name) = link_name {
+        header.set_link_name(link_name).unwrap();
+    }
+    header.set_cksum();
+    let mut builder = tar::Builder::new(fs::File::create(path).unwrap());
+    builder.append(&header, std::io::empty()).unwrap();
+    builder.finish().unwrap();
+}
+
+fn extract_with_default_limits(archive_path: &Path) -> (tempfile::TempDir, Result<ArchiveFormat, ImportError>) {
+    let destination = tempfile::TempDir::new().unwrap();
+    let result = extract_archive_with_limits(archive_path, destination.path(), ExtractionLimits::default());
+    (destination, result)
+}
+
+#[test]
+fn extraction_rejects_tar_entries_outside_the_destination() {
+    let import_dir = tempfile::TempDir::new().unwrap();
+    let archive_path = import_dir.path().join("drop_archive");
+    for name in [&b"../evil.mp3"[..], b"/tmp/evil.mp3", b"drop/../../evil.mp3"] {
+        write_tar_entry(&archive_path, name, tar::EntryType::Regular, None);
+
+        let (destination, result) = extract_with_default_limits(&archive_path);
+
+        assert!(matches!(result, Err(ImportError::UnsafeArchiveEntry)), "{:?}", String::from_utf8_lossy(name));
+        assert!(!destination.path().parent().unwrap().join("evil.mp3").exists());
+    }
+}
+
+#[test]
+fn extraction_rejects_tar_links_and_device_files() {
+    let import_dir = tempfile::TempDir::new().unwrap();
+    let archive_path = import_dir.path().join("drop_archive");
+    for (entry_type, link_name) in [
+        (tar::EntryType::Symlink, Some("/etc/passwd")),
+        (tar::EntryType::Link, Some("/etc/passwd")),
+        (tar::EntryType::Char, None),
+        (tar::EntryType::Block, None),
+        (tar::EntryType::Fifo, None),
+    ] {
+        write_tar_entry(&archive_path, b"track001.mp3", entry_type, link_name);
+
+        let (destination, result) = extract_with_default_limits(&archive_path);
+
+        assert!(matches!(result, Err(ImportError::UnsafeArchiveEntry)), "{:?}", entry_type);
+        assert!(fs::symlink_metadata(destination.path().join("track001.mp3")).is_err());
+    }
+}
+
+#[test]
+fn extraction_rejects_zip_entries_outside_the_destination_and_symlinks() {
+    let import_dir = tempfile::TempDir::new().unwrap();
+    let archive_path = import_dir.path().join("drop_archive");
+    let mut zip_writer = zip::ZipWriter::new(fs::File::create(&archive_path).unwrap());
+    zip_writer.start_file("../evil.mp3", zip::write::SimpleFileOptions::default()).unwrap();
+    zip_writer.finish().unwrap();
+
+    let (_destination, result) = extract_with_default_limits(&archive_path);
+    assert!(matches!(result, Err(ImportError::UnsafeArchiveEntry)));
+
+    let mut zip_writer = zip::ZipWriter::new(fs::File::create(&archive_path).unwrap());
+    zip_writer.add_symlink("track001.mp3", "/etc/passwd", zip::write::SimpleFileOptions::default()).unwrap();
+    zip_writer.finish().unwrap();
+
+    let (destination, result) = extract_with_default_limits(&archive_path);
+    assert!(matches!(result, Err(ImportError::UnsafeArchiveEntry)));
+    assert!(fs::symlink_metadata(destination.path().join("track001.mp3")).is_err());
+}
+
+#[test]
+fn extraction_enforces_entry_count_and_total_size_limits() {
+    let import_dir = tempfile::TempDir::new().unwrap();
+    let archive_path = import_dir.path().join("drop_archive");
+    for archive_format in [ArchiveFormat::Zip, ArchiveFormat::Tar] {
+        write_drop_archive(archive_format, &archive_path);
+        let destination = tempfile::TempDir::new().unwrap();
+
+        let result = extract_archive_with_limits(&archive_path, destination.path(), ExtractionLimits::new(1024 * 1024, 2, 100));
+        assert!(matches!(result, Err(ImportError::TooManyArchiveEntries)), "{:?}", archive_format);
+
+        let result = extract_archive_with_limits(&archive_path, destination.path(), ExtractionLimits::new(10, 100, 100));
+        assert!(matches!(result, Err(ImportError::ArchiveTooLarge)), "{:?}", archive_format);
+    }
+}
 
-    // delete created directories
-    fs::remove_dir_all(&directory_path).expect("removing directory failed");
+#[test]
+fn extraction_enforces_compression_ratio_limit() {
+    let import_dir = tempfile::TempDir::new().unwrap();
+    let archive_path = import_dir.path().join("drop_archive");
+    let mut builder = tar::Builder::new(
+        flate2::write::GzEncoder::new(fs::File::create(&archive_path).unwrap(), flate2::Compression::best())
+    );
+    let zeros = vec![0u8; 1024 * 1024];
+    let mut header = tar::Header::new_gnu();
+    header.set_size(zeros.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder.append_data(&mut header, "track001.mp3", zeros.as_slice()).unwrap();
+    builder.into_inner().unwrap().finish().unwrap();
+
+    let (_destination, result) = extract_with_default_limits(&archive_path);
+
+    assert!(matches!(result, Err(ImportError::ArchiveCompressionRatioTooHigh)));
+}
+
+#[test]
+fn check_unarchived_drop_files_rejects_tracks_outside_the_drop_directory() {
+    let drop_dir = tempfile::TempDir::new().unwrap();
+    for track in ["../../etc/passwd", "/etc/passwd"] {
+        fs::write(
+            drop_dir.path().join("drop.txt"),
+            format!("artist_name = \"Cool Rasta\"\nplaylist_name = \"Rasta's playlist\"\ntracks = [\"{track}\"]\n"),
+        ).unwrap();
+
+        let result = check_unarchived_drop_files(drop_dir.path().to_str().unwrap());
+
+        assert!(matches!(result, Err(ImportError::TrackPathOutsideDropDirectory)), "{track}");
+    }
+}
+
+/// An ID3v2.3 tag holding text frames
+fn id3_tag(frames: &[(&[u8; 4], &str)]) -> Vec<u8> {
+    let mut body = Vec::new();
+    for (frame_id, text) in frames {
+        body.extend_from_slice(*frame_id);
+        body.extend_from_slice(&(text.len() as u32 + 1).to_be_bytes());
+        body.extend_from_slice(&[0, 0, 0]);
+        body.extend_from_slice(text.as_bytes());
+    }
+    let size = body.len() as u32;
+    let mut tag = b"ID3\x03\x00\x00".to_vec();
+    // the tag size is synchsafe, 7 bits per byte
+    tag.extend_from_slice(&[(size >> 21) as u8 & 0x7f, (size >> 14) as u8 & 0x7f, (size >> 7) as u8 & 0x7f, size as u8 & 0x7f]);
+    tag.extend_from_slice(&body);
+    tag
+}
+
+/// Silent MPEG-1 layer III frames, 128 kbit/s at 44.1 kHz in mono
+fn mp3_frames(nb_frames: usize) -> Vec<u8> {
+    let mut frame = vec![0u8; 417];
+    frame[..4].copy_from_slice(&[0xff, 0xfb, 0x90, 0xc0]);
+    frame.repeat(nb_frames)
+}
+
+/// A FLAC stream declaring 2 seconds of 16 bits stereo at 44.1 kHz in its STREAMINFO block,
+/// followed by a single silent frame
+fn flac_stream() -> Vec<u8> {
+    let mut flac = b"fLaC".to_vec();
+    flac.extend_from_slice(&[0x80, 0, 0, 34]);
+    flac.extend_from_slice(&4096u16.to_be_bytes());
+    flac.extend_from_slice(&4096u16.to_be_bytes());
+    flac.extend_from_slice(&[0; 6]);
+    let sample_format: u64 = (44100 << 44) | (1 << 41) | (15 << 36) | 88200;
+    flac.extend_from_slice(&sample_format.to_be_bytes());
+    flac.extend_from_slice(&[0; 16]);
+    // 4096 samples per block at 44.1 kHz, independent channels of 16 bits, frame number 0
+    let mut frame = vec![0xff, 0xf8, 0xc9, 0x18, 0x00];
+    let crc8 = frame.iter().fold(0u8, |crc, byte| (0..8).fold(crc ^ byte, |crc, _| if crc & 0x80 != 0 { (crc << 1) ^ 0x07 } else { crc << 1 }));
+    frame.push(crc8);
+    // a constant subframe of 0 per channel
+    frame.extend_from_slice(&[0; 6]);
+    let crc16 = frame.iter().fold(0u16, |crc, byte| (0..8).fold(crc ^ ((*byte as u16) << 8), |crc, _| if crc & 0x8000 != 0 { (crc << 1) ^ 0x8005 } else { crc << 1 }));
+    frame.extend_from_slice(&crc16.to_be_bytes());
+    flac.extend(frame);
+    flac
+}
+
+#[test]
+fn probe_audio_file_reads_wav_duration_bitrate_and_info_tags() {
+    let audio_probe = probe_audio_file(Path::new("tests/resources/import_path/untar_drop/ok/drop_ok/track001.wav")).unwrap();
+
+    assert_eq!(AudioFormat::Wav, audio_probe.format());
+    assert_eq!(Some(Duration::from_secs(1)), audio_probe.duration());
+    assert_eq!(Some(8000), audio_probe.sample_rate());
+    assert!(audio_probe.bitrate().is_some_and(|bitrate| (64_000..65_000).contains(&bitrate)));
+    assert_eq!(Some("Intro"), audio_probe.tags().title());
+    assert_eq!(Some("Reggae"), audio_probe.tags().genre());
+}
+
+#[test]
+fn probe_audio_file_recognises_mp3_and_flac_whatever_their_extension() {
+    let audio_dir = tempfile::TempDir::new().unwrap();
+    let mp3_path = audio_dir.path().join("track.flac");
+    let mut mp3 = id3_tag(&[(b"TIT2", "Sunrise"), (b"TSRC", "USRC17607839"), (b"TRCK", "2/12")]);
+    mp3.extend(mp3_frames(20));
+    fs::write(&mp3_path, mp3).unwrap();
+    let flac_path = audio_dir.path().join("track.mp3");
+    fs::write(&flac_path, flac_stream()).unwrap();
+
+    let audio_probe = probe_audio_file(&mp3_path).unwrap();
+    assert_eq!(AudioFormat::Mp3, audio_probe.format());
+    assert_eq!(Some("Sunrise"), audio_probe.tags().title());
+    assert_eq!(Some("USRC17607839"), audio_probe.tags().isrc());
+    assert_eq!(Some(2), audio_probe.tags().track_number());
+
+    let audio_probe = probe_audio_file(&flac_path).unwrap();
+    assert_eq!(AudioFormat::Flac, audio_probe.format());
+    assert_eq!(Some(Duration::from_secs(2)), audio_probe.duration());
+}
+
+#[test]
+fn probe_audio_file_rejects_empty_and_non_audio_files() {
+    let audio_dir = tempfile::TempDir::new().unwrap();
+    for (file_name, content) in [("empty.mp3", &b""[..]), ("text.mp3", b"artist_name = \"Cool Rasta\""), ("cover.wav", b"\x89PNG\r\n\x1a\n")] {
+        let path = audio_dir.path().join(file_name);
+        fs::write(&path, content).unwrap();
+
+        assert!(matches!(probe_audio_file(&path), Err(ImportError::InvalidAudioTrack)), "{file_name}");
+    }
+}
+
+#[test]
+fn check_unarchived_drop_files_rejects_tracks_which_are_not_audio() {
+    let drop_dir = tempfile::TempDir::new().unwrap();
+    fs::copy("tests/resources/import_path/untar_drop/ok/drop_ok/drop.txt", drop_dir.path().join("drop.txt")).unwrap();
+    fs::copy(FIXTURE_TRACK, drop_dir.path().join("track001.wav")).unwrap();
+    fs::copy(FIXTURE_TRACK, drop_dir.path().join("track002.wav")).unwrap();
+    fs::write(drop_dir.path().join("track003.wav"), "").unwrap();
+
+    let result = check_unarchived_drop_files(drop_dir.path().to_str().unwrap());
+
+    assert!(matches!(result, Err(ImportError::InvalidAudioTrack)));
+}
+
+#[test]
+fn check_unarchived_drop_files_fills_missing_manifest_fields_from_the_tracks() {
+    let (_, drop_manifest) = check_unarchived_drop_files("tests/resources/import_path/untar_drop/ok/drop_ok").unwrap();
+
+    assert_eq!(Some("Intro"), drop_manifest.tracks()[0].title());
+    assert_eq!(Some(1), drop_manifest.tracks()[0].duration_seconds());
+    assert_eq!(None, drop_manifest.tracks()[1].title());
+    assert_eq!(Some(1), drop_manifest.tracks()[1].duration_seconds());
+    assert_eq!(Some("Reggae"), drop_manifest.genre());
+
+    // the request carries them to the recorded tracks
+    let drop_request = DropRequest::from(&drop_manifest);
+    assert_eq!(Some("Intro"), drop_request.track_metadata()[0].title());
+    assert_eq!(Some(1), drop_request.track_metadata()[1].duration_seconds());
+}
+
+#[test]
+fn probe_artwork_reads_the_format_from_the_content() {
+    let artwork_dir = tempfile::TempDir::new().unwrap();
+    let png_path = artwork_dir.path().join("cover.jpg");
+    image::RgbaImage::new(40, 30).save_with_format(&png_path, image::ImageFormat::Png).unwrap();
+    let jpeg_path = artwork_dir.path().join("cover.png");
+    image::RgbImage::new(20, 10).save_with_format(&jpeg_path, image::ImageFormat::Jpeg).unwrap();
+    let text_path = artwork_dir.path().join("cover.webp");
+    fs::write(&text_path, "not an image").unwrap();
+
+    let artwork_image = probe_artwork(&png_path).unwrap();
+    assert_eq!((ArtworkFormat::Png, 40, 30), (artwork_image.format(), artwork_image.width(), artwork_image.height()));
+    assert_eq!(ArtworkFormat::Jpeg, probe_artwork(&jpeg_path).unwrap().format());
+    assert!(matches!(probe_artwork(&text_path), Err(ImportError::InvalidArtwork)));
+}
+
+#[test]
+fn write_artwork_variants_resizes_within_each_size_without_enlarging() {
+    let artwork_dir = tempfile::TempDir::new().unwrap();
+    let source = artwork_dir.path().join("cover.png");
+    image::RgbaImage::from_pixel(1300, 650, image::Rgba([200, 40, 40, 255])).save(&source).unwrap();
+    let destination = artwork_dir.path().join("variants");
+    fs::create_dir(&destination).unwrap();
+
+    let artwork_image = probe_artwork(&source).unwrap();
+    write_artwork_variants(&source, &artwork_image, &destination).unwrap();
+
+    assert_eq!(fs::read(&source).unwrap(), fs::read(destination.join("original.png")).unwrap());
+    for (artwork_size, format, dimensions) in [
+        (ArtworkSize::Large, image::ImageFormat::Jpeg, (1200, 600)),
+        (ArtworkSize::Medium, image::ImageFormat::Jpeg, (600, 300)),
+        (ArtworkSize::Small, image::ImageFormat::Jpeg, (300, 150)),
+        (ArtworkSize::Thumbnail, image::ImageFormat::WebP, (150, 75)),
+    ] {
+        let variant_path = destination.join(artwork_size.file_name(artwork_image.format()));
+        let variant = image::ImageReader::open(&variant_path).unwrap().with_guessed_format().unwrap();
+        assert_eq!(Some(format), variant.format(), "{:?}", artwork_size);
+        assert_eq!(dimensions, variant.into_dimensions().unwrap(), "{:?}", artwork_size);
+    }
+
+    image::RgbImage::new(100, 80).save(&source).unwrap();
+    let artwork_image = probe_artwork(&source).unwrap();
+    write_artwork_variants(&source, &artwork_image, &destination).unwrap();
+    assert_eq!((100, 80), image::image_dimensions(destination.join("large.jpg")).unwrap());
+}
+
+#[test]
+fn check_unarchived_drop_files_rejects_an_artwork_which_is_not_an_image() {
+    let drop_dir = tempfile::TempDir::new().unwrap();
+    fs::copy("tests/resources/manifest/drop_v2.txt", drop_dir.path().join("drop.txt")).unwrap();
+    fs::copy(FIXTURE_TRACK, drop_dir.path().join("track001.wav")).unwrap();
+    fs::copy(FIXTURE_TRACK, drop_dir.path().join("track002.wav")).unwrap();
+    fs::write(drop_dir.path().join("cover.jpg"), "not an image").unwrap();
+
+    let result = check_unarchived_drop_files(drop_dir.path().to_str().unwrap());
+    assert!(matches!(result, Err(ImportError::InvalidArtwork)));
+}
+
+#[test]
+fn hash_api_key_is_sha256_hex() {
+    assert_eq!("5e884898da28047151d0e56f8dc6292773603d0d6aabbdd62a11ef721d1542d8", hash_api_key("password"));
+}
+
+#[tokio::test]
+async fn api_keys_are_loaded_from_conf() {
+    let config = create_conf_from_toml_file("tests/resources/conf/api_keys/app.toml").unwrap();
+    assert_eq!(2, config.api_keys().len());
+
+    let api_key_repo = InMemoryApiKeyRepo::from_conf(config.api_keys());
+    let api_key = api_key_repo.get_by_hash(&hash_api_key("password")).await.unwrap();
+    assert_eq!("ci", api_key.name());
+    assert!(api_key.has_scope(ApiKeyScope::Import));
+    assert!(!api_key.has_scope(ApiKeyScope::AdminRead));
+    assert!(api_key.expires_at().is_none());
+
+    let backoffice = api_key_repo.get(2).await.unwrap();
+    assert_eq!(vec![ApiKeyScope::AdminRead, ApiKeyScope::AdminWrite], backoffice.scopes());
+    assert!(backoffice.expires_at().is_some());
+}
+
+#[tokio::test]
+async fn rotate_api_key_keeps_old_key_valid_during_grace_period() {
+    let config = create_conf_from_toml_file("tests/resources/conf/api_keys/app.toml").unwrap();
+    let api_key_repo = InMemoryApiKeyRepo::from_conf(config.api_keys());
+
+    let (new_key, plain_key) = rotate_api_key(&api_key_repo, 1, chrono::Duration::minutes(10)).await.unwrap();
+
+    assert_ne!(1, new_key.id());
+    assert_eq!("ci", new_key.name());
+    assert_eq!(new_key, api_key_repo.get_by_hash(&hash_api_key(&plain_key)).await.unwrap());
+    let old_key = api_key_repo.get(1).await.unwrap();
+    let now = chrono::Utc::now().naive_utc();
+    assert!(!old_key.is_expired(now));
+    assert!(old_key.is_expired(now + chrono::Duration::minutes(11)));
+}
+
+#[test]
+fn stable_file_tracker_waits_until_size_stops_changing() {
+    let mut tracker = StableFileTracker::default();
+    let path = PathBuf::from("/import/drop_1.tar.gz");
+    let delay = Duration::from_secs(2);
+    let start = Instant::now();
+    tracker.observe_written(path.clone(), 10, start);
+
+    assert!(tracker.take_stable_files(start + Duration::from_secs(1), delay, |_| Some(20)).is_empty());
+    assert!(tracker.take_stable_files(start + Duration::from_secs(2), delay, |_| Some(20)).is_empty());
+    assert_eq!(vec![path], tracker.take_stable_files(start + Duration::from_secs(3), delay, |_| Some(20)));
+    assert!(tracker.take_stable_files(start + Duration::from_secs(4), delay, |_| Some(20)).is_empty());
+}
+
+#[test]
+fn stable_file_tracker_forgets_deleted_files() {
+    let mut tracker = StableFileTracker::default();
+    let start = Instant::now();
+    tracker.observe_written(PathBuf::from("/import/drop_1.tar.gz"), 10, start);
+
+    assert!(tracker.take_stable_files(start + Duration::from_secs(3), Duration::from_secs(2), |_| None).is_empty());
+    assert!(tracker.is_empty());
+}
+
+#[test]
+fn stable_file_tracker_reports_a_rename_once() {
+    let mut tracker = StableFileTracker::default();
+    let path = PathBuf::from("/import/drop_1.tar.gz");
+    let start = Instant::now();
+    tracker.observe_written(path.clone(), 10, start);
+
+    assert!(tracker.observe_renamed(path.clone(), start, Duration::from_secs(2)));
+    assert!(!tracker.observe_renamed(path.clone(), start + Duration::from_millis(10), Duration::from_secs(2)));
+    assert!(tracker.take_stable_files(start + Duration::from_secs(3), Duration::from_secs(2), |_| Some(10)).is_empty());
+}
+
+#[test]
+fn filter_pages_follow_the_sort_and_break_ties_by_id() {
+    let drops = vec![
+        Drop::new(1, 20, 1, 1, 1),
+        Drop::new(2, 10, 1, 1, 2),
+        Drop::new(3, 20, 2, 1, 3),
+        Drop::new(4, 30, 1, 1, 4),
+    ];
+    let filter = Filter::new().sort_by(DropField::ArtistId, Order::Desc).limit(2);
+
+    let first_page = filter.page(drops.clone());
+    assert_eq!(vec![4, 3], first_page.items.iter().map(Drop::id).collect::<Vec<_>>());
+    assert_eq!(Some(3), first_page.next_cursor);
+    let second_page = filter.clone().after(first_page.next_cursor).page(drops.clone());
+    assert_eq!(vec![1, 2], second_page.items.iter().map(Drop::id).collect::<Vec<_>>());
+    assert_eq!(None, second_page.next_cursor);
+
+    let single_type = Filter::new().eq(DropField::TypeId, 2i16).page(drops.clone());
+    assert_eq!(vec![3], single_type.items.iter().map(Drop::id).collect::<Vec<_>>());
+    assert!(Filter::new().after(Some(42)).page(drops).items.is_empty());
+}
+
+#[test]
+fn migrations_are_embedded_in_version_order() {
+    let versions: Vec<i64> = MIGRATOR.iter().map(|migration| migration.version).collect();
+    assert_eq!(Some(&1), versions.first());
+    assert!(versions.windows(2).all(|pair| pair[0] < pair[1]));
+    assert!(MIGRATOR.iter().all(|migration| !migration.checksum.is_empty()));
+}
+
+#[test]
+fn byte_ranges_are_read_from_range_headers() {
+    assert_eq!(Some(ByteRange { start: 2, end: Some(5) }), ByteRange::from_header("bytes=2-5"));
+    assert_eq!(Some(ByteRange { start: 10, end: None }), ByteRange::from_header("bytes=10-"));
+    assert_eq!(None, ByteRange::from_header("bytes=5-2"));
+    assert_eq!(None, ByteRange::from_header("bytes=-500"));
+    assert_eq!(None, ByteRange::from_header("items=0-1"));
+    let ranged_content = RangedContent { content: MediaContent::from_bytes(&b"llo"[..]), start: 2, end: 4, total_size: 11 };
+    assert_eq!("bytes 2-4/11", ranged_content.content_range());
+}
+
+#[tokio::test]
+async fn http_origin_store_skips_the_bytes_before_a_range_its_server_ignores() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let router = axum::Router::new().route("/media/track_1", axum::routing::get(|| async { "hello world" }));
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+    let media_store = HttpOriginStore::new(format!("http://{addr}/media"));
+
+    let ranged_content = media_store.get_range("track_1", ByteRange { start: 6, end: Some(8) }).await.unwrap();
+    assert_eq!("bytes 6-8/11", ranged_content.content_range());
+    assert_eq!(Some(3), ranged_content.content.size);
+    assert_eq!(b"wor".to_vec(), ranged_content.content.into_bytes().await.unwrap());
+    assert_eq!(MediaStoreError::InvalidRange, media_store.get_range("track_1", ByteRange { start: 11, end: None }).await.unwrap_err());
+}
+
+#[tokio::test]
+async fn local_media_store_puts_reads_lists_and_deletes_files() {
+    let root = tempfile::TempDir::new().unwrap();
+    let media_store = LocalMediaStore::new(root.path().to_path_buf());
+
+    media_store.put("playlist_1/track_1", MediaContent::from_bytes(&b"hello world"[..])).await.unwrap();
+    media_store.put("playlist_1/track_2", MediaContent::from_bytes(&b"second"[..])).await.unwrap();
+    media_store.put("playlist_10/track_1", MediaContent::from_bytes(&b"other"[..])).await.unwrap();
+    assert_eq!(b"hello world".to_vec(), fs::read(root.path().join("playlist_1/track_1")).unwrap());
+    assert_eq!(b"second".to_vec(), media_store.get("playlist_1/track_2").await.unwrap().into_bytes().await.unwrap());
+    assert_eq!(vec!["playlist_1/track_1", "playlist_1/track_2"], media_store.list("playlist_1/").await.unwrap());
+    assert_eq!(3, media_store.list("").await.unwrap().len());
+
+    let ranged_content = media_store.get_range("playlist_1/track_1", ByteRange { start: 6, end: Some(100) }).await.unwrap();
+    assert_eq!((6, 10, 11), (ranged_content.start, ranged_content.end, ranged_content.total_size));
+    assert_eq!(Some(5), ranged_content.content.size);
+    assert_eq!(b"world".to_vec(), ranged_content.content.into_bytes().await.unwrap());
+    assert_eq!(MediaStoreError::InvalidRange, media_store.get_range("playlist_1/track_1", ByteRange { start: 11, end: None }).await.unwrap_err());
+
+    move_prefix(&media_store, "playlist_1/", ".unpublished_playlist_1/").await.unwrap();
+    assert_eq!(vec![".unpublished_playlist_1/track_1", ".unpublished_playlist_1/track_2"], media_store.list(".unpublished_playlist_1/").await.unwrap());
+    assert!(!root.path().join("playlist_1").exists());
+
+    // deleting the last media of a directory removes it, a missing media is not an error
+    media_store.delete("playlist_10/track_1").await.unwrap();
+    media_store.delete("playlist_10/track_1").await.unwrap();
+    assert!(!root.path().join("playlist_10").exists());
+    assert_eq!(MediaStoreError::NotFound, media_store.get("playlist_10/track_1").await.unwrap_err());
+    assert_eq!(MediaStoreError::InvalidKey, media_store.get("../secret.txt").await.unwrap_err());
+    assert_eq!(MediaStoreError::InvalidKey, media_store.put("/etc/passwd", MediaContent::from_bytes(Vec::new())).await.unwrap_err());
+}
+
+#[test]
+fn track_keys_follow_the_content_hash_or_the_playlist_directory() {
+    let drop = Drop::new(1, 2, DropType::Single.id(), 0, 4);
+    let hash = "84d89877f0d4041efb6bf91a16f0248f2fd573e6af05c19f96bedb9f882f7882";
+    let track = Track::new(1, 4, 2, "02.flac".to_string(), None, None);
+    assert_eq!(Ok(format!("track_content/84/{hash}")), track_key(&drop, &track.clone().with_content_hash(hash)));
+    assert_eq!(Ok("playlist_4/track_2".to_string()), track_key(&drop, &track));
+    assert_eq!(Ok(".unpublished_playlist_4/track_2".to_string()), track_key(&drop.with_published(false), &track));
+}
+
+#[test]
+fn track_content_key_rejects_a_hash_which_is_not_a_sha256() {
+    assert_eq!(Err(MediaStoreError::InvalidKey), track_content_key(""));
+    assert_eq!(Err(MediaStoreError::InvalidKey), track_content_key("8"));
+    assert_eq!(Err(MediaStoreError::InvalidKey), track_content_key("é4d89877f0d4041efb6bf91a16f0248f2fd573e6af05c19f96bedb9f882f788"));
+    assert_eq!(Err(MediaStoreError::InvalidKey), track_content_key("84D89877f0d4041efb6bf91a16f0248f2fd573e6af05c19f96bedb9f882f7882"));
+    assert_eq!(Err(MediaStoreError::InvalidKey), track_content_key("../84d89877f0d4041efb6bf91a16f0248f2fd573e6af05c19f96bedb9f882f7"));
+}
+
+#[test]
+fn sqlx_errors_are_typed_as_repository_errors() {
+    assert!(matches!(RepositoryError::from(sqlx::Error::RowNotFound), RepositoryError::EntityNotFound));
+    assert!(matches!(RepositoryError::from(sqlx::Error::PoolTimedOut), RepositoryError::Timeout));
+    assert!(matches!(RepositoryError::from(sqlx::Error::PoolClosed), RepositoryError::Unavailable));
+    assert!(matches!(RepositoryError::from(sqlx::Error::ColumnNotFound("name".to_string())), RepositoryError::DatabaseError(_)));
+}
+
+#[test]
+fn typed_repository_errors_are_kept_by_the_import() {
+    let otherwise = || ImportError::CantCreateDropFromDropRequest;
+    assert!(matches!(ImportError::from_repository_error(RepositoryError::UniqueViolation, otherwise()), ImportError::DuplicateEntity));
+    assert!(matches!(ImportError::from_repository_error(RepositoryError::ForeignKeyViolation, otherwise()), ImportError::MissingReferencedEntity));
+    assert!(matches!(ImportError::from_repository_error(RepositoryError::Timeout, otherwise()), ImportError::DatabaseTimeout));
+    assert!(matches!(ImportError::from_repository_error(RepositoryError::Unavailable, otherwise()), ImportError::DatabaseUnavailable));
+    assert!(matches!(ImportError::from_repository_error(RepositoryError::EntityNotFound, otherwise()), ImportError::CantCreateDropFromDropRequest));
+    assert!(ImportError::DatabaseUnavailable.is_create_phase_error());
 }